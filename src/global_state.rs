@@ -1,3 +1,6 @@
+use crate::plan::concurrent::pacer::MarkingPacer;
+use crate::util::accounting_context::AccountingTable;
+use crate::util::stress::GCStress;
 use atomic_refcell::AtomicRefCell;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
@@ -51,6 +54,19 @@ pub struct GlobalState {
     pub(crate) live_bytes_in_last_gc: AtomicRefCell<HashMap<&'static str, LiveBytesStats>>,
     /// The number of used pages at the end of the last GC. This can be used to estimate how many pages we have allocated since last GC.
     pub(crate) used_pages_after_last_gc: AtomicUsize,
+    /// The number of roots reported in the current GC.
+    pub(crate) roots_in_current_gc: AtomicUsize,
+    /// The number of roots reported in the last GC.
+    pub(crate) roots_in_last_gc: AtomicUsize,
+    /// Allocated bytes, live bytes and quotas for each accounting context. This is only used if
+    /// the option `accounting_contexts` is enabled.
+    pub(crate) accounting: AccountingTable,
+    /// The seeded stress testing state. This is only used if the option `stress_seed`,
+    /// `randomize_gc_work_order` or `stress_random_defrag` is set.
+    pub(crate) stress: GCStress,
+    /// The pacer of concurrent marking.  This is only used if the option
+    /// `concurrent_marking_assists` is set.
+    pub(crate) pacer: MarkingPacer,
 }
 
 impl GlobalState {
@@ -195,6 +211,21 @@ impl GlobalState {
     pub(crate) fn get_used_pages_after_last_gc(&self) -> usize {
         self.used_pages_after_last_gc.load(Ordering::Relaxed)
     }
+
+    /// Count roots reported to a roots work factory in the current GC.
+    pub(crate) fn inc_roots(&self, roots: usize) {
+        self.roots_in_current_gc.fetch_add(roots, Ordering::Relaxed);
+    }
+
+    /// Called at the end of a GC.  Remember the number of roots reported in the GC.
+    pub(crate) fn on_roots_counted(&self) {
+        let roots = self.roots_in_current_gc.swap(0, Ordering::Relaxed);
+        self.roots_in_last_gc.store(roots, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get_roots_in_last_gc(&self) -> usize {
+        self.roots_in_last_gc.load(Ordering::Relaxed)
+    }
 }
 
 impl Default for GlobalState {
@@ -218,6 +249,11 @@ impl Default for GlobalState {
             malloc_bytes: AtomicUsize::new(0),
             live_bytes_in_last_gc: AtomicRefCell::new(HashMap::new()),
             used_pages_after_last_gc: AtomicUsize::new(0),
+            roots_in_current_gc: AtomicUsize::new(0),
+            roots_in_last_gc: AtomicUsize::new(0),
+            accounting: AccountingTable::default(),
+            stress: GCStress::default(),
+            pacer: MarkingPacer::default(),
         }
     }
 }
@@ -17,14 +17,17 @@ use crate::plan::AllocationSemantics;
 use crate::plan::{Mutator, MutatorContext};
 use crate::scheduler::WorkBucketStage;
 use crate::scheduler::{GCWork, GCWorker};
+use crate::util::accounting_context::{AccountingContext, AccountingQuota, AccountingStats};
 use crate::util::alloc::allocator::AllocationOptions;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::handle_table::{GlobalHandle, HandleKind};
 use crate::util::heap::layout::vm_layout::vm_layout;
 use crate::util::opaque_pointer::*;
 use crate::util::{Address, ObjectReference};
 use crate::vm::slot::MemorySlice;
 use crate::vm::ReferenceGlue;
+use crate::vm::ReferenceKind;
 use crate::vm::VMBinding;
 
 use std::collections::HashMap;
@@ -104,6 +107,55 @@ pub fn set_vm_space<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, start: Address,
         .set_vm_region(start, size);
 }
 
+/// Save the objects in the given spaces as a heap image. See [`crate::util::heap_image`].
+///
+/// The binding must ensure no threads are allocating and GC does not start while executing this
+/// function, in the same way as [`crate::MMTK::enumerate_objects`].  References in the saved
+/// objects are temporarily rewritten while the objects are copied, so mutators must not access
+/// those objects, either.  An error is returned if a saved object refers to an object outside the
+/// saved spaces, or if a space name is unknown.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The current thread. It is passed to [`crate::vm::Scanning::scan_object`].
+/// * `spaces`: The names of the spaces to save.
+/// * `roots`: Objects that the binding needs to find after restoring the image. They must be in the saved spaces.
+/// * `preferred_start`: The address that the image will be restored at. It must be page-aligned.
+#[cfg(feature = "heap_image")]
+pub fn save_heap_image<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMThread,
+    spaces: &[&str],
+    roots: &[ObjectReference],
+    preferred_start: Address,
+) -> std::io::Result<crate::util::heap_image::HeapImage> {
+    crate::util::heap_image::HeapImage::build(mmtk, tls, spaces, roots, preferred_start)
+}
+
+/// Restore a heap image into the VM space, and return the root objects that were passed to
+/// [`save_heap_image`], in the same order. The binding must have mapped at least
+/// [`crate::util::heap_image::HeapImage::size`] bytes of memory at `start`. If `start` is not
+/// [`crate::util::heap_image::HeapImage::preferred_start`], the references in the image are
+/// relocated. As with [`set_vm_space`], the memory must be outside the address range that MMTk
+/// uses for its own spaces. The binding should call this before any GC happens. An error is
+/// returned, and the VM space is not changed, if `start` is not page-aligned or if the image
+/// contains a reference to outside the image.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The current thread. It is passed to [`crate::vm::Scanning::scan_object`].
+/// * `image`: The heap image to restore.
+/// * `start`: The start of the memory to restore the image to. It must be page-aligned.
+#[cfg(feature = "heap_image")]
+pub fn restore_heap_image<VM: VMBinding>(
+    mmtk: &'static mut MMTK<VM>,
+    tls: VMThread,
+    image: &crate::util::heap_image::HeapImage,
+    start: Address,
+) -> std::io::Result<Vec<ObjectReference>> {
+    image.restore(mmtk, tls, start)
+}
+
 /// Request MMTk to create a mutator for the given thread. The ownership
 /// of returned boxed mutator is transferred to the binding, and the binding needs to take care of its
 /// lifetime. For performance reasons, A VM should store the returned mutator in a thread local storage
@@ -151,6 +203,28 @@ pub fn flush_mutator<VM: VMBinding>(mutator: &mut Mutator<VM>) {
     mutator.flush()
 }
 
+/// Set the accounting context of a mutator. Allocations of the mutator are charged to this context
+/// unless [`AllocationOptions::accounting_context`] says otherwise. This only has effect if the
+/// option `accounting_contexts` is enabled.
+///
+/// Arguments:
+/// * `mutator`: A reference to the mutator.
+/// * `context`: The accounting context. It cannot be [`AccountingContext::INHERIT`].
+pub fn set_mutator_accounting_context<VM: VMBinding>(
+    mutator: &Mutator<VM>,
+    context: AccountingContext,
+) {
+    mutator.set_accounting_context(context)
+}
+
+/// Get the accounting context of a mutator.
+///
+/// Arguments:
+/// * `mutator`: A reference to the mutator.
+pub fn get_mutator_accounting_context<VM: VMBinding>(mutator: &Mutator<VM>) -> AccountingContext {
+    mutator.get_accounting_context()
+}
+
 /// Allocate memory for an object.
 ///
 /// When the allocation is successful, it returns the starting address of the new object.  The
@@ -546,23 +620,40 @@ pub fn get_malloc_bytes<VM: VMBinding>(mmtk: &MMTK<VM>) -> usize {
 /// the current thread, and trigger a GC. Otherwise, it will simply return.
 /// Usually a binding does not need to call this function. MMTk will poll for GC during its allocation.
 /// However, if a binding uses counted malloc (which won't poll for GC), they may want to poll for GC manually.
+/// With the option `incremental_marking`, this also does a slice of marking if marking is in progress.
 /// This function should only be used by mutator threads.
 pub fn gc_poll<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMMutatorThread) {
-    use crate::vm::{ActivePlan, Collection};
+    use crate::vm::ActivePlan;
     debug_assert!(
         VM::VMActivePlan::is_mutator(tls.0),
         "gc_poll() can only be called by a mutator thread."
     );
 
+    if mmtk.state.pacer.is_marking_on_mutators() {
+        mmtk.gc_trigger.do_incremental_marking_slice(tls);
+    }
+
     if mmtk.gc_trigger.poll(false, None) {
         debug!("Collection required");
         if !mmtk.state.is_initialized() {
             panic!("GC is not allowed here: collection is not initialized (did you call initialize_collection()?).");
         }
-        VM::VMCollection::block_for_gc(tls);
+        mmtk.gc_trigger.wait_for_gc(tls);
     }
 }
 
+/// Run the requested or ongoing GC to completion on the current mutator thread.  This is only
+/// supported if the option `mutator_driven_gc` is set.  In that mode, MMTk calls this internally
+/// whenever a mutator has to wait for a GC, instead of calling
+/// [`crate::vm::Collection::block_for_gc`].  A binding may also call it from its own safepoints to
+/// wait for a requested GC.  It returns immediately if no GC is in progress or requested.
+///
+/// Only one mutator runs a GC at a time.  If another mutator is running the GC, this blocks in
+/// [`crate::vm::Collection::block_for_gc_driver`] until that mutator returns.
+pub fn run_gc_to_completion<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMMutatorThread) {
+    mmtk.scheduler.run_gc_on_mutator(tls);
+}
+
 /// Wrapper for [`crate::scheduler::GCWorker::run`].
 pub fn start_worker<VM: VMBinding>(
     mmtk: &'static MMTK<VM>,
@@ -628,6 +719,62 @@ pub fn live_bytes_in_last_gc<VM: VMBinding>(
     mmtk.state.live_bytes_in_last_gc.borrow().clone()
 }
 
+/// Set the soft and hard quotas of an accounting context. This replaces any quota previously set
+/// for the context. See [`crate::util::accounting_context`].
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `context`: The accounting context.
+/// * `quota`: The new quota for the context.
+pub fn set_accounting_quota<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    context: AccountingContext,
+    quota: AccountingQuota,
+) {
+    if !*mmtk.options.accounting_contexts {
+        warn!("set_accounting_quota() is called when accounting_contexts = false");
+    }
+    mmtk.state.accounting.set_quota(context, quota);
+}
+
+/// Remove an accounting context, including its quota and statistics. A binding may call this
+/// when it no longer uses a context, e.g. after a tenant is unloaded.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `context`: The accounting context to remove.
+pub fn remove_accounting_context<VM: VMBinding>(mmtk: &MMTK<VM>, context: AccountingContext) {
+    mmtk.state.accounting.remove(context);
+}
+
+/// Get the accounting statistics of a context, or `None` if MMTk has not seen the context.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `context`: The accounting context.
+pub fn get_accounting_stats<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    context: AccountingContext,
+) -> Option<AccountingStats> {
+    if !*mmtk.options.accounting_contexts {
+        warn!("get_accounting_stats() is called when accounting_contexts = false");
+    }
+    mmtk.state.accounting.get_stats(context)
+}
+
+/// Get the accounting statistics of all the contexts that MMTk has seen.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn accounting_stats<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+) -> HashMap<AccountingContext, AccountingStats> {
+    if !*mmtk.options.accounting_contexts {
+        warn!("accounting_stats() is called when accounting_contexts = false");
+    }
+    mmtk.state.accounting.all_stats()
+}
+
 /// Return the starting address of the heap. *Note that currently MMTk uses
 /// a fixed address range as heap.*
 pub fn starting_heap_address() -> Address {
@@ -665,6 +812,31 @@ pub fn handle_user_collection_request<VM: VMBinding>(
     mmtk.handle_user_collection_request(tls, false, false)
 }
 
+/// Explain why an object is alive.  This triggers a GC.  When all mutators are stopped, and
+/// before the GC starts, MMTk scans the roots and runs a separate breadth-first trace that records
+/// how each object is reached.  It returns the shortest chain of objects and slots from a root to
+/// `object`, or `None` if `object` is not reachable from any root.  [`PathToRoot::print`] prints
+/// the path for debugging.
+///
+/// This is intended for debugging suspected leaks.  The roots are scanned once more than usual in
+/// the GC, and [`crate::vm::Scanning::prepare_for_roots_re_scanning`] is called between the two
+/// scans.  Multiple threads can query at the same time.  The queries pending when a GC starts are
+/// answered together in that GC.
+///
+/// [`PathToRoot::print`]: crate::util::liveness::PathToRoot::print
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that requests the query.  It must be a mutator thread.
+/// * `object`: The object to explain.
+pub fn explain_liveness<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMMutatorThread,
+    object: ObjectReference,
+) -> Option<crate::util::liveness::PathToRoot<VM::VMSlot>> {
+    mmtk.explain_liveness(tls, object)
+}
+
 /// Is the object alive?
 ///
 /// Arguments:
@@ -734,6 +906,24 @@ pub fn find_object_from_internal_pointer(
     crate::util::is_mmtk_object::check_internal_reference(internal_ptr, max_search_bytes)
 }
 
+/// Find the released memory that contains `addr`, if MMTk has protected it because the
+/// `guard_released_memory` option is enabled.
+///
+/// The VM binding can call this from its handler of `SIGSEGV` (or `SIGBUS`) with the faulting
+/// address to report the use of a stale reference into a released block, page or copy-space region,
+/// including the space that released it.  This function does not block, and it does not allocate.
+/// It returns `None` if the address is not guarded, or if another thread is guarding or unguarding
+/// memory at the same time.
+///
+/// Free lines in Immix blocks are poisoned but not protected.  A reference loaded from a poisoned
+/// line consists of the byte `0xdb`, which usually faults with a different faulting address.
+///
+/// Arguments:
+/// * `addr`: The faulting address.
+pub fn find_guarded_memory(addr: Address) -> Option<crate::util::heap::GuardedMemory> {
+    crate::util::heap::memory_guard::find(addr)
+}
+
 /// Return true if the `object` lies in a region of memory where
 /// -   only MMTk can allocate into, or
 /// -   only MMTk's delegated memory allocator (such as a malloc implementation) can allocate into
@@ -805,6 +995,17 @@ pub fn add_soft_candidate<VM: VMBinding>(mmtk: &MMTK<VM>, reff: ObjectReference)
     mmtk.reference_processors.add_soft_candidate(reff);
 }
 
+/// Get the current value of the soft reference clock, in milliseconds.  MMTk advances the clock at
+/// the end of each GC.  If the `Lru` soft reference policy is used, the binding should record
+/// this value in a soft reference when the soft reference is created and whenever its referent is
+/// accessed, and return the recorded value from `ReferenceGlue::get_soft_reference_timestamp`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn soft_reference_clock<VM: VMBinding>(mmtk: &MMTK<VM>) -> u64 {
+    mmtk.reference_processors.soft_reference_clock()
+}
+
 /// Add a reference to the list of phantom references. A binding may
 /// call this either when a weak reference is created, or when a weak reference is traced during GC.
 ///
@@ -815,6 +1016,94 @@ pub fn add_phantom_candidate<VM: VMBinding>(mmtk: &MMTK<VM>, reff: ObjectReferen
     mmtk.reference_processors.add_phantom_candidate(reff);
 }
 
+/// Add a reference of the given kind to the list of references of that kind. A binding may call
+/// this either when a reference is created, or when a reference is traced during GC.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `kind`: The reference kind.  It is either a built-in kind, or returned from
+///   [`crate::MMTKBuilder::register_reference_kind`].
+/// * `reff`: The reference to add.
+pub fn add_reference_candidate<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    kind: ReferenceKind,
+    reff: ObjectReference,
+) {
+    mmtk.reference_processors.add_candidate(kind, reff);
+}
+
+/// Add an ephemeron to the list of ephemerons. MMTk keeps the value of an ephemeron alive only if
+/// the ephemeron and its key are both reachable, and clears the ephemeron if its key dies.  The key
+/// and the value are accessed via the ephemeron methods of [`ReferenceGlue`], and the binding must
+/// not report them when scanning the ephemeron object.
+///
+/// A binding should call this when an ephemeron is created. An ephemeron needs to be added only
+/// once, and MMTk keeps track of it until it is cleared or dies.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `ephemeron`: The ephemeron to add.
+pub fn add_ephemeron_candidate<VM: VMBinding>(mmtk: &MMTK<VM>, ephemeron: ObjectReference) {
+    mmtk.ephemeron_processor.add_candidate(ephemeron);
+}
+
+/// Create a strong handle in the global handle table of MMTk. The object is kept alive as long as
+/// the handle is not released, and the handle is updated if the object is moved.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `object`: The object the handle refers to.
+pub fn create_strong_handle<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    object: ObjectReference,
+) -> GlobalHandle {
+    mmtk.handle_table.create(HandleKind::Strong, object)
+}
+
+/// Create a weak handle in the global handle table of MMTk. The handle does not keep the object
+/// alive.  It is cleared when the object dies, and updated if the object is moved.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `object`: The object the handle refers to.
+pub fn create_weak_handle<VM: VMBinding>(mmtk: &MMTK<VM>, object: ObjectReference) -> GlobalHandle {
+    mmtk.handle_table.create(HandleKind::Weak, object)
+}
+
+/// Get the object a handle refers to. Return `None` if the handle is a weak handle that has been
+/// cleared.  This should not be called while mutators are stopped for a GC, as the handle may not
+/// have been updated yet.
+///
+/// Resolving a weak handle makes its object strongly reachable from the mutator.  This calls the
+/// weak reference load barrier of the mutator, so that the object is kept alive if concurrent
+/// marking is in progress.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `mutator`: The mutator that resolves the handle.
+/// * `handle`: A handle that has not been released.
+pub fn resolve_handle<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    mutator: &mut Mutator<VM>,
+    handle: GlobalHandle,
+) -> Option<ObjectReference> {
+    let object = mmtk.handle_table.resolve(handle);
+    if let (HandleKind::Weak, Some(object)) = (handle.kind(), object) {
+        mutator.barrier.load_weak_reference(object);
+    }
+    object
+}
+
+/// Release a handle created by [`create_strong_handle`] or [`create_weak_handle`]. The handle must
+/// not be used after this.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `handle`: The handle to release.
+pub fn release_handle<VM: VMBinding>(mmtk: &MMTK<VM>, handle: GlobalHandle) {
+    mmtk.handle_table.release(handle)
+}
+
 /// Generic hook to allow benchmarks to be harnessed. We do a full heap
 /// GC, and then start recording statistics for MMTk.
 ///
@@ -834,6 +1123,38 @@ pub fn harness_end<VM: VMBinding>(mmtk: &'static MMTK<VM>) {
     mmtk.harness_end();
 }
 
+/// Get the statistics of the work packets executed in each GC since statistics are enabled by
+/// [`harness_begin`], or since the last call to [`reset_work_packet_stats`], in the order of the
+/// GCs.  The statistics are broken down by GC
+/// workers and work packet types, and include the concurrent work executed before each GC.
+/// Unlike the summary printed by [`harness_end`], the type names are not shortened.  Work
+/// packets executed since the last GC are not included.
+///
+/// This should not be called while GC workers are executing work packets, including concurrent
+/// work.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+#[cfg(feature = "work_packet_stats")]
+pub fn work_packet_stats<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+) -> Vec<crate::scheduler::GCWorkPacketStats> {
+    mmtk.scheduler.work_packet_stats()
+}
+
+/// Discard the statistics returned by [`work_packet_stats`], e.g. between benchmark iterations.
+/// This does not affect the summary printed by [`harness_end`].
+///
+/// This should not be called while GC workers are executing work packets, including concurrent
+/// work.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+#[cfg(feature = "work_packet_stats")]
+pub fn reset_work_packet_stats<VM: VMBinding>(mmtk: &MMTK<VM>) {
+    mmtk.scheduler.reset_work_packet_stats();
+}
+
 /// Register a finalizable object. MMTk will retain the liveness of
 /// the object even if it is not reachable from the program.
 /// Note that finalization upon exit is not supported.
@@ -849,7 +1170,7 @@ pub fn add_finalizer<VM: VMBinding>(
         warn!("add_finalizer() is called when no_finalizer = true");
     }
 
-    mmtk.finalizable_processor.lock().unwrap().add(object);
+    mmtk.finalizable_processor.add(object);
 }
 
 /// Pin an object. MMTk will make sure that the object does not move
@@ -899,6 +1220,13 @@ pub fn is_pinned(object: ObjectReference) -> bool {
 /// the VM to make sure they are properly finalized before reclaimed by the GC. This call is non-blocking,
 /// and will return None if no object is ready for finalization.
 ///
+/// The `finalization_mode` option changes which objects are returned.  In the `Ordered` mode, an
+/// object is not returned while it is reachable from another finalizable object that is not
+/// finalized, unless the two objects are in the same cycle.  In the `Guardian` mode, the returned
+/// objects are not kept alive, and their children are not resurrected.  The binding may only use
+/// the returned object references as identities, and must not dereference them, because the objects
+/// may have been reclaimed, or their memory reused.
+///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 pub fn get_finalized_object<VM: VMBinding>(
@@ -908,10 +1236,7 @@ pub fn get_finalized_object<VM: VMBinding>(
         warn!("get_finalized_object() is called when no_finalizer = true");
     }
 
-    mmtk.finalizable_processor
-        .lock()
-        .unwrap()
-        .get_ready_object()
+    mmtk.finalizable_processor.get_ready_object()
 }
 
 /// Pop all the finalizers that were registered for finalization. The returned objects may or may not be ready for
@@ -928,10 +1253,7 @@ pub fn get_all_finalizers<VM: VMBinding>(
         warn!("get_all_finalizers() is called when no_finalizer = true");
     }
 
-    mmtk.finalizable_processor
-        .lock()
-        .unwrap()
-        .get_all_finalizers()
+    mmtk.finalizable_processor.get_all_finalizers()
 }
 
 /// Pop finalizers that were registered and associated with a certain object. The returned objects may or may not be ready for finalization.
@@ -948,15 +1270,13 @@ pub fn get_finalizers_for<VM: VMBinding>(
         warn!("get_finalizers() is called when no_finalizer = true");
     }
 
-    mmtk.finalizable_processor
-        .lock()
-        .unwrap()
-        .get_finalizers_for(object)
+    mmtk.finalizable_processor.get_finalizers_for(object)
 }
 
 /// Get the number of workers. MMTk spawns worker threads for the 'threads' defined in the options.
-/// So the number of workers is derived from the threads option. Note the feature single_worker overwrites
-/// the threads option, and force one worker thread.
+/// So the number of workers is derived from the threads option, unless it has been changed with
+/// [`set_num_of_workers`]. Note the feature single_worker overwrites the threads option, and force one
+/// worker thread.
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
@@ -964,6 +1284,77 @@ pub fn num_of_workers<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> usize {
     mmtk.scheduler.num_workers()
 }
 
+/// Change the number of GC workers at run time, for example, to follow the number of CPUs available
+/// to the process.  The number can be changed up to the option `max_threads` (or `threads` if
+/// `max_threads` is smaller).
+///
+/// If a GC is in progress, this waits until it finishes.  Then all GC worker threads exit, reporting
+/// [`crate::vm::GCWorkerExitReason::Resize`], and this function blocks until they have all exited.
+/// Then MMTk calls [`crate::vm::Collection::spawn_gc_thread`] for the new number of workers.  This
+/// function returns after spawning them, without waiting for them to start.
+///
+/// If the option `mutator_driven_gc` is set and there is only one worker, no GC thread exits, and
+/// the binding must not call this function while a mutator is running a GC.
+///
+/// The binding must not call this function concurrently with itself,
+/// [`crate::mmtk::MMTK::prepare_to_fork`] or [`crate::mmtk::MMTK::shutdown`], and must not call it
+/// on a GC worker thread.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that changes the number of workers.  It is passed to
+///   [`crate::vm::Collection::spawn_gc_thread`].
+/// * `num_workers`: The new number of workers, at least 1.  It includes the worker driven by
+///   mutators if the option `mutator_driven_gc` is set.
+pub fn set_num_of_workers<VM: VMBinding>(
+    mmtk: &'static MMTK<VM>,
+    tls: VMThread,
+    num_workers: usize,
+) {
+    assert!(
+        mmtk.state.is_initialized(),
+        "MMTk collection has not been initialized, yet (was initialize_collection() called before?)"
+    );
+    assert!(
+        num_workers >= 1 && num_workers <= mmtk.scheduler.max_workers(),
+        "The number of GC workers must be between 1 and {}: {}",
+        mmtk.scheduler.max_workers(),
+        num_workers
+    );
+    mmtk.scheduler.set_num_workers(tls, num_workers);
+}
+
+/// Set the CPU budget of GC workers while mutators are running, i.e. when GC workers execute
+/// concurrent work such as concurrent marking.  This overrides the options
+/// `concurrent_gc_worker_fraction` and `concurrent_gc_duty_cycle`.  If concurrent work is in
+/// progress, the new budget takes effect immediately.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `worker_fraction`: The fraction of GC workers that may execute concurrent work, in (0, 1].
+///   It is rounded up to at least one worker.
+/// * `duty_cycle`: The fraction of time each GC worker may spend executing concurrent work, in
+///   (0, 1].
+pub fn set_concurrent_gc_cpu_budget<VM: VMBinding>(
+    mmtk: &'static MMTK<VM>,
+    worker_fraction: f64,
+    duty_cycle: f64,
+) {
+    use crate::scheduler::concurrent_budget::ConcurrentWorkBudget;
+    assert!(
+        ConcurrentWorkBudget::is_valid_fraction(worker_fraction),
+        "Invalid fraction of GC workers: {}",
+        worker_fraction
+    );
+    assert!(
+        ConcurrentWorkBudget::is_valid_fraction(duty_cycle),
+        "Invalid duty cycle: {}",
+        duty_cycle
+    );
+    mmtk.scheduler
+        .set_concurrent_budget(worker_fraction, duty_cycle);
+}
+
 /// Add a work packet to the given work bucket. Note that this simply adds the work packet to the given
 /// work bucket, and the scheduler will decide when to execute the work packet.
 ///
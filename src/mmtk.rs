@@ -1,32 +1,39 @@
 //! MMTk instance.
 use crate::global_state::{GcStatus, GlobalState};
+use crate::plan::concurrent::pacer::MarkingPacer;
 use crate::plan::CreateGeneralPlanArgs;
 use crate::plan::Plan;
 use crate::policy::sft_map::{create_sft_map, SFTMap};
-use crate::scheduler::GCWorkScheduler;
+use crate::scheduler::concurrent_budget::ConcurrentWorkBudget;
+use crate::scheduler::{CustomStage, GCWorkScheduler, WorkBucketStage};
 
 #[cfg(feature = "vo_bit")]
 use crate::util::address::ObjectReference;
 #[cfg(feature = "analysis")]
 use crate::util::analysis::AnalysisManager;
+use crate::util::ephemeron_processor::EphemeronProcessor;
 use crate::util::finalizable_processor::FinalizableProcessor;
+use crate::util::handle_table::HandleTable;
 use crate::util::heap::gc_trigger::GCTrigger;
 use crate::util::heap::layout::heap_parameters::MAX_SPACES;
 use crate::util::heap::layout::vm_layout::{vm_layout, VMLayout};
 use crate::util::heap::layout::{self, Mmapper, VMMap};
 use crate::util::heap::HeapMeta;
+use crate::util::liveness::{LivenessQueries, PathToRoot};
 use crate::util::opaque_pointer::*;
-use crate::util::options::Options;
+use crate::util::options::{AffinityKind, Options};
 use crate::util::reference_processor::ReferenceProcessors;
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::SanityChecker;
 #[cfg(feature = "extreme_assertions")]
 use crate::util::slot_logger::SlotLogger;
 use crate::util::statistics::stats::Stats;
+use crate::util::stress::GCStress;
 #[cfg(feature = "vm_space")]
 use crate::vm::object_model::ObjectModel;
 use crate::vm::ReferenceGlue;
 use crate::vm::VMBinding;
+use crate::vm::{ReferenceKind, ReferenceKindSpec};
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::default::Default;
@@ -61,6 +68,10 @@ pub static SFT_MAP: InitializeOnce<Box<dyn SFTMap>> = InitializeOnce::new();
 pub struct MMTKBuilder {
     /// The options for this instance.
     pub options: Options,
+    /// The reference kinds, including the built-in kinds.
+    reference_kinds: Vec<ReferenceKindSpec>,
+    /// The stage after which each custom stage is placed.
+    custom_stages: Vec<WorkBucketStage>,
 }
 
 impl MMTKBuilder {
@@ -77,6 +88,8 @@ impl MMTKBuilder {
     pub fn new_no_env_vars() -> Self {
         MMTKBuilder {
             options: Options::default(),
+            reference_kinds: ReferenceProcessors::builtin_kinds(),
+            custom_stages: vec![],
         }
     }
 
@@ -97,9 +110,53 @@ impl MMTKBuilder {
         VMLayout::set_custom_vm_layout(constants)
     }
 
+    /// Register a reference kind in addition to the built-in soft, weak and phantom references,
+    /// and return its identifier.
+    pub fn register_reference_kind(&mut self, spec: ReferenceKindSpec) -> ReferenceKind {
+        self.reference_kinds.push(spec);
+        ReferenceKind(self.reference_kinds.len() - 1)
+    }
+
+    /// Declare a stop-the-world stage that is opened after the stage `after` is drained, and
+    /// return it.  Like built-in buckets, the VM binding can add work packets to its bucket with
+    /// [`crate::memory_manager::add_work_packet`], and a work packet can set the sentinel of the
+    /// bucket via [`crate::scheduler::GCWorker::scheduler`].
+    ///
+    /// The stage is placed immediately after `after`, or after the custom stages already placed
+    /// after `after`.  For example, a stage after [`WorkBucketStage::Closure`] is opened before
+    /// [`WorkBucketStage::SoftRefClosure`].  `after` can be any stop-the-world stage except
+    /// [`WorkBucketStage::Final`], including a custom stage declared before.
+    ///
+    /// Panics if `after` is not such a stage, or if [`CustomStage::MAX`] stages are already
+    /// declared.
+    pub fn add_custom_stage(&mut self, after: WorkBucketStage) -> WorkBucketStage {
+        let index = self.custom_stages.len();
+        assert!(
+            index < CustomStage::MAX,
+            "At most {} custom stages can be declared",
+            CustomStage::MAX
+        );
+        assert!(
+            after.can_precede_custom_stage(index),
+            "Cannot place a custom stage after {:?}",
+            after
+        );
+        self.custom_stages.push(after);
+        WorkBucketStage::Custom(CustomStage(index as u8))
+    }
+
     /// Build an MMTk instance from the builder.
+    ///
+    /// Panics if some options cannot be used together.  See [`Options::validate_combination`].
     pub fn build<VM: VMBinding>(&self) -> MMTK<VM> {
-        MMTK::new(Arc::new(self.options.clone()))
+        if let Err(reason) = self.options.validate_combination() {
+            panic!("The options cannot be used together: {reason}");
+        }
+        MMTK::new(
+            Arc::new(self.options.clone()),
+            &self.reference_kinds,
+            &self.custom_stages,
+        )
     }
 }
 
@@ -116,8 +173,10 @@ pub struct MMTK<VM: VMBinding> {
     pub(crate) state: Arc<GlobalState>,
     pub(crate) plan: UnsafeCell<Box<dyn Plan<VM = VM>>>,
     pub(crate) reference_processors: ReferenceProcessors,
+    pub(crate) ephemeron_processor: EphemeronProcessor,
     pub(crate) finalizable_processor:
-        Mutex<FinalizableProcessor<<VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType>>,
+        FinalizableProcessor<<VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType>,
+    pub(crate) handle_table: HandleTable,
     pub(crate) scheduler: Arc<GCWorkScheduler<VM>>,
     #[cfg(feature = "sanity")]
     pub(crate) sanity_checker: Mutex<SanityChecker<VM::VMSlot>>,
@@ -125,6 +184,8 @@ pub struct MMTK<VM: VMBinding> {
     pub(crate) slot_logger: SlotLogger<VM::VMSlot>,
     pub(crate) gc_trigger: Arc<GCTrigger<VM>>,
     pub(crate) stats: Arc<Stats>,
+    /// The queries of [`crate::memory_manager::explain_liveness`].
+    pub(crate) liveness_queries: Mutex<LivenessQueries<VM::VMSlot>>,
     #[cfg(feature = "sanity")]
     inside_sanity: AtomicBool,
     /// Analysis counters. The feature analysis allows us to periodically stop the world and collect some statistics.
@@ -137,7 +198,11 @@ unsafe impl<VM: VMBinding> Send for MMTK<VM> {}
 
 impl<VM: VMBinding> MMTK<VM> {
     /// Create an MMTK instance. This is not public. Bindings should use [`MMTKBuilder::build`].
-    pub(crate) fn new(options: Arc<Options>) -> Self {
+    pub(crate) fn new(
+        options: Arc<Options>,
+        reference_kinds: &[ReferenceKindSpec],
+        custom_stages: &[WorkBucketStage],
+    ) -> Self {
         // Verify the Mmapper can handle the required address space size.
         vm_layout().validate_address_space();
 
@@ -146,15 +211,37 @@ impl<VM: VMBinding> MMTK<VM> {
         crate::policy::sft_map::SFTRefStorage::pre_use_check();
         SFT_MAP.initialize_once(&create_sft_map);
 
-        let num_workers = if cfg!(feature = "single_worker") {
-            1
+        let (num_workers, max_workers) =
+            if cfg!(feature = "single_worker") || *options.deterministic_gc_work {
+                (1, 1)
+            } else {
+                (
+                    *options.threads,
+                    usize::max(*options.threads, *options.max_threads),
+                )
+            };
+
+        let affinity = if *options.numa_aware && *options.thread_affinity == AffinityKind::OsDefault
+        {
+            AffinityKind::NumaNodes
         } else {
-            *options.threads
+            (*options.thread_affinity).clone()
         };
+        let scheduler = GCWorkScheduler::new(
+            num_workers,
+            max_workers,
+            affinity,
+            *options.numa_aware,
+            *options.mutator_driven_gc,
+            custom_stages,
+            ConcurrentWorkBudget::new(&options),
+        );
 
-        let scheduler = GCWorkScheduler::new(num_workers, (*options.thread_affinity).clone());
-
-        let state = Arc::new(GlobalState::default());
+        let state = Arc::new(GlobalState {
+            stress: GCStress::new(&options),
+            pacer: MarkingPacer::new(&options),
+            ..Default::default()
+        });
 
         let gc_trigger = Arc::new(GCTrigger::new(
             options.clone(),
@@ -224,10 +311,12 @@ impl<VM: VMBinding> MMTK<VM> {
             options,
             state,
             plan: UnsafeCell::new(plan),
-            reference_processors: ReferenceProcessors::new(),
-            finalizable_processor: Mutex::new(FinalizableProcessor::<
+            reference_processors: ReferenceProcessors::new(reference_kinds),
+            ephemeron_processor: EphemeronProcessor::new(),
+            finalizable_processor: FinalizableProcessor::<
                 <VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType,
-            >::new()),
+            >::new(),
+            handle_table: HandleTable::new(),
             scheduler,
             #[cfg(feature = "sanity")]
             sanity_checker: Mutex::new(SanityChecker::new()),
@@ -239,6 +328,7 @@ impl<VM: VMBinding> MMTK<VM> {
             analysis_manager: Arc::new(AnalysisManager::new(stats.clone())),
             gc_trigger,
             stats,
+            liveness_queries: Mutex::new(LivenessQueries::new()),
         }
     }
 
@@ -443,14 +533,31 @@ impl<VM: VMBinding> MMTK<VM> {
             .gc_trigger
             .handle_user_collection_request(force, exhaustive)
         {
-            use crate::vm::Collection;
-            VM::VMCollection::block_for_gc(tls);
+            self.gc_trigger.wait_for_gc(tls);
             true
         } else {
             false
         }
     }
 
+    /// Trigger a GC and find a path from a root to `object` before the GC starts.  See
+    /// [`crate::memory_manager::explain_liveness`].
+    pub fn explain_liveness(
+        &self,
+        tls: VMMutatorThread,
+        object: crate::util::ObjectReference,
+    ) -> Option<PathToRoot<VM::VMSlot>> {
+        let id = self.liveness_queries.lock().unwrap().add(object);
+        loop {
+            self.handle_user_collection_request(tls, true, true);
+            // The query may be added after the roots are scanned in a GC that is already in
+            // progress.  It is answered in the next GC.
+            if let Some(path) = self.liveness_queries.lock().unwrap().take_answer(id) {
+                return path;
+            }
+        }
+    }
+
     /// MMTK has requested stop-the-world activity (e.g., stw within a concurrent gc).
     #[allow(unused)]
     pub fn trigger_internal_collection_request(&self) {
@@ -117,7 +117,7 @@ impl<VM: VMBinding> Plan for Compressor<VM> {
         // Reference processing
         if !*self.base().options.no_reference_types {
             use crate::util::reference_processor::{
-                PhantomRefProcessing, SoftRefProcessing, WeakRefProcessing,
+                FinalRefProcessing, PhantomRefProcessing, SoftRefProcessing, WeakRefProcessing,
             };
             scheduler.work_buckets[WorkBucketStage::SoftRefClosure]
                 .add(SoftRefProcessing::<MarkingTrace<VM>>::new());
@@ -125,6 +125,11 @@ impl<VM: VMBinding> Plan for Compressor<VM> {
                 .add(WeakRefProcessing::<VM>::new());
             scheduler.work_buckets[WorkBucketStage::PhantomRefClosure]
                 .add(PhantomRefProcessing::<VM>::new());
+            if *self.base().options.no_finalizer {
+                // Otherwise, the `Final` reference kinds are processed after finalization.
+                scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(FinalRefProcessing::<VM>::new());
+            }
 
             use crate::util::reference_processor::RefForwarding;
             scheduler.work_buckets[WorkBucketStage::RefForwarding]
@@ -134,6 +139,15 @@ impl<VM: VMBinding> Plan for Compressor<VM> {
             scheduler.work_buckets[WorkBucketStage::Release].add(RefEnqueue::<VM>::new());
         }
 
+        // Ephemeron processing
+        {
+            use crate::util::ephemeron_processor::{EphemeronForwarding, EphemeronProcessing};
+            scheduler.work_buckets[WorkBucketStage::EphemeronClosure]
+                .set_sentinel(Box::new(EphemeronProcessing::<MarkingTrace<VM>>::new()));
+            scheduler.work_buckets[WorkBucketStage::RefForwarding]
+                .add(EphemeronForwarding::<ForwardingTrace<VM>>::new());
+        }
+
         // Finalization
         if !*self.base().options.no_finalizer {
             use crate::util::finalizable_processor::{Finalization, ForwardFinalization};
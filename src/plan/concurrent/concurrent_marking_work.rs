@@ -6,7 +6,7 @@ use crate::policy::gc_work::TraceKind;
 use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
 use crate::util::{scanning_helper, ObjectReference};
 use crate::vm::slot::Slot;
-use crate::vm::{RootsKind, RootsWorkFactory, VMBinding};
+use crate::vm::{ObjectModel, RootsKind, RootsWorkFactory, VMBinding};
 use crate::MMTK;
 
 use std::collections::VecDeque;
@@ -50,6 +50,9 @@ impl<VM: VMBinding, P: ConcurrentPlan<VM = VM> + PlanTraceObject<VM>, const KIND
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
         let tls = worker.tls;
         let trace = PlanTrace::<P, KIND>::from_mmtk(mmtk);
+        let pacer = &mmtk.state.pacer;
+        let count_scan_work = pacer.is_enabled();
+        let mut scanned_bytes = 0;
 
         // These are initial objects.  They may not have been marked.
         let initial_objects = std::mem::take(&mut self.initial_objects);
@@ -82,6 +85,9 @@ impl<VM: VMBinding, P: ConcurrentPlan<VM = VM> + PlanTraceObject<VM>, const KIND
                 })
             });
             trace.post_scan_object(object);
+            if count_scan_work {
+                scanned_bytes += VM::VMObjectModel::get_current_size(object);
+            }
 
             if queue.len() >= Self::CONCURRENT_TRACE_OVERFLOW {
                 let offloaded_objects = queue.drain(..Self::SATB_BUFFER_SIZE).collect();
@@ -90,6 +96,10 @@ impl<VM: VMBinding, P: ConcurrentPlan<VM = VM> + PlanTraceObject<VM>, const KIND
             }
         }
 
+        if count_scan_work {
+            pacer.record_scan_work(scanned_bytes);
+        }
+
         probe!(
             mmtk,
             concurrent_trace_objects,
@@ -204,6 +214,7 @@ impl<VM: VMBinding, P: ConcurrentPlan<VM = VM> + PlanTraceObject<VM>, const KIND
 {
     fn create_process_roots_work(&mut self, slots: Vec<VM::VMSlot>) {
         probe!(mmtk, roots, RootsKind::NORMAL, slots.len());
+        self.mmtk.state.inc_roots(slots.len());
 
         self.debug_assert_initial_mark();
 
@@ -230,6 +241,7 @@ impl<VM: VMBinding, P: ConcurrentPlan<VM = VM> + PlanTraceObject<VM>, const KIND
 
     fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
         probe!(mmtk, roots, RootsKind::PINNING, nodes.len());
+        self.mmtk.state.inc_roots(nodes.len());
 
         self.debug_assert_initial_mark();
 
@@ -245,6 +257,7 @@ impl<VM: VMBinding, P: ConcurrentPlan<VM = VM> + PlanTraceObject<VM>, const KIND
 
     fn create_process_tpinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
         probe!(mmtk, roots, RootsKind::TPINNING, nodes.len());
+        self.mmtk.state.inc_roots(nodes.len());
 
         self.debug_assert_initial_mark();
 
@@ -20,6 +20,7 @@ use crate::scheduler::gc_work::Release;
 use crate::scheduler::gc_work::StopMutators;
 use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::constants::BYTES_IN_PAGE;
 use crate::util::copy::*;
 use crate::util::heap::gc_trigger::SpaceStats;
 use crate::util::heap::VMRequest;
@@ -91,8 +92,16 @@ impl<VM: VMBinding> Plan for ConcurrentImmix<VM> {
             return false;
         }
 
-        let threshold = self.get_total_pages() >> 1;
         let used_pages_after_last_gc = self.common.base.global_state.get_used_pages_after_last_gc();
+        let threshold = if self.common.base.global_state.pacer.is_enabled() {
+            let total_pages = self.get_total_pages();
+            self.common.base.global_state.pacer.trigger_pages(
+                total_pages,
+                total_pages.saturating_sub(used_pages_after_last_gc),
+            )
+        } else {
+            self.get_total_pages() >> 1
+        };
         let used_pages_now = self.get_used_pages();
         let allocated = used_pages_now.saturating_sub(used_pages_after_last_gc);
         if !concurrent_marking_in_progress && allocated > threshold {
@@ -242,6 +251,17 @@ impl<VM: VMBinding> Plan for ConcurrentImmix<VM> {
         let pause = self.current_pause().unwrap();
         if pause == Pause::InitialMark {
             self.set_concurrent_marking_state(true);
+            // The used pages after the previous GC estimate the live objects to mark.  The
+            // estimate is only unavailable if no GC has finished yet.
+            let used_pages = self.get_used_pages();
+            let live_pages = match self.base().global_state.get_used_pages_after_last_gc() {
+                0 => used_pages,
+                pages => pages,
+            };
+            self.base()
+                .global_state
+                .pacer
+                .on_marking_started(live_pages * BYTES_IN_PAGE, used_pages);
         }
         self.previous_pause.store(Some(pause), Ordering::SeqCst);
         self.current_pause.store(None, Ordering::SeqCst);
@@ -286,6 +306,10 @@ impl<VM: VMBinding> Plan for ConcurrentImmix<VM> {
         match pause {
             Pause::Full => {
                 self.set_concurrent_marking_state(false);
+                self.base()
+                    .global_state
+                    .pacer
+                    .on_marking_finished(self.get_used_pages());
             }
             Pause::InitialMark => {
                 debug_assert!(
@@ -301,6 +325,10 @@ impl<VM: VMBinding> Plan for ConcurrentImmix<VM> {
                     mutator.barrier.flush();
                 }
                 self.set_concurrent_marking_state(false);
+                self.base()
+                    .global_state
+                    .pacer
+                    .on_marking_finished(self.get_used_pages());
             }
         }
         info!("{:?} start", pause);
@@ -309,6 +337,31 @@ impl<VM: VMBinding> Plan for ConcurrentImmix<VM> {
     fn concurrent(&self) -> Option<&dyn ConcurrentPlan<VM = VM>> {
         Some(self)
     }
+
+    #[cfg(feature = "heap_verifier")]
+    fn verify_reference(
+        &self,
+        _source: crate::util::ObjectReference,
+        target: crate::util::ObjectReference,
+        point: crate::util::heap_verifier::VerificationPoint,
+    ) -> Option<String> {
+        use crate::util::heap_verifier::VerificationPoint;
+        // SATB: Every object reachable at the initial mark, or allocated during concurrent
+        // marking, is marked when the marking finishes.  An unmarked target in the immix space
+        // means the barrier failed to record an overwritten reference.
+        let marking_finished = matches!(self.current_pause(), Some(Pause::FinalMark | Pause::Full));
+        if point == VerificationPoint::AfterGC
+            && marking_finished
+            && self.immix_space.in_space(target)
+            && !self.immix_space.is_marked(target)
+        {
+            return Some(format!(
+                "SATB invariant violated: the target is not marked after {:?}",
+                self.current_pause().unwrap()
+            ));
+        }
+        None
+    }
 }
 
 impl<VM: VMBinding> ConcurrentImmix<VM> {
@@ -362,6 +415,7 @@ impl<VM: VMBinding> ConcurrentImmix<VM> {
         scheduler.work_buckets[WorkBucketStage::WeakRefClosure].set_enabled(do_closure);
         scheduler.work_buckets[WorkBucketStage::FinalRefClosure].set_enabled(do_closure);
         scheduler.work_buckets[WorkBucketStage::SoftRefClosure].set_enabled(do_closure);
+        scheduler.work_buckets[WorkBucketStage::EphemeronClosure].set_enabled(do_closure);
         scheduler.work_buckets[WorkBucketStage::PhantomRefClosure].set_enabled(do_closure);
     }
 
@@ -403,7 +457,7 @@ impl<VM: VMBinding> ConcurrentImmix<VM> {
         // Reference processing
         if !*self.base().options.no_reference_types {
             use crate::util::reference_processor::{
-                PhantomRefProcessing, SoftRefProcessing, WeakRefProcessing,
+                FinalRefProcessing, PhantomRefProcessing, SoftRefProcessing, WeakRefProcessing,
             };
             scheduler.work_buckets[WorkBucketStage::SoftRefClosure]
                 .add(SoftRefProcessing::<RefTracePolicy<VM>>::new());
@@ -411,11 +465,23 @@ impl<VM: VMBinding> ConcurrentImmix<VM> {
                 .add(WeakRefProcessing::<VM>::new());
             scheduler.work_buckets[WorkBucketStage::PhantomRefClosure]
                 .add(PhantomRefProcessing::<VM>::new());
+            if *self.base().options.no_finalizer {
+                // Otherwise, the `Final` reference kinds are processed after finalization.
+                scheduler.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(FinalRefProcessing::<VM>::new());
+            }
 
             use crate::util::reference_processor::RefEnqueue;
             scheduler.work_buckets[WorkBucketStage::Release].add(RefEnqueue::<VM>::new());
         }
 
+        // Ephemeron processing
+        {
+            use crate::util::ephemeron_processor::EphemeronProcessing;
+            scheduler.work_buckets[WorkBucketStage::EphemeronClosure]
+                .set_sentinel(Box::new(EphemeronProcessing::<RefTracePolicy<VM>>::new()));
+        }
+
         // Finalization
         if !*self.base().options.no_finalizer {
             use crate::util::finalizable_processor::Finalization;
@@ -1,6 +1,7 @@
 pub mod barrier;
 pub(super) mod concurrent_marking_work;
 pub(super) mod global;
+pub(crate) mod pacer;
 
 pub mod immix;
 
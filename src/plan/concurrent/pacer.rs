@@ -0,0 +1,344 @@
+//! Pacing of concurrent marking with mutator assists.
+//!
+//! With the option `concurrent_marking_assists`, mutators that allocate during concurrent marking
+//! pay for their allocation with marking work.  Each mutator has an [`AssistCredit`].  Allocating
+//! `n` bytes in the allocation slow path charges `n * assist_ratio` bytes of scan work to the
+//! mutator.  If the credit goes negative, the mutator first steals credit earned by the GC
+//! workers, and then executes packets of the `Concurrent` bucket until its debt is repaid.  Scan
+//! work done in excess of the debt becomes credit for later allocations.
+//!
+//! The assist ratio is the remaining marking work divided by the remaining heap runway, so
+//! mutators only assist when GC workers fall behind and the heap is about to fill up before
+//! marking finishes.  The pacer also records how much the mutators allocate during each marking
+//! cycle, and uses it to start the next cycle early enough for marking to finish before the heap
+//! is full.
+//!
+//! With the option `incremental_marking`, GC workers do not mark between the pauses at all.
+//! Instead, mutators do marking in slices bounded by time and work, at every allocation slow path
+//! and every call to [`crate::memory_manager::gc_poll`] during marking.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+use atomic::Atomic;
+
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::options::Options;
+
+thread_local! {
+    /// The scan work done by the current thread while it assists marking, or `None` if the
+    /// current thread is not assisting.
+    static ASSIST_WORK: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// The pacer of concurrent marking.
+#[derive(Default)]
+pub(crate) struct MarkingPacer {
+    /// Whether the option `concurrent_marking_assists` or `incremental_marking` is set.
+    enabled: bool,
+    /// Whether the option `incremental_marking` is set.
+    incremental: bool,
+    /// Whether concurrent marking is in progress.
+    marking: AtomicBool,
+    /// The number of marking cycles started so far.  Credit earned in a previous cycle is dropped.
+    cycle: AtomicUsize,
+    /// The estimated number of bytes to scan in the current cycle.
+    expected_scan_bytes: AtomicUsize,
+    /// The number of bytes scanned in the current cycle by GC workers and mutators.
+    scanned_bytes: AtomicUsize,
+    /// Scan work done by GC workers that mutators have not yet stolen.
+    background_credit: AtomicIsize,
+    /// The number of used pages when the current cycle started.
+    used_pages_at_start: AtomicUsize,
+    /// The moving average of the pages allocated during previous marking cycles.  Zero if no
+    /// cycle has finished yet.
+    marking_allocation_pages: Atomic<f64>,
+}
+
+impl MarkingPacer {
+    /// The weight of the latest cycle in `marking_allocation_pages`.
+    const ALLOCATION_AVERAGE_WEIGHT: f64 = 0.5;
+    /// Start marking when the heap runway is this many times the expected allocation during
+    /// marking.
+    const TRIGGER_SAFETY_FACTOR: f64 = 1.5;
+    /// The earliest trigger point, as a fraction of the heap headroom after the last GC.  It stops
+    /// the pacer from starting marking right after a GC if the average allocation is large.
+    const MIN_TRIGGER_FRACTION: f64 = 0.125;
+    /// The latest trigger point, as a fraction of the heap headroom after the last GC.  It stops
+    /// the pacer from starting marking so late that the heap is full first if little was allocated
+    /// during previous cycles.
+    const MAX_TRIGGER_FRACTION: f64 = 0.875;
+
+    pub fn new(options: &Options) -> Self {
+        Self {
+            enabled: *options.concurrent_marking_assists || *options.incremental_marking,
+            incremental: *options.incremental_marking,
+            ..Default::default()
+        }
+    }
+
+    /// Is pacing enabled?  If not, marking work is not counted, and mutators never assist.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Is concurrent marking in progress with pacing enabled?
+    pub fn is_marking(&self) -> bool {
+        self.marking.load(Ordering::Relaxed)
+    }
+
+    /// Is incremental marking enabled?
+    pub fn is_incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// Is marking in progress, and done by mutators only?  If so, GC workers leave the work
+    /// packets of the `Concurrent` bucket to mutators.
+    pub fn is_marking_on_mutators(&self) -> bool {
+        self.incremental && self.is_marking()
+    }
+
+    /// Called at the end of the initial mark pause.  `live_bytes` estimates the marking work.
+    pub fn on_marking_started(&self, live_bytes: usize, used_pages: usize) {
+        if !self.enabled {
+            return;
+        }
+        self.cycle.fetch_add(1, Ordering::Relaxed);
+        self.expected_scan_bytes
+            .store(live_bytes, Ordering::Relaxed);
+        self.scanned_bytes.store(0, Ordering::Relaxed);
+        self.background_credit.store(0, Ordering::Relaxed);
+        self.used_pages_at_start
+            .store(used_pages, Ordering::Relaxed);
+        self.marking.store(true, Ordering::SeqCst);
+    }
+
+    /// Called when mutators are stopped for the final mark pause, or for a full GC that ends
+    /// concurrent marking.
+    pub fn on_marking_finished(&self, used_pages: usize) {
+        if !self.marking.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        let allocated =
+            used_pages.saturating_sub(self.used_pages_at_start.load(Ordering::Relaxed)) as f64;
+        let average = self.marking_allocation_pages.load(Ordering::Relaxed);
+        let average = if average == 0.0 {
+            allocated
+        } else {
+            average + (allocated - average) * Self::ALLOCATION_AVERAGE_WEIGHT
+        };
+        self.marking_allocation_pages
+            .store(average, Ordering::Relaxed);
+        debug!(
+            "Marking finished.  Scanned {} of {} expected bytes.  Allocated {} pages during marking (average {:.1}).",
+            self.scanned_bytes.load(Ordering::Relaxed),
+            self.expected_scan_bytes.load(Ordering::Relaxed),
+            allocated,
+            average,
+        );
+    }
+
+    /// The number of pages that mutators may allocate after the last GC before the next marking
+    /// cycle starts.  `headroom_pages` is the number of free pages after the last GC.  Without the
+    /// allocation history of a previous cycle, marking starts when half of the heap is allocated.
+    pub fn trigger_pages(&self, total_pages: usize, headroom_pages: usize) -> usize {
+        let average = self.marking_allocation_pages.load(Ordering::Relaxed);
+        if average == 0.0 {
+            return total_pages >> 1;
+        }
+        let runway = average * Self::TRIGGER_SAFETY_FACTOR;
+        let headroom = headroom_pages as f64;
+        (headroom - runway).clamp(
+            headroom * Self::MIN_TRIGGER_FRACTION,
+            headroom * Self::MAX_TRIGGER_FRACTION,
+        ) as usize
+    }
+
+    /// The number of bytes of scan work that each allocated byte is charged.
+    fn assist_ratio(&self, used_pages: usize, total_pages: usize) -> f64 {
+        let expected = self.expected_scan_bytes.load(Ordering::Relaxed);
+        let scanned = self.scanned_bytes.load(Ordering::Relaxed);
+        let remaining_work = expected.saturating_sub(scanned);
+        if remaining_work == 0 {
+            return 0.0;
+        }
+        // Leave at least one page of runway so that the ratio stays finite.
+        let runway_pages = total_pages.saturating_sub(used_pages).max(1);
+        remaining_work as f64 / (runway_pages * BYTES_IN_PAGE) as f64
+    }
+
+    /// Charge `allocated` bytes to the `credit` of a mutator.  Return the scan work the mutator
+    /// needs to do to repay its debt, or zero if the mutator is not in debt.
+    pub fn charge(
+        &self,
+        credit: &AssistCredit,
+        allocated: usize,
+        used_pages: usize,
+        total_pages: usize,
+    ) -> usize {
+        let cycle = self.cycle.load(Ordering::Relaxed);
+        if credit.cycle.swap(cycle, Ordering::Relaxed) != cycle {
+            credit.credit.store(0, Ordering::Relaxed);
+        }
+        let cost = (allocated as f64 * self.assist_ratio(used_pages, total_pages)).ceil() as isize;
+        let mut balance = credit.credit.load(Ordering::Relaxed) - cost;
+        if balance < 0 {
+            balance += self.steal_background_credit(-balance);
+        }
+        credit.credit.store(balance, Ordering::Relaxed);
+        if balance < 0 {
+            -balance as usize
+        } else {
+            0
+        }
+    }
+
+    /// Take at most `wanted` bytes of credit from the GC workers.  Return the credit taken.
+    fn steal_background_credit(&self, wanted: isize) -> isize {
+        let result = self.background_credit.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |available| (available > 0).then(|| available - available.min(wanted)),
+        );
+        match result {
+            Ok(available) => available.min(wanted),
+            Err(_) => 0,
+        }
+    }
+
+    /// Called by a mutator after assisting marking.  `work` is the scan work it has done.  If
+    /// `out_of_work` is true, the mutator stopped because there was no marking work left, and its
+    /// remaining debt is forgiven.
+    pub fn repay(&self, credit: &AssistCredit, work: usize, out_of_work: bool) {
+        let balance = credit.credit.load(Ordering::Relaxed) + work as isize;
+        let balance = if out_of_work { balance.max(0) } else { balance };
+        credit.credit.store(balance, Ordering::Relaxed);
+    }
+
+    /// Record `bytes` of scan work done by the current thread.  It is credited to the current
+    /// thread if it is assisting marking, or to the GC workers otherwise.
+    pub fn record_scan_work(&self, bytes: usize) {
+        self.scanned_bytes.fetch_add(bytes, Ordering::Relaxed);
+        let assisting = ASSIST_WORK.with(|work| match work.get() {
+            Some(done) => {
+                work.set(Some(done + bytes));
+                true
+            }
+            None => false,
+        });
+        if !assisting {
+            self.background_credit
+                .fetch_add(bytes as isize, Ordering::Relaxed);
+        }
+    }
+
+    /// Start counting the scan work of the current thread as assist work.
+    pub fn begin_assist(&self) {
+        ASSIST_WORK.with(|work| {
+            debug_assert!(work.get().is_none(), "Nested marking assists");
+            work.set(Some(0));
+        });
+    }
+
+    /// The assist work done by the current thread since [`Self::begin_assist`].
+    pub fn assist_work_done(&self) -> usize {
+        ASSIST_WORK.with(|work| work.get().unwrap_or(0))
+    }
+
+    /// Stop counting the assist work of the current thread.  Return the work done.
+    pub fn end_assist(&self) -> usize {
+        ASSIST_WORK.with(|work| work.take().unwrap_or(0))
+    }
+}
+
+/// The assist credit of a mutator, in bytes of scan work.  It is only accessed by the mutator
+/// thread.
+#[derive(Default)]
+pub(crate) struct AssistCredit {
+    /// Scan work done in excess of the allocation charged, or the debt if negative.
+    credit: AtomicIsize,
+    /// The marking cycle that `credit` belongs to.
+    cycle: AtomicUsize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled_pacer() -> MarkingPacer {
+        let mut options = Options::default();
+        assert!(options.set_from_string("concurrent_marking_assists", "true"));
+        MarkingPacer::new(&options)
+    }
+
+    #[test]
+    fn assists_repay_debt_after_background_credit() {
+        let pacer = enabled_pacer();
+        let credit = AssistCredit::default();
+        // 100 pages to scan and 100 pages of runway: one byte of work per allocated byte.
+        pacer.on_marking_started(100 * BYTES_IN_PAGE, 100);
+        assert!(pacer.is_marking());
+        pacer.record_scan_work(10);
+        // The first 10 bytes of debt are paid by the GC workers.
+        assert_eq!(pacer.charge(&credit, 30, 100, 200), 20);
+
+        pacer.begin_assist();
+        pacer.record_scan_work(25);
+        assert_eq!(pacer.assist_work_done(), 25);
+        pacer.repay(&credit, pacer.end_assist(), false);
+        // 5 bytes of credit are left for the next allocation.
+        assert_eq!(pacer.charge(&credit, 5, 100, 200), 0);
+        assert_eq!(pacer.charge(&credit, 1, 100, 200), 1);
+
+        // Debt is forgiven if there is no work left, and dropped when the next cycle starts.
+        pacer.repay(&credit, 0, true);
+        assert_eq!(pacer.charge(&credit, 0, 100, 200), 0);
+        pacer.on_marking_finished(150);
+        assert!(!pacer.is_marking());
+    }
+
+    #[test]
+    fn trigger_follows_marking_allocation() {
+        let pacer = enabled_pacer();
+        assert_eq!(pacer.trigger_pages(1000, 900), 500);
+        pacer.on_marking_started(BYTES_IN_PAGE, 100);
+        pacer.on_marking_finished(300);
+        // 200 pages were allocated during marking.  Start marking 300 pages before the heap is
+        // full.
+        assert_eq!(pacer.trigger_pages(1000, 900), 600);
+        pacer.on_marking_started(BYTES_IN_PAGE, 100);
+        pacer.on_marking_finished(1000);
+        // The average is now 550 pages, but marking does not start right after a GC.
+        assert_eq!(pacer.trigger_pages(1000, 800), 100);
+    }
+
+    #[test]
+    fn trigger_leaves_room_before_heap_is_full() {
+        let pacer = enabled_pacer();
+        pacer.on_marking_started(BYTES_IN_PAGE, 100);
+        pacer.on_marking_finished(101);
+        // Only one page was allocated during marking, but marking still starts before the last
+        // eighth of the headroom is allocated.
+        assert_eq!(pacer.trigger_pages(1000, 800), 700);
+    }
+
+    #[test]
+    fn incremental_marking_is_on_mutators() {
+        let mut options = Options::default();
+        assert!(options.set_from_string("incremental_marking", "true"));
+        let pacer = MarkingPacer::new(&options);
+        assert!(pacer.is_enabled());
+        assert!(!pacer.is_marking_on_mutators());
+        pacer.on_marking_started(BYTES_IN_PAGE, 100);
+        assert!(pacer.is_marking_on_mutators());
+        pacer.on_marking_finished(100);
+        assert!(!pacer.is_marking_on_mutators());
+    }
+
+    #[test]
+    fn disabled_pacer_never_marks() {
+        let pacer = MarkingPacer::new(&Options::default());
+        pacer.on_marking_started(BYTES_IN_PAGE, 100);
+        assert!(!pacer.is_marking());
+    }
+}
@@ -347,6 +347,20 @@ pub trait Plan: 'static + HasSpaces + Sync + Downcast {
         true
     }
 
+    /// The heap verifier found a reference from `source` to `target` at `point`, and the target
+    /// is a valid object.  A plan can implement this to check plan specific invariants, such as
+    /// the SATB invariant of a concurrent plan.  Return a description of the violated invariant,
+    /// or `None` if the reference is valid.
+    #[cfg(feature = "heap_verifier")]
+    fn verify_reference(
+        &self,
+        _source: ObjectReference,
+        _target: ObjectReference,
+        _point: crate::util::heap_verifier::VerificationPoint,
+    ) -> Option<String> {
+        None
+    }
+
     /// Call `space.verify_side_metadata_sanity` for all spaces in this plan.
     fn verify_side_metadata_sanity(&self) {
         let mut side_metadata_sanity_checker = SideMetadataSanity::new();
@@ -683,6 +697,9 @@ impl<VM: VMBinding> BasePlan<VM> {
             self.global_state
                 .allocation_bytes
                 .store(0, Ordering::SeqCst);
+            self.global_state
+                .stress
+                .schedule_next_stress_gc(*self.options.stress_factor);
         }
 
         debug!(
@@ -54,6 +54,9 @@ impl<VM: VMBinding> GCWork<VM> for UpdateReferences<VM> {
         mmtk.scheduler
             .worker_group
             .get_and_clear_worker_live_bytes();
+        mmtk.scheduler
+            .worker_group
+            .get_and_clear_worker_live_bytes_per_accounting_context();
 
         for mutator in VM::VMActivePlan::mutators() {
             mmtk.scheduler.work_buckets[WorkBucketStage::SecondRoots].add(ScanMutatorRoots::<
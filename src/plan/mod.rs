@@ -45,7 +45,7 @@ mod generational;
 mod sticky;
 
 mod compressor;
-mod concurrent;
+pub(crate) mod concurrent;
 mod immix;
 mod markcompact;
 mod marksweep;
@@ -4,7 +4,8 @@ use crate::plan::barriers::Barrier;
 use crate::plan::global::Plan;
 use crate::plan::AllocationSemantics;
 use crate::policy::space::Space;
-use crate::util::alloc::allocator::AllocationOptions;
+use crate::util::accounting_context::AccountingContext;
+use crate::util::alloc::allocator::{AllocationOptions, AllocatorContext};
 use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
 use crate::util::alloc::Allocator;
 use crate::util::{Address, ObjectReference};
@@ -292,6 +293,23 @@ impl<VM: VMBinding> Mutator<VM> {
         }
     }
 
+    /// Set the accounting context of this mutator. All the allocators of a mutator share the same
+    /// context. See [`crate::util::accounting_context`].
+    pub fn set_accounting_context(&self, context: AccountingContext) {
+        self.allocator_context().set_accounting_context(context)
+    }
+
+    /// Get the accounting context of this mutator.
+    pub fn get_accounting_context(&self) -> AccountingContext {
+        self.allocator_context().get_accounting_context()
+    }
+
+    /// Get the allocator context shared by all the allocators of this mutator.
+    fn allocator_context(&self) -> &AllocatorContext<VM> {
+        let selector = self.config.allocator_mapping[AllocationSemantics::Default];
+        unsafe { self.allocators.get_allocator(selector) }.get_context()
+    }
+
     /// Get the allocator for the selector.
     ///
     /// # Safety
@@ -5,7 +5,9 @@ use crate::{
         tracing::{gc_work::DefaultObjectTracerContext, SlotOfTrace, Trace},
         VectorObjectQueue, VectorQueue,
     },
-    scheduler::{GCWork, GCWorker, GCWorkerShared, WorkBucketStage},
+    scheduler::{
+        GCWork, GCWorker, GCWorkerShared, WorkBucketStage, WorkPriority, EDGES_WORK_BUFFER_SIZE,
+    },
     util::{ObjectReference, VMWorkerThread},
     vm::{slot::Slot, ObjectTracerContext, Scanning, VMBinding},
     MMTK,
@@ -82,6 +84,33 @@ impl<T: Trace> GCWork<T::VM> for ProcessSlots<T> {
 
         self.flush(worker, queue);
     }
+
+    fn priority(&self) -> WorkPriority {
+        priority_by_length(self.slots.len())
+    }
+
+    fn estimated_cost(&self) -> Option<usize> {
+        Some(self.slots.len())
+    }
+
+    fn split(&mut self) -> Option<Box<dyn GCWork<T::VM>>> {
+        let slots = split_half(&mut self.slots)?;
+        Some(Box::new(Self::new(slots, self.bucket)))
+    }
+}
+
+/// Packets larger than the usual buffer size, such as large lists of roots, start first.
+fn priority_by_length(len: usize) -> WorkPriority {
+    if len > EDGES_WORK_BUFFER_SIZE {
+        WorkPriority::High
+    } else {
+        WorkPriority::Normal
+    }
+}
+
+/// Split off the second half of `items`, or return `None` if there are fewer than two items.
+pub(super) fn split_half<T>(items: &mut Vec<T>) -> Option<Vec<T>> {
+    (items.len() >= 2).then(|| items.split_off(items.len() / 2))
 }
 
 /// A work packet for scanning objects and optionally do node-enqueuing tracing during a
@@ -139,6 +168,16 @@ impl<T: Trace> ProcessNodes<T> {
             for object in self.objects.iter().copied() {
                 GCWorkerShared::<T::VM>::increase_live_bytes(&mut live_bytes_stats, object);
             }
+            if *worker.mmtk.get_options().accounting_contexts {
+                let mut live_bytes_per_context =
+                    worker.shared.live_bytes_per_accounting_context.borrow_mut();
+                for object in self.objects.iter().copied() {
+                    GCWorkerShared::<T::VM>::increase_live_bytes_for_accounting_context(
+                        &mut live_bytes_per_context,
+                        object,
+                    );
+                }
+            }
         }
 
         for object in self.objects.iter().copied() {
@@ -217,4 +256,75 @@ impl<T: Trace> GCWork<T::VM> for ProcessNodes<T> {
 
         trace!("ScanObjects End");
     }
+
+    fn priority(&self) -> WorkPriority {
+        priority_by_length(self.objects.len())
+    }
+
+    fn estimated_cost(&self) -> Option<usize> {
+        Some(self.objects.len())
+    }
+
+    fn split(&mut self) -> Option<Box<dyn GCWork<T::VM>>> {
+        let objects = split_half(&mut self.objects)?;
+        Some(Box::new(Self::new(objects, self.bucket)))
+    }
+}
+
+#[cfg(all(test, feature = "mock_test"))]
+mod tests {
+    use super::*;
+    use crate::plan::tracing::UnsupportedTrace;
+    use crate::util::test_util::mock_vm::MockVM;
+    use crate::util::Address;
+
+    type MockTrace = UnsupportedTrace<MockVM>;
+
+    fn addresses(n: usize) -> impl Iterator<Item = Address> {
+        (1..=n).map(|i| unsafe { Address::from_usize(i * 16) })
+    }
+
+    #[test]
+    fn split_process_slots() {
+        let mut work =
+            ProcessSlots::<MockTrace>::new(addresses(5).collect(), WorkBucketStage::Closure);
+        let part = work.split().unwrap();
+        assert_eq!(work.estimated_cost(), Some(2));
+        assert_eq!(part.estimated_cost(), Some(3));
+        assert_eq!(work.slots, addresses(2).collect::<Vec<_>>());
+
+        let part = work.split().unwrap();
+        assert_eq!(part.estimated_cost(), Some(1));
+        assert!(work.split().is_none());
+        assert_eq!(work.estimated_cost(), Some(1));
+    }
+
+    #[test]
+    fn split_process_nodes() {
+        let objects = addresses(4)
+            .map(|address| ObjectReference::from_raw_address(address).unwrap())
+            .collect::<Vec<_>>();
+        let mut work = ProcessNodes::<MockTrace>::new(objects.clone(), WorkBucketStage::Closure);
+        let part = work.split().unwrap();
+        assert_eq!(work.objects, objects[..2]);
+        assert_eq!(part.estimated_cost(), Some(2));
+
+        let mut empty = ProcessNodes::<MockTrace>::new(vec![], WorkBucketStage::Closure);
+        assert!(empty.split().is_none());
+    }
+
+    #[test]
+    fn long_packets_have_high_priority() {
+        let slots = |n| addresses(n).collect::<Vec<_>>();
+        let work =
+            ProcessSlots::<MockTrace>::new(slots(EDGES_WORK_BUFFER_SIZE), WorkBucketStage::Closure);
+        assert_eq!(work.priority(), WorkPriority::Normal);
+        let mut work = ProcessSlots::<MockTrace>::new(
+            slots(EDGES_WORK_BUFFER_SIZE + 2),
+            WorkBucketStage::Closure,
+        );
+        assert_eq!(work.priority(), WorkPriority::High);
+        work.split().unwrap();
+        assert_eq!(work.priority(), WorkPriority::Normal);
+    }
 }
@@ -3,14 +3,17 @@ use std::marker::PhantomData;
 use crate::{
     plan::{
         tracing::{
-            gc_work::closure::{ProcessNodes, ProcessSlots},
+            gc_work::closure::{split_half, ProcessNodes, ProcessSlots},
             Trace,
         },
         VectorObjectQueue,
     },
-    scheduler::{GCWork, GCWorker, WorkBucketStage},
+    scheduler::{GCWork, GCWorker, WorkBucketStage, WorkPriority},
     util::ObjectReference,
-    vm::{RootsKind, RootsWorkFactory, VMBinding},
+    vm::{
+        slot::{SimpleSlot, Slot},
+        RootsKind, RootsWorkFactory, VMBinding,
+    },
     MMTK,
 };
 
@@ -52,6 +55,7 @@ impl<VM: VMBinding, DT: Trace<VM = VM>, PT: Trace<VM = VM>> RootsWorkFactory<VM:
         // and our `capture.bt` mentions all of them, `bpftrace` may complain that it cannot find
         // one or more of those USDT trace points in the binary.
         probe!(mmtk, roots, RootsKind::NORMAL, slots.len());
+        self.mmtk.state.inc_roots(slots.len());
 
         #[cfg(feature = "sanity")]
         self.mmtk
@@ -69,6 +73,7 @@ impl<VM: VMBinding, DT: Trace<VM = VM>, PT: Trace<VM = VM>> RootsWorkFactory<VM:
 
     fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
         probe!(mmtk, roots, RootsKind::PINNING, nodes.len());
+        self.mmtk.state.inc_roots(nodes.len());
 
         #[cfg(feature = "sanity")]
         self.mmtk
@@ -88,6 +93,7 @@ impl<VM: VMBinding, DT: Trace<VM = VM>, PT: Trace<VM = VM>> RootsWorkFactory<VM:
 
     fn create_process_tpinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
         probe!(mmtk, roots, RootsKind::TPINNING, nodes.len());
+        self.mmtk.state.inc_roots(nodes.len());
 
         #[cfg(feature = "sanity")]
         self.mmtk
@@ -102,6 +108,18 @@ impl<VM: VMBinding, DT: Trace<VM = VM>, PT: Trace<VM = VM>> RootsWorkFactory<VM:
             ProcessPinningRoots::<VM, PT, PT>::new(nodes, WorkBucketStage::TPinningClosure),
         );
     }
+
+    fn create_process_handle_roots_work(&mut self, slots: Vec<SimpleSlot>) {
+        probe!(mmtk, roots, RootsKind::NORMAL, slots.len());
+        self.mmtk.state.inc_roots(slots.len());
+
+        // The sanity checker reads strong handles from the handle table directly.
+        crate::memory_manager::add_work_packet(
+            self.mmtk,
+            WorkBucketStage::Closure,
+            ProcessHandleRoots::<DT>::new(slots, WorkBucketStage::Closure),
+        );
+    }
 }
 
 impl<VM: VMBinding, DT: Trace<VM = VM>, PT: Trace<VM = VM>> DefaultRootsWorkFactory<VM, DT, PT> {
@@ -199,4 +217,73 @@ impl<VM: VMBinding, R2OT: Trace<VM = VM>, O2OT: Trace<VM = VM>> GCWork<VM>
 
         trace!("ProcessPinningRoots End");
     }
+
+    fn priority(&self) -> WorkPriority {
+        WorkPriority::High
+    }
+
+    fn estimated_cost(&self) -> Option<usize> {
+        Some(self.roots.len())
+    }
+
+    fn split(&mut self) -> Option<Box<dyn GCWork<VM>>> {
+        let roots = split_half(&mut self.roots)?;
+        Some(Box::new(Self::new(roots, self.bucket)))
+    }
+}
+
+/// This work packet processes strong handles in the [`HandleTable`] as roots during stop-the-world
+/// tracing GC.  Unlike roots reported by the VM binding, the slots of handles are always
+/// [`SimpleSlot`], so they are processed separately from [`ProcessSlots`].
+///
+/// [`HandleTable`]: crate::util::handle_table::HandleTable
+pub(crate) struct ProcessHandleRoots<T: Trace> {
+    phantom: PhantomData<T>,
+    slots: Vec<SimpleSlot>,
+    bucket: WorkBucketStage,
+}
+
+impl<T: Trace> ProcessHandleRoots<T> {
+    pub fn new(slots: Vec<SimpleSlot>, bucket: WorkBucketStage) -> Self {
+        Self {
+            phantom: PhantomData,
+            slots,
+            bucket,
+        }
+    }
+}
+
+impl<T: Trace> GCWork<T::VM> for ProcessHandleRoots<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        trace!("ProcessHandleRoots");
+
+        let root_objects_to_scan = {
+            let mut queue = VectorObjectQueue::new();
+
+            let trace = T::from_mmtk(mmtk);
+
+            for slot in self.slots.iter() {
+                let Some(object) = slot.load() else {
+                    continue;
+                };
+                let new_object = trace.trace_object(worker, object, &mut queue);
+                if T::may_move_objects() && new_object != object {
+                    slot.store(new_object);
+                }
+            }
+
+            queue.take()
+        };
+
+        if !root_objects_to_scan.is_empty() {
+            let work = ProcessNodes::<T>::new(root_objects_to_scan, self.bucket);
+            worker.add_work(self.bucket, work);
+        }
+
+        trace!("ProcessHandleRoots End");
+    }
+
+    fn priority(&self) -> WorkPriority {
+        WorkPriority::High
+    }
 }
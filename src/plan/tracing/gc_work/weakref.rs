@@ -3,6 +3,7 @@ use std::marker::PhantomData;
 use crate::{
     plan::tracing::{gc_work::DefaultObjectTracerContext, Trace},
     scheduler::{GCWork, GCWorker, WorkBucketStage},
+    util::handle_table::{ForwardWeakHandles, ScheduleWeakHandleProcessing},
     vm::{Collection, Scanning, VMBinding},
     MMTK,
 };
@@ -27,7 +28,7 @@ impl<T: Trace> VMProcessWeakRefs<T> {
 }
 
 impl<T: Trace> GCWork<T::VM> for VMProcessWeakRefs<T> {
-    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, _mmtk: &'static MMTK<T::VM>) {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
         trace!("VMProcessWeakRefs");
 
         let stage = WorkBucketStage::VMRefClosure;
@@ -43,6 +44,10 @@ impl<T: Trace> GCWork<T::VM> for VMProcessWeakRefs<T> {
             let new_self = Box::new(Self::new());
 
             worker.scheduler().work_buckets[stage].set_sentinel(new_self);
+        } else if mmtk.handle_table.has_weak_handles() {
+            // Process weak handles after the transitive closure expanded by the VM binding.
+            worker.scheduler().work_buckets[stage]
+                .set_sentinel(Box::new(ScheduleWeakHandleProcessing));
         }
     }
 }
@@ -67,13 +72,16 @@ impl<T: Trace> VMForwardWeakRefs<T> {
 }
 
 impl<T: Trace> GCWork<T::VM> for VMForwardWeakRefs<T> {
-    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, _mmtk: &'static MMTK<T::VM>) {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
         trace!("VMForwardWeakRefs");
 
         let stage = WorkBucketStage::VMRefForwarding;
 
         let tracer_factory = DefaultObjectTracerContext::<T>::new(stage);
-        <T::VM as VMBinding>::VMScanning::forward_weak_refs(worker, tracer_factory)
+        <T::VM as VMBinding>::VMScanning::forward_weak_refs(worker, tracer_factory);
+
+        worker.scheduler().work_buckets[stage]
+            .bulk_add(ForwardWeakHandles::<T>::create_packets(mmtk));
     }
 }
 
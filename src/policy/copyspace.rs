@@ -218,6 +218,11 @@ impl<VM: VMBinding> CopySpace<VM> {
             // Clear VO bits because all objects in the space are dead.
             #[cfg(feature = "vo_bit")]
             crate::util::metadata::vo_bit::bzero_vo_bit(start, size);
+
+            // Stale references into the from-space will fault until the memory is reused.
+            if *self.common.options.guard_released_memory {
+                crate::util::heap::memory_guard::guard(start, size, self.get_name());
+            }
         }
 
         unsafe {
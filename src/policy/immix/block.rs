@@ -1,8 +1,9 @@
 use super::defrag::Histogram;
 use super::line::Line;
 use super::ImmixSpace;
+use crate::policy::space::Space;
 use crate::util::constants::*;
-use crate::util::heap::blockpageresource::BlockPool;
+use crate::util::heap::blockpageresource::NumaBlockPool;
 use crate::util::heap::chunk_map::Chunk;
 use crate::util::linear_scan::{Region, RegionIterator};
 use crate::util::metadata::side_metadata::{MetadataByteArrayRef, SideMetadataSpec};
@@ -237,6 +238,7 @@ impl Block {
             let mut holes = 0;
             let mut prev_line_is_marked = true;
             let line_mark_state = line_mark_state.unwrap();
+            let poison_free_lines = *space.common().options.guard_released_memory;
 
             for line in self.lines() {
                 if line.is_marked(line_mark_state) {
@@ -253,6 +255,10 @@ impl Block {
                     }
                     #[cfg(feature = "immix_zero_on_release")]
                     crate::util::memory::zero(line.start(), Line::BYTES);
+                    // Lines are smaller than pages, so free lines can only be poisoned.
+                    if poison_free_lines {
+                        crate::util::heap::memory_guard::poison(line.start(), Line::BYTES);
+                    }
 
                     // We need to clear the pin bit if it is on the side, as this line can be reused
                     #[cfg(feature = "object_pinning")]
@@ -333,18 +339,21 @@ impl Block {
     }
 }
 
-/// A non-block single-linked list to store blocks.
+/// A non-block single-linked list to store blocks.  Blocks are split by NUMA nodes if
+/// `numa_aware` is set, so that allocators reuse blocks on their own nodes first.
 pub struct ReusableBlockPool {
-    queue: BlockPool<Block>,
+    queue: NumaBlockPool<Block>,
     num_workers: usize,
+    numa_aware: bool,
 }
 
 impl ReusableBlockPool {
     /// Create empty block list
-    pub fn new(num_workers: usize) -> Self {
+    pub fn new(num_workers: usize, numa_aware: bool) -> Self {
         Self {
-            queue: BlockPool::new(num_workers),
+            queue: NumaBlockPool::new(num_workers, numa_aware),
             num_workers,
+            numa_aware,
         }
     }
 
@@ -365,7 +374,7 @@ impl ReusableBlockPool {
 
     /// Clear the list.
     pub fn reset(&mut self) {
-        self.queue = BlockPool::new(self.num_workers);
+        self.queue = NumaBlockPool::new(self.num_workers, self.numa_aware);
     }
 
     /// Iterate all the blocks in the queue. Call the visitor for each reported block.
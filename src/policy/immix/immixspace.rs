@@ -350,12 +350,14 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         let common =
             CommonSpace::new(args.into_policy_args(true, false, Self::side_metadata_specs()));
         let space_index = common.descriptor.get_index();
+        let numa_aware = *common.options.numa_aware;
         ImmixSpace {
             pr: if common.vmrequest.is_discontiguous() {
                 BlockPageResource::new_discontiguous(
                     Block::LOG_PAGES,
                     vm_map,
-                    scheduler.num_workers(),
+                    scheduler.max_workers(),
+                    numa_aware,
                 )
             } else {
                 BlockPageResource::new_contiguous(
@@ -363,7 +365,8 @@ impl<VM: VMBinding> ImmixSpace<VM> {
                     common.start,
                     common.extent,
                     vm_map,
-                    scheduler.num_workers(),
+                    scheduler.max_workers(),
+                    numa_aware,
                 )
             },
             common,
@@ -371,7 +374,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             line_mark_state: AtomicU8::new(Line::RESET_MARK_STATE),
             line_unavail_state: AtomicU8::new(Line::RESET_MARK_STATE),
             lines_consumed: AtomicUsize::new(0),
-            reusable_blocks: ReusableBlockPool::new(scheduler.num_workers()),
+            reusable_blocks: ReusableBlockPool::new(scheduler.max_workers(), numa_aware),
             defrag: Defrag::default(),
             // Set to the correct mark state when inititialized. We cannot rely on prepare to set it (prepare may get skipped in nursery GCs).
             mark_state: Self::MARKED_STATE,
@@ -414,7 +417,8 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             user_triggered_collection,
             self.reusable_blocks.len() == 0,
             full_heap_system_gc,
-            *self.common.options.immix_always_defrag,
+            *self.common.options.immix_always_defrag
+                || self.common.global_state.stress.random_defrag() == Some(true),
         );
         self.defrag.in_defrag()
     }
@@ -448,6 +452,11 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             let threshold = self.defrag.defrag_spill_threshold.load(Ordering::Acquire);
             // # Safety: ImmixSpace reference is always valid within this collection cycle.
             let space = unsafe { &*(self as *const Self) };
+            let random_defrag_salt = if space.in_defrag() {
+                self.common.global_state.stress.random_defrag_source_salt()
+            } else {
+                None
+            };
             let work_packets = self.chunk_map.generate_tasks(|chunk| {
                 Box::new(PrepareBlockState {
                     space,
@@ -457,6 +466,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
                     } else {
                         None
                     },
+                    random_defrag_salt,
                     unlog_bits_op,
                 })
             });
@@ -564,6 +574,9 @@ impl<VM: VMBinding> ImmixSpace<VM> {
     /// Release a block.
     pub fn release_block(&self, block: Block) {
         block.deinit();
+        if *self.common.options.guard_released_memory {
+            crate::util::heap::memory_guard::guard(block.start(), Block::BYTES, self.get_name());
+        }
         self.pr.release_block(block);
     }
 
@@ -923,6 +936,9 @@ pub struct PrepareBlockState<VM: VMBinding> {
     pub space: &'static ImmixSpace<VM>,
     pub chunk: Chunk,
     pub defrag_threshold: Option<usize>,
+    /// If set, defrag sources are chosen pseudo-randomly with this salt instead of by the number
+    /// of holes.  This is only set with the option `stress_random_defrag`.
+    pub random_defrag_salt: Option<u64>,
     pub unlog_bits_op: UnlogBitsOperation,
 }
 
@@ -955,6 +971,9 @@ impl<VM: VMBinding> GCWork<VM> for PrepareBlockState<VM> {
             } else if *mmtk.options.immix_defrag_every_block {
                 // Set every block as defrag source if so desired.
                 true
+            } else if let Some(salt) = self.random_defrag_salt {
+                // Choose defrag sources randomly for stress testing.
+                crate::util::stress::is_random_defrag_source(salt, block.start().as_usize())
             } else if let Some(defrag_threshold) = self.defrag_threshold {
                 // This GC is a defrag GC.
                 block.get_holes() > defrag_threshold
@@ -379,8 +379,13 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
             if self.clear_log_bit_on_sweep {
                 VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.clear::<VM>(object, Ordering::SeqCst);
             }
-            self.pr
-                .release_pages(get_super_page(object.to_object_start::<VM>()));
+            let start = get_super_page(object.to_object_start::<VM>());
+            if *self.common.options.guard_released_memory {
+                let bytes =
+                    crate::util::conversions::pages_to_bytes(self.pr.get_allocated_pages(start));
+                crate::util::heap::memory_guard::guard(start, bytes, self.get_name());
+            }
+            self.pr.release_pages(start);
         };
         if sweep_nursery {
             for object in self.treadmill.collect_nursery() {
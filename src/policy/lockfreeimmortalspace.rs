@@ -287,7 +287,7 @@ impl<VM: VMBinding> LockFreeImmortalSpace<VM> {
 
         // Eagerly memory map the entire heap (also zero all the memory)
         let strategy = MmapStrategy::default()
-            .transparent_hugepages(*args.options.transparent_hugepages)
+            .huge_page(args.options.huge_page_support_for_space(args.name))
             .prot(crate::util::os::MmapProtection::ReadWrite)
             .replace(true)
             .reserve(true);
@@ -324,7 +324,8 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
                 BlockPageResource::new_discontiguous(
                     Block::LOG_PAGES,
                     vm_map,
-                    scheduler.num_workers(),
+                    scheduler.max_workers(),
+                    *common.options.numa_aware,
                 )
             } else {
                 BlockPageResource::new_contiguous(
@@ -332,7 +333,8 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
                     common.start,
                     common.extent,
                     vm_map,
-                    scheduler.num_workers(),
+                    scheduler.max_workers(),
+                    *common.options.numa_aware,
                 )
             },
             common,
@@ -457,6 +459,9 @@ impl<VM: VMBinding> MarkSweepSpace<VM> {
         self.block_clear_metadata(block);
 
         block.deinit();
+        if *self.common.options.guard_released_memory {
+            crate::util::heap::memory_guard::guard(block.start(), Block::BYTES, self.get_name());
+        }
         self.pr.release_block(block);
     }
 
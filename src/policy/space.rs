@@ -12,7 +12,7 @@ use crate::util::ObjectReference;
 use crate::util::heap::layout::vm_layout::{vm_layout, LOG_BYTES_IN_CHUNK};
 use crate::util::heap::{PageResource, VMRequest};
 use crate::util::options::Options;
-use crate::vm::{ActivePlan, Collection};
+use crate::vm::ActivePlan;
 
 use crate::util::constants::LOG_BYTES_IN_MBYTE;
 use crate::util::conversions;
@@ -78,7 +78,10 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
 
         // Should we poll before acquring pages from page resources so that it can trigger a GC?
         // - If tls is collector, we cannot attempt a GC.
-        let should_poll = VM::VMActivePlan::is_mutator(tls);
+        // - If a mutator is driving a GC (see the option `mutator_driven_gc`), it allocates as a
+        //   GC worker, and cannot attempt a GC, either.
+        let should_poll =
+            VM::VMActivePlan::is_mutator(tls) && !crate::scheduler::is_current_thread_gc_worker();
 
         // If we should poll, do it now.  Record if it has triggered a GC.
         // If we should not poll, GC is not triggered.
@@ -160,7 +163,7 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
                     res.pages,
                     self.common()
                         .options
-                        .transparent_hugepages_as_huge_page_support(),
+                        .huge_page_support_for_space(self.get_name()),
                     self.common().mmap_protection(),
                     &MmapAnnotation::Space {
                         name: self.get_name(),
@@ -195,6 +198,25 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
             mmap();
         }
 
+        if *self.common().options.guard_released_memory {
+            // The memory may have been released and guarded by any space.
+            crate::util::heap::memory_guard::unguard(
+                res.start,
+                bytes,
+                self.common().mmap_protection(),
+            );
+        }
+
+        if *self.common().options.numa_aware {
+            // Blocks are placed on the node that their chunk was acquired for.  Other pages are
+            // placed on the node of the current thread.
+            use crate::util::numa::{CHUNK_NODES, NUMA_TOPOLOGY};
+            match CHUNK_NODES.get(res.start) {
+                Some(node) => NUMA_TOPOLOGY.place_on_node(res.start, bytes, node),
+                None => NUMA_TOPOLOGY.place_on_current_node(res.start, bytes),
+            }
+        }
+
         // TODO: Concurrent zeroing
         if self.common().zeroed {
             crate::util::memory::zero(res.start, bytes);
@@ -244,7 +266,7 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
         attempted_allocation_and_failed: bool,
     ) {
         assert!(
-            VM::VMActivePlan::is_mutator(tls),
+            VM::VMActivePlan::is_mutator(tls) && !crate::scheduler::is_current_thread_gc_worker(),
             "A non-mutator thread failed to get pages from page resource.  \
             Copying GC plans should compute the copying headroom carefully to prevent this."
         );
@@ -282,7 +304,8 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
             .policy
             .on_pending_allocation(total_pages_reserved);
 
-        VM::VMCollection::block_for_gc(VMMutatorThread(tls)); // We have checked that this is mutator
+        // We have checked that this is mutator
+        self.get_gc_trigger().wait_for_gc(VMMutatorThread(tls));
     }
 
     fn address_in_space(&self, start: Address) -> bool {
@@ -0,0 +1,113 @@
+//! Choosing the number of GC workers that participate in a GC.
+//!
+//! If the option `adaptive_gc_workers` is set, MMTk estimates the amount of work of each GC when
+//! the GC is scheduled, and only lets enough workers for that amount of work participate.  Other
+//! workers stay parked in `WorkerMonitor`, so a small nursery GC on a machine with many cores does
+//! not pay for waking up and synchronizing all the workers.
+
+use crate::global_state::GlobalState;
+use crate::plan::{is_nursery_gc, Plan};
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::options::Options;
+use crate::vm::VMBinding;
+
+/// Roots are counted as if each of them needs as much work as tracing a small object.
+const WORK_BYTES_PER_ROOT: usize = 64;
+
+/// The amount of work of a GC, estimated when the GC is scheduled.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct GCWorkEstimate {
+    /// Whether the GC only collects the nursery.
+    pub nursery_gc: bool,
+    /// The number of bytes allocated since the last GC.  This is the size of the nursery in
+    /// generational plans.
+    pub allocated_bytes: usize,
+    /// The number of live bytes after the last GC.
+    pub live_bytes_in_last_gc: usize,
+    /// The number of roots in the last GC.
+    pub roots_in_last_gc: usize,
+}
+
+impl GCWorkEstimate {
+    /// Estimate the work of the GC that has just been scheduled.
+    pub fn for_current_gc<VM: VMBinding>(plan: &dyn Plan<VM = VM>, state: &GlobalState) -> Self {
+        let used_pages_after_last_gc = state.get_used_pages_after_last_gc();
+        let allocated_pages = plan
+            .get_used_pages()
+            .saturating_sub(used_pages_after_last_gc);
+        // Use the live bytes counted in the last GC if `count_live_bytes_in_gc` is set, or the
+        // pages used after the last GC otherwise.
+        let counted_live_bytes: usize = state
+            .live_bytes_in_last_gc
+            .borrow()
+            .values()
+            .map(|stats| stats.live_bytes)
+            .sum();
+        let live_bytes_in_last_gc = if counted_live_bytes != 0 {
+            counted_live_bytes
+        } else {
+            used_pages_after_last_gc * BYTES_IN_PAGE
+        };
+        Self {
+            nursery_gc: is_nursery_gc(plan),
+            allocated_bytes: allocated_pages * BYTES_IN_PAGE,
+            live_bytes_in_last_gc,
+            roots_in_last_gc: state.get_roots_in_last_gc(),
+        }
+    }
+
+    /// The estimated number of bytes the GC needs to trace or copy.  A nursery GC only traces the
+    /// objects allocated since the last GC, while a full-heap GC also traces the old live objects.
+    pub fn work_bytes(&self) -> usize {
+        let heap_bytes = if self.nursery_gc {
+            self.allocated_bytes
+        } else {
+            self.allocated_bytes
+                .saturating_add(self.live_bytes_in_last_gc)
+        };
+        heap_bytes.saturating_add(self.roots_in_last_gc.saturating_mul(WORK_BYTES_PER_ROOT))
+    }
+
+    /// Choose the number of active workers for the GC, between 1 and `max_workers`.  It is never
+    /// more than the number of available CPUs.
+    pub fn active_workers(&self, options: &Options, max_workers: usize) -> usize {
+        let wanted = self
+            .work_bytes()
+            .div_ceil(*options.gc_work_bytes_per_worker);
+        wanted.clamp(1, max_workers.min(num_cpus::get()).max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nursery_gc_needs_fewer_workers() {
+        let mut options = Options::default();
+        assert!(options.set_from_string("gc_work_bytes_per_worker", "1048576"));
+        let max_workers = num_cpus::get();
+
+        let mut estimate = GCWorkEstimate {
+            nursery_gc: true,
+            allocated_bytes: 1 << 20,
+            live_bytes_in_last_gc: 512 << 20,
+            roots_in_last_gc: 100,
+        };
+        assert_eq!(estimate.work_bytes(), (1 << 20) + 100 * WORK_BYTES_PER_ROOT);
+        assert_eq!(
+            estimate.active_workers(&options, max_workers),
+            2.min(max_workers)
+        );
+
+        estimate.nursery_gc = false;
+        assert_eq!(estimate.active_workers(&options, max_workers), max_workers);
+        assert_eq!(estimate.active_workers(&options, 1), 1);
+
+        // At least one worker participates.
+        assert_eq!(
+            GCWorkEstimate::default().active_workers(&options, max_workers),
+            1
+        );
+    }
+}
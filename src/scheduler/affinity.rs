@@ -1,4 +1,5 @@
 use super::worker::ThreadId;
+use crate::util::numa::NUMA_TOPOLOGY;
 use crate::util::options::AffinityKind;
 use crate::util::os::*;
 
@@ -19,6 +20,16 @@ impl AffinityKind {
                 debug!("Set affinity for thread {} to core {}", thread, cpu);
                 OS::bind_current_thread_to_core(cpu);
             }
+            AffinityKind::NumaNodes => {
+                // Bind the current thread to all the cores of its node
+                if let Some(node) = NUMA_TOPOLOGY.node_for_worker(thread) {
+                    debug!(
+                        "Set affinity for thread {} to NUMA node {}",
+                        thread, node.id
+                    );
+                    OS::bind_current_thread_to_cpuset(node.cores.as_slice());
+                }
+            }
         }
     }
 }
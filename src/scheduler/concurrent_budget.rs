@@ -0,0 +1,111 @@
+//! The CPU budget of GC workers while mutators are running.
+//!
+//! Concurrent GC work, such as concurrent marking, competes with mutators for CPUs.  The budget
+//! limits it in two ways:
+//!
+//! -   Only a fraction of GC workers execute work packets while mutators are running.  Other
+//!     workers stay parked as if they were inactive (see `WorkerMonitor`) until the next pause.
+//! -   Each worker spends at most a fraction of its time (its duty cycle) executing work packets.
+//!     After executing packets, a worker sleeps long enough to keep within its duty cycle.
+//!
+//! The budget is set with the options `concurrent_gc_worker_fraction` and
+//! `concurrent_gc_duty_cycle`, and can be changed at run time with
+//! [`crate::memory_manager::set_concurrent_gc_cpu_budget`].
+
+use crate::util::options::Options;
+use atomic::Atomic;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// The CPU budget of GC workers while mutators are running.
+pub(crate) struct ConcurrentWorkBudget {
+    /// The fraction of GC workers that execute work packets while mutators are running.
+    worker_fraction: Atomic<f64>,
+    /// The fraction of time each GC worker may spend executing work packets while mutators are
+    /// running.
+    duty_cycle: Atomic<f64>,
+}
+
+impl ConcurrentWorkBudget {
+    /// A worker does not sleep until it owes at least this much sleep, so that it does not sleep
+    /// after each small packet.
+    pub const MIN_SLEEP: Duration = Duration::from_millis(1);
+
+    pub fn new(options: &Options) -> Self {
+        Self {
+            worker_fraction: Atomic::new(*options.concurrent_gc_worker_fraction),
+            duty_cycle: Atomic::new(*options.concurrent_gc_duty_cycle),
+        }
+    }
+
+    /// Is `value` a valid fraction of workers or duty cycle?
+    pub fn is_valid_fraction(value: f64) -> bool {
+        value > 0.0 && value <= 1.0
+    }
+
+    /// Change the budget.
+    pub fn set(&self, worker_fraction: f64, duty_cycle: f64) {
+        debug_assert!(Self::is_valid_fraction(worker_fraction));
+        debug_assert!(Self::is_valid_fraction(duty_cycle));
+        self.worker_fraction
+            .store(worker_fraction, Ordering::Relaxed);
+        self.duty_cycle.store(duty_cycle, Ordering::Relaxed);
+    }
+
+    /// Return true if workers sleep between work packets while mutators are running.
+    pub fn is_duty_cycle_limited(&self) -> bool {
+        self.duty_cycle.load(Ordering::Relaxed) < 1.0
+    }
+
+    /// The number of workers out of `num_workers` that may execute work packets while mutators
+    /// are running.  It is rounded up, and at least one unless `num_workers` is zero.
+    pub fn max_workers(&self, num_workers: usize) -> usize {
+        let fraction = self.worker_fraction.load(Ordering::Relaxed);
+        ((num_workers as f64 * fraction).ceil() as usize).clamp(1.min(num_workers), num_workers)
+    }
+
+    /// How long a worker should sleep after executing work packets for `busy`, so that it is
+    /// busy for at most the duty cycle.
+    pub fn sleep_after(&self, busy: Duration) -> Duration {
+        let duty_cycle = self.duty_cycle.load(Ordering::Relaxed);
+        busy.mul_f64(1.0 / duty_cycle - 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_budget(worker_fraction: f64, duty_cycle: f64) -> ConcurrentWorkBudget {
+        let budget = ConcurrentWorkBudget::new(&Options::default());
+        budget.set(worker_fraction, duty_cycle);
+        budget
+    }
+
+    #[test]
+    fn max_workers_rounds_up() {
+        let budget = new_budget(0.25, 1.0);
+        assert_eq!(budget.max_workers(8), 2);
+        assert_eq!(budget.max_workers(5), 2);
+        assert_eq!(budget.max_workers(1), 1);
+        assert_eq!(budget.max_workers(0), 0);
+        assert_eq!(
+            ConcurrentWorkBudget::new(&Options::default()).max_workers(8),
+            8
+        );
+    }
+
+    #[test]
+    fn sleep_keeps_within_duty_cycle() {
+        let budget = new_budget(1.0, 0.25);
+        assert!(budget.is_duty_cycle_limited());
+        assert_eq!(
+            budget.sleep_after(Duration::from_millis(1)),
+            Duration::from_millis(3)
+        );
+
+        let budget = new_budget(1.0, 1.0);
+        assert!(!budget.is_duty_cycle_limited());
+        assert_eq!(budget.sleep_after(Duration::from_millis(1)), Duration::ZERO);
+    }
+}
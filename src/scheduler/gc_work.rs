@@ -4,6 +4,7 @@ use crate::global_state::GcStatus;
 use crate::vm::*;
 use crate::*;
 use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
 
 pub struct ScheduleCollection;
 
@@ -25,6 +26,9 @@ impl<VM: VMBinding> GCWork<VM> for ScheduleCollection {
 
         // Let the plan to schedule collection work
         mmtk.get_plan().schedule_collection(worker.scheduler());
+
+        // The plan has decided the kind of this GC.  Choose how many workers participate.
+        worker.scheduler().choose_active_workers(mmtk);
     }
 }
 
@@ -66,7 +70,13 @@ impl<C: GCWorkContext> GCWork<C::VM> for Prepare<C> {
             mmtk.scheduler.work_buckets[WorkBucketStage::Prepare].bulk_add(prepare_mutator_packets);
         }
 
-        for w in &mmtk.scheduler.worker_group.workers_shared {
+        // Inactive workers do not copy objects in this GC, so they do not need to wake up.
+        let active_workers = mmtk.scheduler.num_active_workers();
+        for w in mmtk
+            .scheduler
+            .worker_group
+            .active_workers_shared(active_workers)
+        {
             let result = w.designated_work.push(Box::new(PrepareCollector));
             debug_assert!(result.is_ok());
         }
@@ -100,6 +110,14 @@ pub struct PrepareCollector;
 impl<VM: VMBinding> GCWork<VM> for PrepareCollector {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
         trace!("Prepare Collector");
+        if worker
+            .shared
+            .copy_context_prepared
+            .swap(true, Ordering::SeqCst)
+        {
+            // The worker was inactive when the last GC it took part in released copy contexts.
+            worker.get_copy_context_mut().release();
+        }
         worker.get_copy_context_mut().prepare();
         mmtk.get_plan().prepare_worker(worker);
     }
@@ -144,9 +162,17 @@ impl<C: GCWorkContext + 'static> GCWork<C::VM> for Release<C> {
         );
         mmtk.scheduler.work_buckets[WorkBucketStage::Release].bulk_add(release_mutator_packets);
 
-        for w in &mmtk.scheduler.worker_group.workers_shared {
-            let result = w.designated_work.push(Box::new(ReleaseCollector));
-            debug_assert!(result.is_ok());
+        // Inactive workers release their copy contexts when they are activated again.
+        let active_workers = mmtk.scheduler.num_active_workers();
+        for w in mmtk
+            .scheduler
+            .worker_group
+            .active_workers_shared(active_workers)
+        {
+            if w.copy_context_prepared.load(Ordering::SeqCst) {
+                let result = w.designated_work.push(Box::new(ReleaseCollector));
+                debug_assert!(result.is_ok());
+            }
         }
     }
 }
@@ -179,6 +205,31 @@ impl<VM: VMBinding> GCWork<VM> for ReleaseCollector {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
         trace!("Release Collector");
         worker.get_copy_context_mut().release();
+        worker
+            .shared
+            .copy_context_prepared
+            .store(false, Ordering::SeqCst);
+    }
+}
+
+/// Report a GC worker event to the binding on the thread of the worker that executes this packet.
+/// Each worker creates it for the events pending in its `GCWorkerShared::pending_events`.
+pub(crate) struct ReportWorkerEvent(pub GCWorkerEvent);
+
+impl<VM: VMBinding> GCWork<VM> for ReportWorkerEvent {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        match self.0 {
+            GCWorkerEvent::GCStarted => worker
+                .shared
+                .gc_started_reported
+                .store(true, Ordering::SeqCst),
+            GCWorkerEvent::GCFinished => worker
+                .shared
+                .gc_started_reported
+                .store(false, Ordering::SeqCst),
+            _ => {}
+        }
+        VM::VMCollection::on_gc_worker_event(worker.tls, worker.ordinal, self.0);
     }
 }
 
@@ -231,6 +282,13 @@ impl<C: GCWorkContext> GCWork<C::VM> for StopMutators<C> {
             }
         });
         trace!("stop_all_mutators end");
+        #[cfg(feature = "heap_verifier")]
+        crate::util::heap_verifier::verify_heap(
+            mmtk,
+            worker.tls,
+            crate::util::heap_verifier::VerificationPoint::BeforeGC,
+        );
+        crate::util::liveness::answer_pending_queries(mmtk, worker.tls);
         mmtk.get_plan().notify_mutators_paused(&mmtk.scheduler);
         mmtk.scheduler.notify_mutators_paused(mmtk);
         if !self.skip_roots {
@@ -261,6 +319,10 @@ impl<C: GCWorkContext> GCWork<C::VM> for ScanMutatorRoots<C> {
             mmtk.set_gc_status(GcStatus::GcProper);
         }
     }
+
+    fn priority(&self) -> WorkPriority {
+        WorkPriority::High
+    }
 }
 
 #[derive(Default)]
@@ -277,5 +339,13 @@ impl<C: GCWorkContext> GCWork<C::VM> for ScanVMSpecificRoots<C> {
         trace!("ScanStaticRoots");
         let factory = C::make_roots_work_factory(mmtk);
         <C::VM as VMBinding>::VMScanning::scan_vm_specific_roots(worker.tls, factory);
+
+        // Strong handles in the handle table are also VM-specific roots.
+        let mut factory = C::make_roots_work_factory(mmtk);
+        mmtk.handle_table.scan_strong_handles(&mut factory);
+    }
+
+    fn priority(&self) -> WorkPriority {
+        WorkPriority::High
     }
 }
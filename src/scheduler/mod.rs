@@ -8,26 +8,32 @@
 /// constant shall be used as the max lengths of those lists.
 pub const EDGES_WORK_BUFFER_SIZE: usize = 4096;
 
+mod active_workers;
 pub(crate) mod affinity;
+pub(crate) mod concurrent_budget;
 
 #[allow(clippy::module_inception)]
 mod scheduler;
 pub(crate) use scheduler::GCWorkScheduler;
 
 mod stat;
+pub use stat::{GCWorkPacketStats, WorkPacketStat};
+mod work_cost;
 mod work_counter;
+pub use work_counter::WorkCounterBase;
 
 pub(crate) mod work;
-pub use work::GCWork;
 pub(crate) use work::GCWorkContext;
+pub use work::{GCWork, WorkPriority};
 
 mod work_bucket;
-pub use work_bucket::WorkBucketStage;
+pub use work_bucket::{CustomStage, WorkBucketStage};
 
 mod worker;
 mod worker_goals;
 mod worker_monitor;
 pub(crate) use worker::current_worker_ordinal;
+pub(crate) use worker::is_current_thread_gc_worker;
 pub use worker::GCWorker;
 pub(crate) use worker::GCWorkerShared;
 
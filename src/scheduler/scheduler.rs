@@ -1,9 +1,12 @@
 use self::worker::PollResult;
 
+use super::active_workers::GCWorkEstimate;
+use super::concurrent_budget::ConcurrentWorkBudget;
 use super::gc_work::ScheduleCollection;
 use super::stat::SchedulerStat;
 use super::work_bucket::*;
-use super::worker::{GCWorker, ThreadId, WorkerGroup};
+use super::work_cost::WorkCostModel;
+use super::worker::{GCWorker, GCWorkerShared, ThreadId, WorkerGroup};
 use super::worker_goals::{WorkerGoal, WorkerGoals};
 use super::worker_monitor::{LastParkedResult, WorkerMonitor};
 use super::*;
@@ -14,24 +17,46 @@ use crate::plan::tracing::gc_work::weakref::{
 };
 use crate::util::opaque_pointer::*;
 use crate::util::options::AffinityKind;
-use crate::vm::Collection;
 use crate::vm::VMBinding;
+use crate::vm::{Collection, GCWorkerEvent, GCWorkerExitReason};
 use crate::Plan;
-use crossbeam::deque::Steal;
+use crossbeam::deque::{self, Steal};
 use enum_map::{Enum, EnumMap};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 pub struct GCWorkScheduler<VM: VMBinding> {
     /// Work buckets
     pub work_buckets: EnumMap<WorkBucketStage, WorkBucket<VM>>,
+    /// The stop-the-world stages in the order they are opened, including the custom stages
+    /// declared by the VM binding.
+    stw_stages: Vec<WorkBucketStage>,
     /// Workers
     pub(crate) worker_group: Arc<WorkerGroup<VM>>,
     /// For synchronized communication between workers and with mutators.
     pub(crate) worker_monitor: Arc<WorkerMonitor>,
     /// How to assign the affinity of each GC thread. Specified by the user.
     affinity: AffinityKind,
+    /// Whether workers prefer stealing work from workers on the same NUMA node.
+    numa_aware: bool,
+    /// Idle `GCWorker` instances for mutators that assist concurrent marking or mark incrementally.
+    /// They are created with GC threads if the option `concurrent_marking_assists` or
+    /// `incremental_marking` is set.
+    #[allow(clippy::vec_box)] // See `WorkerCreationState::Surrendered`.
+    assist_workers: Mutex<Vec<Box<GCWorker<VM>>>>,
+    /// The estimated time of the work packets that report their costs.
+    pub(crate) work_cost_model: WorkCostModel,
+    /// The CPU budget of workers while mutators are running.
+    pub(crate) concurrent_budget: ConcurrentWorkBudget,
+    /// The statistics of the work packets executed in each GC since statistics were enabled or
+    /// reset.
+    #[cfg(feature = "work_packet_stats")]
+    work_packet_stats: Mutex<Vec<GCWorkPacketStats>>,
+    /// Whether workers have been given `ReportWorkerEvent(GCFinished)` packets in the current GC.
+    /// Only accessed by the last parked worker, which holds the mutex `WorkerMonitor::sync`.
+    gc_finished_reported: AtomicBool,
 }
 
 // FIXME: GCWorkScheduler should be naturally Sync, but we cannot remove this `impl` yet.
@@ -40,20 +65,31 @@ pub struct GCWorkScheduler<VM: VMBinding> {
 unsafe impl<VM: VMBinding> Sync for GCWorkScheduler<VM> {}
 
 impl<VM: VMBinding> GCWorkScheduler<VM> {
-    pub fn new(num_workers: usize, affinity: AffinityKind) -> Arc<Self> {
+    pub(crate) fn new(
+        num_workers: usize,
+        max_workers: usize,
+        affinity: AffinityKind,
+        numa_aware: bool,
+        mutator_driven: bool,
+        custom_stages: &[WorkBucketStage],
+        concurrent_budget: ConcurrentWorkBudget,
+    ) -> Arc<Self> {
         let worker_monitor: Arc<WorkerMonitor> = Arc::new(WorkerMonitor::new(num_workers));
-        let worker_group = WorkerGroup::new(num_workers);
+        let worker_group = WorkerGroup::new(num_workers, max_workers, mutator_driven);
 
         // Create work buckets for workers.
         let mut work_buckets = EnumMap::from_fn(|stage: WorkBucketStage| {
             WorkBucket::new(stage, worker_monitor.clone())
         });
+        for i in 0..custom_stages.len() {
+            work_buckets[WorkBucketStage::Custom(CustomStage(i as u8))].set_enabled(true);
+        }
+        let stw_stages = WorkBucketStage::stw_stages_in_order(custom_stages);
 
         // Set the open condition of each bucket.
         {
             let mut open_stages: Vec<WorkBucketStage> = vec![WorkBucketStage::FIRST_STW_STAGE];
-            let stages = (0..WorkBucketStage::LENGTH).map(WorkBucketStage::from_usize);
-            for stage in stages {
+            for &stage in stw_stages.iter() {
                 if stage.is_sequentially_opened() {
                     let cur_stages = open_stages.clone();
                     // Other work packets will be opened after previous stages are done
@@ -74,9 +110,17 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
 
         Arc::new(Self {
             work_buckets,
+            stw_stages,
             worker_group,
             worker_monitor,
             affinity,
+            numa_aware,
+            assist_workers: Mutex::new(vec![]),
+            work_cost_model: Default::default(),
+            concurrent_budget,
+            #[cfg(feature = "work_packet_stats")]
+            work_packet_stats: Default::default(),
+            gc_finished_reported: AtomicBool::new(false),
         })
     }
 
@@ -84,17 +128,198 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         self.worker_group.as_ref().worker_count()
     }
 
+    /// The maximum number of workers.  [`Self::num_workers`] can be changed up to this number, so
+    /// per-worker data structures indexed by worker ordinals should have this many entries.
+    pub fn max_workers(&self) -> usize {
+        self.worker_group.capacity()
+    }
+
+    /// The number of workers that participate in the current GC.  It is less than
+    /// [`Self::num_workers`] if the option `adaptive_gc_workers` chose fewer workers.
+    pub fn num_active_workers(&self) -> usize {
+        self.worker_monitor.active_workers()
+    }
+
+    /// Choose the number of workers that participate in the GC that has just been scheduled.
+    pub(crate) fn choose_active_workers(&self, mmtk: &'static MMTK<VM>) {
+        let old_active_workers = self.worker_monitor.effective_active_workers();
+        // Workers kept parked by the CPU budget for concurrent work participate in the GC.
+        let active_workers = if *mmtk.options.adaptive_gc_workers {
+            let estimate = GCWorkEstimate::for_current_gc(mmtk.get_plan(), &mmtk.state);
+            let active_workers = estimate.active_workers(&mmtk.options, self.num_workers());
+            debug!(
+                "{} of {} GC workers are active for about {} bytes of work: {:?}",
+                active_workers,
+                self.num_workers(),
+                estimate.work_bytes(),
+                estimate
+            );
+            active_workers
+        } else {
+            self.num_active_workers()
+        };
+
+        if VM::VMCollection::report_gc_events_on_workers() {
+            self.move_gc_started_events(old_active_workers, active_workers);
+        }
+        self.worker_monitor.remove_concurrent_worker_limit();
+        if *mmtk.options.adaptive_gc_workers {
+            self.worker_monitor.set_active_workers(active_workers);
+        }
+    }
+
+    /// `GCStarted` was given to `old_active_workers` workers when the GC started.  Give it to the
+    /// workers that are about to be activated, too, and take it back from the workers that are
+    /// about to be deactivated if they have not reported it yet.
+    fn move_gc_started_events(&self, old_active_workers: usize, active_workers: usize) {
+        let running_workers = self.worker_group.running_workers_shared();
+        for w in running_workers
+            .iter()
+            .take(active_workers)
+            .skip(old_active_workers)
+        {
+            // The worker that executes `ScheduleCollection` has reported it.
+            if !w.gc_started_reported.load(Ordering::SeqCst) {
+                w.pending_events.push(GCWorkerEvent::GCStarted);
+            }
+        }
+        for w in running_workers
+            .iter()
+            .take(old_active_workers)
+            .skip(active_workers)
+        {
+            // A worker that has reported it reports `GCFinished`, too.
+            while let Some(event) = w.pending_events.pop() {
+                debug_assert_eq!(event, GCWorkerEvent::GCStarted);
+            }
+        }
+    }
+
+    /// The number of workers that may execute concurrent work while mutators are running.  The
+    /// worker driven by mutators does not run while mutators are running, so it is not counted
+    /// towards the budget.
+    fn concurrent_worker_limit(&self) -> usize {
+        let thread_count = self.worker_group.thread_count();
+        let driver_count = self.worker_group.worker_count() - thread_count;
+        driver_count + self.concurrent_budget.max_workers(thread_count)
+    }
+
+    /// Change the CPU budget of workers while mutators are running.  If concurrent work is in
+    /// progress, the number of workers executing it changes immediately.
+    pub(crate) fn set_concurrent_budget(&self, worker_fraction: f64, duty_cycle: f64) {
+        self.concurrent_budget.set(worker_fraction, duty_cycle);
+        self.worker_monitor
+            .update_concurrent_worker_limit(self.concurrent_worker_limit());
+    }
+
     /// Create GC threads for the first time.  It will also create the `GCWorker` instances.
     ///
     /// Currently GC threads only include worker threads, and we currently have only one worker
-    /// group.  We may add more worker groups in the future.
+    /// group.  We may add more worker groups in the future.  If the option `mutator_driven_gc` is
+    /// set, worker 0 is not spawned, but driven by mutators in [`Self::run_gc_on_mutator`].
     pub fn spawn_gc_threads(self: &Arc<Self>, mmtk: &'static MMTK<VM>, tls: VMThread) {
+        if *mmtk.options.mutator_driven_gc {
+            // The driver is idle until a mutator drives it.
+            self.worker_monitor.park_idle_driver();
+        }
+        if mmtk.state.pacer.is_enabled() && mmtk.get_plan().concurrent().is_some() {
+            self.create_assist_workers(mmtk);
+        }
         self.worker_group.initial_spawn(tls, mmtk);
     }
 
+    /// Create one `GCWorker` for assisting concurrent marking (or marking incrementally) per GC
+    /// worker.  At most that many mutators mark at the same time.  Their ordinals follow those of the GC workers,
+    /// including retired workers.
+    fn create_assist_workers(self: &Arc<Self>, mmtk: &'static MMTK<VM>) {
+        let num_workers = self.num_workers();
+        let first_ordinal = self.max_workers();
+        let workers = (0..num_workers)
+            .map(|i| {
+                Box::new(GCWorker::new(
+                    mmtk,
+                    first_ordinal + i,
+                    self.clone(),
+                    Arc::new(GCWorkerShared::new(None)),
+                    deque::Worker::new_fifo(),
+                ))
+            })
+            .collect();
+        *self.assist_workers.lock().unwrap() = workers;
+    }
+
+    /// Execute work packets of the `Concurrent` bucket on the mutator `tls` while `should_continue`
+    /// returns true.  This is called by mutators that assist concurrent marking.  Return `true` if
+    /// the mutator stopped because the bucket had no work, or `false` if it stopped because
+    /// `should_continue` returned false or all the assisting `GCWorker` instances were in use.
+    pub(crate) fn assist_concurrent_work(
+        &self,
+        tls: VMMutatorThread,
+        mut should_continue: impl FnMut() -> bool,
+    ) -> bool {
+        let Some(mut worker) = self.assist_workers.lock().unwrap().pop() else {
+            return false;
+        };
+        worker.tls = VMWorkerThread(tls.0);
+        let mmtk = worker.mmtk;
+        let bucket = &self.work_buckets[WorkBucketStage::Concurrent];
+        let mut out_of_work = false;
+        // Packets taken by this mutator are neither in the bucket nor in any GC worker.  Keep the
+        // bucket from looking drained until they are done or put back.
+        bucket.begin_assist();
+        while should_continue() {
+            let mut work = match worker.local_work_buffer.pop() {
+                Some(work) => work,
+                None => match bucket.poll(&worker.local_work_buffer) {
+                    Steal::Success(work) => work,
+                    Steal::Retry => continue,
+                    Steal::Empty => {
+                        out_of_work = true;
+                        break;
+                    }
+                },
+            };
+            work.do_work(&mut worker, mmtk);
+        }
+        // Nobody steals from the local queue of an assisting worker.  Give the packets back to the
+        // bucket so that GC workers can execute them.
+        while let Some(work) = worker.local_work_buffer.pop() {
+            bucket.add_boxed(work);
+        }
+        bucket.end_assist();
+        self.assist_workers.lock().unwrap().push(worker);
+        out_of_work
+    }
+
+    /// Run the requested or ongoing GC to completion on the current mutator thread, which drives
+    /// worker 0.  This is only supported if the option `mutator_driven_gc` is set.  It returns
+    /// when neither a GC is in progress nor a GC is requested.  If another mutator is driving
+    /// the GC, this blocks in [`Collection::block_for_gc_driver`] until that mutator returns.
+    pub(crate) fn run_gc_on_mutator(&self, tls: VMMutatorThread) {
+        let mut driver = match self.worker_group.try_lock_driver() {
+            Some(driver) => driver,
+            None => {
+                let mut driver = None;
+                VM::VMCollection::block_for_gc_driver(tls, &mut || {
+                    driver = Some(self.worker_group.lock_driver());
+                });
+                driver.expect("block_for_gc_driver() returned without calling wait()")
+            }
+        };
+        let worker = driver
+            .as_mut()
+            .expect("No GC worker for mutators.  Is the option mutator_driven_gc set, and was initialize_collection() called?");
+        self.worker_monitor.unpark_driver();
+        worker.run_on_mutator(tls);
+    }
+
     /// Ask all GC workers to exit for forking.
     pub fn stop_gc_threads_for_forking(self: &Arc<Self>) {
         self.worker_group.prepare_surrender_buffer();
+        if self.worker_group.thread_count() == 0 {
+            debug!("There are no GC threads to stop for forking.");
+            return;
+        }
 
         debug!("A mutator is requesting GC threads to stop for forking...");
         self.worker_monitor.make_request(WorkerGoal::StopForFork);
@@ -103,11 +328,46 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
     /// Ask all GC workers to exit permanently.
     pub fn shutdown_gc_threads(self: &Arc<Self>) {
         self.worker_group.prepare_surrender_buffer();
+        if self.worker_group.thread_count() == 0 {
+            debug!("There are no GC threads to shut down.");
+            return;
+        }
 
         info!("A mutator is requesting GC threads to shut down...");
         self.worker_monitor.make_request(WorkerGoal::Shutdown);
     }
 
+    /// Change the number of GC workers to `num_workers`.  All GC threads exit after the current GC
+    /// (if any) finishes, and the remaining workers are spawned again with their `GCWorker`
+    /// structs, which keep their local work queues.  Each worker resolves its affinity and creates
+    /// its copy context again when it starts running.  `tls` is passed down to
+    /// [`crate::vm::Collection::spawn_gc_thread`].
+    pub(crate) fn set_num_workers(self: &Arc<Self>, tls: VMThread, num_workers: usize) {
+        if num_workers == self.num_workers() {
+            return;
+        }
+
+        self.worker_group.prepare_surrender_buffer();
+        if self.worker_group.thread_count() > 0 {
+            debug!("Stopping GC threads to change the number of workers...");
+            self.worker_monitor.make_request(WorkerGoal::StopForResize);
+            self.worker_monitor
+                .wait_for_all_workers_exited(WorkerGoal::StopForResize);
+        }
+
+        info!(
+            "Changing the number of GC workers from {} to {}",
+            self.num_workers(),
+            num_workers
+        );
+        self.worker_group.resize(num_workers);
+        self.worker_monitor.set_worker_count(num_workers);
+        // Concurrent work may be in progress.  Follow the new number of threads.
+        self.worker_monitor
+            .update_concurrent_worker_limit(self.concurrent_worker_limit());
+        self.worker_group.respawn(tls);
+    }
+
     /// Surrender the `GCWorker` struct of a GC worker when it exits.
     pub fn surrender_gc_worker(&self, worker: Box<GCWorker<VM>>) {
         let all_surrendered = self.worker_group.surrender_gc_worker(worker);
@@ -115,7 +375,7 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         if all_surrendered {
             debug!(
                 "All {} workers surrendered.",
-                self.worker_group.worker_count()
+                self.worker_group.thread_count()
             );
             self.worker_monitor.on_all_workers_exited();
         }
@@ -128,6 +388,16 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         self.worker_group.respawn(tls)
     }
 
+    /// Why workers are exiting.  Called by workers after polling returned `Err(WorkerShouldExit)`.
+    pub(crate) fn worker_exit_reason(&self) -> GCWorkerExitReason {
+        match self.worker_monitor.current_goal() {
+            Some(WorkerGoal::StopForFork) => GCWorkerExitReason::Fork,
+            Some(WorkerGoal::Shutdown) => GCWorkerExitReason::Shutdown,
+            Some(WorkerGoal::StopForResize) => GCWorkerExitReason::Resize,
+            goal => panic!("Worker exiting when the current goal is {:?}", goal),
+        }
+    }
+
     /// Resolve the affinity of a thread.
     pub fn resolve_affinity(&self, thread: ThreadId) {
         self.affinity.resolve_affinity(thread);
@@ -146,6 +416,49 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         self.work_buckets[WorkBucketStage::Unconstrained].add_no_notify(ScheduleCollection);
     }
 
+    /// Let the workers that may execute work packets of the GC that is starting report
+    /// `GCStarted` on their own threads before they execute other work packets.  They are the
+    /// active workers and the last parked worker `worker`, which executes `ScheduleCollection`.
+    /// Called by the last parked worker.
+    fn report_gc_started(&self, worker: &GCWorker<VM>) {
+        let active_workers = self.worker_monitor.effective_active_workers();
+        for (ordinal, w) in self
+            .worker_group
+            .running_workers_shared()
+            .iter()
+            .enumerate()
+        {
+            if ordinal < active_workers || ordinal == worker.ordinal {
+                w.pending_events.push(GCWorkerEvent::GCStarted);
+            }
+        }
+    }
+
+    /// Let the workers that reported `GCStarted` report `GCFinished` on their own threads.  Called
+    /// by the last parked worker.
+    fn report_gc_finished(&self) {
+        for w in self.worker_group.running_workers_shared() {
+            if w.gc_started_reported.load(Ordering::SeqCst) {
+                w.pending_events.push(GCWorkerEvent::GCFinished);
+            }
+        }
+    }
+
+    /// Wake up the workers that have designated work or events to report.  Only active workers
+    /// are given designated work, but a worker deactivated during a GC may still have to report
+    /// `GCFinished`.  Called by the last parked worker.
+    fn wake_up_workers_with_designated_work(&self) -> LastParkedResult {
+        let active_workers = self.worker_monitor.effective_active_workers();
+        if self
+            .worker_group
+            .inactive_workers_have_designated_work(active_workers)
+        {
+            LastParkedResult::WakeAllIncludingInactive
+        } else {
+            LastParkedResult::WakeAll
+        }
+    }
+
     /// Schedule all the common work packets
     pub fn schedule_common_work<C: GCWorkContext<VM = VM>>(&self, plan: &'static C::PlanType) {
         use crate::scheduler::gc_work::*;
@@ -176,13 +489,18 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         // Reference processing
         if !*plan.base().options.no_reference_types {
             use crate::util::reference_processor::{
-                PhantomRefProcessing, SoftRefProcessing, WeakRefProcessing,
+                FinalRefProcessing, PhantomRefProcessing, SoftRefProcessing, WeakRefProcessing,
             };
             self.work_buckets[WorkBucketStage::SoftRefClosure]
                 .add(SoftRefProcessing::<C::DefaultTrace>::new());
             self.work_buckets[WorkBucketStage::WeakRefClosure].add(WeakRefProcessing::<VM>::new());
             self.work_buckets[WorkBucketStage::PhantomRefClosure]
                 .add(PhantomRefProcessing::<VM>::new());
+            if *plan.base().options.no_finalizer {
+                // Otherwise, the `Final` reference kinds are processed after finalization.
+                self.work_buckets[WorkBucketStage::FinalRefClosure]
+                    .add(FinalRefProcessing::<VM>::new());
+            }
 
             use crate::util::reference_processor::RefForwarding;
             if plan.constraints().needs_forward_after_liveness {
@@ -194,6 +512,17 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             self.work_buckets[WorkBucketStage::Release].add(RefEnqueue::<VM>::new());
         }
 
+        // Ephemeron processing
+        {
+            use crate::util::ephemeron_processor::{EphemeronForwarding, EphemeronProcessing};
+            self.work_buckets[WorkBucketStage::EphemeronClosure]
+                .set_sentinel(Box::new(EphemeronProcessing::<C::DefaultTrace>::new()));
+            if plan.constraints().needs_forward_after_liveness {
+                self.work_buckets[WorkBucketStage::RefForwarding]
+                    .add(EphemeronForwarding::<C::DefaultTrace>::new());
+            }
+        }
+
         // Finalization
         if !*plan.base().options.no_finalizer {
             use crate::util::finalizable_processor::{Finalization, ForwardFinalization};
@@ -287,11 +616,7 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         debug!("update_buckets");
         let mut buckets_updated = false;
         let mut new_packets = false;
-        for i in 0..WorkBucketStage::LENGTH {
-            let id = WorkBucketStage::from_usize(i);
-            if id.is_always_open() {
-                continue;
-            }
+        for &id in self.stw_stages.iter() {
             let bucket = &self.work_buckets[id];
             if !bucket.is_enabled() {
                 debug!("Work bucket {:?} is disabled. Skip.", id);
@@ -301,7 +626,7 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             let bucket_opened = bucket.update(self);
             buckets_updated = buckets_updated || bucket_opened;
             if bucket_opened {
-                probe!(mmtk, bucket_opened, id);
+                probe!(mmtk, bucket_opened, id.into_usize());
                 new_packets = new_packets || !bucket.is_drained();
                 if new_packets {
                     // Quit the loop. There are already new packets in the newly opened buckets.
@@ -368,26 +693,45 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
     fn poll_schedulable_work_once(&self, worker: &GCWorker<VM>) -> Steal<Box<dyn GCWork<VM>>> {
         let mut should_retry = false;
         // Try find a packet that can be processed only by this worker.
-        if let Some(w) = worker.shared.designated_work.pop() {
+        if let Some(w) = worker.shared.poll_designated_work() {
             return Steal::Success(w);
         }
-        // Try get a packet from a work bucket.
-        for work_bucket in self.work_buckets.values() {
+        // Try get a packet from a work bucket.  With incremental marking, marking packets are left
+        // to mutators until the final mark pause.
+        let skip_concurrent = worker.mmtk.state.pacer.is_marking_on_mutators();
+        for (id, work_bucket) in self.work_buckets.iter() {
+            if skip_concurrent && id == WorkBucketStage::Concurrent {
+                continue;
+            }
             match work_bucket.poll(&worker.local_work_buffer) {
                 Steal::Success(w) => return Steal::Success(w),
                 Steal::Retry => should_retry = true,
                 _ => {}
             }
         }
-        // Try steal some packets from any worker
-        for (id, worker_shared) in self.worker_group.workers_shared.iter().enumerate() {
-            if id == worker.ordinal {
-                continue;
-            }
-            match worker_shared.stealer.as_ref().unwrap().steal() {
-                Steal::Success(w) => return Steal::Success(w),
-                Steal::Retry => should_retry = true,
-                _ => {}
+        // Try steal some packets from any worker.  If we are NUMA-aware, try workers on the same
+        // node first.
+        let my_node = worker.shared.numa_node.load(Ordering::Relaxed);
+        let passes: &[Option<bool>] = if self.numa_aware {
+            &[Some(true), Some(false)]
+        } else {
+            &[None]
+        };
+        for same_node in passes {
+            for (id, worker_shared) in self.worker_group.workers_shared.iter().enumerate() {
+                if id == worker.ordinal {
+                    continue;
+                }
+                if let Some(same_node) = same_node {
+                    if (worker_shared.numa_node.load(Ordering::Relaxed) == my_node) != *same_node {
+                        continue;
+                    }
+                }
+                match worker_shared.stealer.as_ref().unwrap().steal() {
+                    Steal::Success(w) => return Steal::Success(w),
+                    Steal::Retry => should_retry = true,
+                    _ => {}
+                }
             }
         }
         if should_retry {
@@ -433,8 +777,16 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             }
 
             let ordinal = worker.ordinal;
-            self.worker_monitor
-                .park_and_wait(ordinal, |goals| self.on_last_parked(worker, goals))?;
+            let on_last_parked = |goals: &mut WorkerGoals| self.on_last_parked(worker, goals);
+            if worker.driven_by_mutator {
+                self.worker_monitor
+                    .park_driver_and_wait(ordinal, on_last_parked)?;
+            } else {
+                VM::VMCollection::on_gc_worker_event(worker.tls, ordinal, GCWorkerEvent::Parked);
+                let result = self.worker_monitor.park_and_wait(ordinal, on_last_parked);
+                VM::VMCollection::on_gc_worker_event(worker.tls, ordinal, GCWorkerEvent::Unparked);
+                result?;
+            }
         }
     }
 
@@ -453,7 +805,7 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                 // In stop-the-world GC, mutators cannot request for GC while GC is in progress.
                 // When we support concurrent GC, we should remove this assertion.
                 assert!(
-                    !goals.debug_is_requested(WorkerGoal::Gc),
+                    !goals.is_requested(WorkerGoal::Gc),
                     "GC request sent to WorkerMonitor while GC is still in progress."
                 );
 
@@ -463,12 +815,27 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                 // During GC, if all workers parked, all open buckets must have been drained.
                 self.assert_all_open_buckets_are_empty();
 
+                if self.worker_group.has_designated_work() {
+                    trace!("Some workers have designated work.");
+                    return self.wake_up_workers_with_designated_work();
+                }
+
                 // Find more work for workers to do.
                 let found_more_work = self.find_more_work_for_workers();
 
                 if found_more_work {
                     LastParkedResult::WakeAll
+                } else if VM::VMCollection::report_gc_events_on_workers()
+                    && !self.gc_finished_reported.swap(true, Ordering::Relaxed)
+                {
+                    // All work packets have been executed.  Let workers report it before the GC
+                    // finishes.  This wakes up all workers that took part in the GC, so it is only
+                    // done if the binding wants the event.
+                    self.report_gc_finished();
+                    self.wake_up_workers_with_designated_work()
                 } else {
+                    self.gc_finished_reported.store(false, Ordering::Relaxed);
+
                     // GC finished.
                     let concurrent_work_scheduled = self.on_gc_finished(worker);
 
@@ -486,7 +853,7 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                     }
                 }
             }
-            WorkerGoal::StopForFork | WorkerGoal::Shutdown => {
+            WorkerGoal::StopForFork | WorkerGoal::StopForResize | WorkerGoal::Shutdown => {
                 panic!(
                     "Worker {} parked again when it is asked to exit.",
                     worker.ordinal
@@ -523,22 +890,22 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
                 }
 
                 self.add_schedule_collection_packet();
+                // Other workers report it when they wake up for work packets, or when the last
+                // parked worker finds they have events to report.
+                if VM::VMCollection::report_gc_events_on_workers() {
+                    self.report_gc_started(worker);
+                }
                 LastParkedResult::WakeSelf
             }
-            WorkerGoal::StopForFork | WorkerGoal::Shutdown => {
+            WorkerGoal::StopForFork | WorkerGoal::StopForResize | WorkerGoal::Shutdown => {
                 trace!("A mutator requested {:?}", goal);
-                LastParkedResult::WakeAll
+                LastParkedResult::WakeAllIncludingInactive
             }
         }
     }
 
     /// Find more work for workers to do.  Return true if more work is available.
     fn find_more_work_for_workers(&self) -> bool {
-        if self.worker_group.has_designated_work() {
-            trace!("Some workers have designated work.");
-            return true;
-        }
-
         // See if any bucket has a sentinel.
         if self.schedule_sentinels() {
             trace!("Some sentinels are scheduled.");
@@ -572,12 +939,23 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         // Tell GC trigger that GC ended - this happens before we resume mutators.
         mmtk.gc_trigger.policy.on_gc_end(mmtk);
 
+        // Verify the heap before the plan forgets the kind of the GC that just finished.
+        #[cfg(feature = "heap_verifier")]
+        crate::util::heap_verifier::verify_heap(
+            mmtk,
+            worker.tls,
+            crate::util::heap_verifier::VerificationPoint::AfterGC,
+        );
+
         // All other workers are parked, so it is safe to access the Plan instance mutably.
         probe!(mmtk, plan_end_of_gc_begin);
         let plan_mut: &mut dyn Plan<VM = VM> = unsafe { mmtk.get_plan_mut() };
         plan_mut.end_of_gc(worker.tls);
         probe!(mmtk, plan_end_of_gc_end);
 
+        mmtk.reference_processors
+            .on_gc_end(mmtk.get_plan().get_free_pages());
+
         // Compute the elapsed time of the GC.
         let start_time = {
             let mut gc_start_time = worker.mmtk.state.gc_start_time.borrow_mut();
@@ -614,8 +992,23 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             }
         }
 
+        if *mmtk.get_options().accounting_contexts {
+            let live_bytes_per_context = (*mmtk.get_options().count_live_bytes_in_gc).then(|| {
+                mmtk.scheduler
+                    .worker_group
+                    .get_and_clear_worker_live_bytes_per_accounting_context()
+            });
+            mmtk.state
+                .accounting
+                .on_gc_end(live_bytes_per_context.as_ref());
+        }
+
+        #[cfg(feature = "work_packet_stats")]
+        self.on_gc_finished_work_packet_stats();
+
         mmtk.state
             .set_used_pages_after_last_gc(mmtk.get_plan().get_used_pages());
+        mmtk.state.on_roots_counted();
 
         #[cfg(feature = "extreme_assertions")]
         if crate::util::slot_logger::should_check_duplicate_slots(mmtk.get_plan()) {
@@ -628,6 +1021,10 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
 
         let concurrent_work_scheduled = self.schedule_concurrent_packets();
         self.debug_assert_all_stw_buckets_closed();
+        if concurrent_work_scheduled {
+            self.worker_monitor
+                .set_concurrent_worker_limit(self.concurrent_worker_limit());
+        }
 
         // Set to NotInGC after everything, and right before resuming mutators.
         mmtk.set_gc_status(GcStatus::NotInGC);
@@ -636,6 +1033,54 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         concurrent_work_scheduled
     }
 
+    /// Record the statistics of the work packets executed in this GC, and refine the cost model
+    /// with them.
+    #[cfg(feature = "work_packet_stats")]
+    fn on_gc_finished_work_packet_stats(&self) {
+        let gc_stats = GCWorkPacketStats {
+            workers: self
+                .worker_group
+                .workers_shared
+                .iter()
+                .map(|worker| worker.borrow_stat_mut().take_gc_stats())
+                .collect(),
+        };
+        if gc_stats.workers.iter().all(|worker| worker.is_empty()) {
+            // Statistics are not enabled.
+            return;
+        }
+        let costs = gc_stats
+            .merged()
+            .into_iter()
+            .map(|(name, stat)| {
+                let sample = super::work_cost::WorkCostSample {
+                    units: stat.work_units,
+                    nanos: stat.time.total as u64,
+                };
+                (name, sample)
+            })
+            .collect();
+        self.work_cost_model.update(&costs);
+        self.work_packet_stats.lock().unwrap().push(gc_stats);
+    }
+
+    /// Get the statistics of the work packets executed in each GC since statistics were enabled
+    /// or last reset.
+    #[cfg(feature = "work_packet_stats")]
+    pub(crate) fn work_packet_stats(&self) -> Vec<GCWorkPacketStats> {
+        self.work_packet_stats.lock().unwrap().clone()
+    }
+
+    /// Discard the statistics returned by [`Self::work_packet_stats`], and those of the work
+    /// packets executed since the last GC.
+    #[cfg(feature = "work_packet_stats")]
+    pub(crate) fn reset_work_packet_stats(&self) {
+        for worker in &self.worker_group.workers_shared {
+            worker.borrow_stat_mut().take_gc_stats();
+        }
+        self.work_packet_stats.lock().unwrap().clear();
+    }
+
     pub fn enable_stat(&self) {
         for worker in &self.worker_group.workers_shared {
             let worker_stat = worker.borrow_stat();
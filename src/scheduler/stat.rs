@@ -9,6 +9,67 @@ use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+/// The statistics of the work packets of one type.
+#[derive(Clone, Debug, Default)]
+pub struct WorkPacketStat {
+    /// The number of packets executed.
+    pub count: usize,
+    /// The total units of work of the packets, as reported by
+    /// [`crate::scheduler::GCWork::estimated_cost`], e.g. the number of slots processed by
+    /// `ProcessSlots`.  It is zero for packets that do not report their costs.
+    pub work_units: usize,
+    /// The execution times of the packets, in nanoseconds.
+    pub time: WorkCounterBase,
+    /// The readings of the hardware performance counters given by the option
+    /// `work_perf_events`, keyed by event names.  It is empty unless the feature `perf_counter`
+    /// is enabled.
+    pub perf_events: Vec<(String, WorkCounterBase)>,
+}
+
+impl WorkPacketStat {
+    /// Merge the statistics of another worker or another GC.
+    pub fn merge(&mut self, other: &WorkPacketStat) {
+        self.count += other.count;
+        self.work_units += other.work_units;
+        self.time.merge_inplace(&other.time);
+        for (name, other_reading) in other.perf_events.iter() {
+            self.perf_event_mut(name).merge_inplace(other_reading);
+        }
+    }
+
+    fn perf_event_mut(&mut self, name: &str) -> &mut WorkCounterBase {
+        let index = match self.perf_events.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.perf_events.push((name.to_owned(), Default::default()));
+                self.perf_events.len() - 1
+            }
+        };
+        &mut self.perf_events[index].1
+    }
+}
+
+/// The statistics of the work packets executed in one GC, keyed by the type names of the work
+/// packets.  The concurrent work executed by GC workers since the previous GC is included.
+#[derive(Clone, Debug, Default)]
+pub struct GCWorkPacketStats {
+    /// The statistics of each GC worker, indexed by worker ordinals.
+    pub workers: Vec<HashMap<&'static str, WorkPacketStat>>,
+}
+
+impl GCWorkPacketStats {
+    /// Merge the statistics of all workers.
+    pub fn merged(&self) -> HashMap<&'static str, WorkPacketStat> {
+        let mut merged = HashMap::<&'static str, WorkPacketStat>::new();
+        for worker in self.workers.iter() {
+            for (name, stat) in worker.iter() {
+                merged.entry(name).or_default().merge(stat);
+            }
+        }
+        merged
+    }
+}
+
 /// Merge and print the work-packet level statistics from all worker threads
 #[derive(Default)]
 pub struct SchedulerStat {
@@ -158,6 +219,8 @@ impl SchedulerStat {
 pub struct WorkStat {
     type_id: TypeId,
     type_name: &'static str,
+    /// The estimated cost of the packet, or zero if it does not report its cost.
+    work_units: usize,
 }
 
 impl WorkStat {
@@ -173,13 +236,23 @@ impl WorkStat {
             .insert(self.type_id, self.type_name);
         // Increment work count
         *worker_stat.work_counts.entry(self.type_id).or_insert(0) += 1;
-        // Stop counters
-        worker_stat
-            .work_counters
-            .entry(self.type_id)
-            .and_modify(|v| {
-                v.iter_mut().for_each(|c| c.stop());
-            });
+        let gc_stat = worker_stat.gc_stats.entry(self.type_name).or_default();
+        gc_stat.count += 1;
+        gc_stat.work_units += self.work_units;
+        // Stop counters, and record their readings for this packet in the statistics of this GC.
+        // The first counter measures time.
+        if let Some(counters) = worker_stat.work_counters.get_mut(&self.type_id) {
+            for (i, c) in counters.iter_mut().enumerate() {
+                let before = c.get_base().total;
+                c.stop();
+                let reading = c.get_base().total - before;
+                if i == 0 {
+                    gc_stat.time.merge_val(reading);
+                } else {
+                    gc_stat.perf_event_mut(&c.name()).merge_val(reading);
+                }
+            }
+        }
     }
 }
 
@@ -188,6 +261,9 @@ pub struct WorkerLocalStat<C> {
     work_id_name_map: HashMap<TypeId, &'static str>,
     work_counts: HashMap<TypeId, usize>,
     work_counters: HashMap<TypeId, Vec<Box<dyn WorkCounter>>>,
+    /// The statistics of the work packets executed since the last GC, keyed by type name.  They
+    /// are taken at the end of each GC.
+    gc_stats: HashMap<&'static str, WorkPacketStat>,
     enabled: AtomicBool,
     _phantom: PhantomData<C>,
 }
@@ -200,6 +276,7 @@ impl<C> Default for WorkerLocalStat<C> {
             work_id_name_map: Default::default(),
             work_counts: Default::default(),
             work_counters: Default::default(),
+            gc_stats: Default::default(),
             enabled: AtomicBool::new(false),
             _phantom: Default::default(),
         }
@@ -213,17 +290,24 @@ impl<VM: VMBinding> WorkerLocalStat<VM> {
     pub fn enable(&self) {
         self.enabled.store(true, Ordering::SeqCst);
     }
+    /// Take the statistics of the work packets executed since the last call.
+    pub fn take_gc_stats(&mut self) -> HashMap<&'static str, WorkPacketStat> {
+        std::mem::take(&mut self.gc_stats)
+    }
+
     /// Measure the execution of a work packet by starting all counters for that
-    /// type
+    /// type.  `cost` is the estimated cost of the packet, if any.
     pub fn measure_work(
         &mut self,
         work_id: TypeId,
         work_name: &'static str,
+        cost: Option<usize>,
         mmtk: &'static MMTK<VM>,
     ) -> WorkStat {
         let stat = WorkStat {
             type_id: work_id,
             type_name: work_name,
+            work_units: cost.unwrap_or(0),
         };
         if self.is_enabled() {
             self.work_counters
@@ -250,3 +334,46 @@ impl<VM: VMBinding> WorkerLocalStat<VM> {
         counters
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(count: usize, work_units: usize, times: &[f64], cycles: f64) -> WorkPacketStat {
+        let mut stat = WorkPacketStat {
+            count,
+            work_units,
+            ..Default::default()
+        };
+        for time in times {
+            stat.time.merge_val(*time);
+        }
+        stat.perf_event_mut("CYCLES").merge_val(cycles);
+        stat
+    }
+
+    #[test]
+    fn merge_workers() {
+        let gc_stats = GCWorkPacketStats {
+            workers: vec![
+                HashMap::from([("Foo", stat(2, 10, &[1.0, 3.0], 100.0))]),
+                HashMap::from([
+                    ("Foo", stat(1, 5, &[2.0], 50.0)),
+                    ("Bar", stat(1, 0, &[4.0], 70.0)),
+                ]),
+            ],
+        };
+        let merged = gc_stats.merged();
+        assert_eq!(merged.len(), 2);
+        let foo = &merged["Foo"];
+        assert_eq!(foo.count, 3);
+        assert_eq!(foo.work_units, 15);
+        assert_eq!(foo.time.total, 6.0);
+        assert_eq!(foo.time.min, 1.0);
+        assert_eq!(foo.time.max, 3.0);
+        assert_eq!(foo.perf_events.len(), 1);
+        assert_eq!(foo.perf_events[0].0, "CYCLES");
+        assert_eq!(foo.perf_events[0].1.total, 150.0);
+        assert_eq!(merged["Bar"].count, 1);
+    }
+}
@@ -2,9 +2,23 @@ use super::worker::*;
 use crate::plan::tracing::gc_work::root::DefaultRootsWorkFactory;
 use crate::vm::{RootsWorkFactory, VMBinding};
 use crate::{mmtk::MMTK, plan::tracing::Trace};
+use enum_map::Enum;
 #[cfg(feature = "work_packet_stats")]
 use std::any::{type_name, TypeId};
 
+/// The priority of a work packet within its work bucket.  Workers take packets of higher
+/// priorities first.  Members are listed from the highest priority to the lowest priority.
+#[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
+pub enum WorkPriority {
+    /// Packets that should start as early as possible, such as root scanning and packets
+    /// that are much larger than usual.  Starting long packets early shortens the tail of a stage.
+    High,
+    /// The default priority.
+    Normal,
+    /// Packets that can wait until other packets of the bucket have started.
+    Low,
+}
+
 /// This defines a GC work packet which are assigned to the [`GCWorker`]s by the scheduler.
 /// Work packets carry payloads that indicate the work to be done. For example, a work packet may
 /// contain a pointer to a stack that must be scanned, or it may contain a large buffer of pointers
@@ -39,7 +53,12 @@ pub trait GCWork<VM: VMBinding>: 'static + Send {
         // Start collecting statistics
         let stat = {
             let mut worker_stat = worker.shared.borrow_stat_mut();
-            worker_stat.measure_work(TypeId::of::<Self>(), type_name::<Self>(), mmtk)
+            worker_stat.measure_work(
+                TypeId::of::<Self>(),
+                type_name::<Self>(),
+                self.estimated_cost(),
+                mmtk,
+            )
         };
 
         // Do the actual work
@@ -57,6 +76,26 @@ pub trait GCWork<VM: VMBinding>: 'static + Send {
     fn get_type_name(&self) -> &'static str {
         std::any::type_name::<Self>()
     }
+
+    /// The priority of this work packet within its bucket.
+    fn priority(&self) -> WorkPriority {
+        WorkPriority::Normal
+    }
+
+    /// The estimated cost of this work packet in units defined by its type, such as the number of
+    /// slots or objects to process, or `None` if unknown.  The scheduler splits packets whose
+    /// estimated time exceeds the option `work_packet_split_us` when other workers are idle.  The
+    /// time of a unit of each type is refined from measurements if the feature
+    /// `work_packet_stats` is enabled.
+    fn estimated_cost(&self) -> Option<usize> {
+        None
+    }
+
+    /// Split off about half of the work of this packet as a new packet, or return `None` if this
+    /// packet cannot be split.  The new packet must be able to execute in parallel with this one.
+    fn split(&mut self) -> Option<Box<dyn GCWork<VM>>> {
+        None
+    }
 }
 
 use crate::plan::Plan;
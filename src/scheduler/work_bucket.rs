@@ -2,39 +2,59 @@ use super::worker_monitor::WorkerMonitor;
 use super::*;
 use crate::vm::VMBinding;
 use crossbeam::deque::{Injector, Steal, Worker};
-use enum_map::Enum;
-use std::sync::atomic::{AtomicBool, Ordering};
+use enum_map::{Enum, EnumArray, EnumMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// The queues of a bucket, one for each [`WorkPriority`].
 pub(super) struct BucketQueue<VM: VMBinding> {
-    queue: Injector<Box<dyn GCWork<VM>>>,
+    queues: EnumMap<WorkPriority, Injector<Box<dyn GCWork<VM>>>>,
 }
 
 impl<VM: VMBinding> BucketQueue<VM> {
     fn new() -> Self {
         Self {
-            queue: Injector::new(),
+            queues: EnumMap::from_fn(|_| Injector::new()),
         }
     }
 
     fn is_empty(&self) -> bool {
-        self.queue.is_empty()
+        self.queues.values().all(|queue| queue.is_empty())
     }
 
+    /// Take a packet of the highest priority available.  High-priority packets are taken one at a
+    /// time so that they spread over workers.  Packets of other priorities are taken in batches,
+    /// and the rest of the batch is moved to the local queue `dest`.
     fn steal_batch_and_pop(
         &self,
         dest: &Worker<Box<dyn GCWork<VM>>>,
     ) -> Steal<Box<dyn GCWork<VM>>> {
-        self.queue.steal_batch_and_pop(dest)
+        let mut result = Steal::Empty;
+        for (priority, queue) in self.queues.iter() {
+            let steal = if priority == WorkPriority::High {
+                queue.steal()
+            } else {
+                queue.steal_batch_and_pop(dest)
+            };
+            result = result.or_else(|| steal);
+            if result.is_success() {
+                break;
+            }
+        }
+        result
     }
 
     fn push(&self, w: Box<dyn GCWork<VM>>) {
-        self.queue.push(w);
+        self.queues[w.priority()].push(w);
+    }
+
+    fn push_with_priority(&self, w: Box<dyn GCWork<VM>>, priority: WorkPriority) {
+        self.queues[priority].push(w);
     }
 
     fn push_all(&self, ws: Vec<Box<dyn GCWork<VM>>>) {
         for w in ws {
-            self.queue.push(w);
+            self.push(w);
         }
     }
 
@@ -44,10 +64,10 @@ impl<VM: VMBinding> BucketQueue<VM> {
     pub fn debug_dump_packets(&self) -> Vec<String> {
         let mut items = Vec::new();
 
-        {
+        for queue in self.queues.values() {
             // Drain queue by stealing until empty
             loop {
-                match self.queue.steal() {
+                match queue.steal() {
                     crossbeam::deque::Steal::Success(work) => {
                         items.push(work);
                     }
@@ -66,7 +86,7 @@ impl<VM: VMBinding> BucketQueue<VM> {
         // Push items back into the queue
         {
             for work in items {
-                self.queue.push(work);
+                self.push(work);
             }
         }
 
@@ -86,8 +106,8 @@ pub struct WorkBucket<VM: VMBinding> {
     enabled: AtomicBool,
     /// The stage name of this bucket.
     stage: WorkBucketStage,
+    /// The queued packets.  Workers take packets of higher priorities first.
     queue: BucketQueue<VM>,
-    prioritized_queue: Option<BucketQueue<VM>>,
     monitor: Arc<WorkerMonitor>,
     /// The open condition for a bucket. If this is `Some`, the bucket will be open
     /// when the condition is met. If this is `None`, the bucket needs to be open manually.
@@ -104,6 +124,10 @@ pub struct WorkBucket<VM: VMBinding> {
     /// recursively, such as ephemerons and Java-style SoftReference and finalizers.  Sentinels
     /// can be used repeatedly to discover and process more such objects.
     sentinel: Mutex<Option<Box<dyn GCWork<VM>>>>,
+    /// The number of mutators executing packets taken from this bucket.  The packets they hold,
+    /// including those in their local queues and those they are executing, are not in `queue`,
+    /// so the bucket is not drained until they finish.
+    in_flight_assists: AtomicUsize,
 }
 
 impl<VM: VMBinding> WorkBucket<VM> {
@@ -113,10 +137,10 @@ impl<VM: VMBinding> WorkBucket<VM> {
             enabled: AtomicBool::new(stage.is_enabled_by_default()),
             stage,
             queue: BucketQueue::new(),
-            prioritized_queue: None,
             monitor,
             can_open: None,
             sentinel: Mutex::new(None),
+            in_flight_assists: AtomicUsize::new(0),
         }
     }
 
@@ -128,9 +152,10 @@ impl<VM: VMBinding> WorkBucket<VM> {
         self.enabled.load(Ordering::Relaxed)
     }
 
-    pub fn enable_prioritized_queue(&mut self) {
-        self.prioritized_queue = Some(BucketQueue::new());
-    }
+    /// Buckets used to need a separate queue for [`WorkBucket::add_prioritized`].  Every bucket
+    /// now queues packets by [`WorkPriority`], so this does nothing.
+    #[deprecated = "Every bucket accepts prioritized packets.  This method does nothing."]
+    pub fn enable_prioritized_queue(&mut self) {}
 
     fn notify_one_worker(&self) {
         // If the bucket is not open, don't notify anyone.
@@ -162,15 +187,28 @@ impl<VM: VMBinding> WorkBucket<VM> {
     /// Test if the bucket is drained
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
-            && self
-                .prioritized_queue
-                .as_ref()
-                .map(|q| q.is_empty())
-                .unwrap_or(true)
     }
 
+    /// Test if the bucket is drained, i.e. it has no queued packets, and no mutator is executing
+    /// packets taken from it.
     pub fn is_drained(&self) -> bool {
-        !self.is_enabled() || (self.is_open() && self.is_empty())
+        !self.is_enabled()
+            || (self.is_open()
+                && self.is_empty()
+                && self.in_flight_assists.load(Ordering::SeqCst) == 0)
+    }
+
+    /// Called before a mutator takes packets from this bucket to assist GC workers.  The bucket
+    /// is not drained until the matching call to [`WorkBucket::end_assist`].
+    pub(crate) fn begin_assist(&self) {
+        self.in_flight_assists.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Called after an assisting mutator has executed its packets, and put the packets it did
+    /// not execute back into this bucket.
+    pub(crate) fn end_assist(&self) {
+        let old = self.in_flight_assists.fetch_sub(1, Ordering::SeqCst);
+        debug_assert_ne!(old, 0, "end_assist() called without begin_assist()");
     }
 
     /// Close the bucket
@@ -183,14 +221,14 @@ impl<VM: VMBinding> WorkBucket<VM> {
         self.open.store(false, Ordering::Relaxed);
     }
 
-    /// Add a work packet to this bucket
-    /// Panic if this bucket cannot receive prioritized packets.
+    /// Add a work packet to this bucket with [`WorkPriority::High`], regardless of the priority
+    /// of the packet.
     pub fn add_prioritized(&self, work: Box<dyn GCWork<VM>>) {
-        self.prioritized_queue.as_ref().unwrap().push(work);
+        self.queue.push_with_priority(work, WorkPriority::High);
         self.notify_one_worker();
     }
 
-    /// Add a work packet to this bucket
+    /// Add a work packet to this bucket.  It is queued according to [`GCWork::priority`].
     pub fn add<W: GCWork<VM>>(&self, work: W) {
         self.queue.push(Box::new(work));
         self.notify_one_worker();
@@ -215,10 +253,11 @@ impl<VM: VMBinding> WorkBucket<VM> {
         self.queue.push(work);
     }
 
-    /// Add multiple packets with a higher priority.
-    /// Panic if this bucket cannot receive prioritized packets.
+    /// Add multiple packets with [`WorkPriority::High`].
     pub fn bulk_add_prioritized(&self, work_vec: Vec<Box<dyn GCWork<VM>>>) {
-        self.prioritized_queue.as_ref().unwrap().push_all(work_vec);
+        for work in work_vec {
+            self.queue.push_with_priority(work, WorkPriority::High);
+        }
         self.notify_all_workers();
     }
 
@@ -236,13 +275,7 @@ impl<VM: VMBinding> WorkBucket<VM> {
         if !self.is_enabled() || !self.is_open() || self.is_empty() {
             return Steal::Empty;
         }
-        if let Some(prioritized_queue) = self.prioritized_queue.as_ref() {
-            prioritized_queue
-                .steal_batch_and_pop(worker)
-                .or_else(|| self.queue.steal_batch_and_pop(worker))
-        } else {
-            self.queue.steal_batch_and_pop(worker)
-        }
+        self.queue.steal_batch_and_pop(worker)
     }
 
     pub fn set_open_condition(
@@ -304,7 +337,10 @@ impl<VM: VMBinding> WorkBucket<VM> {
 
 /// This enum defines all the work bucket types. The scheduler
 /// will instantiate a work bucket for each stage defined here.
+///
+/// New stages may be added in the future, so bindings that match on this enum need a wildcard arm.
 #[derive(Debug, Enum, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum WorkBucketStage {
     /// This bucket is always open.
     Unconstrained,
@@ -327,6 +363,10 @@ pub enum WorkBucketStage {
     Closure,
     /// Handle Java-style soft references, and potentially expand the transitive closure.
     SoftRefClosure,
+    /// Trace the values of ephemerons whose keys are reachable until reaching a fixpoint.  The
+    /// ephemerons whose keys are not reachable are cleared here only if no finalization follows.
+    /// Otherwise they are cleared in `FinalRefClosure`, after finalization may resurrect their keys.
+    EphemeronClosure,
     /// Handle Java-style weak references.
     WeakRefClosure,
     /// Resurrect Java-style finalizable objects, and potentially expand the transitive closure.
@@ -345,7 +385,8 @@ pub enum WorkBucketStage {
     /// Scan roots again to initiate another transitive closure to update roots and reference
     /// after computing the forwarding addresses (mark-compact-only).
     SecondRoots,
-    /// Update Java-style weak references after computing forwarding addresses (mark-compact-only).
+    /// Update Java-style weak references and ephemerons after computing forwarding addresses
+    /// (mark-compact-only).
     ///
     /// NOTE: This stage should be updated to adapt to the VM-side reference handling.  It shall
     /// be kept after removing `{Soft,Weak,Final,Phantom}RefClosure`.
@@ -366,6 +407,36 @@ pub enum WorkBucketStage {
     Release,
     /// Resume mutators and end GC.
     Final,
+    /// A stage declared by the VM binding with [`crate::MMTKBuilder::add_custom_stage`].  Custom
+    /// stages are stop-the-world stages, opened in the order given when they are declared.
+    Custom(CustomStage),
+}
+
+/// The identifier of a custom stage.  At most [`CustomStage::MAX`] custom stages can be declared.
+/// The buckets of the stages that are not declared are disabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CustomStage(pub(crate) u8);
+
+impl CustomStage {
+    /// The maximum number of custom stages.
+    pub const MAX: usize = 8;
+}
+
+impl Enum for CustomStage {
+    const LENGTH: usize = Self::MAX;
+
+    fn from_usize(value: usize) -> Self {
+        debug_assert!(value < Self::MAX);
+        CustomStage(value as u8)
+    }
+
+    fn into_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl<V> EnumArray<V> for CustomStage {
+    type Array = [V; Self::MAX];
 }
 
 impl WorkBucketStage {
@@ -391,9 +462,12 @@ impl WorkBucketStage {
         )
     }
 
-    /// Is this stage enabled by default?
+    /// Is this stage enabled by default?  Custom stages are enabled when they are declared.
     pub const fn is_enabled_by_default(&self) -> bool {
-        !matches!(self, WorkBucketStage::Concurrent)
+        !matches!(
+            self,
+            WorkBucketStage::Concurrent | WorkBucketStage::Custom(_)
+        )
     }
 
     /// Is this stage sequentially opened? All the stop-the-world stages, except the first one, are sequentially opened.
@@ -413,4 +487,118 @@ impl WorkBucketStage {
             WorkBucketStage::Unconstrained | WorkBucketStage::Concurrent
         )
     }
+
+    /// Can a custom stage be placed after this stage, given the number of custom stages declared
+    /// so far?
+    pub(crate) fn can_precede_custom_stage(&self, num_custom_stages: usize) -> bool {
+        match self {
+            WorkBucketStage::Custom(custom) => (custom.0 as usize) < num_custom_stages,
+            WorkBucketStage::Final => false,
+            stage => stage.is_stw(),
+        }
+    }
+
+    /// The stop-the-world stages in the order they are opened.  `custom_stages[i]` is the stage
+    /// after which `Custom(CustomStage(i))` is placed.  A custom stage is placed after the custom
+    /// stages previously placed after the same stage.
+    pub(crate) fn stw_stages_in_order(custom_stages: &[WorkBucketStage]) -> Vec<WorkBucketStage> {
+        let mut stages: Vec<WorkBucketStage> = (0..WorkBucketStage::LENGTH)
+            .map(WorkBucketStage::from_usize)
+            .filter(|stage| stage.is_stw() && !matches!(stage, WorkBucketStage::Custom(_)))
+            .collect();
+        for (i, after) in custom_stages.iter().enumerate() {
+            debug_assert!(after.can_precede_custom_stage(i));
+            let mut index = stages.iter().position(|stage| stage == after).unwrap() + 1;
+            while matches!(stages.get(index), Some(WorkBucketStage::Custom(_))) {
+                index += 1;
+            }
+            stages.insert(index, WorkBucketStage::Custom(CustomStage(i as u8)));
+        }
+        stages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_stages_are_opened_in_declared_positions() {
+        let custom = |i| WorkBucketStage::Custom(CustomStage(i));
+        let stages = WorkBucketStage::stw_stages_in_order(&[
+            WorkBucketStage::Closure,
+            WorkBucketStage::Closure,
+            custom(0),
+            WorkBucketStage::Release,
+        ]);
+        let position = |stage| stages.iter().position(|s| *s == stage).unwrap();
+        assert_eq!(stages[0], WorkBucketStage::FIRST_STW_STAGE);
+        assert_eq!(*stages.last().unwrap(), WorkBucketStage::Final);
+        assert_eq!(position(custom(0)), position(WorkBucketStage::Closure) + 1);
+        assert_eq!(position(custom(1)), position(custom(0)) + 1);
+        assert_eq!(position(custom(2)), position(custom(1)) + 1);
+        assert_eq!(
+            position(WorkBucketStage::SoftRefClosure),
+            position(custom(2)) + 1
+        );
+        assert_eq!(position(custom(3)), position(WorkBucketStage::Final) - 1);
+        assert_eq!(
+            stages.len(),
+            WorkBucketStage::LENGTH - CustomStage::MAX - 2 + 4
+        );
+    }
+
+    #[cfg(feature = "mock_test")]
+    #[test]
+    fn bucket_queue_takes_higher_priorities_first() {
+        use crate::util::test_util::mock_vm::MockVM;
+        use crate::MMTK;
+
+        /// A packet that is only queued, not executed.
+        struct Packet(WorkPriority);
+
+        impl GCWork<MockVM> for Packet {
+            fn do_work(&mut self, _worker: &mut GCWorker<MockVM>, _mmtk: &'static MMTK<MockVM>) {
+                unreachable!()
+            }
+
+            fn priority(&self) -> WorkPriority {
+                self.0
+            }
+        }
+
+        let queue = BucketQueue::<MockVM>::new();
+        queue.push(Box::new(Packet(WorkPriority::Low)));
+        queue.push(Box::new(Packet(WorkPriority::Normal)));
+        queue.push(Box::new(Packet(WorkPriority::Normal)));
+        queue.push(Box::new(Packet(WorkPriority::High)));
+        queue.push_with_priority(Box::new(Packet(WorkPriority::Low)), WorkPriority::High);
+
+        let local = Worker::new_fifo();
+        let mut taken = vec![];
+        while let Steal::Success(work) = queue.steal_batch_and_pop(&local) {
+            taken.push(work.priority());
+        }
+        assert!(queue.is_empty());
+        // High-priority packets are taken one at a time, in the order they were added.  Normal
+        // packets are taken in a batch, and the rest of the batch is in the local queue.
+        assert_eq!(
+            taken[..3],
+            [WorkPriority::High, WorkPriority::Low, WorkPriority::Normal]
+        );
+        assert_eq!(taken.last(), Some(&WorkPriority::Low));
+        assert_eq!(taken.len() + local.len(), 5);
+        while let Some(work) = local.pop() {
+            assert_eq!(work.priority(), WorkPriority::Normal);
+        }
+    }
+
+    #[test]
+    fn custom_stages_must_follow_stw_stages() {
+        assert!(WorkBucketStage::Closure.can_precede_custom_stage(0));
+        assert!(!WorkBucketStage::Concurrent.can_precede_custom_stage(0));
+        assert!(!WorkBucketStage::Final.can_precede_custom_stage(0));
+        assert!(!WorkBucketStage::Custom(CustomStage(1)).can_precede_custom_stage(1));
+        assert!(WorkBucketStage::Custom(CustomStage(1)).can_precede_custom_stage(2));
+    }
 }
@@ -0,0 +1,122 @@
+//! Cost estimates of work packets.
+//!
+//! A work packet may report an estimated cost in units defined by its type (see
+//! [`crate::scheduler::GCWork::estimated_cost`]).  The cost model converts the units into time.  If
+//! the feature `work_packet_stats` is enabled, the time of each unit is refined after each GC from
+//! the measured execution time of the packets executed while statistics are enabled.  Each worker
+//! keeps a copy of the model in a [`WorkerCostEstimates`], so that it does not take the lock of the
+//! model for each packet.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+/// The total units and execution time of the measured packets of one type.
+#[cfg(any(feature = "work_packet_stats", test))]
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct WorkCostSample {
+    /// The sum of the estimated costs of the packets.
+    pub units: usize,
+    /// The sum of the execution times of the packets, in nanoseconds.
+    pub nanos: u64,
+}
+
+/// The time of one cost unit of each work packet type, keyed by the type name.
+#[derive(Default)]
+pub(crate) struct WorkCostModel {
+    nanos_per_unit: RwLock<HashMap<&'static str, f64>>,
+    /// Incremented each time `nanos_per_unit` is refined.
+    generation: AtomicUsize,
+}
+
+impl WorkCostModel {
+    /// The time of one unit for packet types that have not been measured.  It is about the time
+    /// to trace one slot.
+    const DEFAULT_NANOS_PER_UNIT: f64 = 20.0;
+    /// The weight of the latest measurement when refining the time of a unit.
+    #[cfg(any(feature = "work_packet_stats", test))]
+    const UPDATE_WEIGHT: f64 = 0.5;
+
+    /// Copy the time of a unit of each packet type into `estimates`, unless it is up to date.
+    fn refresh(&self, estimates: &mut WorkerCostEstimates) {
+        let generation = self.generation.load(Ordering::Acquire);
+        if estimates.generation == Some(generation) {
+            return;
+        }
+        estimates
+            .nanos_per_unit
+            .clone_from(&self.nanos_per_unit.read().unwrap());
+        estimates.generation = Some(generation);
+    }
+
+    /// Refine the time of a unit with the measurements of a GC.
+    #[cfg(any(feature = "work_packet_stats", test))]
+    pub fn update(&self, samples: &HashMap<&'static str, WorkCostSample>) {
+        let mut nanos_per_unit = self.nanos_per_unit.write().unwrap();
+        for (name, sample) in samples {
+            if sample.units == 0 {
+                continue;
+            }
+            let measured = sample.nanos as f64 / sample.units as f64;
+            nanos_per_unit
+                .entry(name)
+                .and_modify(|old| *old += (measured - *old) * Self::UPDATE_WEIGHT)
+                .or_insert(measured);
+        }
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// A copy of a [`WorkCostModel`] owned by one worker.  It is refreshed when the model changes,
+/// which only happens between GCs.
+#[derive(Default)]
+pub(crate) struct WorkerCostEstimates {
+    /// The generation of the model that `nanos_per_unit` was copied from, or `None` if it has not
+    /// been copied.
+    generation: Option<usize>,
+    nanos_per_unit: HashMap<&'static str, f64>,
+}
+
+impl WorkerCostEstimates {
+    /// The estimated execution time, in nanoseconds, of one cost unit of a packet of type `name`.
+    /// This only takes the lock of `model` if `model` has changed since the last call.
+    pub fn nanos_per_unit(&mut self, model: &WorkCostModel, name: &'static str) -> f64 {
+        model.refresh(self);
+        self.nanos_per_unit
+            .get(name)
+            .copied()
+            .unwrap_or(WorkCostModel::DEFAULT_NANOS_PER_UNIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurements_refine_estimates() {
+        let model = WorkCostModel::default();
+        let mut estimates = WorkerCostEstimates::default();
+        assert_eq!(estimates.nanos_per_unit(&model, "Foo"), 20.0);
+
+        let sample = WorkCostSample {
+            units: 200,
+            nanos: 40_000,
+        };
+        model.update(&HashMap::from([
+            ("Foo", sample),
+            ("Bar", Default::default()),
+        ]));
+        assert_eq!(estimates.nanos_per_unit(&model, "Foo"), 200.0);
+        assert_eq!(estimates.nanos_per_unit(&model, "Bar"), 20.0);
+
+        model.update(&HashMap::from([(
+            "Foo",
+            WorkCostSample {
+                units: 10,
+                nanos: 1000,
+            },
+        )]));
+        assert_eq!(estimates.nanos_per_unit(&model, "Foo"), 150.0);
+    }
+}
@@ -10,10 +10,13 @@ use std::time::Instant;
 ///
 /// Stores the total, min and max of counter readings
 #[derive(Copy, Clone, Debug)]
-pub(super) struct WorkCounterBase {
-    pub(super) total: f64,
-    pub(super) min: f64,
-    pub(super) max: f64,
+pub struct WorkCounterBase {
+    /// The sum of the readings.
+    pub total: f64,
+    /// The minimum reading, or infinity if there are no readings.
+    pub min: f64,
+    /// The maximum reading, or negative infinity if there are no readings.
+    pub max: f64,
 }
 
 /// Make [`WorkCounter`] trait objects cloneable
@@ -1,18 +1,26 @@
+use super::concurrent_budget::ConcurrentWorkBudget;
+use super::gc_work::ReportWorkerEvent;
 use super::stat::WorkerLocalStat;
 use super::work_bucket::*;
+use super::work_cost::WorkerCostEstimates;
 use super::*;
 use crate::mmtk::MMTK;
+use crate::util::accounting_context::AccountingContext;
 use crate::util::copy::GCWorkerCopyContext;
 use crate::util::heap::layout::heap_parameters::MAX_SPACES;
+use crate::util::numa::NUMA_TOPOLOGY;
 use crate::util::opaque_pointer::*;
+use crate::util::stress::{ReportSeedOnPanic, StressRng};
 use crate::util::ObjectReference;
-use crate::vm::{Collection, GCThreadContext, VMBinding};
+use crate::vm::{Collection, GCThreadContext, GCWorkerEvent, VMBinding};
 use atomic::Atomic;
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut};
 use crossbeam::deque::{self, Stealer};
-use crossbeam::queue::ArrayQueue;
-use std::sync::atomic::Ordering;
+use crossbeam::queue::{ArrayQueue, SegQueue};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Represents the ID of a GC worker thread.
 pub type ThreadId = usize;
@@ -33,6 +41,12 @@ pub fn current_worker_ordinal() -> ThreadId {
     ordinal
 }
 
+/// Return true if the current thread is executing GC work as a GC worker.  This includes a mutator
+/// thread that drives a GC if the option `mutator_driven_gc` is set.
+pub(crate) fn is_current_thread_gc_worker() -> bool {
+    WORKER_ORDINAL.with(|x| x.load(Ordering::Relaxed)) != ThreadId::MAX
+}
+
 /// The struct has one instance per worker, but is shared between workers via the scheduler
 /// instance.  This structure is used for communication between workers, e.g. adding designated
 /// work packets, stealing work packets from other workers, and collecting per-worker statistics.
@@ -44,10 +58,25 @@ pub struct GCWorkerShared<VM: VMBinding> {
     /// at the end of a GC, and reset this counter.
     /// The live bytes are stored in an array. The index is the index from the space descriptor.
     pub live_bytes_per_space: AtomicRefCell<[usize; MAX_SPACES]>,
+    /// Accumulated bytes for live objects of each accounting context in this GC. This is only
+    /// used if both `count_live_bytes_in_gc` and `accounting_contexts` are enabled.
+    pub live_bytes_per_accounting_context: AtomicRefCell<HashMap<AccountingContext, usize>>,
     /// A queue of GCWork that can only be processed by the owned thread.
     pub designated_work: ArrayQueue<Box<dyn GCWork<VM>>>,
+    /// Events to report on the thread of this worker before it executes any other work packet.
+    /// Unlike `designated_work`, this queue is unbounded, so adding an event never fails.
+    pub(crate) pending_events: SegQueue<GCWorkerEvent>,
+    /// Whether this worker has reported `GCWorkerEvent::GCStarted` for the current GC.  Only such
+    /// workers report `GCWorkerEvent::GCFinished`.
+    pub(crate) gc_started_reported: AtomicBool,
+    /// Whether the copy context of this worker has been prepared for a GC, and not released since.
+    /// Only workers active in a GC prepare and release their copy contexts, so a worker that is
+    /// inactive when a GC releases them releases its copy context when it is activated again.
+    pub(crate) copy_context_prepared: AtomicBool,
     /// Handle for stealing packets from the current worker
     pub stealer: Option<Stealer<Box<dyn GCWork<VM>>>>,
+    /// The NUMA node that the worker runs on.  It is set when the worker starts.
+    pub numa_node: AtomicU16,
 }
 
 impl<VM: VMBinding> GCWorkerShared<VM> {
@@ -55,11 +84,24 @@ impl<VM: VMBinding> GCWorkerShared<VM> {
         Self {
             stat: Default::default(),
             live_bytes_per_space: AtomicRefCell::new([0; MAX_SPACES]),
+            live_bytes_per_accounting_context: AtomicRefCell::new(HashMap::new()),
             designated_work: ArrayQueue::new(16),
+            pending_events: SegQueue::new(),
+            gc_started_reported: AtomicBool::new(false),
+            copy_context_prepared: AtomicBool::new(false),
             stealer,
+            numa_node: AtomicU16::new(0),
         }
     }
 
+    /// Take a pending event to report, as a work packet, or a designated work packet.
+    pub(crate) fn poll_designated_work(&self) -> Option<Box<dyn GCWork<VM>>> {
+        if let Some(event) = self.pending_events.pop() {
+            return Some(Box::new(ReportWorkerEvent(event)));
+        }
+        self.designated_work.pop()
+    }
+
     pub(crate) fn increase_live_bytes(
         live_bytes_per_space: &mut [usize; MAX_SPACES],
         object: ObjectReference,
@@ -83,6 +125,17 @@ impl<VM: VMBinding> GCWorkerShared<VM> {
             live_bytes_per_space[space_index] += bytes;
         }
     }
+
+    pub(crate) fn increase_live_bytes_for_accounting_context(
+        live_bytes_per_context: &mut HashMap<AccountingContext, usize>,
+        object: ObjectReference,
+    ) {
+        use crate::vm::object_model::ObjectModel;
+
+        let bytes = VM::VMObjectModel::get_current_size(object);
+        let context = VM::VMObjectModel::get_accounting_context(object);
+        *live_bytes_per_context.entry(context).or_insert(0) += bytes;
+    }
 }
 
 /// A GC worker.  This part is privately owned by a worker thread.
@@ -101,6 +154,20 @@ pub struct GCWorker<VM: VMBinding> {
     pub shared: Arc<GCWorkerShared<VM>>,
     /// Local work packet queue.
     pub local_work_buffer: deque::Worker<Box<dyn GCWork<VM>>>,
+    /// The generator that picks the next work packet if the option `randomize_gc_work_order` is set.
+    work_order_rng: Option<StressRng>,
+    /// Work packets taken from the local queue, to be executed in a pseudo-random order.  This is
+    /// only used if `work_order_rng` is set.
+    shuffled_work: Vec<Box<dyn GCWork<VM>>>,
+    /// Whether this worker is driven by mutators instead of running on its own thread.  See the
+    /// option `mutator_driven_gc`.
+    pub(crate) driven_by_mutator: bool,
+    /// How long this worker should sleep to keep within the duty cycle of concurrent work.  See
+    /// the option `concurrent_gc_duty_cycle`.
+    concurrent_sleep_debt: Duration,
+    /// This worker's copy of the cost model of the scheduler, used for splitting oversized work
+    /// packets.
+    cost_estimates: WorkerCostEstimates,
 }
 
 unsafe impl<VM: VMBinding> Sync for GCWorkerShared<VM> {}
@@ -121,7 +188,8 @@ impl<VM: VMBinding> GCWorkerShared<VM> {
 }
 
 /// A special error type that indicate a worker should exit.
-/// This may happen if the VM needs to fork and asks workers to exit.
+/// This may happen if the VM needs to fork and asks workers to exit.  For the worker driven by
+/// mutators, it means the mutator should return from the GC.
 #[derive(Debug)]
 pub(crate) struct WorkerShouldExit;
 
@@ -148,6 +216,11 @@ impl<VM: VMBinding> GCWorker<VM> {
             mmtk,
             shared,
             local_work_buffer,
+            work_order_rng: mmtk.state.stress.work_order_rng(ordinal),
+            shuffled_work: vec![],
+            driven_by_mutator: false,
+            concurrent_sleep_debt: Duration::ZERO,
+            cost_estimates: WorkerCostEstimates::default(),
         }
     }
 
@@ -196,10 +269,14 @@ impl<VM: VMBinding> GCWorker<VM> {
     /// 3. Poll from open global work-buckets
     /// 4. Steal from other workers
     fn poll(&mut self) -> PollResult<VM> {
-        if let Some(work) = self.shared.designated_work.pop() {
+        if let Some(work) = self.shared.poll_designated_work() {
             return Ok(work);
         }
 
+        if self.work_order_rng.is_some() {
+            return self.poll_shuffled();
+        }
+
         if let Some(work) = self.local_work_buffer.pop() {
             return Ok(work);
         }
@@ -207,9 +284,30 @@ impl<VM: VMBinding> GCWorker<VM> {
         self.scheduler().poll(self)
     }
 
+    /// Poll a work packet in a pseudo-random order for stress testing.  All the packets in the
+    /// local queue are moved into `shuffled_work`, and one of them is picked at random.  The worker
+    /// only polls the scheduler, and may park, when it has no packets left.
+    fn poll_shuffled(&mut self) -> PollResult<VM> {
+        while let Some(work) = self.local_work_buffer.pop() {
+            self.shuffled_work.push(work);
+        }
+        if self.shuffled_work.is_empty() {
+            let work = self.scheduler().poll(self)?;
+            self.shuffled_work.push(work);
+            while let Some(work) = self.local_work_buffer.pop() {
+                self.shuffled_work.push(work);
+            }
+        }
+        let rng = self.work_order_rng.as_mut().unwrap();
+        let index = rng.next_below(self.shuffled_work.len());
+        Ok(self.shuffled_work.swap_remove(index))
+    }
+
     /// Entry point of the worker thread.
     ///
-    /// This function will resolve thread affinity, if it has been specified by the user.
+    /// This function will resolve thread affinity, if it has been specified by the user.  The
+    /// binding is informed of the life cycle of the worker with
+    /// [`crate::vm::Collection::on_gc_worker_event`].
     ///
     /// Each worker will keep polling and executing work packets in a loop.  It runs until the
     /// worker is requested to exit.  Currently a worker may exit after
@@ -227,8 +325,63 @@ impl<VM: VMBinding> GCWorker<VM> {
         );
         WORKER_ORDINAL.with(|x| x.store(self.ordinal, Ordering::SeqCst));
         self.scheduler.resolve_affinity(self.ordinal);
+        self.shared
+            .numa_node
+            .store(NUMA_TOPOLOGY.current_node(), Ordering::Relaxed);
         self.tls = tls;
         self.copy = crate::plan::create_gc_worker_context(tls, mmtk);
+        VM::VMCollection::on_gc_worker_event(tls, self.ordinal, GCWorkerEvent::Started);
+        self.execute_work_packets(mmtk);
+        let reason = mmtk.scheduler.worker_exit_reason();
+        VM::VMCollection::on_gc_worker_event(tls, self.ordinal, GCWorkerEvent::Exiting(reason));
+        debug!(
+            "Worker exiting. ordinal: {}, {}",
+            self.ordinal,
+            crate::util::rust_util::debug_process_thread_id(),
+        );
+        probe!(mmtk, gcworker_exit);
+
+        mmtk.scheduler.surrender_gc_worker(self);
+    }
+
+    /// Run GC work on the current mutator thread until the GC the mutator is waiting for has
+    /// finished.  This is used instead of [`GCWorker::run`] for the worker driven by mutators if
+    /// the option `mutator_driven_gc` is set.  The worker must have been unparked with
+    /// `WorkerMonitor::unpark_driver`.
+    ///
+    /// During the GC, `tls` is the worker `tls` passed to the binding, e.g. to
+    /// [`crate::vm::Collection::stop_all_mutators`] and [`crate::vm::Collection::resume_mutators`].
+    pub(crate) fn run_on_mutator(&mut self, tls: VMMutatorThread) {
+        debug_assert!(self.driven_by_mutator);
+        let mmtk = self.mmtk;
+        debug!(
+            "Mutator {:?} drives GC worker {}. {}",
+            tls,
+            self.ordinal,
+            crate::util::rust_util::debug_process_thread_id(),
+        );
+        // The mutator becomes a GC worker until it returns.
+        let old_ordinal = WORKER_ORDINAL.with(|x| x.swap(self.ordinal, Ordering::SeqCst));
+        debug_assert_eq!(old_ordinal, ThreadId::MAX);
+        self.shared
+            .numa_node
+            .store(NUMA_TOPOLOGY.current_node(), Ordering::Relaxed);
+        let tls = VMWorkerThread(tls.0);
+        if self.tls != tls {
+            // The copy context allocates on behalf of the thread that runs the GC.  Create it again
+            // if a different mutator drove the last GC.
+            self.tls = tls;
+            self.copy = crate::plan::create_gc_worker_context(tls, mmtk);
+        }
+        self.execute_work_packets(mmtk);
+        WORKER_ORDINAL.with(|x| x.store(old_ordinal, Ordering::SeqCst));
+        debug!("Mutator {:?} returns from GC.", tls);
+    }
+
+    /// Poll and execute work packets until polling returns `Err(WorkerShouldExit)`.
+    fn execute_work_packets(&mut self, mmtk: &'static MMTK<VM>) {
+        // Print the stress seed if a work packet panics, so that the failure can be replayed.
+        let _report_seed = mmtk.state.stress.seed().map(ReportSeedOnPanic);
         loop {
             // Instead of having work_start and work_end tracepoints, we have
             // one tracepoint before polling for more work and one tracepoint
@@ -240,7 +393,8 @@ impl<VM: VMBinding> GCWorker<VM> {
             // poll.
             probe!(mmtk, work_poll);
             let Ok(mut work) = self.poll() else {
-                // The worker is asked to exit.  Break from the loop.
+                // The worker is asked to exit, or the mutator driving it should return.  Break
+                // from the loop.
                 break;
             };
             // probe! expands to an empty block on unsupported platforms
@@ -254,16 +408,76 @@ impl<VM: VMBinding> GCWorker<VM> {
             std::hint::black_box(unsafe { *(typename.as_ptr()) });
 
             probe!(mmtk, work, typename.as_ptr(), typename.len());
-            work.do_work_with_stat(&mut self, mmtk);
+            self.split_oversized_work(&mut work);
+            // Work packets executed while mutators are running are concurrent work.
+            let throttled = !self.driven_by_mutator
+                && !mmtk.gc_in_progress()
+                && self.scheduler.concurrent_budget.is_duty_cycle_limited();
+            let start = throttled.then(Instant::now);
+            work.do_work_with_stat(self, mmtk);
+            if let Some(start) = start {
+                self.sleep_for_duty_cycle(start.elapsed());
+            }
         }
-        debug!(
-            "Worker exiting. ordinal: {}, {}",
-            self.ordinal,
-            crate::util::rust_util::debug_process_thread_id(),
-        );
-        probe!(mmtk, gcworker_exit);
+    }
 
-        mmtk.scheduler.surrender_gc_worker(self);
+    /// Sleep after executing concurrent work for `busy` so that this worker is busy for at most
+    /// the duty cycle.  Sleeps shorter than `ConcurrentWorkBudget::MIN_SLEEP` are accumulated.
+    /// The worker sleeps in short intervals, and stops sleeping as soon as a GC starts so that
+    /// it does not delay the pause.
+    fn sleep_for_duty_cycle(&mut self, busy: Duration) {
+        self.concurrent_sleep_debt += self.scheduler.concurrent_budget.sleep_after(busy);
+        while self.concurrent_sleep_debt >= ConcurrentWorkBudget::MIN_SLEEP {
+            if self.mmtk.gc_in_progress() {
+                self.concurrent_sleep_debt = Duration::ZERO;
+                break;
+            }
+            let start = Instant::now();
+            std::thread::sleep(ConcurrentWorkBudget::MIN_SLEEP);
+            self.concurrent_sleep_debt = self.concurrent_sleep_debt.saturating_sub(start.elapsed());
+        }
+    }
+
+    /// Split `work` if its estimated time is longer than the option `work_packet_split_us` and
+    /// some workers are idle.  The split-off parts are pushed to the local queue, from which idle
+    /// workers steal them.  A stolen part may be split again.
+    fn split_oversized_work(&mut self, work: &mut Box<dyn GCWork<VM>>) {
+        let split_us = *self.mmtk.options.work_packet_split_us;
+        if split_us == 0 {
+            return;
+        }
+        // Check the cheap conditions first.  Most packets do not report costs, and most of the
+        // time no workers are idle.
+        let Some(cost) = work.estimated_cost() else {
+            return;
+        };
+        if !self.scheduler.worker_monitor.has_idle_workers() {
+            return;
+        }
+        let limit_nanos = split_us as f64 * 1000.0;
+        let name = work.get_type_name();
+        let nanos_per_unit = self
+            .cost_estimates
+            .nanos_per_unit(&self.scheduler.work_cost_model, name);
+        if cost as f64 * nanos_per_unit <= limit_nanos {
+            return;
+        }
+        let is_oversized = |work: &dyn GCWork<VM>| {
+            work.estimated_cost()
+                .is_some_and(|cost| cost as f64 * nanos_per_unit > limit_nanos)
+        };
+        let mut parts = 0;
+        while is_oversized(work.as_ref()) {
+            let Some(part) = work.split() else {
+                break;
+            };
+            self.local_work_buffer.push(part);
+            parts += 1;
+        }
+        if parts > 0 {
+            trace!("Split {} parts off {}", parts, name);
+            self.scheduler.worker_monitor.notify_work_available(true);
+        }
     }
 }
 
@@ -292,11 +506,28 @@ enum WorkerCreationState<VM: VMBinding> {
 }
 
 /// A worker group to manage all the GC workers.
+///
+/// The group has a fixed capacity, but only the first `num_workers` workers run.  The others are
+/// retired, and can be spawned again if the number of workers grows.  See
+/// [`crate::memory_manager::set_num_of_workers`].
 pub(crate) struct WorkerGroup<VM: VMBinding> {
-    /// Shared worker data
+    /// Shared worker data, including retired workers.
     pub workers_shared: Vec<Arc<GCWorkerShared<VM>>>,
+    /// The number of running workers, including the worker driven by mutators.
+    num_workers: AtomicUsize,
     /// The stateful part.  `None` means state transition is underway.
     state: Mutex<Option<WorkerCreationState<VM>>>,
+    /// `GCWorker` instances of retired workers, i.e. workers whose ordinals are not less than
+    /// `num_workers`.
+    #[allow(clippy::vec_box)] // See `WorkerCreationState::Surrendered`.
+    retired_workers: Mutex<Vec<Box<GCWorker<VM>>>>,
+    /// Whether worker 0 is driven by mutators instead of running on its own thread.  See the
+    /// option `mutator_driven_gc`.
+    mutator_driven: bool,
+    /// The worker driven by mutators.  It is `None` before GC threads are spawned, or if
+    /// `mutator_driven` is false.  A mutator holds the lock while it drives the worker, so other
+    /// mutators that wait for the same GC block on the lock.
+    driver: Mutex<Option<Box<GCWorker<VM>>>>,
 }
 
 /// We have to persuade Rust that `WorkerGroup` is safe to share because the compiler thinks one
@@ -305,13 +536,15 @@ pub(crate) struct WorkerGroup<VM: VMBinding> {
 unsafe impl<VM: VMBinding> Sync for WorkerGroup<VM> {}
 
 impl<VM: VMBinding> WorkerGroup<VM> {
-    /// Create a WorkerGroup
-    pub fn new(num_workers: usize) -> Arc<Self> {
-        let local_work_queues = (0..num_workers)
+    /// Create a WorkerGroup with `num_workers` running workers, and the capacity of `max_workers`
+    /// workers.
+    pub fn new(num_workers: usize, max_workers: usize, mutator_driven: bool) -> Arc<Self> {
+        debug_assert!(num_workers <= max_workers);
+        let local_work_queues = (0..max_workers)
             .map(|_| deque::Worker::new_fifo())
             .collect::<Vec<_>>();
 
-        let workers_shared = (0..num_workers)
+        let workers_shared = (0..max_workers)
             .map(|i| {
                 Arc::new(GCWorkerShared::<VM>::new(Some(
                     local_work_queues[i].stealer(),
@@ -321,7 +554,11 @@ impl<VM: VMBinding> WorkerGroup<VM> {
 
         Arc::new(Self {
             workers_shared,
+            num_workers: AtomicUsize::new(num_workers),
             state: Mutex::new(Some(WorkerCreationState::Initial { local_work_queues })),
+            retired_workers: Mutex::new(vec![]),
+            mutator_driven,
+            driver: Mutex::new(None),
         })
     }
 
@@ -333,13 +570,48 @@ impl<VM: VMBinding> WorkerGroup<VM> {
             panic!("GCWorker structs have already been created");
         };
 
-        let workers = self.create_workers(local_work_queues, mmtk);
+        let mut workers = self.create_workers(local_work_queues, mmtk);
+        *self.retired_workers.lock().unwrap() = workers.split_off(self.worker_count());
+        if self.mutator_driven {
+            // Worker 0 does not have its own thread.  Its copy context is created by the mutator
+            // that drives it.
+            let mut driver = workers.remove(0);
+            driver.driven_by_mutator = true;
+            *self.driver.lock().unwrap() = Some(driver);
+        }
         self.spawn(workers, tls);
 
         *state = Some(WorkerCreationState::Spawned);
     }
 
-    /// Respawn GC threads after stopping for forking.
+    /// Change the number of running workers to `num_workers` while all GC threads are stopped.
+    /// The `GCWorker` instances of workers that are no longer running are retired, and those of
+    /// retired workers that run again are spawned by the next [`Self::respawn`].
+    pub fn resize(&self, num_workers: usize) {
+        debug_assert!(num_workers >= 1 && num_workers <= self.capacity());
+        let mut state = self.state.lock().unwrap();
+        let Some(WorkerCreationState::Surrendered { ref mut workers }) = *state else {
+            panic!("GC threads have not been stopped.");
+        };
+        assert_eq!(
+            workers.len(),
+            self.thread_count(),
+            "GC threads are stopping."
+        );
+
+        let mut retired_workers = self.retired_workers.lock().unwrap();
+        workers.append(&mut retired_workers);
+        workers.sort_by_key(|worker| worker.ordinal);
+        self.num_workers.store(num_workers, Ordering::Relaxed);
+        *retired_workers = workers.split_off(self.thread_count());
+        debug!(
+            "Resized the worker group.  Running: {}, retired: {}",
+            num_workers,
+            retired_workers.len()
+        );
+    }
+
+    /// Respawn GC threads after stopping for forking or resizing.
     pub fn respawn(&self, tls: VMThread) {
         let mut state = self.state.lock().unwrap();
 
@@ -397,7 +669,7 @@ impl<VM: VMBinding> WorkerGroup<VM> {
 
         debug!(
             "Spawned {} worker threads.  {}",
-            self.worker_count(),
+            self.thread_count(),
             crate::util::rust_util::debug_process_thread_id(),
         );
     }
@@ -408,7 +680,7 @@ impl<VM: VMBinding> WorkerGroup<VM> {
         assert!(matches!(*state, Some(WorkerCreationState::Spawned)));
 
         *state = Some(WorkerCreationState::Surrendered {
-            workers: Vec::with_capacity(self.worker_count()),
+            workers: Vec::with_capacity(self.thread_count()),
         })
     }
 
@@ -425,21 +697,68 @@ impl<VM: VMBinding> WorkerGroup<VM> {
             "Worker {} surrendered. ({}/{})",
             ordinal,
             workers.len(),
-            self.worker_count()
+            self.thread_count()
         );
-        workers.len() == self.worker_count()
+        workers.len() == self.thread_count()
     }
 
-    /// Get the number of workers in the group
+    /// Get the number of running workers in the group.
     pub fn worker_count(&self) -> usize {
+        self.num_workers.load(Ordering::Relaxed)
+    }
+
+    /// Get the maximum number of workers in the group, including retired workers.
+    pub fn capacity(&self) -> usize {
         self.workers_shared.len()
     }
 
-    /// Return true if there're any pending designated work
+    /// Get the shared data of the running workers.
+    pub fn running_workers_shared(&self) -> &[Arc<GCWorkerShared<VM>>] {
+        &self.workers_shared[..self.worker_count()]
+    }
+
+    /// Get the number of workers that run on their own GC threads.  It excludes the worker driven
+    /// by mutators.
+    pub fn thread_count(&self) -> usize {
+        self.worker_count() - usize::from(self.mutator_driven)
+    }
+
+    /// Lock the worker driven by mutators.  The result contains `None` if GC threads have not
+    /// been spawned, or the option `mutator_driven_gc` is not set.
+    pub fn lock_driver(&self) -> std::sync::MutexGuard<'_, Option<Box<GCWorker<VM>>>> {
+        self.driver.lock().unwrap()
+    }
+
+    /// Like [`Self::lock_driver`], but return `None` if another thread holds the lock.
+    pub fn try_lock_driver(&self) -> Option<std::sync::MutexGuard<'_, Option<Box<GCWorker<VM>>>>> {
+        match self.driver.try_lock() {
+            Ok(driver) => Some(driver),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+            Err(std::sync::TryLockError::Poisoned(e)) => panic!("{e}"),
+        }
+    }
+
+    /// Get the shared data of the running workers that are active, given the number of active
+    /// workers.
+    pub fn active_workers_shared(&self, active_workers: usize) -> &[Arc<GCWorkerShared<VM>>] {
+        let running_workers = self.running_workers_shared();
+        &running_workers[..active_workers.min(running_workers.len())]
+    }
+
+    /// Return true if there're any pending designated work or events to report
     pub fn has_designated_work(&self) -> bool {
-        self.workers_shared
+        self.running_workers_shared()
+            .iter()
+            .any(|w| !w.designated_work.is_empty() || !w.pending_events.is_empty())
+    }
+
+    /// Return true if any running worker that is not active, given the number of active workers,
+    /// has pending designated work or events to report.
+    pub fn inactive_workers_have_designated_work(&self, active_workers: usize) -> bool {
+        self.running_workers_shared()
             .iter()
-            .any(|w| !w.designated_work.is_empty())
+            .skip(active_workers)
+            .any(|w| !w.designated_work.is_empty() || !w.pending_events.is_empty())
     }
 
     /// Get the live bytes data from the worker, and clear the local data.
@@ -454,4 +773,18 @@ impl<VM: VMBinding> WorkerGroup<VM> {
         });
         ret
     }
+
+    /// Get the live bytes of each accounting context from the workers, and clear the local data.
+    pub fn get_and_clear_worker_live_bytes_per_accounting_context(
+        &self,
+    ) -> HashMap<AccountingContext, usize> {
+        let mut ret = HashMap::new();
+        self.workers_shared.iter().for_each(|w| {
+            let mut live_bytes_per_context = w.live_bytes_per_accounting_context.borrow_mut();
+            for (context, bytes) in live_bytes_per_context.drain() {
+                *ret.entry(context).or_insert(0) += bytes;
+            }
+        });
+        ret
+    }
 }
@@ -22,6 +22,9 @@ pub(crate) struct WorkerGoals {
     current: Option<WorkerGoal>,
     /// Requests received from mutators.  `requests[goal]` is true if the `goal` is requested.
     requests: EnumMap<WorkerGoal, bool>,
+    /// If true, GC requests are not polled.  With the option `mutator_driven_gc`, this is set when
+    /// no mutator is driving GC, because worker 0 only runs when a mutator drives it.
+    gc_deferred: bool,
 }
 
 /// A goal, i.e. something that workers should work together to achieve.
@@ -35,6 +38,8 @@ pub(crate) enum WorkerGoal {
     Shutdown,
     /// Stop all GC threads so that the VM can call `fork()`.
     StopForFork,
+    /// Stop all GC threads so that the number of GC workers can be changed.
+    StopForResize,
 }
 
 impl WorkerGoals {
@@ -50,9 +55,13 @@ impl WorkerGoals {
     }
 
     /// Move the highest priority goal from the pending requests to the current request.  Return
-    /// that goal, or `None` if no goal has been requested.
+    /// that goal, or `None` if no goal has been requested.  GC requests are skipped while they
+    /// are deferred.
     pub fn poll_next_goal(&mut self) -> Option<WorkerGoal> {
         for (goal, requested) in self.requests.iter_mut() {
+            if matches!(goal, WorkerGoal::Gc) && self.gc_deferred {
+                continue;
+            }
             if *requested {
                 *requested = false;
                 self.current = Some(goal);
@@ -74,11 +83,16 @@ impl WorkerGoals {
         self.current = None
     }
 
-    /// Test if the given `goal` is requested.  Used for debug purpose, only.  The workers always
-    /// respond to the request of the highest priority first.
-    pub fn debug_is_requested(&self, goal: WorkerGoal) -> bool {
+    /// Test if the given `goal` is requested.  The workers always respond to the request of the
+    /// highest priority first.
+    pub fn is_requested(&self, goal: WorkerGoal) -> bool {
         self.requests[goal]
     }
+
+    /// Set whether GC requests are deferred.
+    pub fn set_gc_deferred(&mut self, deferred: bool) {
+        self.gc_deferred = deferred;
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +129,22 @@ mod tests {
         assert!(matches!(next_goal, Some(WorkerGoal::Gc)));
         assert!(matches!(goals.current(), Some(WorkerGoal::Gc)));
     }
+
+    #[test]
+    fn test_gc_deferred() {
+        let mut goals = WorkerGoals::default();
+        goals.set_request(WorkerGoal::Gc);
+        goals.set_request(WorkerGoal::StopForFork);
+        goals.set_gc_deferred(true);
+
+        let next_goal = goals.poll_next_goal();
+        assert!(matches!(next_goal, Some(WorkerGoal::StopForFork)));
+        assert!(goals.is_requested(WorkerGoal::Gc));
+
+        goals.on_current_goal_completed();
+        assert!(goals.poll_next_goal().is_none());
+
+        goals.set_gc_deferred(false);
+        assert!(matches!(goals.poll_next_goal(), Some(WorkerGoal::Gc)));
+    }
 }
@@ -1,9 +1,14 @@
 //! This module contains `WorkerMonitor` and related types.  It purposes includes:
 //!
 //! -   allowing workers to park,
-//! -   letting the last parked worker take action, and
-//! -   letting workers and mutators notify workers when workers are given things to do.
+//! -   letting the last parked worker take action,
+//! -   letting workers and mutators notify workers when workers are given things to do, and
+//! -   keeping workers that do not participate in the current GC (inactive workers) parked,
+//! -   keeping workers beyond the CPU budget for concurrent work parked while mutators are
+//!     running, and
+//! -   letting a mutator drive worker 0 if the option `mutator_driven_gc` is set.
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Condvar, Mutex};
 
 use super::{
@@ -18,8 +23,11 @@ pub(crate) enum LastParkedResult {
     ParkSelf,
     /// The last parked worker should unpark and find work packet to do.
     WakeSelf,
-    /// Wake up all parked GC workers.
+    /// Wake up all active parked GC workers.
     WakeAll,
+    /// Wake up all parked GC workers, including inactive ones.  This is needed when inactive
+    /// workers need to exit, or have events to report after they were deactivated during a GC.
+    WakeAllIncludingInactive,
 }
 
 /// A data structure for synchronizing workers with each other and with mutators.
@@ -35,6 +43,27 @@ pub(crate) struct WorkerMonitor {
     /// -   any work packets available, and
     /// -   any field in `sync.goals.requests` set to true.
     workers_have_anything_to_do: Condvar,
+    /// Inactive workers wait on this.  Notified if inactive workers are activated, or have to wake
+    /// up for `LastParkedResult::WakeAllIncludingInactive`.
+    inactive_workers_have_anything_to_do: Condvar,
+    /// The worker driven by a mutator waits on this.  Notified if workers have things to do, or if
+    /// the last parked worker parks, which may mean the GC has finished.
+    driver_has_anything_to_do: Condvar,
+    /// Notified when all GC threads have exited.
+    all_workers_exited: Condvar,
+    /// The number of workers that may execute concurrent work while mutators are running.  Workers
+    /// whose ordinals are not less than this are inactive, like those beyond `sync.active_workers`.
+    /// `usize::MAX` if there is no limit, i.e. outside concurrent work.  It is lowered by the last
+    /// parked worker when it holds `sync`, so it is atomic instead of a field of `sync`.
+    concurrent_worker_limit: AtomicUsize,
+    /// The number of active workers.  Workers whose ordinals are not less than this stay parked
+    /// until they are activated, or `LastParkedResult::WakeAllIncludingInactive` wakes them up.
+    /// It is only changed while holding `sync`.  It is atomic instead of a field of `sync` so that
+    /// the last parked worker can read it in `on_last_parked`, which runs while holding `sync`.
+    active_workers: AtomicUsize,
+    /// The number of parked workers that would unpark if work packets were available.  It is
+    /// updated when `sync` changes, and read without locking `sync`.
+    idle_workers: AtomicUsize,
 }
 
 /// The synchronized part of `WorkerMonitor`.
@@ -43,6 +72,8 @@ struct WorkerMonitorSync {
     parker: WorkerParker,
     /// Current and requested goals.
     goals: WorkerGoals,
+    /// Incremented for each `LastParkedResult::WakeAllIncludingInactive`.
+    inactive_wake_epoch: usize,
 }
 
 /// This struct counts the number of workers parked and identifies the last parked worker.
@@ -90,8 +121,91 @@ impl WorkerMonitor {
             sync: Mutex::new(WorkerMonitorSync {
                 parker: WorkerParker::new(worker_count),
                 goals: Default::default(),
+                inactive_wake_epoch: 0,
             }),
             workers_have_anything_to_do: Default::default(),
+            inactive_workers_have_anything_to_do: Default::default(),
+            driver_has_anything_to_do: Default::default(),
+            all_workers_exited: Default::default(),
+            concurrent_worker_limit: AtomicUsize::new(usize::MAX),
+            active_workers: AtomicUsize::new(worker_count),
+            idle_workers: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of workers that unpark when work packets are available.  This does not lock
+    /// `sync`, so the last parked worker can call it.
+    pub fn effective_active_workers(&self) -> usize {
+        self.active_workers
+            .load(Ordering::Relaxed)
+            .min(self.concurrent_worker_limit.load(Ordering::Relaxed))
+    }
+
+    /// Get the number of active workers.
+    pub fn active_workers(&self) -> usize {
+        self.active_workers.load(Ordering::Relaxed)
+    }
+
+    /// Set the number of active workers.  Only the workers whose ordinals are less than
+    /// `active_workers` unpark when work packets are available.  Other workers keep parked after
+    /// they park next time.
+    pub fn set_active_workers(&self, active_workers: usize) {
+        let sync = self.sync.lock().unwrap();
+        debug_assert!(active_workers >= 1 && active_workers <= sync.parker.worker_count);
+        let old = self.active_workers.swap(active_workers, Ordering::Relaxed);
+        self.update_idle_workers(&sync);
+        if active_workers > old {
+            self.inactive_workers_have_anything_to_do.notify_all();
+        }
+    }
+
+    /// Return true if any active worker is parked, i.e. idle.  This does not lock `sync`, so it is
+    /// cheap enough to call for each work packet, but the result may be slightly out of date.
+    pub fn has_idle_workers(&self) -> bool {
+        self.idle_workers.load(Ordering::Relaxed) > 0
+    }
+
+    /// Update `idle_workers` after the number of parked or active workers changes.
+    fn update_idle_workers(&self, sync: &WorkerMonitorSync) {
+        let inactive_workers = sync.parker.worker_count - self.effective_active_workers();
+        let idle_workers = sync.parker.parked_workers.saturating_sub(inactive_workers);
+        self.idle_workers.store(idle_workers, Ordering::Relaxed);
+    }
+
+    /// Let only the workers whose ordinals are less than `limit` execute work packets until
+    /// [`Self::remove_concurrent_worker_limit`] is called.  Called when concurrent work starts.
+    /// Other workers keep parked after they park next time.  This does not lock `sync`, so the
+    /// last parked worker can call it.
+    pub fn set_concurrent_worker_limit(&self, limit: usize) {
+        debug_assert!(limit >= 1);
+        self.concurrent_worker_limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// Change the limit set by [`Self::set_concurrent_worker_limit`] if concurrent work is still
+    /// limited.
+    pub fn update_concurrent_worker_limit(&self, limit: usize) {
+        debug_assert!(limit >= 1);
+        let result = self.concurrent_worker_limit.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |old| (old != usize::MAX).then_some(limit),
+        );
+        if result.is_ok_and(|old| limit > old) {
+            // Hold the lock so that no worker misses the notification between checking whether it
+            // is inactive and waiting.
+            let _sync = self.sync.lock().unwrap();
+            self.inactive_workers_have_anything_to_do.notify_all();
+        }
+    }
+
+    /// Remove the limit set by [`Self::set_concurrent_worker_limit`].  Called when a GC starts.
+    pub fn remove_concurrent_worker_limit(&self) {
+        let old = self
+            .concurrent_worker_limit
+            .swap(usize::MAX, Ordering::Relaxed);
+        if old != usize::MAX {
+            let _sync = self.sync.lock().unwrap();
+            self.inactive_workers_have_anything_to_do.notify_all();
         }
     }
 
@@ -113,6 +227,35 @@ impl WorkerMonitor {
         } else {
             self.workers_have_anything_to_do.notify_one();
         }
+        self.driver_has_anything_to_do.notify_all();
+    }
+
+    /// Wake up workers according to the result of `on_last_parked`.  Return true if the last
+    /// parked worker should wait, too.
+    fn wake_up_after_last_parked(
+        &self,
+        sync: &mut WorkerMonitorSync,
+        result: LastParkedResult,
+    ) -> bool {
+        match result {
+            LastParkedResult::ParkSelf => {
+                // The GC may have finished.  Let the driver find out.
+                self.driver_has_anything_to_do.notify_all();
+                return true;
+            }
+            LastParkedResult::WakeSelf => {
+                // Continue without waiting.
+            }
+            LastParkedResult::WakeAll => {
+                self.notify_work_available(true);
+            }
+            LastParkedResult::WakeAllIncludingInactive => {
+                sync.inactive_wake_epoch += 1;
+                self.notify_work_available(true);
+                self.inactive_workers_have_anything_to_do.notify_all();
+            }
+        }
+        false
     }
 
     /// Park a worker and wait on the CondVar `workers_have_anything_to_do`.
@@ -136,6 +279,7 @@ impl WorkerMonitor {
 
         // Park this worker
         let all_parked = sync.parker.inc_parked_workers();
+        self.update_idle_workers(&sync);
         trace!(
             "Worker {} parked.  parked/total: {}/{}.  All parked: {}",
             ordinal,
@@ -144,25 +288,13 @@ impl WorkerMonitor {
             all_parked
         );
 
-        let mut should_wait = false;
-
-        if all_parked {
+        let should_wait = if all_parked {
             trace!("Worker {} is the last worker parked.", ordinal);
             let result = on_last_parked(&mut sync.goals);
-            match result {
-                LastParkedResult::ParkSelf => {
-                    should_wait = true;
-                }
-                LastParkedResult::WakeSelf => {
-                    // Continue without waiting.
-                }
-                LastParkedResult::WakeAll => {
-                    self.notify_work_available(true);
-                }
-            }
+            self.wake_up_after_last_parked(&mut sync, result)
         } else {
-            should_wait = true;
-        }
+            true
+        };
 
         if should_wait {
             // Notes on CondVar usage:
@@ -218,11 +350,28 @@ impl WorkerMonitor {
             //     and park again if not available.  The last parked worker will ensure the two
             //     conditions listed above are both false before blocking.  If either condition is
             //     true, the last parked worker will take action.
-            sync = self.workers_have_anything_to_do.wait(sync).unwrap();
+            let is_inactive = || ordinal >= self.effective_active_workers();
+            let epoch = sync.inactive_wake_epoch;
+            if !is_inactive() {
+                sync = self.workers_have_anything_to_do.wait(sync).unwrap();
+                if is_inactive() && sync.inactive_wake_epoch == epoch {
+                    // This worker was deactivated while parked.  Pass the notification to another
+                    // worker so that it is not lost.
+                    self.workers_have_anything_to_do.notify_one();
+                }
+            }
+            // Inactive workers keep parked until they are activated or woken up explicitly.
+            while is_inactive() && sync.inactive_wake_epoch == epoch {
+                sync = self
+                    .inactive_workers_have_anything_to_do
+                    .wait(sync)
+                    .unwrap();
+            }
         }
 
         // Unpark this worker.
         sync.parker.dec_parked_workers();
+        self.update_idle_workers(&sync);
         trace!(
             "Worker {} unparked.  parked/total: {}/{}.",
             ordinal,
@@ -233,7 +382,7 @@ impl WorkerMonitor {
         // If the current goal is an exit goal, the worker thread should exit.
         if matches!(
             sync.goals.current(),
-            Some(WorkerGoal::Shutdown | WorkerGoal::StopForFork)
+            Some(WorkerGoal::Shutdown | WorkerGoal::StopForFork | WorkerGoal::StopForResize)
         ) {
             return Err(WorkerShouldExit);
         }
@@ -241,10 +390,135 @@ impl WorkerMonitor {
         Ok(())
     }
 
+    /// Count the worker driven by mutators as parked, and defer GC requests until a mutator drives
+    /// it.  Called once before GC worker threads are spawned if the option `mutator_driven_gc` is
+    /// set.
+    pub fn park_idle_driver(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.parker.inc_parked_workers();
+        self.update_idle_workers(&sync);
+        sync.goals.set_gc_deferred(true);
+    }
+
+    /// Unpark the worker driven by mutators when a mutator starts driving it.  Workers respond to
+    /// GC requests from now on.
+    pub fn unpark_driver(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.goals.set_gc_deferred(false);
+        sync.parker.dec_parked_workers();
+        self.update_idle_workers(&sync);
+        trace!(
+            "Driver unparked.  parked/total: {}/{}.",
+            sync.parker.parked_workers,
+            sync.parker.worker_count,
+        );
+    }
+
+    /// Whether the worker driven by a mutator should keep working, i.e. a GC is in progress, or
+    /// is requested and will start soon.
+    fn driver_has_gc_to_do(goals: &WorkerGoals) -> bool {
+        match goals.current() {
+            Some(WorkerGoal::Gc) => true,
+            None => goals.is_requested(WorkerGoal::Gc),
+            Some(WorkerGoal::Shutdown | WorkerGoal::StopForFork | WorkerGoal::StopForResize) => {
+                false
+            }
+        }
+    }
+
+    /// Like `park_and_wait`, but for the worker driven by a mutator.  It waits on the CondVar
+    /// `driver_has_anything_to_do`.
+    ///
+    /// This function returns `Ok(())` if the driver should continue working, or
+    /// `Err(WorkerShouldExit)` if the mutator should return from the GC.  A GC is over for the
+    /// mutator when no GC is in progress or requested.  If there are no helper threads, the driver
+    /// also finishes concurrent work before returning because nobody else will do it.  When this
+    /// function returns `Err`, the driver stays parked and GC requests are deferred until
+    /// `unpark_driver` is called again.
+    pub fn park_driver_and_wait<F>(
+        &self,
+        ordinal: usize,
+        on_last_parked: F,
+    ) -> Result<(), WorkerShouldExit>
+    where
+        F: FnOnce(&mut WorkerGoals) -> LastParkedResult,
+    {
+        let mut sync = self.sync.lock().unwrap();
+
+        let all_parked = sync.parker.inc_parked_workers();
+        self.update_idle_workers(&sync);
+        trace!(
+            "Driver {} parked.  parked/total: {}/{}.  All parked: {}",
+            ordinal,
+            sync.parker.parked_workers,
+            sync.parker.worker_count,
+            all_parked
+        );
+
+        let should_continue = if all_parked {
+            trace!("Driver {} is the last worker parked.", ordinal);
+            let result = on_last_parked(&mut sync.goals);
+            let park_self = self.wake_up_after_last_parked(&mut sync, result);
+            let no_helpers = sync.parker.worker_count == 1;
+            !park_self && (no_helpers || Self::driver_has_gc_to_do(&sync.goals))
+        } else if Self::driver_has_gc_to_do(&sync.goals) {
+            // Spurious wake-ups are benign.  The driver will poll for work packets and park again.
+            sync = self.driver_has_anything_to_do.wait(sync).unwrap();
+            Self::driver_has_gc_to_do(&sync.goals)
+        } else {
+            false
+        };
+
+        let exiting = matches!(
+            sync.goals.current(),
+            Some(WorkerGoal::Shutdown | WorkerGoal::StopForFork | WorkerGoal::StopForResize)
+        );
+        if !should_continue || exiting {
+            trace!("Driver {} returns to the mutator.", ordinal);
+            sync.goals.set_gc_deferred(true);
+            return Err(WorkerShouldExit);
+        }
+
+        sync.parker.dec_parked_workers();
+        self.update_idle_workers(&sync);
+        trace!(
+            "Driver {} unparked.  parked/total: {}/{}.",
+            ordinal,
+            sync.parker.parked_workers,
+            sync.parker.worker_count,
+        );
+        Ok(())
+    }
+
+    /// Get the current goal.
+    pub fn current_goal(&self) -> Option<WorkerGoal> {
+        self.sync.lock().unwrap().goals.current()
+    }
+
     /// Called when all workers have exited.
     pub fn on_all_workers_exited(&self) {
-        let mut sync = self.sync.try_lock().unwrap();
+        let mut sync = self.sync.lock().unwrap();
         sync.goals.on_current_goal_completed();
+        self.all_workers_exited.notify_all();
+    }
+
+    /// Wait until all GC threads have exited for the exit `goal` requested before.
+    pub fn wait_for_all_workers_exited(&self, goal: WorkerGoal) {
+        let mut sync = self.sync.lock().unwrap();
+        while sync.goals.is_requested(goal) || sync.goals.current().is_some() {
+            sync = self.all_workers_exited.wait(sync).unwrap();
+        }
+    }
+
+    /// Change the number of workers while all GC threads have exited.  All workers will be active
+    /// when they are spawned again.
+    pub fn set_worker_count(&self, worker_count: usize) {
+        let mut sync = self.sync.lock().unwrap();
+        assert!(sync.goals.current().is_none());
+        debug_assert!(sync.parker.parked_workers <= worker_count);
+        sync.parker.worker_count = worker_count;
+        self.active_workers.store(worker_count, Ordering::Relaxed);
+        self.update_idle_workers(&sync);
     }
 }
 
@@ -256,6 +530,7 @@ mod tests {
     };
 
     use super::WorkerMonitor;
+    use crate::scheduler::worker_goals::{WorkerGoal, WorkerGoals};
 
     /// Test if the `WorkerMonitor::park_and_wait` method calls the `on_last_parked` callback
     /// properly.
@@ -343,4 +618,200 @@ mod tests {
         // `on_last_parked` should only be called once.
         assert_eq!(on_last_parked_called.load(Ordering::SeqCst), 1);
     }
+
+    /// Test that inactive workers keep parked for `WakeAll`, and wake up for
+    /// `WakeAllIncludingInactive`.
+    #[test]
+    fn test_inactive_workers_keep_parked() {
+        let number_threads = 4;
+        let active_workers = 2;
+        let worker_monitor = Arc::new(WorkerMonitor::new(number_threads));
+        worker_monitor.set_active_workers(active_workers);
+        let on_last_parked_called = AtomicUsize::new(0);
+        let first_last_parked = AtomicUsize::new(usize::MAX);
+        let should_exit = AtomicBool::new(false);
+        let wake_ups = (0..number_threads)
+            .map(|_| AtomicUsize::new(0))
+            .collect::<Vec<_>>();
+
+        std::thread::scope(|scope| {
+            for ordinal in 0..number_threads {
+                let worker_monitor = worker_monitor.clone();
+                let on_last_parked_called = &on_last_parked_called;
+                let first_last_parked = &first_last_parked;
+                let should_exit = &should_exit;
+                let wake_ups = &wake_ups;
+                scope.spawn(move || {
+                    while !should_exit.load(Ordering::SeqCst) {
+                        worker_monitor
+                            .park_and_wait(ordinal, |_goals| {
+                                if on_last_parked_called.fetch_add(1, Ordering::SeqCst) == 0 {
+                                    // Only wake up active workers the first time.
+                                    first_last_parked.store(ordinal, Ordering::SeqCst);
+                                    super::LastParkedResult::WakeAll
+                                } else {
+                                    should_exit.store(true, Ordering::SeqCst);
+                                    super::LastParkedResult::WakeAllIncludingInactive
+                                }
+                            })
+                            .unwrap();
+                        wake_ups[ordinal].fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        // Inactive workers only woke up to exit, unless they did the work of the last parked
+        // worker for the first time.
+        let first_last_parked = first_last_parked.load(Ordering::SeqCst);
+        for (ordinal, wake_ups) in wake_ups.iter().enumerate().skip(active_workers) {
+            if ordinal != first_last_parked {
+                assert_eq!(wake_ups.load(Ordering::SeqCst), 1);
+            }
+        }
+    }
+
+    /// Test that the limit of concurrent workers only applies between setting and removing it, and
+    /// that it is combined with the number of active workers.
+    #[test]
+    fn test_concurrent_worker_limit() {
+        let worker_monitor = WorkerMonitor::new(4);
+        let effective_active_workers =
+            |worker_monitor: &WorkerMonitor| worker_monitor.effective_active_workers();
+
+        worker_monitor.update_concurrent_worker_limit(1);
+        assert_eq!(effective_active_workers(&worker_monitor), 4);
+
+        worker_monitor.set_concurrent_worker_limit(1);
+        assert_eq!(effective_active_workers(&worker_monitor), 1);
+        worker_monitor.update_concurrent_worker_limit(3);
+        assert_eq!(effective_active_workers(&worker_monitor), 3);
+        worker_monitor.set_active_workers(2);
+        assert_eq!(effective_active_workers(&worker_monitor), 2);
+
+        worker_monitor.remove_concurrent_worker_limit();
+        worker_monitor.set_active_workers(4);
+        assert_eq!(effective_active_workers(&worker_monitor), 4);
+    }
+
+    /// Test that `has_idle_workers` counts parked workers, except inactive ones.
+    #[test]
+    fn test_has_idle_workers() {
+        let worker_monitor = WorkerMonitor::new(2);
+        assert!(!worker_monitor.has_idle_workers());
+
+        std::thread::scope(|scope| {
+            let parked = scope.spawn(|| {
+                worker_monitor
+                    .park_and_wait(1, |_goals| unreachable!("Worker 0 never parks"))
+                    .unwrap();
+            });
+            while !worker_monitor.has_idle_workers() {
+                std::thread::yield_now();
+            }
+
+            // Worker 1 is parked, but it is not idle if it is inactive.
+            worker_monitor.set_active_workers(1);
+            assert!(!worker_monitor.has_idle_workers());
+            worker_monitor.set_active_workers(2);
+            assert!(worker_monitor.has_idle_workers());
+
+            worker_monitor.notify_work_available(true);
+            parked.join().unwrap();
+        });
+        assert!(!worker_monitor.has_idle_workers());
+    }
+
+    /// Emulate the last parked worker of the scheduler.  A GC starts when it is polled, and
+    /// finishes the next time the last worker parks.
+    fn emulate_on_last_parked(
+        goals: &mut WorkerGoals,
+        gcs_started: &AtomicUsize,
+        gcs_finished: &AtomicUsize,
+    ) -> super::LastParkedResult {
+        match goals.current() {
+            Some(WorkerGoal::Gc) => {
+                gcs_finished.fetch_add(1, Ordering::SeqCst);
+                goals.on_current_goal_completed();
+                super::LastParkedResult::ParkSelf
+            }
+            Some(_) => unreachable!(),
+            None => match goals.poll_next_goal() {
+                Some(WorkerGoal::Gc) => {
+                    gcs_started.fetch_add(1, Ordering::SeqCst);
+                    super::LastParkedResult::WakeSelf
+                }
+                Some(_) => super::LastParkedResult::WakeAllIncludingInactive,
+                None => super::LastParkedResult::ParkSelf,
+            },
+        }
+    }
+
+    /// Test that a mutator can drive a GC without any GC worker thread.
+    #[test]
+    fn test_driver_without_helpers() {
+        let gcs_started = AtomicUsize::new(0);
+        let gcs_finished = AtomicUsize::new(0);
+        let worker_monitor = WorkerMonitor::new(1);
+        worker_monitor.park_idle_driver();
+        worker_monitor.make_request(WorkerGoal::Gc);
+
+        worker_monitor.unpark_driver();
+        while worker_monitor
+            .park_driver_and_wait(0, |goals| {
+                emulate_on_last_parked(goals, &gcs_started, &gcs_finished)
+            })
+            .is_ok()
+        {}
+        assert_eq!(gcs_started.load(Ordering::SeqCst), 1);
+        assert_eq!(gcs_finished.load(Ordering::SeqCst), 1);
+
+        // The driver returns immediately if no GC is requested.
+        worker_monitor.unpark_driver();
+        assert!(worker_monitor
+            .park_driver_and_wait(0, |goals| {
+                emulate_on_last_parked(goals, &gcs_started, &gcs_finished)
+            })
+            .is_err());
+        assert_eq!(gcs_started.load(Ordering::SeqCst), 1);
+    }
+
+    /// Test that helper workers do not start a GC before a mutator drives the driver worker, and
+    /// that the driver returns when the GC finished.
+    #[test]
+    fn test_driver_with_helper() {
+        let gcs_started = AtomicUsize::new(0);
+        let gcs_finished = AtomicUsize::new(0);
+        let worker_monitor = WorkerMonitor::new(2);
+        worker_monitor.park_idle_driver();
+        worker_monitor.make_request(WorkerGoal::Gc);
+
+        std::thread::scope(|scope| {
+            let worker_monitor = &worker_monitor;
+            let gcs_started = &gcs_started;
+            let gcs_finished = &gcs_finished;
+            let helper = scope.spawn(move || {
+                while worker_monitor
+                    .park_and_wait(1, |goals| {
+                        emulate_on_last_parked(goals, gcs_started, gcs_finished)
+                    })
+                    .is_ok()
+                {}
+            });
+
+            worker_monitor.unpark_driver();
+            while worker_monitor
+                .park_driver_and_wait(0, |goals| {
+                    emulate_on_last_parked(goals, gcs_started, gcs_finished)
+                })
+                .is_ok()
+            {}
+            assert_eq!(gcs_started.load(Ordering::SeqCst), 1);
+            assert_eq!(gcs_finished.load(Ordering::SeqCst), 1);
+
+            // The helper still responds to exit requests while the driver is idle.
+            worker_monitor.make_request(WorkerGoal::Shutdown);
+            helper.join().unwrap();
+        });
+    }
 }
@@ -0,0 +1,278 @@
+//! Per-context memory accounting and quotas.
+//!
+//! A VM binding may tag mutators (via [`crate::memory_manager::set_mutator_accounting_context`])
+//! or individual allocation requests (via [`crate::util::alloc::AllocationOptions`]) with an
+//! [`AccountingContext`], for example one context per tenant of a multi-tenant server.  When the
+//! option `accounting_contexts` is enabled, MMTk tracks the bytes allocated by each context, and
+//! optionally the bytes of live objects of each context after each GC.  A context may also be
+//! given a soft and a hard quota.  Exceeding the soft quota calls
+//! [`crate::vm::Collection::on_accounting_soft_quota_exceeded`], and exceeding the hard quota makes
+//! allocations of that context (and only that context) fail, and calls
+//! [`crate::vm::Collection::on_accounting_hard_quota_exceeded`].
+//!
+//! Allocated bytes are recorded in the allocation slow path.  For allocators that do thread-local
+//! allocation, the slow path charges the thread-local buffer it acquires rather than the individual
+//! objects allocated in the fast path, so the number is an approximation with a granularity of
+//! the thread-local buffer size.  The hard quota is checked against the same number before the
+//! slow path allocates, so a context never goes beyond its hard limit.  Objects allocated in the
+//! fast path are charged to the context of the slow path allocation that acquired the buffer.  If
+//! allocation requests of one mutator are charged to different contexts with
+//! [`crate::util::alloc::AllocationOptions::accounting_context`], the bytes are only attributed to
+//! the right context at the granularity of a thread-local buffer.  With the options `stress_factor`
+//! and `precise_stress`, every allocation goes through the slow path, and is charged precisely.
+//! Live bytes are only counted if `count_live_bytes_in_gc` is also
+//! enabled, in which case [`crate::vm::ObjectModel::get_accounting_context`] is used to attribute
+//! each live object to a context.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// An identifier of an accounting context.  The binding decides what a context represents, e.g. a
+/// tenant, an arena, or an isolate.
+#[repr(transparent)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct AccountingContext(pub u32);
+
+impl AccountingContext {
+    /// The context that every mutator belongs to unless the binding assigns another one.
+    pub const DEFAULT: Self = Self(0);
+    /// A special value for [`crate::util::alloc::AllocationOptions::accounting_context`].  It means
+    /// the allocation is charged to the context of the allocating mutator.  It cannot be used as
+    /// the context of a mutator.
+    pub const INHERIT: Self = Self(u32::MAX);
+}
+
+impl Default for AccountingContext {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Quotas for an accounting context.  The usage of a context is the sum of its live bytes in the
+/// last GC and the bytes it allocated since the last GC (see [`AccountingStats::usage`]).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountingQuota {
+    /// When the usage goes beyond this limit, MMTk calls
+    /// [`crate::vm::Collection::on_accounting_soft_quota_exceeded`].  The callback is called once
+    /// each time the limit is crossed, and is re-armed after a GC brings the usage under the limit.
+    pub soft_limit: Option<usize>,
+    /// Allocations of the context that would bring the usage beyond this limit fail.  MMTk calls
+    /// [`crate::vm::Collection::on_accounting_hard_quota_exceeded`] and returns a null address.
+    /// It does not call [`crate::vm::Collection::out_of_memory`], because other contexts can still
+    /// allocate.
+    pub hard_limit: Option<usize>,
+}
+
+/// Accounting statistics of a context.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountingStats {
+    /// Bytes allocated by the context since the end of the last GC.
+    pub allocated_bytes_since_last_gc: usize,
+    /// Bytes allocated by the context since it was first seen by MMTk.
+    pub total_allocated_bytes: usize,
+    /// Bytes of live objects of the context found in the last GC.  This is only updated if the
+    /// option `count_live_bytes_in_gc` is enabled.
+    pub live_bytes_in_last_gc: usize,
+}
+
+impl AccountingStats {
+    /// The current usage of the context, i.e. the live bytes in the last GC plus the bytes
+    /// allocated since then.
+    pub fn usage(&self) -> usize {
+        self.live_bytes_in_last_gc + self.allocated_bytes_since_last_gc
+    }
+}
+
+/// The result of charging an allocation to a context.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChargeResult {
+    /// The context is within its soft limit, or the soft limit has already been reported.
+    WithinQuota,
+    /// The allocation takes the context beyond its soft limit for the first time since the
+    /// callback was last armed.  The payload is the usage after the allocation and the soft limit.
+    SoftLimitExceeded { usage: usize, limit: usize },
+}
+
+#[derive(Default)]
+struct ContextEntry {
+    stats: AccountingStats,
+    quota: AccountingQuota,
+    /// Whether the soft limit callback has been called since the usage last dropped below the
+    /// soft limit.
+    soft_limit_reported: bool,
+}
+
+/// The table of all accounting contexts seen by an MMTk instance.  It is only accessed in the
+/// allocation slow path, by API functions, and at the end of GC.
+#[derive(Default)]
+pub(crate) struct AccountingTable {
+    contexts: Mutex<HashMap<AccountingContext, ContextEntry>>,
+}
+
+impl AccountingTable {
+    /// If allocating `bytes` more would take `context` beyond its hard limit, return the current
+    /// usage and the hard limit of the context.
+    pub fn exceeds_hard_limit(
+        &self,
+        context: AccountingContext,
+        bytes: usize,
+    ) -> Option<(usize, usize)> {
+        debug_assert_ne!(context, AccountingContext::INHERIT);
+        let contexts = self.contexts.lock().unwrap();
+        let entry = contexts.get(&context)?;
+        let limit = entry.quota.hard_limit?;
+        let usage = entry.stats.usage();
+        (usage + bytes > limit).then_some((usage, limit))
+    }
+
+    /// Charge `context` with `bytes` of successful allocation.
+    pub fn charge(&self, context: AccountingContext, bytes: usize) -> ChargeResult {
+        debug_assert_ne!(context, AccountingContext::INHERIT);
+        let mut contexts = self.contexts.lock().unwrap();
+        let entry = contexts.entry(context).or_default();
+        entry.stats.allocated_bytes_since_last_gc += bytes;
+        entry.stats.total_allocated_bytes += bytes;
+        let usage = entry.stats.usage();
+        match entry.quota.soft_limit {
+            Some(limit) if usage > limit && !entry.soft_limit_reported => {
+                entry.soft_limit_reported = true;
+                ChargeResult::SoftLimitExceeded { usage, limit }
+            }
+            _ => ChargeResult::WithinQuota,
+        }
+    }
+
+    /// Set the quota for `context`.
+    pub fn set_quota(&self, context: AccountingContext, quota: AccountingQuota) {
+        let mut contexts = self.contexts.lock().unwrap();
+        let entry = contexts.entry(context).or_default();
+        entry.quota = quota;
+        entry.soft_limit_reported = false;
+    }
+
+    /// Forget about `context`, including its quota and statistics.
+    pub fn remove(&self, context: AccountingContext) {
+        self.contexts.lock().unwrap().remove(&context);
+    }
+
+    /// Get the statistics of `context`, if MMTk has seen the context.
+    pub fn get_stats(&self, context: AccountingContext) -> Option<AccountingStats> {
+        self.contexts
+            .lock()
+            .unwrap()
+            .get(&context)
+            .map(|entry| entry.stats)
+    }
+
+    /// Get the statistics of all the contexts.
+    pub fn all_stats(&self) -> HashMap<AccountingContext, AccountingStats> {
+        self.contexts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(context, entry)| (*context, entry.stats))
+            .collect()
+    }
+
+    /// Called at the end of a GC.  `live_bytes` is the live bytes of each context found in this GC,
+    /// or `None` if live bytes were not counted.
+    pub fn on_gc_end(&self, live_bytes: Option<&HashMap<AccountingContext, usize>>) {
+        let mut contexts = self.contexts.lock().unwrap();
+        if let Some(live_bytes) = live_bytes {
+            // Contexts that we have not seen in the allocation slow path may still own objects,
+            // e.g. objects allocated in the fast path before the first slow path allocation.
+            for context in live_bytes.keys() {
+                contexts.entry(*context).or_default();
+            }
+        }
+        for (context, entry) in contexts.iter_mut() {
+            entry.stats.allocated_bytes_since_last_gc = 0;
+            if let Some(live_bytes) = live_bytes {
+                entry.stats.live_bytes_in_last_gc = live_bytes.get(context).copied().unwrap_or(0);
+            }
+            if entry
+                .quota
+                .soft_limit
+                .is_none_or(|limit| entry.stats.usage() <= limit)
+            {
+                entry.soft_limit_reported = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TENANT: AccountingContext = AccountingContext(42);
+
+    #[test]
+    fn charge_without_quota() {
+        let table = AccountingTable::default();
+        assert_eq!(table.charge(TENANT, 100), ChargeResult::WithinQuota);
+        assert_eq!(table.charge(TENANT, 50), ChargeResult::WithinQuota);
+        let stats = table.get_stats(TENANT).unwrap();
+        assert_eq!(stats.allocated_bytes_since_last_gc, 150);
+        assert_eq!(stats.total_allocated_bytes, 150);
+        assert!(table.get_stats(AccountingContext::DEFAULT).is_none());
+    }
+
+    #[test]
+    fn soft_limit_reported_once() {
+        let table = AccountingTable::default();
+        table.set_quota(
+            TENANT,
+            AccountingQuota {
+                soft_limit: Some(100),
+                hard_limit: None,
+            },
+        );
+        assert_eq!(table.charge(TENANT, 80), ChargeResult::WithinQuota);
+        assert_eq!(
+            table.charge(TENANT, 40),
+            ChargeResult::SoftLimitExceeded {
+                usage: 120,
+                limit: 100
+            }
+        );
+        assert_eq!(table.charge(TENANT, 40), ChargeResult::WithinQuota);
+
+        // A GC that frees everything re-arms the callback.
+        table.on_gc_end(Some(&HashMap::new()));
+        assert_eq!(table.get_stats(TENANT).unwrap().usage(), 0);
+        assert_eq!(
+            table.charge(TENANT, 101),
+            ChargeResult::SoftLimitExceeded {
+                usage: 101,
+                limit: 100
+            }
+        );
+    }
+
+    #[test]
+    fn hard_limit() {
+        let table = AccountingTable::default();
+        table.set_quota(
+            TENANT,
+            AccountingQuota {
+                soft_limit: None,
+                hard_limit: Some(100),
+            },
+        );
+        assert_eq!(table.exceeds_hard_limit(TENANT, 100), None);
+        assert_eq!(table.charge(TENANT, 100), ChargeResult::WithinQuota);
+        assert_eq!(table.exceeds_hard_limit(TENANT, 1), Some((100, 100)));
+        // Other contexts are not affected.
+        assert_eq!(
+            table.exceeds_hard_limit(AccountingContext::DEFAULT, 1000),
+            None
+        );
+
+        // Live objects count towards the usage.
+        let live_bytes = HashMap::from([(TENANT, 60)]);
+        table.on_gc_end(Some(&live_bytes));
+        assert_eq!(table.exceeds_hard_limit(TENANT, 41), Some((60, 100)));
+        assert_eq!(table.exceeds_hard_limit(TENANT, 40), None);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::global_state::GlobalState;
+use crate::plan::concurrent::pacer::AssistCredit;
+use crate::util::accounting_context::{AccountingContext, ChargeResult};
 use crate::util::address::Address;
 #[cfg(feature = "analysis")]
 use crate::util::analysis::AnalysisManager;
@@ -7,7 +9,7 @@ use crate::util::options::Options;
 use crate::MMTK;
 
 use std::cell::RefCell;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 
 use crate::policy::space::Space;
@@ -60,6 +62,14 @@ pub struct AllocationOptions {
     ///
     /// If `fasle`, the allocation will return null immediately when out of memory.
     pub allow_oom_call: bool,
+
+    /// The accounting context that this allocation is charged to.
+    ///
+    /// **The default is [`AccountingContext::INHERIT`]**, which charges the allocation to the
+    /// accounting context of the mutator.
+    ///
+    /// This only has effect if the option `accounting_contexts` is enabled.
+    pub accounting_context: AccountingContext,
 }
 
 /// The default value for `AllocationOptions` has the same semantics as calling [`Allocator::alloc`]
@@ -70,6 +80,7 @@ impl Default for AllocationOptions {
             allow_overcommit: false,
             at_safepoint: true,
             allow_oom_call: true,
+            accounting_context: AccountingContext::INHERIT,
         }
     }
 }
@@ -255,6 +266,10 @@ pub struct AllocatorContext<VM: VMBinding> {
     pub state: Arc<GlobalState>,
     /// Have we thrown an OOM already?
     pub thrown_oom: AtomicBool,
+    /// The accounting context of the mutator that owns this context.
+    accounting_context: AtomicU32,
+    /// The credit of the mutator for assisting concurrent marking.
+    pub(crate) assist_credit: AssistCredit,
     pub options: Arc<Options>,
     pub gc_trigger: Arc<GCTrigger<VM>>,
     #[cfg(feature = "analysis")]
@@ -267,6 +282,8 @@ impl<VM: VMBinding> AllocatorContext<VM> {
             alloc_options: AllocationOptionsHolder::new(AllocationOptions::default()),
             state: mmtk.state.clone(),
             thrown_oom: AtomicBool::new(false),
+            accounting_context: AtomicU32::new(AccountingContext::DEFAULT.0),
+            assist_credit: AssistCredit::default(),
             options: mmtk.options.clone(),
             gc_trigger: mmtk.gc_trigger.clone(),
             #[cfg(feature = "analysis")]
@@ -285,6 +302,28 @@ impl<VM: VMBinding> AllocatorContext<VM> {
     pub fn get_alloc_options(&self) -> AllocationOptions {
         self.alloc_options.get_alloc_options()
     }
+
+    pub fn set_accounting_context(&self, context: AccountingContext) {
+        assert_ne!(
+            context,
+            AccountingContext::INHERIT,
+            "AccountingContext::INHERIT cannot be used as the accounting context of a mutator."
+        );
+        // Relaxed is fine since this is only accessed by the owning thread.
+        self.accounting_context.store(context.0, Ordering::Relaxed);
+    }
+
+    pub fn get_accounting_context(&self) -> AccountingContext {
+        AccountingContext(self.accounting_context.load(Ordering::Relaxed))
+    }
+
+    /// The accounting context that the current allocation request is charged to.
+    fn effective_accounting_context(&self) -> AccountingContext {
+        match self.get_alloc_options().accounting_context {
+            AccountingContext::INHERIT => self.get_accounting_context(),
+            context => context,
+        }
+    }
 }
 
 fn reset_allocation_state<VM: VMBinding, A: Allocator<VM> + ?Sized>(allocator: &A) {
@@ -293,6 +332,27 @@ fn reset_allocation_state<VM: VMBinding, A: Allocator<VM> + ?Sized>(allocator: &
     context.thrown_oom.store(false, Ordering::Relaxed);
 }
 
+/// The number of bytes we consider allocated by a successful slow path allocation of `size` bytes.
+fn slow_path_allocated_bytes<VM: VMBinding, A: Allocator<VM> + ?Sized>(
+    allocator: &A,
+    size: usize,
+) -> usize {
+    let options = &allocator.get_context().options;
+    if (options.is_stress_test_gc_enabled() && *options.precise_stress)
+        || !allocator.does_thread_local_allocation()
+    {
+        // For precise stress test, or for allocators that do not have thread local buffer,
+        // we know exactly how many bytes we allocate.
+        size
+    } else {
+        // Otherwise, we count the entire thread local buffer size as allocated.
+        crate::util::conversions::raw_align_up(
+            size,
+            allocator.get_thread_local_buffer_granularity(),
+        )
+    }
+}
+
 /// A trait which implements allocation routines. Every allocator needs to implements this trait.
 pub trait Allocator<VM: VMBinding>: Downcast {
     /// Return the [`VMThread`] associated with this allocator instance.
@@ -439,10 +499,38 @@ pub trait Allocator<VM: VMBinding>: Downcast {
     /// * `offset` the required offset in bytes.
     fn alloc_slow_inline(&mut self, size: usize, align: usize, offset: usize) -> Address {
         let tls = self.get_tls();
-        let is_mutator = VM::VMActivePlan::is_mutator(tls);
+        // A mutator driving a GC (see the option `mutator_driven_gc`) allocates as a GC worker.
+        let is_mutator =
+            VM::VMActivePlan::is_mutator(tls) && !crate::scheduler::is_current_thread_gc_worker();
         let stress_test = self.get_context().options.is_stress_test_gc_enabled();
         assert!(!self.get_context().thrown_oom.load(Ordering::Relaxed), "We should not enter alloc_slow_inline if we have already thrown OOM for this allocation request.");
 
+        // Fail the allocation if it would exceed the hard quota of its accounting context. We do
+        // not trigger GC for this. The binding may trigger a GC and retry if it wants to.  Check
+        // the bytes that a successful allocation would be charged, so that a context cannot go
+        // beyond its hard limit by up to a thread-local buffer.
+        let accounting = is_mutator && *self.get_context().options.accounting_contexts;
+        if accounting {
+            let context = self.get_context().effective_accounting_context();
+            let charged_size = slow_path_allocated_bytes(self, size);
+            if let Some((usage, limit)) = self
+                .get_context()
+                .state
+                .accounting
+                .exceeds_hard_limit(context, charged_size)
+            {
+                trace!("Allocation exceeds the hard quota of {:?}", context);
+                VM::VMCollection::on_accounting_hard_quota_exceeded(
+                    VMMutatorThread(tls),
+                    context,
+                    usage,
+                    charged_size,
+                    limit,
+                );
+                return Address::ZERO;
+            }
+        }
+
         // Information about the previous collection.
         let mut emergency_collection = false;
         let mut previous_result_zero = false;
@@ -491,19 +579,7 @@ pub trait Allocator<VM: VMBinding>: Downcast {
                 // Only update the allocation bytes if we haven't failed a previous allocation in this loop
                 if stress_test && self.get_context().state.is_initialized() && !previous_result_zero
                 {
-                    let allocated_size = if *self.get_context().options.precise_stress
-                        || !self.does_thread_local_allocation()
-                    {
-                        // For precise stress test, or for allocators that do not have thread local buffer,
-                        // we know exactly how many bytes we allocate.
-                        size
-                    } else {
-                        // For normal stress test, we count the entire thread local buffer size as allocated.
-                        crate::util::conversions::raw_align_up(
-                            size,
-                            self.get_thread_local_buffer_granularity(),
-                        )
-                    };
+                    let allocated_size = slow_path_allocated_bytes(self, size);
                     let _allocation_bytes = self
                         .get_context()
                         .state
@@ -525,6 +601,36 @@ pub trait Allocator<VM: VMBinding>: Downcast {
                     }
                 }
 
+                if accounting {
+                    let context = self.get_context().effective_accounting_context();
+                    let allocated_size = slow_path_allocated_bytes(self, size);
+                    if let ChargeResult::SoftLimitExceeded { usage, limit } = self
+                        .get_context()
+                        .state
+                        .accounting
+                        .charge(context, allocated_size)
+                    {
+                        VM::VMCollection::on_accounting_soft_quota_exceeded(
+                            VMMutatorThread(tls),
+                            context,
+                            usage,
+                            limit,
+                        );
+                    }
+                }
+
+                // Do incremental marking, or pay for the allocation with marking work if
+                // concurrent marking is behind.
+                if self.get_context().state.pacer.is_marking() {
+                    let allocated_size = slow_path_allocated_bytes(self, size);
+                    let context = self.get_context();
+                    context.gc_trigger.on_allocation_during_marking(
+                        VMMutatorThread(tls),
+                        &context.assist_credit,
+                        allocated_size,
+                    );
+                }
+
                 return result;
             }
 
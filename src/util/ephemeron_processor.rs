@@ -0,0 +1,329 @@
+//! Built-in processing of ephemerons.
+//!
+//! An ephemeron is an object that holds a key and a value.  It does not keep its key alive, and it
+//! keeps its value alive only if both the ephemeron and its key are reachable.  Ephemerons are
+//! commonly used to implement weak maps.  If the key of a live ephemeron dies, MMTk clears the
+//! ephemeron with [`ReferenceGlue::clear_ephemeron`].
+//!
+//! The binding registers ephemerons with [`crate::memory_manager::add_ephemeron_candidate`], and
+//! must not report the key and the value of a registered ephemeron when scanning the ephemeron
+//! object.  MMTk accesses the key and the value through [`ReferenceGlue`].
+//!
+//! After the strong closure (and retaining soft references), MMTk processes ephemerons in rounds
+//! in the [`WorkBucketStage::EphemeronClosure`] bucket.  In each round, the pending ephemerons are
+//! divided into work packets.  Each work packet traces the values of the ephemerons whose keys
+//! have been reached, which may make the keys of other ephemerons reachable.  Rounds are repeated
+//! until no more values are traced.
+//!
+//! If finalization is enabled, the remaining ephemerons are not cleared at this point, because
+//! their keys may be resurrected by finalization.  The processing is suspended, and resumed in the
+//! [`WorkBucketStage::FinalRefClosure`] bucket after the transitive closure from the resurrected
+//! objects (see [`crate::util::finalizable_processor::FinishFinalization`]).  When the rounds reach
+//! a fixpoint again, the remaining ephemerons have dead keys, and are cleared.
+
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::plan::tracing::gc_work::DefaultObjectTracerContext;
+use crate::plan::tracing::Trace;
+use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::ObjectReference;
+use crate::vm::{ObjectTracer, ObjectTracerContext, ReferenceGlue, VMBinding};
+use crate::MMTK;
+
+/// The number of ephemerons processed in one work packet.
+const EPHEMERONS_PER_PACKET: usize = 512;
+
+/// Holds the ephemerons registered to MMTk, and the state of ephemeron processing in a GC.
+pub struct EphemeronProcessor {
+    sync: Mutex<EphemeronProcessorSync>,
+    /// Whether any value has been traced (or any ephemeron has been added) since the last round
+    /// started.  If not, the processing has reached a fixpoint.
+    progress: AtomicBool,
+}
+
+#[derive(Default)]
+struct EphemeronProcessorSync {
+    /// Registered ephemerons.  Outside ephemeron processing, all ephemerons are here.
+    candidates: HashSet<ObjectReference>,
+    /// Ephemerons whose keys (or the ephemerons themselves) have not been reached in the current
+    /// GC, yet.
+    pending: Vec<ObjectReference>,
+    /// Ephemerons whose keys are live in the current GC.  They hold the updated references.
+    resolved: Vec<ObjectReference>,
+    /// The number of rounds started in the current GC.  It is zero if we are not processing
+    /// ephemerons.
+    rounds: usize,
+}
+
+impl EphemeronProcessor {
+    pub fn new() -> Self {
+        Self {
+            sync: Mutex::new(EphemeronProcessorSync::default()),
+            progress: AtomicBool::new(false),
+        }
+    }
+
+    /// Add an ephemeron.  If it is added during ephemeron processing (e.g. discovered when
+    /// scanning a value), it will be processed in the next round.
+    pub fn add_candidate(&self, ephemeron: ObjectReference) {
+        let mut sync = self.sync.lock().unwrap();
+        if sync.rounds > 0 {
+            sync.pending.push(ephemeron);
+            self.progress.store(true, Ordering::SeqCst);
+        } else {
+            sync.candidates.insert(ephemeron);
+        }
+    }
+
+    /// The number of registered ephemerons.  This is only accurate outside GC.
+    pub fn num_candidates(&self) -> usize {
+        self.sync.lock().unwrap().candidates.len()
+    }
+
+    /// Start a new round.  Return the pending ephemerons divided into batches, or `None` if there
+    /// is nothing to do, i.e. we have reached a fixpoint.
+    fn start_round(&self) -> Option<Vec<Vec<ObjectReference>>> {
+        let mut sync = self.sync.lock().unwrap();
+        let made_progress = self.progress.swap(false, Ordering::SeqCst);
+        if sync.rounds == 0 {
+            sync.pending = sync.candidates.drain().collect();
+        } else if !made_progress {
+            return None;
+        }
+        if sync.pending.is_empty() {
+            return None;
+        }
+        sync.rounds += 1;
+        let pending = std::mem::take(&mut sync.pending);
+        debug!(
+            "Ephemeron round {}: {} pending, {} resolved",
+            sync.rounds,
+            pending.len(),
+            sync.resolved.len()
+        );
+        Some(
+            pending
+                .chunks(EPHEMERONS_PER_PACKET)
+                .map(|chunk| chunk.to_vec())
+                .collect(),
+        )
+    }
+
+    /// Process a batch of ephemerons, tracing the values of those whose keys are live.
+    fn process<VM: VMBinding, OT: ObjectTracer>(
+        &self,
+        tracer: &mut OT,
+        ephemerons: &[ObjectReference],
+    ) {
+        let mut pending = vec![];
+        let mut resolved = vec![];
+        let mut traced_values = false;
+
+        for &ephemeron in ephemerons {
+            if !ephemeron.is_live() {
+                // The ephemeron may be reached later.
+                pending.push(ephemeron);
+                continue;
+            }
+            let new_ephemeron = ephemeron.get_forwarded_object().unwrap_or(ephemeron);
+            let Some(key) = VM::VMReferenceGlue::get_ephemeron_key(new_ephemeron) else {
+                // The ephemeron has been cleared by the program.  Drop it.
+                trace!("Ephemeron {} has no key", new_ephemeron);
+                continue;
+            };
+            if !key.is_live() {
+                pending.push(ephemeron);
+                continue;
+            }
+            // The key is live, so the ephemeron keeps the value alive.
+            let new_key = tracer.trace_object(key);
+            VM::VMReferenceGlue::set_ephemeron_key(new_ephemeron, new_key);
+            if let Some(value) = VM::VMReferenceGlue::get_ephemeron_value(new_ephemeron) {
+                let new_value = tracer.trace_object(value);
+                VM::VMReferenceGlue::set_ephemeron_value(new_ephemeron, new_value);
+                traced_values = true;
+            }
+            trace!("Ephemeron {} resolved with key {}", new_ephemeron, new_key);
+            resolved.push(new_ephemeron);
+        }
+
+        if traced_values {
+            self.progress.store(true, Ordering::SeqCst);
+        }
+        let mut sync = self.sync.lock().unwrap();
+        sync.pending.extend(pending);
+        sync.resolved.extend(resolved);
+    }
+
+    /// Suspend ephemeron processing at a fixpoint, keeping the pending ephemerons.  Ephemerons
+    /// added before processing is resumed are also pending, and the first round after resuming
+    /// processes all the pending ephemerons.
+    fn suspend(&self) {
+        let sync = self.sync.lock().unwrap();
+        debug!(
+            "Ephemeron processing suspended after {} rounds: {} pending, {} resolved",
+            sync.rounds,
+            sync.pending.len(),
+            sync.resolved.len()
+        );
+        self.progress.store(true, Ordering::SeqCst);
+    }
+
+    /// Finish ephemeron processing.  Clear live ephemerons whose keys are dead, and drop dead
+    /// ephemerons.
+    fn finish<VM: VMBinding>(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        let mut num_cleared = 0;
+        for ephemeron in std::mem::take(&mut sync.pending) {
+            if ephemeron.is_live() {
+                let new_ephemeron = ephemeron.get_forwarded_object().unwrap_or(ephemeron);
+                trace!("Clear ephemeron {}", new_ephemeron);
+                VM::VMReferenceGlue::clear_ephemeron(new_ephemeron);
+                num_cleared += 1;
+            }
+        }
+        let resolved = std::mem::take(&mut sync.resolved);
+        debug!(
+            "Ephemerons processed in {} rounds: {} retained, {} cleared",
+            sync.rounds,
+            resolved.len(),
+            num_cleared
+        );
+        sync.candidates.extend(resolved);
+        sync.rounds = 0;
+        self.progress.store(false, Ordering::SeqCst);
+    }
+
+    /// Forward the ephemerons and their keys and values.  This is only needed for plans that
+    /// compute the new addresses of objects after determining liveness.
+    fn forward<VM: VMBinding, OT: ObjectTracer>(&self, tracer: &mut OT) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.candidates = sync
+            .candidates
+            .iter()
+            .map(|&ephemeron| {
+                if let Some(key) = VM::VMReferenceGlue::get_ephemeron_key(ephemeron) {
+                    VM::VMReferenceGlue::set_ephemeron_key(ephemeron, tracer.trace_object(key));
+                }
+                if let Some(value) = VM::VMReferenceGlue::get_ephemeron_value(ephemeron) {
+                    VM::VMReferenceGlue::set_ephemeron_value(ephemeron, tracer.trace_object(value));
+                }
+                tracer.trace_object(ephemeron)
+            })
+            .collect();
+    }
+}
+
+impl Default for EphemeronProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start a round of ephemeron processing.  This is the sentinel of the bucket `stage`, so each
+/// round starts after the transitive closure expanded by the previous round.  At a fixpoint in the
+/// [`WorkBucketStage::EphemeronClosure`] bucket, the processing is suspended if finalization is
+/// enabled.  Otherwise, the processing finishes, and `then` is added to the bucket.
+pub(crate) struct EphemeronProcessing<T: Trace> {
+    stage: WorkBucketStage,
+    then: Option<Box<dyn GCWork<T::VM>>>,
+}
+
+impl<T: Trace> EphemeronProcessing<T> {
+    /// Process ephemerons after the strong closure.
+    pub fn new() -> Self {
+        Self {
+            stage: WorkBucketStage::EphemeronClosure,
+            then: None,
+        }
+    }
+
+    /// Resume ephemeron processing after finalizable objects are resurrected, and add `then` to the
+    /// [`WorkBucketStage::FinalRefClosure`] bucket when it finishes.
+    pub fn after_finalization(then: Option<Box<dyn GCWork<T::VM>>>) -> Self {
+        Self {
+            stage: WorkBucketStage::FinalRefClosure,
+            then,
+        }
+    }
+}
+
+impl<T: Trace> Default for EphemeronProcessing<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Trace> GCWork<T::VM> for EphemeronProcessing<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let stage = self.stage;
+        match mmtk.ephemeron_processor.start_round() {
+            Some(batches) => {
+                let packets = batches
+                    .into_iter()
+                    .map(|ephemerons| {
+                        Box::new(ProcessEphemerons::<T> {
+                            ephemerons,
+                            stage,
+                            phantom_data: PhantomData,
+                        }) as Box<dyn GCWork<T::VM>>
+                    })
+                    .collect();
+                worker.scheduler().work_buckets[stage].bulk_add(packets);
+                worker.scheduler().work_buckets[stage].set_sentinel(Box::new(Self {
+                    stage,
+                    then: self.then.take(),
+                }));
+            }
+            None if stage == WorkBucketStage::EphemeronClosure && !*mmtk.options.no_finalizer => {
+                // Finalization may resurrect the keys of the pending ephemerons.
+                mmtk.ephemeron_processor.suspend();
+            }
+            None => {
+                mmtk.ephemeron_processor.finish::<T::VM>();
+                if let Some(then) = self.then.take() {
+                    worker.scheduler().work_buckets[stage].add_boxed(then);
+                }
+            }
+        }
+    }
+}
+
+/// Process a batch of ephemerons in a round.
+pub(crate) struct ProcessEphemerons<T: Trace> {
+    ephemerons: Vec<ObjectReference>,
+    stage: WorkBucketStage,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for ProcessEphemerons<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let tracer_context = DefaultObjectTracerContext::<T>::new(self.stage);
+        tracer_context.with_tracer(worker, |tracer| {
+            mmtk.ephemeron_processor
+                .process::<T::VM, _>(tracer, &self.ephemerons);
+        });
+    }
+}
+
+/// Forward ephemerons after computing forwarding addresses (mark-compact-only).
+#[derive(Default)]
+pub(crate) struct EphemeronForwarding<T: Trace>(PhantomData<T>);
+
+impl<T: Trace> EphemeronForwarding<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Trace> GCWork<T::VM> for EphemeronForwarding<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let tracer_context = DefaultObjectTracerContext::<T>::new(WorkBucketStage::RefForwarding);
+        tracer_context.with_tracer(worker, |tracer| {
+            mmtk.ephemeron_processor.forward::<T::VM, _>(tracer);
+        });
+    }
+}
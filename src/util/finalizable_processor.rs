@@ -2,117 +2,251 @@ use crate::plan::is_nursery_gc;
 use crate::plan::tracing::gc_work::DefaultObjectTracerContext;
 use crate::plan::tracing::Trace;
 use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::ephemeron_processor::EphemeronProcessing;
+use crate::util::options::FinalizationMode;
 use crate::util::reference_processor::RescanReferences;
-use crate::util::ObjectReference;
-use crate::util::VMWorkerThread;
-use crate::vm::{Collection, ObjectTracer, VMBinding};
+use crate::util::{ObjectReference, VMWorkerThread};
+use crate::vm::slot::Slot;
+use crate::vm::{Collection, ObjectTracer, ReferenceGlue, ReferenceStrength, Scanning, VMBinding};
 use crate::vm::{Finalizable, ObjectTracerContext};
 use crate::MMTK;
+use crossbeam::queue::SegQueue;
+use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// The number of finalizable objects processed in one work packet.
+const FINALIZABLES_PER_PACKET: usize = 512;
 
 /// A special processor for Finalizable objects.
+///
+/// Finalization is done in three steps, each of which is divided into work packets:
+/// 1. [`Finalization`] divides the candidates to scan into [`ScanFinalizables`] packets, which
+///    find the candidates that are no longer reachable, and make them ready for finalization.
+/// 2. [`ResurrectFinalizables`] divides the scanned candidates and the ready objects into
+///    [`KeepFinalizablesAlive`] packets, which trace them.  This expands the transitive closure.
+/// 3. [`FinishFinalization`] informs the binding after the transitive closure is finished.
+///
+/// We must not trace any candidate before all the candidates are scanned.  See the comments in
+/// [`ScanFinalizables`].
+///
+/// In the `Ordered` [`FinalizationMode`], [`OrderFinalizables`] finds the dependencies between the
+/// unreachable objects before step 2.  An object stays a candidate if it is reachable from another
+/// object that is not finalized, yet, unless both objects are in the same cycle.  This makes
+/// finalizers run in topological order.
+///
+/// In the `Guardian` [`FinalizationMode`], step 2 does not keep the objects ready for finalization
+/// alive, so their children are not resurrected, either.  Those objects are not scanned again in
+/// later GCs.
 // TODO: we should consider if we want to merge FinalizableProcessor with ReferenceProcessor,
 // and treat final reference as a special reference type in ReferenceProcessor.
-#[derive(Default)]
 pub struct FinalizableProcessor<F: Finalizable> {
+    /// Candidates added since they were last moved into `candidates`.  Mutators can add
+    /// candidates without taking any lock.
+    new_candidates: SegQueue<F>,
+    sync: Mutex<FinalizableProcessorSync<F>>,
+}
+
+struct FinalizableProcessorSync<F: Finalizable> {
     /// Candidate objects that has finalizers with them
     candidates: Vec<F>,
     /// Index into candidates to record where we are up to in the last scan of the candidates.
     /// Index after nursery_index are new objects inserted after the last GC.
     nursery_index: usize,
     /// Objects that can be finalized. They are actually dead, but we keep them alive
-    /// until the binding pops them from the queue.
+    /// until the binding pops them from the queue, except in the guardian mode.
     ready_for_finalize: Vec<F>,
 }
 
+impl<F: Finalizable> FinalizableProcessorSync<F> {
+    fn take_new_candidates(&mut self, new_candidates: &SegQueue<F>) {
+        while let Some(f) = new_candidates.pop() {
+            self.candidates.push(f);
+        }
+    }
+}
+
 impl<F: Finalizable> FinalizableProcessor<F> {
     pub fn new() -> Self {
         Self {
-            candidates: vec![],
-            nursery_index: 0,
-            ready_for_finalize: vec![],
+            new_candidates: SegQueue::new(),
+            sync: Mutex::new(FinalizableProcessorSync {
+                candidates: vec![],
+                nursery_index: 0,
+                ready_for_finalize: vec![],
+            }),
         }
     }
 
-    pub fn add(&mut self, object: F) {
-        self.candidates.push(object);
+    /// Add a candidate.  This does not take any lock.
+    pub fn add(&self, object: F) {
+        self.new_candidates.push(object);
     }
 
     fn forward_finalizable_reference<OT: ObjectTracer>(tracer: &mut OT, finalizable: &mut F) {
         finalizable.keep_alive::<OT>(tracer);
     }
 
-    pub fn scan<VM: VMBinding, OT: ObjectTracer>(
-        &mut self,
-        tls: VMWorkerThread,
-        tracer: &mut OT,
-        nursery: bool,
-    ) {
-        let start = if nursery { self.nursery_index } else { 0 };
+    /// Take the candidates to scan in this GC, and return them in batches, together with the
+    /// number of candidates and ready objects before scanning.
+    /// Objects ready for finalization are scanned again unless `rescan_ready` is false.
+    fn start_scan(&self, nursery: bool, rescan_ready: bool) -> (Vec<Vec<F>>, usize, usize) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.take_new_candidates(&self.new_candidates);
+        let num_candidates = sync.candidates.len();
+        let num_ready_for_finalize = sync.ready_for_finalize.len();
+        debug!(
+            "Finalization, {} objects in candidates, {} objects ready to finalize",
+            num_candidates, num_ready_for_finalize
+        );
+
+        let start = if nursery { sync.nursery_index } else { 0 };
 
         // We should go through ready_for_finalize objects and keep them alive.
         // Unlike candidates, those objects are known to be alive. This means
         // theoratically we could do the following loop at any time in a GC (not necessarily after closure phase).
         // But we have to iterate through candidates after closure.
-        self.candidates.append(&mut self.ready_for_finalize);
-        debug_assert!(self.ready_for_finalize.is_empty());
+        // In the guardian mode, however, those objects were not kept alive, and must not be accessed.
+        if rescan_ready {
+            let mut ready_for_finalize = std::mem::take(&mut sync.ready_for_finalize);
+            sync.candidates.append(&mut ready_for_finalize);
+        }
 
-        for mut f in self.candidates.drain(start..).collect::<Vec<F>>() {
+        // The scanned candidates that are still alive are pushed back after `start`.
+        sync.nursery_index = start;
+        let mut to_scan = sync.candidates.split_off(start);
+        let mut batches = vec![];
+        while !to_scan.is_empty() {
+            let rest = to_scan.split_off(to_scan.len().min(FINALIZABLES_PER_PACKET));
+            batches.push(std::mem::replace(&mut to_scan, rest));
+        }
+        (batches, num_candidates, num_ready_for_finalize)
+    }
+
+    /// Scan a batch of candidates.  Live candidates are pushed back to candidates, and dead ones
+    /// become ready for finalization.  This does not trace any object.
+    fn scan(&self, finalizables: Vec<F>) {
+        let mut live = vec![];
+        let mut ready = vec![];
+        for f in finalizables {
             let reff = f.get_reference();
             trace!("Pop {:?} for finalization", reff);
             if reff.is_live() {
-                FinalizableProcessor::<F>::forward_finalizable_reference(tracer, &mut f);
                 trace!("{:?} is live, push {:?} back to candidates", reff, f);
-                self.candidates.push(f);
-                continue;
+                live.push(f);
+            } else {
+                ready.push(f);
             }
-
-            // We should not at this point mark the object as live. A binding may register an object
-            // multiple times with different finalizer methods. If we mark the object as live here, and encounter
-            // the same object later in the candidates list (possibly with a different finalizer method),
-            // we will erroneously think the object never died, and won't push it to the ready_to_finalize
-            // queue.
-            // So we simply push the object to the ready_for_finalize queue, and mark them as live objects later.
-            self.ready_for_finalize.push(f);
         }
+        let mut sync = self.sync.lock().unwrap();
+        sync.candidates.extend(live);
+        sync.ready_for_finalize.extend(ready);
+    }
 
-        // Keep the finalizable objects alive.
-        self.forward_finalizable(tracer, nursery);
+    /// Return the references of the objects ready for finalization.
+    fn ready_references(&self) -> HashSet<ObjectReference> {
+        let sync = self.sync.lock().unwrap();
+        sync.ready_for_finalize
+            .iter()
+            .map(|f| f.get_reference())
+            .collect()
+    }
 
-        // Set nursery_index to the end of the candidates (the candidates before the index are scanned)
-        self.nursery_index = self.candidates.len();
+    /// Put the given objects ready for finalization back to the candidates (for ordered
+    /// finalization).
+    fn postpone_ready_objects(&self, to_postpone: &HashSet<ObjectReference>) {
+        let mut sync = self.sync.lock().unwrap();
+        let (postponed, ready): (Vec<F>, Vec<F>) = std::mem::take(&mut sync.ready_for_finalize)
+            .into_iter()
+            .partition(|f| to_postpone.contains(&f.get_reference()));
+        debug!(
+            "Ordered finalization: {} objects ready, {} objects postponed",
+            ready.len(),
+            postponed.len()
+        );
+        sync.ready_for_finalize = ready;
+        sync.candidates.extend(postponed);
+    }
+
+    /// Take the objects to be kept alive, i.e. the scanned candidates (or all candidates if
+    /// `all_candidates` is true) and the ready objects (if `include_ready` is true), in batches of
+    /// `(candidates, ready)`.
+    fn take_objects_to_keep_alive(
+        &self,
+        all_candidates: bool,
+        include_ready: bool,
+    ) -> Vec<(Vec<F>, Vec<F>)> {
+        let mut sync = self.sync.lock().unwrap();
+        sync.take_new_candidates(&self.new_candidates);
+        let start = if all_candidates {
+            0
+        } else {
+            sync.nursery_index
+        };
+        let candidates = sync.candidates.split_off(start);
+        let ready = if include_ready {
+            std::mem::take(&mut sync.ready_for_finalize)
+        } else {
+            vec![]
+        };
 
-        VM::VMCollection::schedule_finalization(tls);
+        let mut batches = vec![];
+        let mut candidates = candidates.into_iter().peekable();
+        let mut ready = ready.into_iter().peekable();
+        while candidates.peek().is_some() || ready.peek().is_some() {
+            let batch_candidates: Vec<F> =
+                candidates.by_ref().take(FINALIZABLES_PER_PACKET).collect();
+            let batch_ready = ready
+                .by_ref()
+                .take(FINALIZABLES_PER_PACKET - batch_candidates.len())
+                .collect();
+            batches.push((batch_candidates, batch_ready));
+        }
+        batches
     }
 
-    pub fn forward_candidate<OT: ObjectTracer>(&mut self, tracer: &mut OT, _nursery: bool) {
-        self.candidates
+    /// Keep a batch of candidates and ready objects alive, and put them back.
+    fn keep_alive<OT: ObjectTracer>(
+        &self,
+        tracer: &mut OT,
+        mut candidates: Vec<F>,
+        mut ready: Vec<F>,
+    ) {
+        candidates
             .iter_mut()
+            .chain(ready.iter_mut())
             .for_each(|f| FinalizableProcessor::<F>::forward_finalizable_reference(tracer, f));
+        let mut sync = self.sync.lock().unwrap();
+        sync.candidates.extend(candidates);
+        sync.ready_for_finalize.extend(ready);
     }
 
-    pub fn forward_finalizable<OT: ObjectTracer>(&mut self, tracer: &mut OT, _nursery: bool) {
-        self.ready_for_finalize
-            .iter_mut()
-            .for_each(|f| FinalizableProcessor::<F>::forward_finalizable_reference(tracer, f));
+    /// Finish finalization in this GC, and return the number of candidates and ready objects.
+    fn finish_scan(&self) -> (usize, usize) {
+        let mut sync = self.sync.lock().unwrap();
+        // Set nursery_index to the end of the candidates (the candidates before the index are scanned)
+        sync.nursery_index = sync.candidates.len();
+        (sync.candidates.len(), sync.ready_for_finalize.len())
     }
 
-    pub fn get_ready_object(&mut self) -> Option<F> {
-        self.ready_for_finalize.pop()
+    pub fn get_ready_object(&self) -> Option<F> {
+        self.sync.lock().unwrap().ready_for_finalize.pop()
     }
 
-    pub fn get_all_finalizers(&mut self) -> Vec<F> {
-        let mut ret = std::mem::take(&mut self.candidates);
-        let ready_objects = std::mem::take(&mut self.ready_for_finalize);
+    pub fn get_all_finalizers(&self) -> Vec<F> {
+        let mut sync = self.sync.lock().unwrap();
+        sync.take_new_candidates(&self.new_candidates);
+        let mut ret = std::mem::take(&mut sync.candidates);
+        let ready_objects = std::mem::take(&mut sync.ready_for_finalize);
         ret.extend(ready_objects);
 
         // We removed objects from candidates. Reset nursery_index
-        self.nursery_index = 0;
+        sync.nursery_index = 0;
 
         ret
     }
 
-    pub fn get_finalizers_for(&mut self, object: ObjectReference) -> Vec<F> {
+    pub fn get_finalizers_for(&self, object: ObjectReference) -> Vec<F> {
         // Drain filter for finalizers that equal to 'object':
         // * for elements that equal to 'object', they will be removed from the original vec, and returned.
         // * for elements that do not equal to 'object', they will be left in the original vec.
@@ -130,53 +264,369 @@ impl<F: Finalizable> FinalizableProcessor<F> {
             }
             ret
         };
-        let mut ret: Vec<F> = drain_filter(&mut self.candidates);
-        ret.extend(drain_filter(&mut self.ready_for_finalize));
+        let mut sync = self.sync.lock().unwrap();
+        sync.take_new_candidates(&self.new_candidates);
+        let mut ret: Vec<F> = drain_filter(&mut sync.candidates);
+        ret.extend(drain_filter(&mut sync.ready_for_finalize));
 
         // We removed objects from candidates. Reset nursery_index
-        self.nursery_index = 0;
+        sync.nursery_index = 0;
 
         ret
     }
 }
 
+impl<F: Finalizable> Default for FinalizableProcessor<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type FinalizableOf<VM> = <<VM as VMBinding>::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType;
+
+/// Create [`KeepFinalizablesAlive`] work packets for the candidates and ready objects.
+fn keep_alive_packets<T: Trace>(
+    mmtk: &'static MMTK<T::VM>,
+    all_candidates: bool,
+    stage: WorkBucketStage,
+) -> Vec<Box<dyn GCWork<T::VM>>> {
+    // Objects ready for finalization are not kept alive in the guardian mode.
+    let include_ready = *mmtk.options.finalization_mode != FinalizationMode::Guardian;
+    mmtk.finalizable_processor
+        .take_objects_to_keep_alive(all_candidates, include_ready)
+        .into_iter()
+        .map(|(candidates, ready)| {
+            Box::new(KeepFinalizablesAlive::<T> {
+                candidates,
+                ready,
+                stage,
+            }) as Box<dyn GCWork<T::VM>>
+        })
+        .collect()
+}
+
+/// Start finalization.  This divides the candidates into [`ScanFinalizables`] work packets.
 #[derive(Default)]
 pub struct Finalization<T: Trace>(PhantomData<T>);
 
 impl<T: Trace> GCWork<T::VM> for Finalization<T> {
     fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
-        if !*mmtk.options.no_reference_types {
-            // Rescan soft and weak references at the end of the transitive closure from resurrected
-            // objects.  New soft and weak references may be discovered during this.
-            let rescan = Box::new(RescanReferences {
-                soft: true,
-                weak: true,
+        let stage = WorkBucketStage::FinalRefClosure;
+        let mode = *mmtk.options.finalization_mode;
+        let (batches, num_candidates_begin, num_ready_for_finalize_begin) =
+            mmtk.finalizable_processor.start_scan(
+                is_nursery_gc(mmtk.get_plan()),
+                mode != FinalizationMode::Guardian,
+            );
+
+        let resurrect = ResurrectFinalizables::<T> {
+            num_candidates_begin,
+            num_ready_for_finalize_begin,
+            phantom_data: PhantomData,
+        };
+        if mode == FinalizationMode::Ordered {
+            worker.scheduler().work_buckets[stage]
+                .set_sentinel(Box::new(OrderFinalizables { resurrect }));
+        } else {
+            worker.scheduler().work_buckets[stage].set_sentinel(Box::new(resurrect));
+        }
+        let packets = batches
+            .into_iter()
+            .map(|finalizables| {
+                Box::new(ScanFinalizables::<T::VM> { finalizables }) as Box<dyn GCWork<T::VM>>
+            })
+            .collect();
+        worker.scheduler().work_buckets[stage].bulk_add(packets);
+    }
+}
+
+impl<T: Trace> Finalization<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Scan a batch of finalizable candidates.
+pub struct ScanFinalizables<VM: VMBinding> {
+    finalizables: Vec<FinalizableOf<VM>>,
+}
+
+impl<VM: VMBinding> GCWork<VM> for ScanFinalizables<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        // We should not at this point mark any object as live. A binding may register an object
+        // multiple times with different finalizer methods. If we mark the object as live here, and encounter
+        // the same object later in the candidates list (possibly with a different finalizer method),
+        // we will erroneously think the object never died, and won't push it to the ready_to_finalize
+        // queue.
+        // So we simply push the object to the ready_for_finalize queue, and mark them as live objects later.
+        mmtk.finalizable_processor
+            .scan(std::mem::take(&mut self.finalizables));
+    }
+}
+
+/// Find the dependencies between the objects ready for finalization, and then make the objects that
+/// depend on other such objects candidates again (ordered-finalization-only).  This is the sentinel
+/// of the [`WorkBucketStage::FinalRefClosure`] bucket after all candidates are scanned.
+pub struct OrderFinalizables<T: Trace> {
+    resurrect: ResurrectFinalizables<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for OrderFinalizables<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let stage = WorkBucketStage::FinalRefClosure;
+        let ready = Arc::new(mmtk.finalizable_processor.ready_references());
+        let dependencies = Arc::new(Mutex::new(vec![]));
+        worker.scheduler().work_buckets[stage].set_sentinel(Box::new(PostponeFinalizables {
+            resurrect: ResurrectFinalizables {
                 phantom_data: PhantomData,
-            });
-            worker.scheduler().work_buckets[WorkBucketStage::FinalRefClosure].set_sentinel(rescan);
+                ..self.resurrect
+            },
+            dependencies: dependencies.clone(),
+        }));
+        let objects: Vec<ObjectReference> = ready.iter().copied().collect();
+        let packets = objects
+            .chunks(FINALIZABLES_PER_PACKET)
+            .map(|objects| {
+                Box::new(FindFinalizableDependencies::<T::VM> {
+                    objects: objects.to_vec(),
+                    ready: ready.clone(),
+                    dependencies: dependencies.clone(),
+                    phantom_data: PhantomData,
+                }) as Box<dyn GCWork<T::VM>>
+            })
+            .collect();
+        worker.scheduler().work_buckets[stage].bulk_add(packets);
+    }
+}
+
+/// Visit the children of an object.
+fn visit_children<VM: VMBinding>(
+    tls: VMWorkerThread,
+    object: ObjectReference,
+    mut visit: impl FnMut(ObjectReference),
+) {
+    if VM::VMScanning::support_slot_enqueuing(tls, object) {
+        VM::VMScanning::scan_object(tls, object, &mut |slot: VM::VMSlot| {
+            if let Some(child) = slot.load() {
+                visit(child);
+            }
+        });
+    } else {
+        VM::VMScanning::scan_object_and_trace_edges(tls, object, &mut |child| {
+            visit(child);
+            child
+        });
+    }
+}
+
+/// For each object in a batch of objects ready for finalization, find the objects ready for
+/// finalization that are reachable from it without going through other such objects or live
+/// objects (ordered-finalization-only).  This does not trace any object.  It is safe to scan the
+/// unreachable objects because they are not reclaimed until the end of the GC.
+pub struct FindFinalizableDependencies<VM: VMBinding> {
+    objects: Vec<ObjectReference>,
+    ready: Arc<HashSet<ObjectReference>>,
+    /// The dependencies found, as `(from, to)` pairs.
+    dependencies: Arc<Mutex<Vec<(ObjectReference, ObjectReference)>>>,
+    phantom_data: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> GCWork<VM> for FindFinalizableDependencies<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        let mut dependencies = vec![];
+        for from in self.objects.iter().copied() {
+            let mut visited = HashSet::new();
+            let mut to_scan = vec![from];
+            while let Some(object) = to_scan.pop() {
+                visit_children::<VM>(worker.tls, object, |child| {
+                    // Live objects cannot reach unreachable objects.
+                    if child.is_live() || !visited.insert(child) {
+                        return;
+                    }
+                    if self.ready.contains(&child) {
+                        dependencies.push((from, child));
+                    } else {
+                        to_scan.push(child);
+                    }
+                });
+            }
         }
+        self.dependencies.lock().unwrap().extend(dependencies);
+    }
+}
 
-        let mut finalizable_processor = mmtk.finalizable_processor.lock().unwrap();
-        let num_candidates_begin = finalizable_processor.candidates.len();
-        let num_ready_for_finalize_begin = finalizable_processor.ready_for_finalize.len();
-        debug!(
-            "Finalization, {} objects in candidates, {} objects ready to finalize",
-            num_candidates_begin, num_ready_for_finalize_begin
-        );
+/// Put the objects ready for finalization that are reachable from other such objects back to
+/// candidates, and then continue with [`ResurrectFinalizables`] (ordered-finalization-only).
+/// Objects in the same cycle do not postpone each other.
+pub struct PostponeFinalizables<T: Trace> {
+    resurrect: ResurrectFinalizables<T>,
+    dependencies: Arc<Mutex<Vec<(ObjectReference, ObjectReference)>>>,
+}
 
-        let tracer_context = DefaultObjectTracerContext::<T>::new(WorkBucketStage::FinalRefClosure);
-        let tls = worker.tls;
+impl<T: Trace> GCWork<T::VM> for PostponeFinalizables<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let dependencies = std::mem::take(&mut *self.dependencies.lock().unwrap());
+        mmtk.finalizable_processor
+            .postpone_ready_objects(&find_postponed(&dependencies));
+        self.resurrect.do_work(worker, mmtk);
+    }
+}
+
+/// Given the dependencies `(from, to)` between the objects ready for finalization, find the objects
+/// that are reachable from an object not in the same strongly connected component.
+fn find_postponed(dependencies: &[(ObjectReference, ObjectReference)]) -> HashSet<ObjectReference> {
+    let mut objects = vec![];
+    let mut index_of = HashMap::new();
+    for object in dependencies.iter().flat_map(|&(from, to)| [from, to]) {
+        index_of.entry(object).or_insert_with(|| {
+            objects.push(object);
+            objects.len() - 1
+        });
+    }
+    let mut successors = vec![vec![]; objects.len()];
+    for (from, to) in dependencies {
+        successors[index_of[from]].push(index_of[to]);
+    }
+
+    let component = strongly_connected_components(&successors);
+    // Any path from another component into a component ends with an edge between components.
+    let postponed_components: HashSet<usize> = dependencies
+        .iter()
+        .map(|(from, to)| (component[index_of[from]], component[index_of[to]]))
+        .filter(|(from, to)| from != to)
+        .map(|(_, to)| to)
+        .collect();
+    objects
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| postponed_components.contains(&component[*index]))
+        .map(|(_, object)| object)
+        .collect()
+}
+
+/// Find the strongly connected components of a graph given as the successors of each node, with
+/// Tarjan's algorithm.  Return the component of each node.  This does not recurse, because the
+/// graph may be a long chain.
+fn strongly_connected_components(successors: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let num_nodes = successors.len();
+    let mut index = vec![UNVISITED; num_nodes];
+    let mut low_link = vec![0; num_nodes];
+    let mut on_stack = vec![false; num_nodes];
+    let mut component = vec![UNVISITED; num_nodes];
+    let mut stack = vec![];
+    let mut next_index = 0;
+    let mut num_components = 0;
+
+    for root in 0..num_nodes {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // Each frame is a node and the index of its next successor to visit.
+        let mut frames = vec![(root, 0)];
+        index[root] = next_index;
+        low_link[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(frame) = frames.last_mut() {
+            let node = frame.0;
+            if let Some(&successor) = successors[node].get(frame.1) {
+                frame.1 += 1;
+                if index[successor] == UNVISITED {
+                    index[successor] = next_index;
+                    low_link[successor] = next_index;
+                    next_index += 1;
+                    stack.push(successor);
+                    on_stack[successor] = true;
+                    frames.push((successor, 0));
+                } else if on_stack[successor] {
+                    low_link[node] = low_link[node].min(index[successor]);
+                }
+                continue;
+            }
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+            if low_link[node] == index[node] {
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component[member] = num_components;
+                    if member == node {
+                        break;
+                    }
+                }
+                num_components += 1;
+            }
+        }
+    }
+    component
+}
+
+/// Keep the scanned candidates and the objects ready for finalization alive.  This is the sentinel
+/// of the [`WorkBucketStage::FinalRefClosure`] bucket after all candidates are scanned.
+pub struct ResurrectFinalizables<T: Trace> {
+    num_candidates_begin: usize,
+    num_ready_for_finalize_begin: usize,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for ResurrectFinalizables<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let stage = WorkBucketStage::FinalRefClosure;
+        worker.scheduler().work_buckets[stage].set_sentinel(Box::new(FinishFinalization::<T> {
+            num_candidates_begin: self.num_candidates_begin,
+            num_ready_for_finalize_begin: self.num_ready_for_finalize_begin,
+            phantom_data: PhantomData,
+        }));
+        let packets = keep_alive_packets::<T>(mmtk, false, stage);
+        worker.scheduler().work_buckets[stage].bulk_add(packets);
+    }
+}
+
+/// Keep a batch of finalizable candidates and objects ready for finalization alive.  This is used
+/// for resurrecting finalizable objects, and for forwarding them (mark-compact-only).
+pub struct KeepFinalizablesAlive<T: Trace> {
+    candidates: Vec<FinalizableOf<T::VM>>,
+    ready: Vec<FinalizableOf<T::VM>>,
+    stage: WorkBucketStage,
+}
+
+impl<T: Trace> GCWork<T::VM> for KeepFinalizablesAlive<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let candidates = std::mem::take(&mut self.candidates);
+        let ready = std::mem::take(&mut self.ready);
+        let tracer_context = DefaultObjectTracerContext::<T>::new(self.stage);
         tracer_context.with_tracer(worker, |tracer| {
-            finalizable_processor.scan::<T::VM, _>(tls, tracer, is_nursery_gc(mmtk.get_plan()));
+            mmtk.finalizable_processor
+                .keep_alive(tracer, candidates, ready);
         });
+    }
+}
 
-        let num_candidates_end = finalizable_processor.candidates.len();
-        let num_ready_for_finalize_end = finalizable_processor.ready_for_finalize.len();
+/// Finish finalization after the transitive closure from the resurrected objects.  This is the
+/// sentinel of the [`WorkBucketStage::FinalRefClosure`] bucket after resurrection.  It then resumes
+/// ephemeron processing, because the resurrected objects may be the keys of ephemerons.
+pub struct FinishFinalization<T: Trace> {
+    num_candidates_begin: usize,
+    num_ready_for_finalize_begin: usize,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for FinishFinalization<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let (num_candidates_end, num_ready_for_finalize_end) =
+            mmtk.finalizable_processor.finish_scan();
 
         debug!(
             "Finished finalization, {} objects in candidates, {} objects ready to finalize",
             num_candidates_end, num_ready_for_finalize_end
         );
+        let num_candidates_begin = self.num_candidates_begin;
+        let num_ready_for_finalize_begin = self.num_ready_for_finalize_begin;
         probe!(
             mmtk,
             finalization,
@@ -185,12 +635,26 @@ impl<T: Trace> GCWork<T::VM> for Finalization<T> {
             num_ready_for_finalize_begin,
             num_ready_for_finalize_end
         );
-    }
-}
 
-impl<T: Trace> Finalization<T> {
-    pub fn new() -> Self {
-        Self(PhantomData)
+        <T::VM as VMBinding>::VMCollection::schedule_finalization(worker.tls);
+
+        // Rescan soft and weak references at the end of the transitive closure from resurrected
+        // objects and ephemerons.  New soft and weak references may be discovered during this.
+        // This also processes the reference kinds of the `Final` strength for the first time.
+        let rescan = (!*mmtk.options.no_reference_types).then(|| {
+            Box::new(RescanReferences {
+                strengths: &[
+                    ReferenceStrength::Soft,
+                    ReferenceStrength::Weak,
+                    ReferenceStrength::Final,
+                ],
+                stage: WorkBucketStage::FinalRefClosure,
+                phantom_data: PhantomData,
+            }) as Box<dyn GCWork<T::VM>>
+        });
+        worker.scheduler().work_buckets[WorkBucketStage::FinalRefClosure].set_sentinel(Box::new(
+            EphemeronProcessing::<T>::after_finalization(rescan),
+        ));
     }
 }
 
@@ -200,16 +664,9 @@ pub struct ForwardFinalization<T: Trace>(PhantomData<T>);
 impl<T: Trace> GCWork<T::VM> for ForwardFinalization<T> {
     fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
         trace!("Forward finalization");
-        let mut finalizable_processor = mmtk.finalizable_processor.lock().unwrap();
-
-        let tracer_context =
-            DefaultObjectTracerContext::<T>::new(WorkBucketStage::FinalizableForwarding);
-        tracer_context.with_tracer(worker, |tracer| {
-            finalizable_processor.forward_candidate(tracer, is_nursery_gc(mmtk.get_plan()));
-
-            finalizable_processor.forward_finalizable(tracer, is_nursery_gc(mmtk.get_plan()));
-        });
-        trace!("Finished forwarding finlizable");
+        let stage = WorkBucketStage::FinalizableForwarding;
+        let packets = keep_alive_packets::<T>(mmtk, true, stage);
+        worker.scheduler().work_buckets[stage].bulk_add(packets);
     }
 }
 impl<T: Trace> ForwardFinalization<T> {
@@ -217,3 +674,59 @@ impl<T: Trace> ForwardFinalization<T> {
         Self(PhantomData)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Address;
+
+    fn object(i: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(0x1000_0000 + i * 16) })
+            .unwrap()
+    }
+
+    fn postponed(dependencies: &[(usize, usize)]) -> Vec<usize> {
+        let dependencies: Vec<_> = dependencies
+            .iter()
+            .map(|&(from, to)| (object(from), object(to)))
+            .collect();
+        let postponed = find_postponed(&dependencies);
+        let mut postponed: Vec<usize> = (0..10)
+            .filter(|&i| postponed.contains(&object(i)))
+            .collect();
+        postponed.sort();
+        postponed
+    }
+
+    #[test]
+    fn chain() {
+        assert_eq!(postponed(&[(0, 1), (1, 2)]), vec![1, 2]);
+    }
+
+    #[test]
+    fn self_reference() {
+        assert!(postponed(&[(0, 0)]).is_empty());
+    }
+
+    #[test]
+    fn cycle() {
+        assert!(postponed(&[(0, 1), (1, 2), (2, 0)]).is_empty());
+    }
+
+    #[test]
+    fn cycle_reachable_from_other_objects() {
+        // 0 -> {1, 2} -> 3, and 4 -> 4
+        assert_eq!(
+            postponed(&[(0, 1), (1, 2), (2, 1), (2, 3), (4, 4)]),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn long_chain() {
+        let dependencies: Vec<_> = (0..100_000).map(|i| (object(i), object(i + 1))).collect();
+        let postponed = find_postponed(&dependencies);
+        assert_eq!(postponed.len(), 100_000);
+        assert!(!postponed.contains(&object(0)));
+    }
+}
@@ -0,0 +1,375 @@
+//! A global handle table managed by MMTk core.
+//!
+//! Runtimes often need to refer to heap objects from outside the heap, such as JNI global
+//! references.  Instead of keeping such references in its own table and reporting them in
+//! [`Scanning::scan_vm_specific_roots`] or [`Scanning::process_weak_refs`], a binding can create
+//! handles in the [`HandleTable`] of an MMTk instance.  The address of a handle does not change
+//! until the handle is released, and MMTk updates the handle when the object it refers to is
+//! moved.
+//!
+//! -   A strong handle keeps its object alive.  Strong handles are reported as roots through
+//!     [`RootsWorkFactory::create_process_handle_roots_work`] whenever MMTk scans VM-specific
+//!     roots.
+//! -   A weak handle does not keep its object alive.  After the binding has processed its own weak
+//!     references in [`Scanning::process_weak_refs`], weak handles that refer to dead objects are
+//!     cleared, and [`HandleTable::resolve`] returns `None` for them afterwards.
+//!
+//! Handles are allocated in blocks, and each block is processed in its own work packet so that
+//! large tables are processed in parallel.
+//!
+//! [`Scanning::scan_vm_specific_roots`]: crate::vm::Scanning::scan_vm_specific_roots
+//! [`Scanning::process_weak_refs`]: crate::vm::Scanning::process_weak_refs
+//! [`RootsWorkFactory::create_process_handle_roots_work`]: crate::vm::RootsWorkFactory::create_process_handle_roots_work
+
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use atomic::{Atomic, Ordering};
+
+use crate::plan::tracing::gc_work::DefaultObjectTracerContext;
+use crate::plan::tracing::Trace;
+use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::{Address, ObjectReference};
+use crate::vm::slot::{SimpleSlot, Slot};
+use crate::vm::{ObjectTracer, ObjectTracerContext, RootsWorkFactory, VMBinding};
+use crate::MMTK;
+
+/// The number of handles in a block.  Each block is processed in one work packet.
+pub const HANDLES_IN_BLOCK: usize = 512;
+
+/// The kind of a [`GlobalHandle`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HandleKind {
+    /// The handle keeps its object alive.
+    Strong,
+    /// The handle does not keep its object alive, and is cleared when the object dies.
+    Weak,
+}
+
+/// A handle in the [`HandleTable`].  It stays valid until it is released with
+/// [`HandleTable::release`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GlobalHandle {
+    entry: Address,
+    kind: HandleKind,
+}
+
+impl GlobalHandle {
+    /// The kind of the handle.
+    pub fn kind(&self) -> HandleKind {
+        self.kind
+    }
+
+    /// The address of the word that holds the object reference of this handle.  The address does
+    /// not change until the handle is released, so a binding may use it as an opaque handle value
+    /// (e.g. a JNI `jobject`).  The word holds 0 if a weak handle has been cleared.  A binding must
+    /// not write to it.  A binding that reads a weak handle from this word instead of calling
+    /// [`crate::memory_manager::resolve_handle`] must call the weak reference load barrier of the
+    /// mutator on the object, like `resolve_handle` does.
+    pub fn entry_address(&self) -> Address {
+        self.entry
+    }
+
+    fn slot(&self) -> SimpleSlot {
+        SimpleSlot::from_address(self.entry)
+    }
+}
+
+/// Handles of one kind.  Blocks are never freed until the table is dropped, so that the entry
+/// addresses stay stable.
+#[derive(Default)]
+struct HandleList {
+    sync: Mutex<HandleListSync>,
+}
+
+#[derive(Default)]
+struct HandleListSync {
+    blocks: Vec<Box<[Atomic<Address>]>>,
+    /// Entries that are not used by any handle.
+    free: Vec<Address>,
+}
+
+impl HandleList {
+    fn allocate(&self, object: ObjectReference) -> Address {
+        let mut sync = self.sync.lock().unwrap();
+        if sync.free.is_empty() {
+            let block: Box<[Atomic<Address>]> = (0..HANDLES_IN_BLOCK)
+                .map(|_| Atomic::new(Address::ZERO))
+                .collect();
+            // Pop from the start of the block first.
+            sync.free.extend(
+                block
+                    .iter()
+                    .rev()
+                    .map(|entry| Address::from_ptr(entry as *const Atomic<Address>)),
+            );
+            sync.blocks.push(block);
+        }
+        let entry = sync.free.pop().unwrap();
+        SimpleSlot::from_address(entry).store(object);
+        entry
+    }
+
+    fn free(&self, entry: Address) {
+        let mut sync = self.sync.lock().unwrap();
+        debug_assert!(
+            sync.blocks.iter().any(|block| block_contains(block, entry)),
+            "{} is not a handle in this table",
+            entry
+        );
+        clear_entry(entry);
+        sync.free.push(entry);
+    }
+
+    fn num_handles(&self) -> usize {
+        let sync = self.sync.lock().unwrap();
+        sync.blocks.len() * HANDLES_IN_BLOCK - sync.free.len()
+    }
+
+    fn blocks(&self) -> Vec<HandleBlock> {
+        let sync = self.sync.lock().unwrap();
+        sync.blocks
+            .iter()
+            .map(|block| HandleBlock {
+                start: Address::from_ptr(block.as_ptr()),
+            })
+            .collect()
+    }
+}
+
+fn block_contains(block: &[Atomic<Address>], entry: Address) -> bool {
+    let start = Address::from_ptr(block.as_ptr());
+    entry >= start && entry < start + std::mem::size_of_val(block)
+}
+
+fn clear_entry(entry: Address) {
+    unsafe { entry.as_ref::<Atomic<Address>>() }.store(Address::ZERO, Ordering::Relaxed);
+}
+
+/// A block of handle entries.  Unused entries and cleared weak handles hold 0.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct HandleBlock {
+    start: Address,
+}
+
+impl HandleBlock {
+    fn slots(&self) -> impl Iterator<Item = SimpleSlot> {
+        let start = self.start;
+        (0..HANDLES_IN_BLOCK).map(move |i| {
+            SimpleSlot::from_address(start + i * std::mem::size_of::<Atomic<Address>>())
+        })
+    }
+
+    /// The slots of the entries that currently refer to objects.
+    fn non_null_slots(&self) -> impl Iterator<Item = (SimpleSlot, ObjectReference)> {
+        self.slots()
+            .filter_map(|slot| slot.load().map(|object| (slot, object)))
+    }
+}
+
+/// The global handle table of an MMTk instance.  See the [module-level documentation](self).
+#[derive(Default)]
+pub struct HandleTable {
+    strong: HandleList,
+    weak: HandleList,
+}
+
+impl HandleTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn list(&self, kind: HandleKind) -> &HandleList {
+        match kind {
+            HandleKind::Strong => &self.strong,
+            HandleKind::Weak => &self.weak,
+        }
+    }
+
+    /// Create a handle of the given kind that refers to `object`.
+    pub fn create(&self, kind: HandleKind, object: ObjectReference) -> GlobalHandle {
+        let entry = self.list(kind).allocate(object);
+        trace!("Create {:?} handle {} for {}", kind, entry, object);
+        GlobalHandle { entry, kind }
+    }
+
+    /// Get the object a handle refers to.  Return `None` if it is a weak handle that has been
+    /// cleared.  The handle must not have been released.  This does not call any barrier.  See
+    /// [`crate::memory_manager::resolve_handle`].
+    pub fn resolve(&self, handle: GlobalHandle) -> Option<ObjectReference> {
+        handle.slot().load()
+    }
+
+    /// Release a handle.  The handle must not be used after this.
+    pub fn release(&self, handle: GlobalHandle) {
+        trace!("Release {:?} handle {}", handle.kind, handle.entry);
+        self.list(handle.kind).free(handle.entry);
+    }
+
+    /// The number of handles of the given kind that have not been released.
+    pub fn num_handles(&self, kind: HandleKind) -> usize {
+        self.list(kind).num_handles()
+    }
+
+    /// Report strong handles as roots.  Each block of handles is reported in a separate call so
+    /// that the resulting work packets can be processed in parallel.
+    pub(crate) fn scan_strong_handles<SL: Slot>(&self, factory: &mut impl RootsWorkFactory<SL>) {
+        for block in self.strong.blocks() {
+            let slots: Vec<SimpleSlot> = block.non_null_slots().map(|(slot, _)| slot).collect();
+            if !slots.is_empty() {
+                factory.create_process_handle_roots_work(slots);
+            }
+        }
+    }
+
+    /// The objects currently referred to by strong handles.
+    #[cfg(feature = "sanity")]
+    pub(crate) fn strong_objects(&self) -> Vec<ObjectReference> {
+        self.strong
+            .blocks()
+            .iter()
+            .flat_map(|block| block.non_null_slots().map(|(_, object)| object))
+            .collect()
+    }
+
+    pub(crate) fn has_weak_handles(&self) -> bool {
+        self.weak.num_handles() != 0
+    }
+}
+
+/// Create a [`ProcessWeakHandles`] work packet for each block of weak handles.  This is scheduled
+/// as the sentinel of the [`WorkBucketStage::VMRefClosure`] bucket after the VM binding finishes
+/// processing its weak references, so that the liveness of all objects is known.
+pub(crate) struct ScheduleWeakHandleProcessing;
+
+impl<VM: VMBinding> GCWork<VM> for ScheduleWeakHandleProcessing {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let packets = mmtk
+            .handle_table
+            .weak
+            .blocks()
+            .into_iter()
+            .map(|block| Box::new(ProcessWeakHandles { block }) as Box<dyn GCWork<VM>>)
+            .collect();
+        worker.scheduler().work_buckets[WorkBucketStage::VMRefClosure].bulk_add(packets);
+    }
+}
+
+/// Clear the weak handles in a block whose objects are dead, and update the others if their
+/// objects have been moved.
+pub(crate) struct ProcessWeakHandles {
+    block: HandleBlock,
+}
+
+impl<VM: VMBinding> GCWork<VM> for ProcessWeakHandles {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        let mut num_cleared = 0;
+        for (slot, object) in self.block.non_null_slots() {
+            if object.is_live() {
+                if let Some(new_object) = object.get_forwarded_object() {
+                    slot.store(new_object);
+                }
+            } else {
+                clear_entry(slot.as_address());
+                num_cleared += 1;
+            }
+        }
+        trace!(
+            "Cleared {} weak handles in block {}",
+            num_cleared,
+            self.block.start
+        );
+    }
+}
+
+/// Forward the weak handles in a block.  This is needed for plans that compute the new addresses
+/// of objects after determining liveness.
+pub(crate) struct ForwardWeakHandles<T: Trace> {
+    block: HandleBlock,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> ForwardWeakHandles<T> {
+    /// Create a work packet for each block of weak handles.
+    pub fn create_packets(mmtk: &'static MMTK<T::VM>) -> Vec<Box<dyn GCWork<T::VM>>> {
+        mmtk.handle_table
+            .weak
+            .blocks()
+            .into_iter()
+            .map(|block| {
+                Box::new(Self {
+                    block,
+                    phantom_data: PhantomData,
+                }) as Box<dyn GCWork<T::VM>>
+            })
+            .collect()
+    }
+}
+
+impl<T: Trace> GCWork<T::VM> for ForwardWeakHandles<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, _mmtk: &'static MMTK<T::VM>) {
+        let tracer_context = DefaultObjectTracerContext::<T>::new(WorkBucketStage::VMRefForwarding);
+        tracer_context.with_tracer(worker, |tracer| {
+            for (slot, object) in self.block.non_null_slots() {
+                let new_object = tracer.trace_object(object);
+                if new_object != object {
+                    slot.store(new_object);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(i: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(0x1000_0000 + i * 16) })
+            .unwrap()
+    }
+
+    #[test]
+    fn create_resolve_release() {
+        let table = HandleTable::new();
+        let strong = table.create(HandleKind::Strong, object(1));
+        let weak = table.create(HandleKind::Weak, object(2));
+        assert_eq!(strong.kind(), HandleKind::Strong);
+        assert_eq!(table.resolve(strong), Some(object(1)));
+        assert_eq!(table.resolve(weak), Some(object(2)));
+        assert_eq!(table.num_handles(HandleKind::Strong), 1);
+        assert_eq!(table.num_handles(HandleKind::Weak), 1);
+
+        // A cleared weak handle resolves to `None`.
+        clear_entry(weak.entry_address());
+        assert_eq!(table.resolve(weak), None);
+
+        table.release(strong);
+        table.release(weak);
+        assert_eq!(table.num_handles(HandleKind::Strong), 0);
+        assert_eq!(table.num_handles(HandleKind::Weak), 0);
+
+        // Released entries are reused.
+        let reused = table.create(HandleKind::Strong, object(3));
+        assert_eq!(reused.entry_address(), strong.entry_address());
+    }
+
+    #[test]
+    fn handles_are_stable_across_blocks() {
+        let table = HandleTable::new();
+        let handles: Vec<GlobalHandle> = (0..HANDLES_IN_BLOCK * 2 + 1)
+            .map(|i| table.create(HandleKind::Strong, object(i)))
+            .collect();
+        assert_eq!(table.strong.blocks().len(), 3);
+        for (i, handle) in handles.iter().enumerate() {
+            assert_eq!(table.resolve(*handle), Some(object(i)));
+        }
+        let non_null: usize = table
+            .strong
+            .blocks()
+            .iter()
+            .map(|block| block.non_null_slots().count())
+            .sum();
+        assert_eq!(non_null, handles.len());
+    }
+}
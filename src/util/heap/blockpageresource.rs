@@ -7,6 +7,7 @@ use crate::util::heap::layout::VMMap;
 use crate::util::heap::pageresource::CommonPageResource;
 use crate::util::heap::space_descriptor::SpaceDescriptor;
 use crate::util::linear_scan::Region;
+use crate::util::numa::{CHUNK_NODES, NUMA_TOPOLOGY};
 use crate::util::opaque_pointer::*;
 use crate::util::rust_util::zeroed_alloc::new_zeroed_vec;
 use crate::vm::*;
@@ -23,8 +24,8 @@ const LOCAL_BUFFER_SIZE: usize = 128;
 /// A fast PageResource for fixed-size block allocation only.
 pub struct BlockPageResource<VM: VMBinding, B: Region + 'static> {
     flpr: FreeListPageResource<VM>,
-    /// A buffer for storing all the free blocks
-    block_queue: BlockPool<B>,
+    /// A buffer for storing all the free blocks, split by the NUMA nodes of their chunks
+    block_queue: NumaBlockPool<B>,
     /// Slow-path allocation synchronization
     sync: Mutex<()>,
 }
@@ -68,11 +69,12 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         bytes: usize,
         vm_map: &'static dyn VMMap,
         num_workers: usize,
+        numa_aware: bool,
     ) -> Self {
         assert!((1 << log_pages) <= PAGES_IN_CHUNK);
         Self {
             flpr: FreeListPageResource::new_contiguous(start, bytes, vm_map),
-            block_queue: BlockPool::new(num_workers),
+            block_queue: NumaBlockPool::new(num_workers, numa_aware),
             sync: Mutex::new(()),
         }
     }
@@ -81,11 +83,12 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         log_pages: usize,
         vm_map: &'static dyn VMMap,
         num_workers: usize,
+        numa_aware: bool,
     ) -> Self {
         assert!((1 << log_pages) <= PAGES_IN_CHUNK);
         Self {
             flpr: FreeListPageResource::new_discontiguous(vm_map),
-            block_queue: BlockPool::new(num_workers),
+            block_queue: NumaBlockPool::new(num_workers, numa_aware),
             sync: Mutex::new(()),
         }
     }
@@ -99,8 +102,9 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         tls: VMThread,
     ) -> Result<PRAllocResult, PRAllocFail> {
         let _guard = self.sync.lock().unwrap();
+        let node = self.block_queue.current_node();
         // Retry fast allocation
-        if let Some(block) = self.block_queue.pop() {
+        if let Some(block) = self.block_queue.pop_for_node(node) {
             self.commit_pages(reserved_pages, required_pages, tls);
             return Result::Ok(PRAllocResult {
                 start: block.start(),
@@ -109,12 +113,26 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
             });
         }
         // Grow space (a chunk at a time)
-        // 1. Grow space
+        // 1. Grow space.  If we cannot, use the free blocks of other NUMA nodes.
         let start: Address = match self.flpr.allocate_one_chunk_no_commit(space_descriptor) {
             Ok(result) => result.start,
-            err => return err,
+            err => {
+                if let Some(block) = self.block_queue.pop_from_other_nodes(node) {
+                    self.commit_pages(reserved_pages, required_pages, tls);
+                    return Result::Ok(PRAllocResult {
+                        start: block.start(),
+                        pages: required_pages,
+                        new_chunk: false,
+                    });
+                }
+                return err;
+            }
         };
         assert!(start.is_aligned_to(BYTES_IN_CHUNK));
+        // The chunk belongs to the node of the current thread.
+        if self.block_queue.num_nodes() > 1 {
+            CHUNK_NODES.set(start, node);
+        }
         // 2. Take the first block int the chunk as the allocation result
         let first_block = start;
         // 3. Push all remaining blocks to one or more block lists
@@ -124,7 +142,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         while cursor < last_block {
             let result = unsafe { array.push_relaxed(B::from_aligned_address(cursor)) };
             if let Err(block) = result {
-                self.block_queue.add_global_array(array);
+                self.block_queue.add_global_array(node, array);
                 array = BlockQueue::new();
                 let result2 = unsafe { array.push_relaxed(block) };
                 debug_assert!(result2.is_ok());
@@ -133,7 +151,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         }
         debug_assert!(!array.is_empty());
         // 4. Push the block list to the global pool
-        self.block_queue.add_global_array(array);
+        self.block_queue.add_global_array(node, array);
         // Finish slow-allocation
         self.commit_pages(reserved_pages, required_pages, tls);
         Result::Ok(PRAllocResult {
@@ -153,8 +171,11 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
     ) -> Result<PRAllocResult, PRAllocFail> {
         debug_assert_eq!(reserved_pages, required_pages);
         debug_assert_eq!(reserved_pages, 1 << Self::LOG_PAGES);
-        // Fast allocate from the blocks list
-        if let Some(block) = self.block_queue.pop() {
+        // Fast allocate from the blocks list of the current node
+        if let Some(block) = self
+            .block_queue
+            .pop_for_node(self.block_queue.current_node())
+        {
             self.commit_pages(reserved_pages, required_pages, tls);
             return Result::Ok(PRAllocResult {
                 start: block.start(),
@@ -291,6 +312,105 @@ impl<B: Region> BlockQueue<B> {
     }
 }
 
+/// Free blocks split by the NUMA nodes of their chunks, so that a thread reuses the free blocks on
+/// its own node.  A block is returned to the pool of the node that its chunk was acquired for (see
+/// [`CHUNK_NODES`]).  There is only one pool if the `numa_aware` option is not set, or if the
+/// machine has only one NUMA node.
+pub struct NumaBlockPool<B: Region> {
+    pools: Vec<BlockPool<B>>,
+}
+
+impl<B: Region> NumaBlockPool<B> {
+    pub fn new(num_workers: usize, numa_aware: bool) -> Self {
+        let num_nodes = if numa_aware {
+            NUMA_TOPOLOGY.num_nodes()
+        } else {
+            1
+        };
+        Self::with_nodes(num_workers, num_nodes)
+    }
+
+    fn with_nodes(num_workers: usize, num_nodes: usize) -> Self {
+        Self {
+            pools: (0..num_nodes)
+                .map(|_| BlockPool::new(num_workers))
+                .collect(),
+        }
+    }
+
+    /// The number of nodes that blocks are split into.
+    pub fn num_nodes(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// The node index of the current thread.
+    pub fn current_node(&self) -> usize {
+        if self.pools.len() > 1 {
+            NUMA_TOPOLOGY.current_node_index()
+        } else {
+            0
+        }
+    }
+
+    fn node_of(&self, block: B) -> usize {
+        if self.pools.len() > 1 {
+            CHUNK_NODES.get(block.start()).unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    fn add_global_array(&self, node: usize, array: BlockQueue<B>) {
+        self.pools[node].add_global_array(array)
+    }
+
+    /// Push a block to the pool of the node of its chunk.
+    pub fn push(&self, block: B) {
+        self.push_to_node(self.node_of(block), block)
+    }
+
+    fn push_to_node(&self, node: usize, block: B) {
+        self.pools[node].push(block)
+    }
+
+    /// Pop a block, preferring the node of the current thread.
+    pub fn pop(&self) -> Option<B> {
+        let node = self.current_node();
+        self.pop_for_node(node)
+            .or_else(|| self.pop_from_other_nodes(node))
+    }
+
+    /// Pop a block on the given node.
+    pub fn pop_for_node(&self, node: usize) -> Option<B> {
+        self.pools[node].pop()
+    }
+
+    /// Pop a block on any node other than the given node, starting from the next node.
+    pub fn pop_from_other_nodes(&self, node: usize) -> Option<B> {
+        let num_nodes = self.pools.len();
+        (1..num_nodes).find_map(|i| self.pools[(node + i) % num_nodes].pop())
+    }
+
+    /// Flush all thread-local queues to the global pools
+    pub fn flush_all(&self) {
+        for pool in self.pools.iter() {
+            pool.flush_all()
+        }
+    }
+
+    /// Get total number of blocks of all nodes
+    pub fn len(&self) -> usize {
+        self.pools.iter().map(|pool| pool.len()).sum()
+    }
+
+    /// Iterate all the blocks of all nodes
+    pub fn iterate_blocks(&self, f: &mut impl FnMut(B)) {
+        for pool in self.pools.iter() {
+            pool.iterate_blocks(f)
+        }
+    }
+}
+
 /// A block queue which contains a global pool and a set of thread-local queues.
 ///
 /// Mutator or collector threads always allocate blocks by poping from the global pool。
@@ -413,3 +533,48 @@ impl<B: Region> BlockPool<B> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::heap::chunk_map::Chunk;
+
+    fn chunk(i: usize) -> Chunk {
+        Chunk::from_aligned_address(unsafe { Address::from_usize((i + 1) << Chunk::LOG_BYTES) })
+    }
+
+    fn add_blocks(pool: &NumaBlockPool<Chunk>, node: usize, blocks: &[usize]) {
+        let array = BlockQueue::new();
+        for i in blocks {
+            unsafe { array.push_relaxed(chunk(*i)) }.unwrap();
+        }
+        pool.add_global_array(node, array);
+    }
+
+    #[test]
+    fn numa_block_pool_prefers_own_node() {
+        let pool = NumaBlockPool::<Chunk>::with_nodes(1, 2);
+        add_blocks(&pool, 0, &[0, 1]);
+        add_blocks(&pool, 1, &[2]);
+        assert_eq!(pool.len(), 3);
+
+        // Blocks on node 1 are only used by node 0 if it has no free blocks.
+        let mut node0 = vec![pool.pop_for_node(0).unwrap(), pool.pop_for_node(0).unwrap()];
+        node0.sort_by_key(|block| block.start());
+        assert_eq!(node0, vec![chunk(0), chunk(1)]);
+        assert_eq!(pool.pop_for_node(0), None);
+        assert_eq!(pool.pop_from_other_nodes(0), Some(chunk(2)));
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.pop_from_other_nodes(1), None);
+    }
+
+    #[test]
+    fn numa_block_pool_single_node() {
+        let pool = NumaBlockPool::<Chunk>::new(1, false);
+        assert_eq!(pool.num_nodes(), 1);
+        assert_eq!(pool.current_node(), 0);
+        add_blocks(&pool, 0, &[0]);
+        assert_eq!(pool.pop(), Some(chunk(0)));
+        assert_eq!(pool.pop(), None);
+    }
+}
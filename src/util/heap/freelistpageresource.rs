@@ -356,6 +356,15 @@ impl<VM: VMBinding> FreeListPageResource<VM> {
         }
     }
 
+    /// Get the number of pages allocated at `first`, which must be the start of an allocation
+    /// returned by this page resource.
+    pub fn get_allocated_pages(&self, first: Address) -> usize {
+        debug_assert!(conversions::is_page_aligned(first));
+        let sync = self.sync.lock().unwrap();
+        let page_offset = conversions::bytes_to_pages_up(first - sync.start);
+        sync.free_list.size(page_offset as _) as usize
+    }
+
     fn release_free_chunks(
         &self,
         freed_page: Address,
@@ -1,11 +1,13 @@
 use atomic::Ordering;
 
 use crate::global_state::GlobalState;
+use crate::plan::concurrent::pacer::AssistCredit;
 use crate::plan::Plan;
 use crate::policy::space::Space;
 use crate::scheduler::GCWorkScheduler;
 use crate::util::constants::BYTES_IN_PAGE;
 use crate::util::conversions;
+use crate::util::opaque_pointer::VMMutatorThread;
 use crate::util::options::{GCTriggerSelector, Options, DEFAULT_MAX_NURSERY, DEFAULT_MIN_NURSERY};
 use crate::vm::Collection;
 use crate::vm::VMBinding;
@@ -13,6 +15,7 @@ use crate::MMTK;
 use std::mem::MaybeUninit;
 use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// GCTrigger is responsible for triggering GCs based on the given policy.
 /// All the decisions about heap limit and GC triggering should be resolved here.
@@ -93,12 +96,97 @@ impl<VM: VMBinding> GCTrigger<VM> {
         }
     }
 
+    /// Block the mutator `tls` until the GC it requested has finished.  If the option
+    /// `mutator_driven_gc` is set, the mutator runs the GC itself.  Otherwise, this calls
+    /// [`Collection::block_for_gc`].
+    pub fn wait_for_gc(&self, tls: VMMutatorThread) {
+        if *self.options.mutator_driven_gc {
+            self.scheduler.run_gc_on_mutator(tls);
+        } else {
+            VM::VMCollection::block_for_gc(tls);
+        }
+    }
+
+    /// Called when the mutator `tls` allocates `allocated` bytes in the allocation slow path during
+    /// marking.  The mutator does a slice of incremental marking, or assists concurrent marking.
+    /// See [`crate::plan::concurrent::pacer`].
+    pub(crate) fn on_allocation_during_marking(
+        &self,
+        tls: VMMutatorThread,
+        credit: &AssistCredit,
+        allocated: usize,
+    ) {
+        if self.state.pacer.is_incremental() {
+            self.do_incremental_marking_slice(tls);
+        } else {
+            self.assist_concurrent_marking(tls, credit, allocated);
+        }
+    }
+
+    /// Let the mutator `tls` do one slice of incremental marking, bounded by the options
+    /// `incremental_marking_slice_us` and `incremental_marking_slice_bytes`.
+    pub(crate) fn do_incremental_marking_slice(&self, tls: VMMutatorThread) {
+        let pacer = &self.state.pacer;
+        let max_bytes = *self.options.incremental_marking_slice_bytes;
+        let max_time = Duration::from_micros(*self.options.incremental_marking_slice_us as u64);
+        let start = Instant::now();
+        pacer.begin_assist();
+        let out_of_work = self.scheduler.assist_concurrent_work(tls, || {
+            (max_bytes == 0 || pacer.assist_work_done() < max_bytes)
+                && (max_time.is_zero() || start.elapsed() < max_time)
+        });
+        let work = pacer.end_assist();
+        trace!(
+            "Mutator {:?} marked {} bytes in {:?}{}",
+            tls,
+            work,
+            start.elapsed(),
+            if out_of_work {
+                " and ran out of work"
+            } else {
+                ""
+            }
+        );
+    }
+
+    /// Charge `allocated` bytes to the assist credit of the mutator `tls` during concurrent
+    /// marking, and let the mutator do marking work until its debt is repaid.
+    fn assist_concurrent_marking(
+        &self,
+        tls: VMMutatorThread,
+        credit: &AssistCredit,
+        allocated: usize,
+    ) {
+        let pacer = &self.state.pacer;
+        let plan = self.plan();
+        let debt = pacer.charge(
+            credit,
+            allocated,
+            plan.get_used_pages(),
+            plan.get_total_pages(),
+        );
+        if debt == 0 {
+            return;
+        }
+        trace!("Mutator {:?} assists marking for {} bytes", tls, debt);
+        pacer.begin_assist();
+        let out_of_work = self
+            .scheduler
+            .assist_concurrent_work(tls, || pacer.assist_work_done() < debt);
+        pacer.repay(credit, pacer.end_assist(), out_of_work);
+    }
+
     /// Clear the "GC requested" flag so that mutators can trigger the next GC.
     /// Called by a GC worker when all mutators have come to a stop.
     pub fn clear_request(&self) {
         self.request_flag.store(false, Ordering::Relaxed);
     }
 
+    /// Return true if a GC is requested, and mutators are not stopped for it, yet.
+    pub(crate) fn is_gc_requested(&self) -> bool {
+        self.request_flag.load(Ordering::Relaxed)
+    }
+
     /// This method is called periodically by the allocation subsystem
     /// (by default, each time a page is consumed), and provides the
     /// collector with an opportunity to collect.
@@ -188,10 +276,12 @@ impl<VM: VMBinding> GCTrigger<VM> {
     }
 
     /// Check if we should do a stress GC now. If GC is initialized and the allocation bytes exceeds
-    /// the stress factor, we should do a stress GC.
+    /// the stress factor, we should do a stress GC. With a stress seed, the number of bytes between
+    /// stress GCs is drawn from the seed instead.
     pub(crate) fn should_do_stress_gc_inner(state: &GlobalState, options: &Options) -> bool {
         state.is_initialized()
-            && (state.allocation_bytes.load(Ordering::SeqCst) > *options.stress_factor)
+            && (state.allocation_bytes.load(Ordering::SeqCst)
+                > state.stress.stress_gc_bytes(*options.stress_factor))
     }
 
     /// Check if the heap is full
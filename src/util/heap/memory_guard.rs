@@ -0,0 +1,199 @@
+//! Guarding released memory for debugging.
+//!
+//! When the `guard_released_memory` option is enabled, spaces poison the memory they release, and
+//! protect it if it is made of whole pages, until a space acquires it again.  A stale reference into
+//! a released block, page or copy-space region then faults at its first use instead of reading or
+//! corrupting memory that has been reused.  Free lines in Immix blocks are smaller than a page, so
+//! they are only poisoned.
+//!
+//! Protected memory is recorded in a process-wide registry, because a released chunk may later be
+//! acquired by a different space.  The VM binding can look up the faulting address in its signal
+//! handler with [`crate::memory_manager::find_guarded_memory`].
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::util::conversions;
+use crate::util::os::*;
+use crate::util::Address;
+
+/// The byte pattern written to released memory.  A word of this pattern is not a valid address
+/// on common 64-bit platforms, so dereferencing a reference loaded from poisoned memory faults.
+pub(crate) const POISON_BYTE: u8 = 0xdb;
+
+/// A range of released memory that is currently protected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuardedMemory {
+    /// The start address of the range.
+    pub start: Address,
+    /// The size of the range in bytes.
+    pub bytes: usize,
+    /// The name of the space that released the range.
+    pub space: &'static str,
+}
+
+impl GuardedMemory {
+    fn end(&self) -> Address {
+        self.start + self.bytes
+    }
+}
+
+/// Protected ranges, indexed by their start addresses.  The ranges never overlap.
+static GUARDED: Mutex<BTreeMap<Address, GuardedMemory>> = Mutex::new(BTreeMap::new());
+
+/// Poison the memory without protecting it.
+pub(crate) fn poison(start: Address, bytes: usize) {
+    crate::util::memory::set(start, POISON_BYTE, bytes);
+}
+
+/// Poison and protect the released memory from `start` to `start + bytes`, which must be
+/// page-aligned and mapped.  It stays protected until [`unguard`] is called for it.
+pub(crate) fn guard(start: Address, bytes: usize, space: &'static str) {
+    debug_assert!(conversions::is_page_aligned(start));
+    debug_assert!(conversions::is_page_aligned(Address::ZERO + bytes));
+    if bytes == 0 {
+        return;
+    }
+
+    poison(start, bytes);
+
+    let mut guarded = GUARDED.lock().unwrap();
+    if let Err(e) = OS::set_memory_access(start, bytes, MmapProtection::NoAccess) {
+        panic!(
+            "Failed at protecting released memory {} ({} bytes) of {}: {:?}",
+            start, bytes, space, e
+        );
+    }
+    trace!("Guard {} ({} bytes) of {}", start, bytes, space);
+    let old = guarded.insert(
+        start,
+        GuardedMemory {
+            start,
+            bytes,
+            space,
+        },
+    );
+    debug_assert!(old.is_none(), "Memory at {start} is guarded twice");
+}
+
+/// Unprotect any guarded memory between `start` and `start + bytes` with the protection `prot`.
+/// Spaces call this when they acquire memory, which may have been released by any space.
+pub(crate) fn unguard(start: Address, bytes: usize, prot: MmapProtection) {
+    let end = start + bytes;
+    let mut guarded = GUARDED.lock().unwrap();
+
+    // Ranges do not overlap, so their ends are sorted like their starts.
+    let overlapping = guarded
+        .range(..end)
+        .rev()
+        .take_while(|(_, memory)| memory.end() > start)
+        .map(|(_, memory)| *memory)
+        .collect::<Vec<_>>();
+
+    for memory in overlapping {
+        guarded.remove(&memory.start);
+
+        let from = memory.start.max(start);
+        let to = memory.end().min(end);
+        if let Err(e) = OS::set_memory_access(from, to - from, prot) {
+            panic!(
+                "Failed at unprotecting guarded memory {} ({} bytes): {:?}",
+                from,
+                to - from,
+                e
+            );
+        }
+        trace!("Unguard {} ({} bytes) of {}", from, to - from, memory.space);
+
+        // Keep the parts outside the acquired range guarded.
+        if memory.start < from {
+            guarded.insert(
+                memory.start,
+                GuardedMemory {
+                    bytes: from - memory.start,
+                    ..memory
+                },
+            );
+        }
+        if to < memory.end() {
+            guarded.insert(
+                to,
+                GuardedMemory {
+                    start: to,
+                    bytes: memory.end() - to,
+                    ..memory
+                },
+            );
+        }
+    }
+}
+
+/// Find the guarded memory that contains `addr`.
+///
+/// This does not block.  It returns `None` if another thread is updating the registry at the time.
+pub(crate) fn find(addr: Address) -> Option<GuardedMemory> {
+    let guarded = GUARDED.try_lock().ok()?;
+    guarded
+        .range(..=addr)
+        .next_back()
+        .map(|(_, memory)| *memory)
+        .filter(|memory| addr < memory.end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+    use crate::util::test_util::{serial_test, with_cleanup};
+
+    #[test]
+    fn guard_and_unguard_part_of_a_range() {
+        serial_test(|| {
+            with_cleanup(
+                || {
+                    let bytes = BYTES_IN_PAGE * 4;
+                    let start = OS::dzmmap_anywhere(
+                        bytes,
+                        BYTES_IN_PAGE,
+                        MmapStrategy::TEST,
+                        mmap_anno_test!(),
+                    )
+                    .unwrap();
+
+                    guard(start, bytes, "test");
+                    assert_eq!(
+                        find(start + BYTES_IN_PAGE * 2),
+                        Some(GuardedMemory {
+                            start,
+                            bytes,
+                            space: "test"
+                        })
+                    );
+
+                    // Acquire the second page.  The first and the last two pages stay guarded.
+                    unguard(
+                        start + BYTES_IN_PAGE,
+                        BYTES_IN_PAGE,
+                        MmapProtection::ReadWrite,
+                    );
+                    assert_eq!(find(start + BYTES_IN_PAGE), None);
+                    assert_eq!(find(start).map(|memory| memory.bytes), Some(BYTES_IN_PAGE));
+                    assert_eq!(
+                        find(start + BYTES_IN_PAGE * 3).map(|memory| memory.start),
+                        Some(start + BYTES_IN_PAGE * 2)
+                    );
+
+                    // The acquired page was poisoned, and is accessible again.
+                    let word = unsafe { (start + BYTES_IN_PAGE).load::<u8>() };
+                    assert_eq!(word, POISON_BYTE);
+
+                    unguard(start, bytes, MmapProtection::ReadWrite);
+                    assert_eq!(find(start), None);
+                    assert_eq!(find(start + BYTES_IN_PAGE * 3), None);
+                    OS::munmap(start, bytes).unwrap();
+                },
+                || {},
+            )
+        })
+    }
+}
@@ -7,6 +7,7 @@ pub(crate) mod externalpageresource;
 pub(crate) mod freelistpageresource;
 pub(crate) mod gc_trigger;
 mod heap_meta;
+pub(crate) mod memory_guard;
 pub(crate) mod monotonepageresource;
 pub(crate) mod pageresource;
 pub(crate) mod regionpageresource;
@@ -20,6 +21,7 @@ pub use self::gc_trigger::GCTriggerPolicy;
 pub use self::gc_trigger::SpaceStats;
 pub(crate) use self::heap_meta::HeapMeta;
 pub use self::layout::vm_layout;
+pub use self::memory_guard::GuardedMemory;
 pub(crate) use self::monotonepageresource::MonotonePageResource;
 pub(crate) use self::pageresource::PageResource;
 pub(crate) use self::regionpageresource::RegionPageResource;
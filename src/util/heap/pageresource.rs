@@ -1,6 +1,7 @@
 use crate::util::address::Address;
 use crate::util::conversions;
 use crate::util::freelist::FreeList;
+use crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK;
 use crate::util::opaque_pointer::*;
 use std::sync::Mutex;
 
@@ -179,6 +180,11 @@ impl CommonPageResource {
         if chunk == *head_discontiguous_region {
             *head_discontiguous_region = self.vm_map.get_next_contiguous_region(chunk);
         }
+        // The chunks may be acquired by another space, possibly for another NUMA node.
+        let chunks = self.vm_map.get_contiguous_region_chunks(chunk);
+        for i in 0..chunks {
+            crate::util::numa::CHUNK_NODES.clear(chunk + i * BYTES_IN_CHUNK);
+        }
         unsafe {
             self.vm_map.free_contiguous_chunks(chunk);
         }
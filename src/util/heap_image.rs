@@ -0,0 +1,397 @@
+//! Persistent heap images.
+//!
+//! A heap image is a snapshot of the objects in selected spaces.  A VM binding can build the same
+//! object graph once, save it with [`crate::memory_manager::save_heap_image`], write it to a file
+//! with [`HeapImage::write_to`], and at the next start-up read it back with
+//! [`HeapImage::read_from`] and map it into the VM space with
+//! [`crate::memory_manager::restore_heap_image`].  The restored objects are immortal and
+//! non-moving, and are traced like any other object in the VM space.
+//!
+//! When saving, objects are copied from their spaces and packed contiguously, and each reference
+//! between them is rewritten to the address that the object will have if the image is restored at
+//! its preferred start address.  The image records the offset of every object, so if the image is
+//! restored at a different address, MMTk scans the restored objects and relocates their
+//! references.  References are read and written through [`crate::vm::Scanning::scan_object`] and
+//! [`crate::vm::slot::Slot`], so compressed or tagged references are supported as long as the
+//! binding can encode addresses in the image.
+//!
+//! The object graph saved in an image must be closed: an object in the image cannot refer to an
+//! object that is not in the image.  MMTk's side metadata (such as VO bits, mark bits and log bits)
+//! is not stored in the image.  It is established again for each object when the image is
+//! restored, using [`crate::MMTK::initialize_vm_space_object`].
+
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::conversions::raw_align_up;
+use crate::util::heap::layout::vm_layout::vm_layout;
+use crate::util::object_enum::ClosureObjectEnumerator;
+use crate::util::opaque_pointer::*;
+use crate::util::{Address, ObjectReference};
+use crate::vm::slot::Slot;
+use crate::vm::{ObjectModel, Scanning, VMBinding};
+use crate::MMTK;
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+const MAGIC: &[u8; 8] = b"MMTKHIMG";
+const VERSION: u32 = 1;
+
+/// A heap image.  See the module-level documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeapImage {
+    /// The address that the image is built for.  Restoring the image at this address does not
+    /// need relocation.
+    preferred_start: Address,
+    /// The contents of the objects, starting from `preferred_start`.
+    data: Vec<u8>,
+    /// The offsets of the object references of all the objects from the start of the image.
+    objects: Vec<usize>,
+    /// The indices (into `objects`) of the root objects.
+    roots: Vec<usize>,
+}
+
+impl HeapImage {
+    /// The address that the image is built for.
+    pub fn preferred_start(&self) -> Address {
+        self.preferred_start
+    }
+
+    /// The number of bytes the binding needs to map to restore the image.  It is a multiple of
+    /// the page size.
+    pub fn size(&self) -> usize {
+        raw_align_up(self.data.len().max(1), BYTES_IN_PAGE)
+    }
+
+    /// The number of objects in the image.
+    pub fn num_objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Serialize the image.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_usize(writer, self.preferred_start.as_usize())?;
+        write_usize(writer, self.data.len())?;
+        write_usize(writer, self.objects.len())?;
+        write_usize(writer, self.roots.len())?;
+        for offset in self.objects.iter().chain(self.roots.iter()) {
+            write_usize(writer, *offset)?;
+        }
+        writer.write_all(&self.data)
+    }
+
+    /// Deserialize an image written by [`HeapImage::write_to`].
+    pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "Not an MMTk heap image"));
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported heap image version {}",
+                    u32::from_le_bytes(version)
+                ),
+            ));
+        }
+        let preferred_start = unsafe { Address::from_usize(read_usize(reader)?) };
+        let data_len = read_usize(reader)?;
+        let n_objects = read_usize(reader)?;
+        let n_roots = read_usize(reader)?;
+        // An image is restored into a single region, so it cannot be larger than a space.  Check
+        // the lengths before allocating anything for them.
+        if data_len > vm_layout().max_space_extent()
+            || preferred_start.as_usize().checked_add(data_len).is_none()
+            || n_objects > data_len
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Corrupted heap image"));
+        }
+        let objects = (0..n_objects)
+            .map(|_| read_usize(reader))
+            .collect::<Result<Vec<_>>>()?;
+        let roots = (0..n_roots)
+            .map(|_| read_usize(reader))
+            .collect::<Result<Vec<_>>>()?;
+        if objects.iter().any(|offset| *offset >= data_len)
+            || roots.iter().any(|index| *index >= n_objects)
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Corrupted heap image"));
+        }
+        let mut data = vec![0u8; data_len];
+        reader.read_exact(&mut data)?;
+        Ok(Self {
+            preferred_start,
+            data,
+            objects,
+            roots,
+        })
+    }
+
+    /// Build an image from the objects in the spaces named `spaces`.
+    pub(crate) fn build<VM: VMBinding>(
+        mmtk: &MMTK<VM>,
+        tls: VMThread,
+        spaces: &[&str],
+        roots: &[ObjectReference],
+        preferred_start: Address,
+    ) -> Result<Self> {
+        if !preferred_start.is_aligned_to(BYTES_IN_PAGE) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The image start {} is not page-aligned", preferred_start),
+            ));
+        }
+
+        let mut objects = vec![];
+        let mut found_spaces = vec![];
+        {
+            let mut enumerator =
+                ClosureObjectEnumerator::<_, VM>::new(|object| objects.push(object));
+            mmtk.get_plan().for_each_space(&mut |space| {
+                if spaces.contains(&space.get_name()) {
+                    found_spaces.push(space.get_name());
+                    space.enumerate_objects(&mut enumerator);
+                }
+            });
+        }
+        if let Some(name) = spaces.iter().find(|name| !found_spaces.contains(name)) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown space {}", name),
+            ));
+        }
+        objects.sort();
+
+        // Decide where each object goes.  We keep the start of each object at the same offset
+        // modulo `MAX_ALIGNMENT` so that the alignment of objects and their fields is preserved.
+        let mut forwarding = HashMap::with_capacity(objects.len());
+        let mut layout = Vec::with_capacity(objects.len());
+        let mut cursor = 0usize;
+        for object in objects.iter() {
+            let start = VM::VMObjectModel::ref_to_object_start(*object);
+            let size = VM::VMObjectModel::get_current_size(*object);
+            let misalignment = start.as_usize() % VM::MAX_ALIGNMENT;
+            let new_start = cursor
+                + (misalignment + VM::MAX_ALIGNMENT - cursor % VM::MAX_ALIGNMENT)
+                    % VM::MAX_ALIGNMENT;
+            let ref_offset = new_start + (object.to_raw_address() - start);
+            forwarding.insert(*object, preferred_start + ref_offset);
+            layout.push((start, size, new_start, ref_offset));
+            cursor = new_start + size;
+        }
+
+        let root_indices = roots
+            .iter()
+            .map(|root| {
+                objects.binary_search(root).map_err(|_| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Root {} is not in the saved spaces", root),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut data = vec![0u8; cursor];
+        for (object, (start, size, new_start, _)) in objects.iter().zip(layout.iter()) {
+            let worker_tls = VMWorkerThread(tls);
+            if !VM::VMScanning::support_slot_enqueuing(worker_tls, *object) {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("Object {} does not support slot enqueuing", object),
+                ));
+            }
+            let mut slots = vec![];
+            VM::VMScanning::scan_object(worker_tls, *object, &mut |slot: VM::VMSlot| {
+                slots.push(slot)
+            });
+
+            // Temporarily point the references at their addresses in the image, copy the object,
+            // and then restore the references.
+            let mut swizzled = Vec::with_capacity(slots.len());
+            let mut dangling = None;
+            for slot in slots {
+                if let Some(target) = slot.load() {
+                    match forwarding.get(&target) {
+                        Some(new_target) => {
+                            let new_target =
+                                ObjectReference::from_raw_address(*new_target).unwrap();
+                            slot.store(new_target);
+                            swizzled.push((slot, target));
+                        }
+                        None => {
+                            dangling = Some(target);
+                            break;
+                        }
+                    }
+                }
+            }
+            if dangling.is_none() {
+                let bytes = unsafe { std::slice::from_raw_parts(start.to_ptr::<u8>(), *size) };
+                data[*new_start..*new_start + *size].copy_from_slice(bytes);
+            }
+            for (slot, target) in swizzled {
+                slot.store(target);
+            }
+            if let Some(target) = dangling {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Object {} refers to {} which is not in the saved spaces",
+                        object, target
+                    ),
+                ));
+            }
+        }
+
+        Ok(Self {
+            preferred_start,
+            data,
+            objects: layout.iter().map(|(_, _, _, offset)| *offset).collect(),
+            roots: root_indices,
+        })
+    }
+
+    /// Copy the image to `start`, relocate it if needed, and add it to the VM space.  Return the
+    /// root objects.  The VM space is not changed if an error is returned.
+    pub(crate) fn restore<VM: VMBinding>(
+        &self,
+        mmtk: &mut MMTK<VM>,
+        tls: VMThread,
+        start: Address,
+    ) -> Result<Vec<ObjectReference>> {
+        if !start.is_aligned_to(BYTES_IN_PAGE) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The image start {} is not page-aligned", start),
+            ));
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                self.data.as_ptr(),
+                start.to_mut_ptr::<u8>(),
+                self.data.len(),
+            );
+        }
+
+        let objects = self
+            .objects
+            .iter()
+            .map(|offset| {
+                ObjectReference::from_raw_address(start + *offset)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Corrupted heap image"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // The image is closed, so every reference must point into the image.  Check all of them
+        // before changing anything, so that a corrupted image is rejected as a whole.
+        let image_end = self.preferred_start + self.data.len();
+        let mut outside = None;
+        for object in objects.iter() {
+            VM::VMScanning::scan_object(VMWorkerThread(tls), *object, &mut |slot: VM::VMSlot| {
+                if let Some(target) = slot.load() {
+                    let addr = target.to_raw_address();
+                    if outside.is_none() && (addr < self.preferred_start || addr >= image_end) {
+                        outside = Some((*object, target));
+                    }
+                }
+            });
+        }
+        if let Some((object, target)) = outside {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Object {} refers to {} which is outside the heap image",
+                    object, target
+                ),
+            ));
+        }
+
+        unsafe { mmtk.get_plan_mut() }
+            .base_mut()
+            .vm_space
+            .set_vm_region(start, self.size());
+        for object in objects.iter() {
+            mmtk.initialize_vm_space_object(*object);
+        }
+
+        if start != self.preferred_start {
+            for object in objects.iter() {
+                VM::VMScanning::scan_object(
+                    VMWorkerThread(tls),
+                    *object,
+                    &mut |slot: VM::VMSlot| {
+                        if let Some(target) = slot.load() {
+                            let offset = target.to_raw_address() - self.preferred_start;
+                            slot.store(ObjectReference::from_raw_address(start + offset).unwrap());
+                        }
+                    },
+                );
+            }
+        }
+
+        Ok(self.roots.iter().map(|index| objects[*index]).collect())
+    }
+}
+
+fn write_usize(writer: &mut impl Write, value: usize) -> Result<()> {
+    writer.write_all(&(value as u64).to_le_bytes())
+}
+
+fn read_usize(reader: &mut impl Read) -> Result<usize> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes))
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Value out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_round_trip() {
+        let image = HeapImage {
+            preferred_start: unsafe { Address::from_usize(0x7000_0000_0000) },
+            data: (0..100u8).collect(),
+            objects: vec![8, 40],
+            roots: vec![1],
+        };
+        assert_eq!(image.size(), BYTES_IN_PAGE);
+        let mut buffer = vec![];
+        image.write_to(&mut buffer).unwrap();
+        let read = HeapImage::read_from(&mut buffer.as_slice()).unwrap();
+        assert_eq!(read, image);
+    }
+
+    #[test]
+    fn reject_bad_image() {
+        let err = HeapImage::read_from(&mut &b"NOTANIMAGE"[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let image = HeapImage {
+            preferred_start: Address::ZERO,
+            data: vec![0; 16],
+            objects: vec![8],
+            roots: vec![0],
+        };
+        let mut buffer = vec![];
+        image.write_to(&mut buffer).unwrap();
+        // Truncated
+        buffer.pop();
+        assert!(HeapImage::read_from(&mut buffer.as_slice()).is_err());
+
+        // The data length is larger than any space.  The length is the second word after the
+        // magic number and the version.
+        let mut buffer = vec![];
+        image.write_to(&mut buffer).unwrap();
+        buffer[20..28].copy_from_slice(&u64::MAX.to_le_bytes());
+        let err = HeapImage::read_from(&mut buffer.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
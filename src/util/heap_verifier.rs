@@ -0,0 +1,192 @@
+//! A heap verifier that checks every reference field in the heap against the invariants of the
+//! spaces and the current plan.
+//!
+//! Unlike the sanity checker, which runs a second trace to check reachability, the verifier walks
+//! all objects that have their valid-object (VO) bits set and checks each of their slots.  It runs
+//! once after mutators are stopped and once right before the plan finishes the GC, so a broken
+//! barrier or a missed slot update is reported at the GC that observes it rather than as a crash
+//! several GCs later.
+//!
+//! The verifier relies on the VO bits identifying the objects that have not been reclaimed.  This
+//! holds because the `heap_verifier` feature enables `vo_bit`, which in turn enables eager
+//! sweeping.  Objects in spaces that are never reclaimed (such as the immortal space) are always
+//! treated as live, even if they are unreachable.
+
+use std::fmt;
+
+use crate::mmtk::SFT_MAP;
+use crate::plan::Plan;
+use crate::util::{ObjectReference, VMWorkerThread};
+use crate::vm::slot::Slot;
+use crate::vm::{ObjectModel, Scanning, VMBinding};
+use crate::MMTK;
+use atomic::Ordering;
+
+/// The maximum number of violations included in the panic message.
+const MAX_REPORTED_VIOLATIONS: usize = 32;
+
+/// The point in a GC at which the heap is verified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationPoint {
+    /// After all mutators are stopped, and before any GC work is done.
+    BeforeGC,
+    /// After all GC work is done, and before the plan finishes the GC in `end_of_gc`.
+    AfterGC,
+}
+
+impl fmt::Display for VerificationPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationPoint::BeforeGC => write!(f, "before GC"),
+            VerificationPoint::AfterGC => write!(f, "after GC"),
+        }
+    }
+}
+
+/// A reference field that violates an invariant.
+pub(crate) struct Violation {
+    /// The slot holding the reference, or `None` if the VM binding does not support slot
+    /// enqueuing for the source object.
+    pub(crate) slot: Option<String>,
+    pub(crate) source: ObjectReference,
+    pub(crate) target: ObjectReference,
+    pub(crate) reason: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let space_of =
+            |object: ObjectReference| SFT_MAP.get_checked(object.to_raw_address()).name();
+        write!(
+            f,
+            "slot {} of {} ({}) -> {} ({}): {}",
+            self.slot.as_deref().unwrap_or("<unknown>"),
+            self.source,
+            space_of(self.source),
+            self.target,
+            space_of(self.target),
+            self.reason
+        )
+    }
+}
+
+/// Verify every reference field of every object in the heap, and panic with a report of the
+/// offending slots if any invariant is violated.
+///
+/// This must be called while mutators are stopped, and while no GC worker is mutating the heap.
+pub(crate) fn verify_heap<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMWorkerThread,
+    point: VerificationPoint,
+) {
+    let violations = find_violations(mmtk, tls, point);
+    if !violations.is_empty() {
+        let report = violations
+            .iter()
+            .take(MAX_REPORTED_VIOLATIONS)
+            .map(|violation| format!("  {violation}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        panic!(
+            "Heap verification {} failed with {} violations (showing at most {}):\n{}",
+            point,
+            violations.len(),
+            MAX_REPORTED_VIOLATIONS,
+            report
+        );
+    }
+}
+
+/// Check every reference field of every object in the heap, and return the fields that violate
+/// any invariant.  The same requirements as [`verify_heap`] apply.
+pub(crate) fn find_violations<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMWorkerThread,
+    point: VerificationPoint,
+) -> Vec<Violation> {
+    let plan = mmtk.get_plan();
+
+    let mut sources = vec![];
+    mmtk.enumerate_objects(|object| sources.push(object));
+
+    let mut violations = vec![];
+    let mut num_references = 0;
+    for source in sources {
+        let mut check = |slot: Option<String>, target: ObjectReference| {
+            num_references += 1;
+            if let Some(reason) = check_reference(plan, source, target, point) {
+                violations.push(Violation {
+                    slot,
+                    source,
+                    target,
+                    reason,
+                });
+            }
+        };
+
+        if VM::VMScanning::support_slot_enqueuing(tls, source) {
+            VM::VMScanning::scan_object(tls, source, &mut |slot: VM::VMSlot| {
+                if let Some(target) = slot.load() {
+                    check(Some(format!("{slot:?}")), target);
+                }
+            });
+        } else {
+            VM::VMScanning::scan_object_and_trace_edges(tls, source, &mut |target| {
+                check(None, target);
+                target
+            });
+        }
+    }
+
+    info!(
+        "Heap verification {}: checked {} references, found {} violations",
+        point,
+        num_references,
+        violations.len()
+    );
+    violations
+}
+
+/// Check a single reference from `source` to `target`.  Return the reason if it is invalid.
+fn check_reference<VM: VMBinding>(
+    plan: &dyn Plan<VM = VM>,
+    source: ObjectReference,
+    target: ObjectReference,
+    point: VerificationPoint,
+) -> Option<String> {
+    // The target must be an object that has not been reclaimed.  After a moving GC, this also
+    // catches slots that still point into the from-space or into released blocks, because their
+    // VO bits are cleared when the memory is released.
+    let sft = SFT_MAP.get_checked(target.to_raw_address());
+    if sft.is_mmtk_object(target.to_raw_address()) != Some(target) {
+        return Some("the target is not a valid object (VO bit not set)".to_string());
+    }
+
+    if let Some(gen) = plan.generational() {
+        let target_in_nursery = gen.is_object_in_nursery(target);
+        match point {
+            VerificationPoint::BeforeGC => {
+                // A mature object that is still unlogged has not been recorded by the write
+                // barrier, so the nursery GC will not find its reference into the nursery.
+                if target_in_nursery
+                    && !gen.is_object_in_nursery(source)
+                    && VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
+                        .is_unlogged::<VM>(source, Ordering::SeqCst)
+                {
+                    return Some(
+                        "an old-to-young reference is not remembered (the source is unlogged)"
+                            .to_string(),
+                    );
+                }
+            }
+            VerificationPoint::AfterGC => {
+                // Every GC of a generational plan evacuates or promotes all nursery objects.
+                if target_in_nursery {
+                    return Some("the target is still in the nursery".to_string());
+                }
+            }
+        }
+    }
+
+    plan.verify_reference(source, target, point)
+}
@@ -0,0 +1,355 @@
+//! Explaining why an object is alive.
+//!
+//! [`crate::memory_manager::explain_liveness`] requests a GC and, once all mutators are stopped and
+//! before the plan starts its own work, runs a separate breadth-first trace from the roots, like the
+//! sanity checker does after a GC.  The trace records the parent of each object it reaches, so it
+//! can return the shortest chain of objects and slots from a root to the requested object.  The
+//! trace does not touch any GC metadata, so the GC that follows is not affected by it.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::plan::MutatorContext;
+use crate::util::opaque_pointer::*;
+use crate::util::{Address, ObjectReference};
+use crate::vm::slot::{SimpleSlot, Slot};
+use crate::vm::{ActivePlan, ObjectModel, RootsWorkFactory, Scanning, VMBinding};
+use crate::MMTK;
+
+/// Where a root comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootSource {
+    /// The root was reported by [`Scanning::scan_roots_in_mutator_thread`] for this mutator, e.g.
+    /// from its stack.
+    Mutator(VMMutatorThread),
+    /// The root was reported by [`Scanning::scan_vm_specific_roots`].
+    VMSpecific,
+    /// The root is a strong handle in MMTk's handle table.  The address is the entry of the handle.
+    Handle(Address),
+}
+
+impl fmt::Display for RootSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RootSource::Mutator(tls) => write!(f, "roots of mutator {:?}", tls),
+            RootSource::VMSpecific => write!(f, "VM-specific roots"),
+            RootSource::Handle(entry) => write!(f, "strong handle at {}", entry),
+        }
+    }
+}
+
+/// An object on a path from a root, and the slot that refers to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathStep<SL: Slot> {
+    /// The slot that refers to `object`.  For the first step, this is the root slot, and it is
+    /// `None` if the root was reported as an object rather than a slot.  For other steps, this is
+    /// a slot of the object in the previous step, and it is `None` if the previous object was
+    /// scanned with [`Scanning::scan_object_and_trace_edges`].
+    pub slot: Option<SL>,
+    /// The object.
+    pub object: ObjectReference,
+}
+
+/// A chain of references from a root to an object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathToRoot<SL: Slot> {
+    /// Where the root comes from.
+    pub root: RootSource,
+    /// The objects from the root object to the requested object, which is the last step.
+    pub steps: Vec<PathStep<SL>>,
+}
+
+impl<SL: Slot> PathToRoot<SL> {
+    /// Print the path to the standard output, with the information of each object printed by
+    /// `mmtk_debug_print_object` and [`ObjectModel::dump_object`].
+    pub fn print<VM: VMBinding<VMSlot = SL>>(&self) {
+        println!("Path from {} ({} objects):", self.root, self.steps.len());
+        for (i, step) in self.steps.iter().enumerate() {
+            match &step.slot {
+                Some(slot) => println!("#{} {} (via slot {:?})", i, step.object, slot),
+                None => println!("#{} {}", i, step.object),
+            }
+            crate::mmtk::mmtk_debug_print_object(step.object);
+            VM::VMObjectModel::dump_object(step.object);
+        }
+    }
+}
+
+/// A liveness query.
+struct LivenessQuery<SL: Slot> {
+    /// Identifies the query.
+    id: usize,
+    /// The object to explain.
+    target: ObjectReference,
+    /// The answer.  It is `None` before the query is answered, and `Some(None)` if the target is
+    /// not reachable.
+    answer: Option<Option<PathToRoot<SL>>>,
+}
+
+/// The liveness queries that are pending or answered but not taken by the querying threads.
+pub(crate) struct LivenessQueries<SL: Slot> {
+    next_id: usize,
+    queries: Vec<LivenessQuery<SL>>,
+}
+
+impl<SL: Slot> LivenessQueries<SL> {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            queries: vec![],
+        }
+    }
+
+    /// Add a query, and return its identifier.
+    pub fn add(&mut self, target: ObjectReference) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queries.push(LivenessQuery {
+            id,
+            target,
+            answer: None,
+        });
+        id
+    }
+
+    /// Remove the query `id` and return its answer if it is answered.
+    pub fn take_answer(&mut self, id: usize) -> Option<Option<PathToRoot<SL>>> {
+        let index = self.queries.iter().position(|query| query.id == id)?;
+        self.queries[index].answer.as_ref()?;
+        self.queries.swap_remove(index).answer
+    }
+
+    fn pending(&mut self) -> impl Iterator<Item = &mut LivenessQuery<SL>> {
+        self.queries
+            .iter_mut()
+            .filter(|query| query.answer.is_none())
+    }
+}
+
+/// A root reported to [`RecordRoots`].
+struct Root<SL: Slot> {
+    source: RootSource,
+    slot: Option<SL>,
+    object: ObjectReference,
+}
+
+/// A roots work factory that records roots instead of creating work packets.
+struct RecordRoots<SL: Slot> {
+    source: RootSource,
+    roots: Arc<Mutex<Vec<Root<SL>>>>,
+}
+
+// Implemented manually because `derive` would require `SL: Clone` on the struct.
+impl<SL: Slot> Clone for RecordRoots<SL> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source,
+            roots: self.roots.clone(),
+        }
+    }
+}
+
+impl<SL: Slot> RecordRoots<SL> {
+    fn record_nodes(&mut self, nodes: Vec<ObjectReference>) {
+        let mut roots = self.roots.lock().unwrap();
+        roots.extend(nodes.into_iter().map(|object| Root {
+            source: self.source,
+            slot: None,
+            object,
+        }));
+    }
+}
+
+impl<SL: Slot + 'static> RootsWorkFactory<SL> for RecordRoots<SL> {
+    fn create_process_roots_work(&mut self, slots: Vec<SL>) {
+        let mut roots = self.roots.lock().unwrap();
+        roots.extend(slots.into_iter().filter_map(|slot| {
+            slot.load().map(|object| Root {
+                source: self.source,
+                slot: Some(slot),
+                object,
+            })
+        }));
+    }
+
+    fn create_process_pinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        self.record_nodes(nodes);
+    }
+
+    fn create_process_tpinning_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        self.record_nodes(nodes);
+    }
+
+    fn create_process_handle_roots_work(&mut self, slots: Vec<SimpleSlot>) {
+        let mut roots = self.roots.lock().unwrap();
+        roots.extend(slots.into_iter().filter_map(|slot| {
+            slot.load().map(|object| Root {
+                source: RootSource::Handle(slot.as_address()),
+                slot: None,
+                object,
+            })
+        }));
+    }
+}
+
+/// Answer the pending liveness queries, if any.  This is called by a GC worker when all mutators
+/// are stopped, before any root is scanned for the GC.
+pub(crate) fn answer_pending_queries<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: VMWorkerThread) {
+    let mut queries = mmtk.liveness_queries.lock().unwrap();
+    if queries.pending().next().is_none() {
+        return;
+    }
+
+    let roots = Arc::new(Mutex::new(vec![]));
+    for mutator in VM::VMActivePlan::mutators() {
+        let factory = RecordRoots {
+            source: RootSource::Mutator(mutator.get_tls()),
+            roots: roots.clone(),
+        };
+        VM::VMScanning::scan_roots_in_mutator_thread(tls, mutator, factory);
+    }
+    let mut factory = RecordRoots {
+        source: RootSource::VMSpecific,
+        roots: roots.clone(),
+    };
+    VM::VMScanning::scan_vm_specific_roots(tls, factory.clone());
+    mmtk.handle_table.scan_strong_handles(&mut factory);
+    // The GC will scan the roots again.
+    VM::VMScanning::prepare_for_roots_re_scanning();
+
+    let roots = std::mem::take(&mut *roots.lock().unwrap());
+    for query in queries.pending() {
+        info!("Explaining the liveness of {}", query.target);
+        let path = find_path(&roots, query.target, |object, visit| {
+            if VM::VMScanning::support_slot_enqueuing(tls, object) {
+                VM::VMScanning::scan_object(tls, object, &mut |slot: VM::VMSlot| {
+                    if let Some(child) = slot.load() {
+                        visit(Some(slot), child);
+                    }
+                });
+            } else {
+                VM::VMScanning::scan_object_and_trace_edges(tls, object, &mut |child| {
+                    visit(None, child);
+                    child
+                });
+            }
+        });
+        match &path {
+            Some(path) => info!("{} is reachable from {}", query.target, path.root),
+            None => info!("{} is not reachable", query.target),
+        }
+        query.answer = Some(path);
+    }
+}
+
+/// How an object was first reached in [`find_path`].
+enum Parent<SL: Slot> {
+    /// The object is the root at this index.
+    Root(usize),
+    /// The object is referred to by a slot of another object.
+    Object(ObjectReference, Option<SL>),
+}
+
+/// Find the shortest path from `roots` to `target` with a breadth-first search.
+/// `scan_children(object, visit)` calls `visit(slot, child)` for each child of `object`.
+fn find_path<SL: Slot>(
+    roots: &[Root<SL>],
+    target: ObjectReference,
+    mut scan_children: impl FnMut(ObjectReference, &mut dyn FnMut(Option<SL>, ObjectReference)),
+) -> Option<PathToRoot<SL>> {
+    let mut parents: HashMap<ObjectReference, Parent<SL>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for (index, root) in roots.iter().enumerate() {
+        parents.entry(root.object).or_insert_with(|| {
+            queue.push_back(root.object);
+            Parent::Root(index)
+        });
+    }
+
+    while !parents.contains_key(&target) {
+        let object = queue.pop_front()?;
+        scan_children(object, &mut |slot, child| {
+            parents.entry(child).or_insert_with(|| {
+                queue.push_back(child);
+                Parent::Object(object, slot)
+            });
+        });
+    }
+
+    // Walk the parents back to the root.
+    let mut steps = vec![];
+    let mut object = target;
+    loop {
+        match parents.remove(&object).unwrap() {
+            Parent::Root(index) => {
+                steps.push(PathStep {
+                    slot: roots[index].slot,
+                    object,
+                });
+                steps.reverse();
+                return Some(PathToRoot {
+                    root: roots[index].source,
+                    steps,
+                });
+            }
+            Parent::Object(parent, slot) => {
+                steps.push(PathStep { slot, object });
+                object = parent;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(id: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(id * 16) }).unwrap()
+    }
+
+    #[test]
+    fn find_the_shortest_path() {
+        // Objects 1 and 2 are roots.  1 -> 3 -> 4 -> 5, and 2 -> 4.  6 is not reachable.
+        let edges = [(1, 3), (3, 4), (4, 5), (2, 4), (6, 5)];
+        let roots = vec![
+            Root {
+                source: RootSource::VMSpecific,
+                slot: None,
+                object: object(1),
+            },
+            Root {
+                source: RootSource::Handle(unsafe { Address::from_usize(0x1000) }),
+                slot: None,
+                object: object(2),
+            },
+        ];
+        let scan = |parent: ObjectReference,
+                    visit: &mut dyn FnMut(Option<Address>, ObjectReference)| {
+            for (from, to) in edges {
+                if object(from) == parent {
+                    // Use the address of the child as the slot.
+                    visit(Some(object(to).to_raw_address()), object(to));
+                }
+            }
+        };
+
+        let path = find_path(&roots, object(5), scan).unwrap();
+        assert_eq!(
+            path.root,
+            RootSource::Handle(unsafe { Address::from_usize(0x1000) })
+        );
+        assert_eq!(
+            path.steps
+                .iter()
+                .map(|step| step.object)
+                .collect::<Vec<_>>(),
+            vec![object(2), object(4), object(5)]
+        );
+        assert_eq!(path.steps[0].slot, None);
+        assert_eq!(path.steps[2].slot, Some(object(5).to_raw_address()));
+
+        assert_eq!(find_path(&roots, object(6), scan), None);
+    }
+}
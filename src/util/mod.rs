@@ -6,6 +6,8 @@
 
 // The following modules are public. MMTk bindings can use them to help implementation.
 
+/// Per-context memory accounting and quotas.
+pub mod accounting_context;
 /// An abstract of memory address and object reference.
 pub mod address;
 /// Allocators
@@ -19,13 +21,23 @@ pub mod constants;
 pub mod conversions;
 /// The copy allocators for a GC worker.
 pub mod copy;
+/// A global handle table for strong and weak references to objects from outside the heap.
+pub mod handle_table;
 /// Heap implementation, including page resource, mmapper, etc.
 pub mod heap;
+/// Saving and restoring heap images.
+#[cfg(feature = "heap_image")]
+pub mod heap_image;
+/// Verifying reference fields against space and plan invariants.
+#[cfg(feature = "heap_verifier")]
+pub mod heap_verifier;
 /// Checking if an address is an valid MMTk object.
 #[cfg(feature = "vo_bit")]
 pub mod is_mmtk_object;
 /// Linear scan through a heap range
 pub mod linear_scan;
+/// Explaining why an object is alive.
+pub mod liveness;
 /// Various malloc implementations (conditionally compiled by features)
 pub mod malloc;
 /// Memory utilities (non-OS dependent). OS dependent memory utilities can be found in [`crate::util::os::OSMemory`].
@@ -48,6 +60,7 @@ pub mod test_util;
 /// An analysis framework for collecting data and profiling in GC.
 #[cfg(feature = "analysis")]
 pub(crate) mod analysis;
+pub(crate) mod ephemeron_processor;
 pub(crate) mod epilogue;
 /// Non-generic refs to generic types of `<VM>`.
 pub(crate) mod erase_vm;
@@ -55,6 +68,8 @@ pub(crate) mod erase_vm;
 pub(crate) mod finalizable_processor;
 /// Logger initialization
 pub(crate) mod logger;
+/// NUMA topology.
+pub(crate) mod numa;
 pub(crate) mod object_enum;
 /// Forwarding word in object copying.
 pub(crate) mod object_forwarding;
@@ -71,6 +86,8 @@ pub(crate) mod scanning_helper;
 pub(crate) mod slot_logger;
 /// Utils for collecting statistics.
 pub(crate) mod statistics;
+/// Deterministic, seeded GC stress testing.
+pub(crate) mod stress;
 /// A treadmill implementation.
 pub(crate) mod treadmill;
 
@@ -0,0 +1,233 @@
+//! NUMA topology, used when the option `numa_aware` is enabled.
+//!
+//! If the OS does not report any NUMA node, we treat the machine as a single node with id 0, so
+//! NUMA-aware code paths behave the same as the default ones.
+
+use crate::util::heap::layout::vm_layout::{vm_layout, LOG_BYTES_IN_CHUNK};
+use crate::util::os::*;
+use crate::util::rust_util::zeroed_alloc::new_zeroed_vec;
+use crate::util::Address;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+lazy_static! {
+    /// The NUMA topology of the machine, detected once.
+    pub(crate) static ref NUMA_TOPOLOGY: NumaTopology = NumaTopology::new(OS::get_numa_nodes());
+    /// The nodes of the chunks that block page resources acquired for a node.  It only tracks
+    /// chunks if the machine has more than one node.
+    pub(crate) static ref CHUNK_NODES: ChunkNodeMap = if NUMA_TOPOLOGY.num_nodes() > 1 {
+        ChunkNodeMap::new(vm_layout().available_start(), vm_layout().available_end())
+    } else {
+        ChunkNodeMap::new(Address::ZERO, Address::ZERO)
+    };
+}
+
+/// The NUMA nodes of the machine.
+pub(crate) struct NumaTopology {
+    nodes: Vec<NumaNode>,
+}
+
+impl NumaTopology {
+    pub fn new(nodes: Vec<NumaNode>) -> Self {
+        // Nodes without cores (e.g. memory-only nodes) cannot run threads.
+        let nodes: Vec<NumaNode> = nodes
+            .into_iter()
+            .filter(|node| !node.cores.is_empty())
+            .collect();
+        Self { nodes }
+    }
+
+    /// The number of nodes.  It is at least 1.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len().max(1)
+    }
+
+    /// The node that GC worker `ordinal` is assigned to.  Workers are assigned to nodes in a round
+    /// robin fashion.  Return `None` if the OS does not report NUMA nodes.
+    pub fn node_for_worker(&self, ordinal: usize) -> Option<&NumaNode> {
+        if self.nodes.is_empty() {
+            None
+        } else {
+            Some(&self.nodes[ordinal % self.nodes.len()])
+        }
+    }
+
+    /// The node of a core.
+    pub fn node_of_core(&self, core: CoreId) -> NumaNodeId {
+        self.nodes
+            .iter()
+            .find(|node| node.cores.binary_search(&core).is_ok())
+            .map_or(0, |node| node.id)
+    }
+
+    /// The node that the current thread is running on.
+    pub fn current_node(&self) -> NumaNodeId {
+        if self.nodes.len() <= 1 {
+            return self.nodes.first().map_or(0, |node| node.id);
+        }
+        OS::get_current_core().map_or(0, |core| self.node_of_core(core))
+    }
+
+    /// The index (from 0 to `num_nodes() - 1`) of the node that the current thread is running
+    /// on.  Per-node data structures are indexed by it.
+    pub fn current_node_index(&self) -> usize {
+        if self.nodes.len() <= 1 {
+            return 0;
+        }
+        let node = self.current_node();
+        self.nodes.iter().position(|n| n.id == node).unwrap_or(0)
+    }
+
+    /// Prefer the node of the current thread for the memory region.  This only affects pages
+    /// that have not been faulted in yet.  Errors are ignored, as this is only a hint.
+    pub fn place_on_current_node(&self, start: Address, size: usize) {
+        self.place_on_node(start, size, self.current_node_index());
+    }
+
+    /// Prefer the node at `index` for the memory region.  Like
+    /// [`NumaTopology::place_on_current_node`], this is only a hint.
+    pub fn place_on_node(&self, start: Address, size: usize, index: usize) {
+        if self.nodes.len() <= 1 {
+            return;
+        }
+        let node = self.nodes[index].id;
+        if let Err(e) = OS::set_preferred_numa_node(start, size, node) {
+            debug!(
+                "Failed to set the preferred NUMA node of {} ({} bytes) to {}: {}",
+                start, size, node, e
+            );
+        }
+    }
+}
+
+/// The node index of each chunk in an address range.  Block page resources record the node that a
+/// chunk is acquired for, so that the blocks in the chunk are placed on that node, and are
+/// returned to the free blocks of that node when released.
+pub(crate) struct ChunkNodeMap {
+    start: Address,
+    /// The node index plus one of each chunk, or 0 if the node of the chunk is not recorded.
+    nodes: Box<[AtomicU8]>,
+}
+
+impl ChunkNodeMap {
+    pub fn new(start: Address, end: Address) -> Self {
+        let chunks = (end - start) >> LOG_BYTES_IN_CHUNK;
+        // The table may be large for 64-bit address spaces.  Allocate it zeroed so that the pages
+        // of chunks that are never used are never touched.
+        let mut zeroed = std::mem::ManuallyDrop::new(new_zeroed_vec::<u8>(chunks));
+        // `AtomicU8` has the same size and alignment as `u8`, and 0 is a valid value.
+        let nodes = unsafe {
+            Vec::from_raw_parts(
+                zeroed.as_mut_ptr() as *mut AtomicU8,
+                zeroed.len(),
+                zeroed.capacity(),
+            )
+        };
+        Self {
+            start,
+            nodes: nodes.into_boxed_slice(),
+        }
+    }
+
+    fn entry(&self, addr: Address) -> Option<&AtomicU8> {
+        if addr < self.start {
+            return None;
+        }
+        self.nodes.get((addr - self.start) >> LOG_BYTES_IN_CHUNK)
+    }
+
+    /// Record the node index of the chunk that contains `addr`.  It does nothing if the address
+    /// is not tracked.
+    pub fn set(&self, addr: Address, index: usize) {
+        if let Some(entry) = self.entry(addr) {
+            entry.store(index as u8 + 1, Ordering::Relaxed);
+        }
+    }
+
+    /// Forget the node of the chunk that contains `addr`.
+    pub fn clear(&self, addr: Address) {
+        if let Some(entry) = self.entry(addr) {
+            entry.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// The node index of the chunk that contains `addr`, if recorded.
+    pub fn get(&self, addr: Address) -> Option<usize> {
+        match self.entry(addr)?.load(Ordering::Relaxed) {
+            0 => None,
+            node => Some(node as usize - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_nodes() -> NumaTopology {
+        NumaTopology::new(vec![
+            NumaNode {
+                id: 0,
+                cores: vec![0, 1, 4, 5],
+            },
+            NumaNode {
+                id: 1,
+                cores: vec![2, 3, 6, 7],
+            },
+            // A memory-only node
+            NumaNode {
+                id: 2,
+                cores: vec![],
+            },
+        ])
+    }
+
+    #[test]
+    fn worker_round_robin() {
+        let topology = two_nodes();
+        assert_eq!(topology.num_nodes(), 2);
+        assert_eq!(topology.node_for_worker(0).unwrap().id, 0);
+        assert_eq!(topology.node_for_worker(1).unwrap().id, 1);
+        assert_eq!(topology.node_for_worker(2).unwrap().id, 0);
+    }
+
+    #[test]
+    fn core_to_node() {
+        let topology = two_nodes();
+        assert_eq!(topology.node_of_core(5), 0);
+        assert_eq!(topology.node_of_core(6), 1);
+        // Unknown cores are treated as on node 0.
+        assert_eq!(topology.node_of_core(100), 0);
+    }
+
+    #[test]
+    fn no_numa() {
+        let topology = NumaTopology::new(vec![]);
+        assert_eq!(topology.num_nodes(), 1);
+        assert!(topology.node_for_worker(3).is_none());
+        assert_eq!(topology.current_node(), 0);
+        assert_eq!(topology.current_node_index(), 0);
+    }
+
+    #[test]
+    fn chunk_nodes() {
+        use crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK;
+        let start = unsafe { Address::from_usize(0x1000_0000_0000) };
+        let map = ChunkNodeMap::new(start, start + 4 * BYTES_IN_CHUNK);
+        assert_eq!(map.get(start), None);
+
+        map.set(start + BYTES_IN_CHUNK, 1);
+        map.set(start + 3 * BYTES_IN_CHUNK, 0);
+        assert_eq!(map.get(start), None);
+        // Any address in the chunk maps to the node of the chunk.
+        assert_eq!(map.get(start + BYTES_IN_CHUNK + 0x1234usize), Some(1));
+        assert_eq!(map.get(start + 3 * BYTES_IN_CHUNK), Some(0));
+
+        map.clear(start + BYTES_IN_CHUNK);
+        assert_eq!(map.get(start + BYTES_IN_CHUNK), None);
+
+        // Addresses outside the range are not tracked.
+        map.set(start + 4 * BYTES_IN_CHUNK, 1);
+        assert_eq!(map.get(start + 4 * BYTES_IN_CHUNK), None);
+        assert_eq!(map.get(Address::ZERO), None);
+    }
+}
@@ -24,6 +24,41 @@ pub enum NurseryZeroingOptions {
     Adaptive,
 }
 
+/// The policy to decide whether MMTk retains the referents of soft references that are reachable
+/// when a GC happens.  Soft references are always cleared in emergency collections.
+#[derive(Copy, Clone, EnumString, Debug, PartialEq, Eq)]
+pub enum SoftReferencePolicy {
+    /// Retain the referents of all reachable soft references.
+    Retain,
+    /// Never retain the referents.  Soft references are treated like weak references.
+    AlwaysClear,
+    /// Retain the referents in nursery GCs, and clear them in full-heap GCs.
+    ClearOnFullHeap,
+    /// Retain the referents of soft references that have been accessed recently.  A soft
+    /// reference is retained if it has been accessed within `soft_reference_lru_ms_per_mb`
+    /// milliseconds per megabyte of free heap at the end of the last GC.  The binding must
+    /// implement `ReferenceGlue::get_soft_reference_timestamp`.
+    Lru,
+}
+
+/// How MMTk processes finalizable objects that become unreachable.
+#[derive(Copy, Clone, EnumString, Debug, PartialEq, Eq)]
+pub enum FinalizationMode {
+    /// All unreachable finalizable objects are ready for finalization at the same time, and are
+    /// kept alive together with their children until the binding pops them.  Finalizers may run
+    /// in any order.
+    Unordered,
+    /// Topological finalization.  An unreachable finalizable object is only ready for
+    /// finalization if it is not reachable from any other finalizable object that is not
+    /// finalized, yet.  Objects in a cycle of finalizable objects are ready for finalization at
+    /// the same time, and their finalizers run in any order.
+    Ordered,
+    /// Guardians.  Unreachable finalizable objects are handed to the binding without being kept
+    /// alive, so their children are not resurrected, either.  The binding may only use the
+    /// object references as identities, and must not access the objects.
+    Guardian,
+}
+
 /// Select a GC plan for MMTk.
 #[derive(Copy, Clone, EnumString, Debug, PartialEq, Eq)]
 pub enum PlanSelector {
@@ -99,6 +134,92 @@ impl FromStr for PerfEventOptions {
     }
 }
 
+/// MMTk option for backing spaces with explicitly reserved huge pages (`MAP_HUGETLB`).
+///
+/// The format is
+/// ```text
+/// <hugetlb> ::= <page-size> ":" <spaces> | <spaces> | ""
+/// <spaces> ::= <space-name> "," <spaces> | <space-name>
+/// ```
+/// `<page-size>` is a number of bytes with an optional suffix `K`, `M` or `G`, such as `2M`. If it
+/// is omitted, the huge page size is 2MB.  For example, `2M:immix,nursery` backs the spaces named
+/// `immix` and `nursery` with 2MB huge pages.
+///
+/// MMTk maps memory for spaces in chunks, so the huge page size must be a power of two that is not
+/// larger than [`crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK`].  The system administrator
+/// needs to reserve enough huge pages of that size (e.g. via `/proc/sys/vm/nr_hugepages`).  If
+/// MMTk fails to map huge pages, it falls back to normal pages for that mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HugeTlbOptions {
+    /// Log2 of the huge page size in bytes.
+    pub log_page_size: u8,
+    /// The names of the spaces backed by huge pages.
+    pub spaces: Vec<String>,
+}
+
+impl HugeTlbOptions {
+    /// The default huge page size is 2MB.
+    pub const DEFAULT_LOG_PAGE_SIZE: u8 = 21;
+
+    fn parse_page_size(size: &str) -> Result<usize, String> {
+        let (number, shift) = match size.chars().last() {
+            Some('K') | Some('k') => (&size[..size.len() - 1], 10),
+            Some('M') | Some('m') => (&size[..size.len() - 1], 20),
+            Some('G') | Some('g') => (&size[..size.len() - 1], 30),
+            _ => (size, 0),
+        };
+        number
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_shl(shift))
+            .ok_or_else(|| format!("Failed to parse huge page size: {}", size))
+    }
+
+    /// Return true if the huge page size can be used for MMTk spaces.
+    pub fn validate(&self) -> bool {
+        use crate::util::constants::LOG_BYTES_IN_PAGE;
+        use crate::util::heap::layout::vm_layout::LOG_BYTES_IN_CHUNK;
+        self.spaces.is_empty()
+            || (cfg!(target_os = "linux")
+                && self.log_page_size > LOG_BYTES_IN_PAGE
+                && self.log_page_size as usize <= LOG_BYTES_IN_CHUNK)
+    }
+}
+
+impl Default for HugeTlbOptions {
+    fn default() -> Self {
+        Self {
+            log_page_size: Self::DEFAULT_LOG_PAGE_SIZE,
+            spaces: vec![],
+        }
+    }
+}
+
+impl FromStr for HugeTlbOptions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (log_page_size, spaces) = match s.split_once(':') {
+            Some((size, spaces)) => {
+                let size = Self::parse_page_size(size)?;
+                if !size.is_power_of_two() {
+                    return Err(format!("Huge page size {} is not a power of two", size));
+                }
+                (size.trailing_zeros() as u8, spaces)
+            }
+            None => (Self::DEFAULT_LOG_PAGE_SIZE, s),
+        };
+        Ok(Self {
+            log_page_size,
+            spaces: spaces
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+}
+
 /// The default min nursery size. This does not affect the actual space we create as nursery. It is
 /// only used in the GC trigger check.
 #[cfg(target_pointer_width = "64")]
@@ -214,9 +335,14 @@ macro_rules! options {
                             return Err(SetOptionByStringError::ValueParseError);
                         };
 
+                        let old_val = (*self.$name).clone();
                         if !self.$name.set(typed_val) {
                             return Err(SetOptionByStringError::ValueValidationError);
                         }
+                        if self.validate_combination().is_err() {
+                            self.$name.set(old_val);
+                            return Err(SetOptionByStringError::ValueValidationError);
+                        }
 
                         Ok(())
                     })*
@@ -335,6 +461,19 @@ impl Options {
         }
     }
 
+    /// Check the options that are only invalid in combination with other options, which the
+    /// validators of individual options cannot check.  Returns the reason if the options cannot be
+    /// used together.  Options set by strings are checked when they are set, and
+    /// [`crate::MMTKBuilder`] checks all options again when building an MMTk instance.
+    pub fn validate_combination(&self) -> Result<(), String> {
+        // Memory mapped with MAP_HUGETLB can only be protected in whole huge pages, while
+        // `guard_released_memory` protects individual pages and blocks.
+        if *self.guard_released_memory && !self.hugetlb_spaces.spaces.is_empty() {
+            return Err("`guard_released_memory` cannot be used with `hugetlb_spaces`".to_string());
+        }
+        Ok(())
+    }
+
     /// Check if the options are set for stress GC. If either stress_factor or analysis_factor is set,
     /// we should do stress GC.
     pub fn is_stress_test_gc_enabled(&self) -> bool {
@@ -350,6 +489,24 @@ impl Options {
             HugePageSupport::No
         }
     }
+
+    /// The huge page support for the memory of a space.  The space is backed by explicitly
+    /// reserved huge pages if it is listed in `hugetlb_spaces`, otherwise it follows
+    /// `transparent_hugepages`.
+    pub fn huge_page_support_for_space(&self, space_name: &str) -> HugePageSupport {
+        if self
+            .hugetlb_spaces
+            .spaces
+            .iter()
+            .any(|name| name == space_name)
+        {
+            HugePageSupport::HugeTlb {
+                log_page_size: self.hugetlb_spaces.log_page_size,
+            }
+        } else {
+            self.transparent_hugepages_as_huge_page_support()
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -366,6 +523,10 @@ pub enum AffinityKind {
     /// Assign all the cores specified in the set to all the GC threads. This allows to have core
     /// exclusivity for GC threads without us caring about which core it gets scheduled on.
     AllInSet(Vec<CoreId>),
+    /// Assign the GC threads to NUMA nodes in a round robin fashion, and bind each thread to all
+    /// the cores of its node.  If the OS does not report NUMA nodes, this is the same as
+    /// `OsDefault`.
+    NumaNodes,
 }
 
 impl AffinityKind {
@@ -381,12 +542,14 @@ impl AffinityKind {
     ///  - "`0,5,8-11`" specifies that the cores 0,5,8,9,10,11 should be used for pinning threads.
     ///  - "`AllInSet:0,5`" specifies that the cores 0,5 should be used for pinning threads using the
     ///    [`AffinityKind::AllInSet`] method.
+    ///  - "`NumaNodes`" specifies [`AffinityKind::NumaNodes`].  It does not take a list of cores.
     fn parse_cpulist(cpulist: &str) -> Result<AffinityKind, String> {
-        let mut cpuset = vec![];
-
         if cpulist.is_empty() {
             return Ok(AffinityKind::OsDefault);
         }
+        if cpulist == "NumaNodes" {
+            return Ok(AffinityKind::NumaNodes);
+        }
 
         // Trying to parse strings such as "RoundRobin:0,1-3"
         // First split on ":" to check if an affinity kind has been specified.
@@ -414,44 +577,7 @@ impl AffinityKind {
             kind_split[0]
         };
 
-        // Split on ',' first and then split on '-' if there is a range
-        for split in cpulist.split(',') {
-            if !split.contains('-') {
-                if !split.is_empty() {
-                    if let Ok(core) = split.parse::<u16>() {
-                        cpuset.push(core);
-                        cpuset.sort_unstable();
-                        cpuset.dedup();
-                        continue;
-                    }
-                }
-            } else {
-                // Contains a range
-                let range: Vec<&str> = split.split('-').collect();
-                if range.len() == 2 {
-                    if let Ok(start) = range[0].parse::<u16>() {
-                        if let Ok(end) = range[1].parse::<u16>() {
-                            if start >= end {
-                                return Err(
-                                    "Starting core id in range should be less than the end"
-                                        .to_string(),
-                                );
-                            }
-
-                            for cpu in start..=end {
-                                cpuset.push(cpu);
-                                cpuset.sort_unstable();
-                                cpuset.dedup();
-                            }
-
-                            continue;
-                        }
-                    }
-                }
-            }
-
-            return Err("Core ids have been incorrectly specified".to_string());
-        }
+        let cpuset = parse_core_list(cpulist)?;
 
         if all_in_set {
             Ok(AffinityKind::AllInSet(cpuset))
@@ -876,8 +1002,30 @@ mod gc_trigger_tests {
 options! {
     /// The GC plan to use.
     plan:                   PlanSelector            [always_valid] = PlanSelector::GenImmix,
-    /// Number of GC worker threads.
+    /// Number of GC worker threads.  It can be changed at run time with `memory_manager::set_num_of_workers`.
     threads:                usize                   [|v: &usize| *v > 0] = num_cpus::get(),
+    /// The maximum number of GC worker threads that `memory_manager::set_num_of_workers` can grow the worker pool
+    /// to.  Per-worker data structures are created for this many workers.  0 means the same as `threads`.
+    max_threads:            usize                   [always_valid] = 0,
+    /// Choose the number of GC workers that participate in each GC from the estimated amount of work in the GC,
+    /// i.e. the bytes allocated since the last GC, the live bytes of the last GC (for full-heap GCs), and the number
+    /// of roots in the last GC. At most `threads` workers participate, and other workers stay parked.
+    adaptive_gc_workers:    bool                    [always_valid] = false,
+    /// The estimated amount of work, in bytes, for each active GC worker if `adaptive_gc_workers` is set.
+    gc_work_bytes_per_worker: usize                 [|v: &usize| *v > 0] = 4 << 20,
+    /// Run each GC on the mutator thread that waits for it instead of calling `Collection::block_for_gc`. The mutator
+    /// executes work packets as GC worker 0 until the GC finishes, and only `threads - 1` helper worker threads are
+    /// spawned, so no GC thread is spawned at all if `threads` is 1.
+    mutator_driven_gc:      bool                    [always_valid] = false,
+    /// Split a work packet before executing it if its estimated execution time is longer than this many microseconds
+    /// and some GC workers are idle. Only packets that report an estimated cost can be split. 0 disables splitting.
+    work_packet_split_us:   usize                   [always_valid] = 1000,
+    /// The fraction of GC workers that may execute concurrent GC work, such as concurrent marking, while mutators are
+    /// running. It is rounded up to at least one worker. Other workers stay parked until the next pause.
+    concurrent_gc_worker_fraction: f64              [|v: &f64| *v > 0.0 && *v <= 1.0] = 1.0,
+    /// The fraction of time each GC worker may spend executing concurrent GC work while mutators are running. Workers
+    /// sleep between work packets to keep within it. 1.0 means workers never sleep.
+    concurrent_gc_duty_cycle: f64                   [|v: &f64| *v > 0.0 && *v <= 1.0] = 1.0,
     /// Enable an optimization that only scans the part of the stack that has changed since the last GC (not supported)
     use_short_stack_scans:  bool                    [always_valid] = false,
     /// Enable a return barrier (not supported)
@@ -898,10 +1046,17 @@ options! {
     full_heap_system_gc:    bool                    [always_valid] = false,
     /// Should finalization be disabled?
     no_finalizer:           bool                    [always_valid] = false,
+    /// How finalizable objects are processed.
+    finalization_mode:      FinalizationMode        [always_valid] = FinalizationMode::Unordered,
     /// Should reference type processing be disabled?
     /// If reference type processing is disabled, no weak reference processing work is scheduled,
     /// and we expect a binding to treat weak references as strong references.
     no_reference_types:     bool                    [always_valid] = false,
+    /// The policy to decide whether to retain the referents of soft references.
+    soft_reference_policy:  SoftReferencePolicy     [always_valid] = SoftReferencePolicy::Retain,
+    /// For the `Lru` soft reference policy, how long (in milliseconds) a soft reference that is not
+    /// accessed keeps its referent alive, per megabyte of free heap.
+    soft_reference_lru_ms_per_mb: usize             [always_valid] = 1000,
     /// The zeroing approach to use for new object allocations. Affects each plan differently. (not supported)
     nursery_zeroing:        NurseryZeroingOptions   [always_valid] = NurseryZeroingOptions::Temporal,
     /// How frequent (every X bytes) should we do a stress GC?
@@ -914,6 +1069,20 @@ options! {
     /// But this should have no obvious mutator overhead, and can be used to test GC performance along with a larger stress
     /// factor (e.g. tens of metabytes).
     precise_stress:         bool                    [always_valid] = true,
+    /// The seed for deterministic stress tests. If this is not zero, the number of bytes allocated between two
+    /// stress GCs is drawn from a pseudo-random sequence seeded with it, between 1 and twice `stress_factor`,
+    /// and the seed is printed if a GC worker panics. It also seeds `randomize_gc_work_order` and
+    /// `stress_random_defrag`, which pick a seed and log it if this is zero.
+    stress_seed:            u64                     [always_valid] = 0,
+    /// Execute all work packets on a single GC worker, so that their order only depends on the program and
+    /// `stress_seed`. This overrides `threads`.
+    deterministic_gc_work:  bool                    [always_valid] = false,
+    /// Execute the work packets available to each GC worker in a pseudo-random order drawn from `stress_seed`.
+    /// This is used to find bugs that depend on the order of work packets.
+    randomize_gc_work_order: bool                   [always_valid] = false,
+    /// Randomly decide whether each Immix GC defragments, and which blocks it evacuates, with decisions drawn
+    /// from `stress_seed`. This is used to test evacuation.
+    stress_random_defrag:   bool                    [always_valid] = false,
     /// The start of vmspace.
     vm_space_start:         Address                 [always_valid] = Address::ZERO,
     /// The size of vmspace.
@@ -961,14 +1130,30 @@ options! {
     /// there is no core with (perceived) ID 12.
     // XXX: This option is currently only supported on Linux.
     thread_affinity:        AffinityKind            [|v: &AffinityKind| v.validate()] = AffinityKind::OsDefault,
+    /// Make MMTk aware of NUMA nodes. Spaces made of blocks (such as Immix and native MarkSweep
+    /// spaces) acquire chunks for the NUMA node of the thread that needs a block, keep the free
+    /// blocks of each node separately, and reuse the blocks of the node of the asking thread
+    /// first. Pages of other spaces prefer the node of the thread that acquires them. GC workers
+    /// prefer stealing work from workers on the same node.
+    /// If `thread_affinity` is not set, GC workers are bound to NUMA nodes as `NumaNodes` does. This
+    /// has no effect on machines with a single NUMA node. (only Linux is supported)
+    numa_aware:             bool                    [always_valid] = false,
     /// Set the GC trigger. This defines the heap size and how MMTk triggers a GC.
     /// Default to a fixed heap size of 0.5x physical memory.
     gc_trigger:             GCTriggerSelector       [|v: &GCTriggerSelector| v.validate()] = GCTriggerSelector::FixedHeapSize((OS::get_system_total_memory().unwrap_or(4 * 1024 * 1024 * 1024) as f64 * 0.5f64) as usize),
     /// Enable transparent hugepage support for MMTk spaces via madvise (only Linux is supported)
     /// This only affects the memory for MMTk spaces.
     transparent_hugepages:  bool                    [|v: &bool| !v || cfg!(target_os = "linux")] = false,
+    /// Back the memory of the listed spaces with explicitly reserved huge pages (`MAP_HUGETLB`). The
+    /// listed spaces do not use transparent huge pages. See [`HugeTlbOptions`] for the format. (only
+    /// Linux is supported)
+    hugetlb_spaces:         HugeTlbOptions          [|v: &HugeTlbOptions| v.validate()] = HugeTlbOptions::default(),
     /// Count live bytes for objects in each space during a GC.
     count_live_bytes_in_gc: bool                    [always_valid] = false,
+    /// Track allocated bytes per accounting context, and enforce the quotas set for each context.
+    /// If `count_live_bytes_in_gc` is also enabled, live bytes are counted per context in each GC.
+    /// See `crate::util::accounting_context`.
+    accounting_contexts:    bool                    [always_valid] = false,
     /// Make every GC a defragment GC. (for debugging)
     immix_always_defrag: bool                       [always_valid] = false,
     /// Mark every allocated block as defragmentation source before GC. (for debugging)
@@ -979,7 +1164,32 @@ options! {
     /// headroom between 1% to 3% of the heap size.
     immix_defrag_headroom_percent: usize            [|v: &usize| *v <= 50] = 2,
     /// Disable concurrent marking in ConcurrentImmix. Setting this to true will make ConcurrentImmix behave exactly like full heap Immix. This option is only intended for debugging.
-    concurrent_immix_disable_concurrent_marking: bool              [always_valid] = false
+    concurrent_immix_disable_concurrent_marking: bool              [always_valid] = false,
+    /// Let mutators that allocate during concurrent marking in ConcurrentImmix do marking work in
+    /// proportion to their allocation, in their allocation slow path.  Mutators only assist when
+    /// GC workers fall behind the allocation.  This also starts each concurrent marking cycle
+    /// based on how much mutators allocated during the previous cycles, instead of when half of
+    /// the heap is allocated.  Work packets executed by assisting mutators receive the mutator
+    /// thread as a `VMWorkerThread`.
+    concurrent_marking_assists: bool              [always_valid] = false,
+    /// Mark incrementally in ConcurrentImmix.  GC workers only work in the initial mark and final
+    /// mark pauses.  Between the pauses, mutators do the marking in slices at each allocation slow
+    /// path and each call to `gc_poll`.  This bounds the pauses on hosts that cannot run GC threads
+    /// alongside mutators.  It takes precedence over `concurrent_marking_assists`.
+    incremental_marking: bool              [always_valid] = false,
+    /// The time limit of each incremental marking slice, in microseconds.  It is checked between
+    /// work packets, so a slice may overrun by the time of one packet.  0 means no time limit.
+    incremental_marking_slice_us: usize              [always_valid] = 500,
+    /// The work limit of each incremental marking slice, in bytes of objects scanned.  It is
+    /// checked between work packets.  0 means no work limit.  If both limits are 0, each slice
+    /// marks until no marking work is left.
+    incremental_marking_slice_bytes: usize              [always_valid] = 0,
+    /// Poison the memory released by any space, and protect released blocks, pages and copy-space
+    /// regions until they are acquired again, so that the use of a stale reference faults
+    /// immediately. The VM binding can identify such faults with
+    /// `memory_manager::find_guarded_memory`. This option is only intended for debugging, and may
+    /// exceed the limit of memory mappings of the process for large heaps.
+    guard_released_memory: bool                     [always_valid] = false
 }
 
 #[cfg(test)]
@@ -1316,6 +1526,14 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_thread_affinity_numa_nodes() {
+        serial_test(|| {
+            let affinity = "NumaNodes".parse::<AffinityKind>();
+            assert_eq!(affinity, Ok(AffinityKind::NumaNodes));
+        })
+    }
+
     #[test]
     fn test_thread_affinity_bad_affinity_kind() {
         serial_test(|| {
@@ -1405,4 +1623,85 @@ mod tests {
             assert_eq!(*options.threads, threads);
         })
     }
+
+    #[test]
+    fn test_soft_reference_policy_option() {
+        serial_test(|| {
+            let mut options = Options::default();
+            assert_eq!(*options.soft_reference_policy, SoftReferencePolicy::Retain);
+
+            assert!(options.set_from_string("soft_reference_policy", "Lru"));
+            assert_eq!(*options.soft_reference_policy, SoftReferencePolicy::Lru);
+            assert!(options.set_from_string("soft_reference_lru_ms_per_mb", "500"));
+            assert_eq!(*options.soft_reference_lru_ms_per_mb, 500);
+
+            assert!(!options.set_from_string("soft_reference_policy", "Sometimes"));
+            assert_eq!(*options.soft_reference_policy, SoftReferencePolicy::Lru);
+        })
+    }
+
+    #[test]
+    fn test_hugetlb_spaces_option() {
+        serial_test(|| {
+            let mut options = Options::default();
+            assert!(options.hugetlb_spaces.spaces.is_empty());
+            assert_eq!(
+                options.huge_page_support_for_space("immix"),
+                HugePageSupport::No
+            );
+
+            let success = options.set_from_string("hugetlb_spaces", "2M:immix,nursery");
+            assert_eq!(success, cfg!(target_os = "linux"));
+            if success {
+                assert_eq!(options.hugetlb_spaces.log_page_size, 21);
+                assert_eq!(
+                    options.huge_page_support_for_space("nursery"),
+                    HugePageSupport::HugeTlb { log_page_size: 21 }
+                );
+                assert_eq!(
+                    options.huge_page_support_for_space("los"),
+                    HugePageSupport::No
+                );
+            }
+        })
+    }
+
+    #[test]
+    fn test_hugetlb_spaces_option_parse() {
+        let default_size: HugeTlbOptions = "immix".parse().unwrap();
+        assert_eq!(
+            default_size.log_page_size,
+            HugeTlbOptions::DEFAULT_LOG_PAGE_SIZE
+        );
+        assert_eq!(default_size.spaces, vec!["immix".to_string()]);
+
+        let kb: HugeTlbOptions = "64K:los".parse().unwrap();
+        assert_eq!(kb.log_page_size, 16);
+
+        assert!("3M:immix".parse::<HugeTlbOptions>().is_err());
+        assert!("huge:immix".parse::<HugeTlbOptions>().is_err());
+        // 1GB pages are larger than a chunk.
+        assert!(!"1G:immix".parse::<HugeTlbOptions>().unwrap().validate());
+        assert!("".parse::<HugeTlbOptions>().unwrap().validate());
+    }
+
+    #[test]
+    fn test_hugetlb_spaces_with_guard_released_memory() {
+        serial_test(|| {
+            let mut options = Options::default();
+            assert!(options.set_from_string("guard_released_memory", "true"));
+            if cfg!(target_os = "linux") {
+                assert!(!options.set_from_string("hugetlb_spaces", "2M:immix"));
+                assert!(options.hugetlb_spaces.spaces.is_empty());
+
+                let mut options = Options::default();
+                assert!(options.set_from_string("hugetlb_spaces", "2M:immix"));
+                assert!(!options.set_from_string("guard_released_memory", "true"));
+                assert!(!*options.guard_released_memory);
+
+                options.guard_released_memory.set(true);
+                assert!(options.validate_combination().is_err());
+            }
+        })
+    }
 }
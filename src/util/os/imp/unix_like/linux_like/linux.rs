@@ -52,6 +52,10 @@ impl OSMemory for Linux {
     fn panic_if_unmapped(start: Address, size: usize) {
         linux_common::panic_if_unmapped(start, size)
     }
+
+    fn set_preferred_numa_node(start: Address, size: usize, node: NumaNodeId) -> Result<()> {
+        linux_common::set_preferred_numa_node(start, size, node)
+    }
 }
 
 impl OSProcess for Linux {
@@ -81,4 +85,12 @@ impl OSProcess for Linux {
     fn bind_current_thread_to_cpuset(core_ids: &[CoreId]) {
         linux_common::bind_current_thread_to_cpuset(core_ids)
     }
+
+    fn get_numa_nodes() -> Vec<NumaNode> {
+        linux_common::get_numa_nodes()
+    }
+
+    fn get_current_core() -> Option<CoreId> {
+        linux_common::get_current_core()
+    }
 }
@@ -38,6 +38,8 @@ pub fn set_hugepage(start: Address, size: usize, options: HugePageSupport) -> Re
             &|| unsafe { libc::madvise(start.to_mut_ptr(), size, libc::MADV_HUGEPAGE) },
             0,
         ),
+        // The huge pages are requested with mmap flags.
+        HugePageSupport::HugeTlb { .. } => Ok(()),
     }
 }
 
@@ -55,6 +57,9 @@ impl MmapStrategy {
         if !self.reserve {
             flags |= libc::MAP_NORESERVE;
         }
+        if let HugePageSupport::HugeTlb { log_page_size } = self.huge_page {
+            flags |= libc::MAP_HUGETLB | ((log_page_size as i32) << libc::MAP_HUGE_SHIFT);
+        }
         flags
     }
 }
@@ -109,7 +114,18 @@ pub fn dzmmap(
     strategy: MmapStrategy,
     annotation: &MmapAnnotation<'_>,
 ) -> MmapResult<Address> {
-    let addr = unix_common::mmap(start, size, strategy, annotation)?;
+    let addr = match unix_common::mmap(start, size, strategy, annotation) {
+        Err(e) if matches!(strategy.huge_page, HugePageSupport::HugeTlb { .. }) => {
+            // Fall back to normal pages, e.g. if not enough huge pages are reserved.
+            warn!(
+                "Failed to map {} with huge pages ({}). Fall back to normal pages.",
+                annotation, e.error
+            );
+            let strategy = strategy.huge_page(HugePageSupport::No);
+            unix_common::mmap(start, size, strategy, annotation)?
+        }
+        result => result?,
+    };
 
     if !cfg!(feature = "no_mmap_annotation") {
         set_vma_name(addr, size, annotation);
@@ -171,3 +187,59 @@ pub fn panic_if_unmapped(start: Address, size: usize) {
         }
     }
 }
+
+pub fn get_numa_nodes() -> Vec<NumaNode> {
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/node") else {
+        return vec![];
+    };
+    let mut nodes: Vec<NumaNode> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let id = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse::<NumaNodeId>()
+                .ok()?;
+            let cpulist = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            let cores = parse_core_list(cpulist.trim()).ok()?;
+            Some(NumaNode { id, cores })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+pub fn get_current_core() -> Option<CoreId> {
+    let core = unsafe { libc::sched_getcpu() };
+    CoreId::try_from(core).ok()
+}
+
+pub fn set_preferred_numa_node(start: Address, size: usize, node: NumaNodeId) -> Result<()> {
+    // `MPOL_PREFERRED` from `linux/mempolicy.h`.  Unlike `MPOL_BIND`, the kernel falls back to
+    // other nodes if the preferred node runs out of memory.
+    const MPOL_PREFERRED: libc::c_long = 1;
+    const BITS_IN_MASK: usize = libc::c_ulong::BITS as usize;
+    if node as usize >= BITS_IN_MASK {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("NUMA node {} is not supported", node),
+        ));
+    }
+    let nodemask: libc::c_ulong = 1 << node;
+    unix_common::wrap_libc_call(
+        &|| unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                start.to_ptr::<libc::c_void>(),
+                size,
+                MPOL_PREFERRED,
+                &nodemask as *const libc::c_ulong,
+                // The kernel reads `maxnode - 1` bits.
+                BITS_IN_MASK + 1,
+                0,
+            )
+        },
+        0,
+    )
+}
@@ -1,4 +1,3 @@
-use bytemuck::NoUninit;
 use std::io::Result;
 
 use crate::util::os::*;
@@ -159,6 +158,14 @@ pub trait OSMemory {
     /// Fallback: As the function is only used for assertions, it can be a no-op, and MMTk will still run and never panics in this function.
     fn panic_if_unmapped(start: Address, size: usize);
 
+    /// Set the preferred NUMA node of a memory region.  Pages in the region that have not been
+    /// faulted in yet will be allocated from that node if possible.
+    ///
+    /// Fallback: For platforms that do not support NUMA, this can be a no-op.
+    fn set_preferred_numa_node(_start: Address, _size: usize, _node: NumaNodeId) -> Result<()> {
+        Ok(())
+    }
+
     /// Get the total memory of the system in bytes.
     fn get_system_total_memory() -> Result<u64> {
         use sysinfo::MemoryRefreshKind;
@@ -311,13 +318,18 @@ pub enum MmapProtection {
 }
 
 /// Support for huge pages
-#[repr(u8)]
-#[derive(Debug, Copy, Clone, NoUninit)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum HugePageSupport {
     /// No support for huge page
     No,
     /// Enable transparent huge pages for the pages that are mapped. This option is only for linux.
     TransparentHugePages,
+    /// Back the pages with explicitly reserved huge pages (`MAP_HUGETLB`) of the given size.  If
+    /// the huge pages cannot be reserved, fall back to normal pages.  This option is only for linux.
+    HugeTlb {
+        /// Log2 of the huge page size in bytes.
+        log_page_size: u8,
+    },
 }
 
 /// Annotation for an mmap entry.
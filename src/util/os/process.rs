@@ -5,6 +5,56 @@ use std::io::Result;
 pub type CoreId = u16;
 /// Representation of number of CPU cores.
 pub type CoreNum = u16;
+/// Representation of a NUMA node identifier.
+pub type NumaNodeId = u16;
+
+/// A NUMA node and the CPU cores on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumaNode {
+    /// The identifier of the node.
+    pub id: NumaNodeId,
+    /// The cores on the node.
+    pub cores: Vec<CoreId>,
+}
+
+/// Parse a list of cores formatted as numbers separated by commas, including ranges, such as
+/// "`0,5,8-11`".  This is the format used by Linux in `/sys/devices/system/node/node*/cpulist`
+/// and by the `thread_affinity` option.  The returned list is sorted and de-duplicated.
+pub fn parse_core_list(list: &str) -> std::result::Result<Vec<CoreId>, String> {
+    let mut cores = vec![];
+    // Split on ',' first and then split on '-' if there is a range
+    for split in list.split(',') {
+        if !split.contains('-') {
+            if !split.is_empty() {
+                if let Ok(core) = split.parse::<CoreId>() {
+                    cores.push(core);
+                    continue;
+                }
+            }
+        } else {
+            // Contains a range
+            let range: Vec<&str> = split.split('-').collect();
+            if range.len() == 2 {
+                if let (Ok(start), Ok(end)) =
+                    (range[0].parse::<CoreId>(), range[1].parse::<CoreId>())
+                {
+                    if start >= end {
+                        return Err(
+                            "Starting core id in range should be less than the end".to_string()
+                        );
+                    }
+                    cores.extend(start..=end);
+                    continue;
+                }
+            }
+        }
+
+        return Err("Core ids have been incorrectly specified".to_string());
+    }
+    cores.sort_unstable();
+    cores.dedup();
+    Ok(cores)
+}
 
 /// Abstraction for OS process operations.
 pub trait OSProcess {
@@ -33,4 +83,17 @@ pub trait OSProcess {
 
     /// Bind the current thread to the specified core set.
     fn bind_current_thread_to_cpuset(core_ids: &[CoreId]);
+
+    /// Return the NUMA nodes of the machine and the cores on each node.
+    /// Fallback: For platforms that do not support NUMA, return an empty vector. MMTk treats the
+    /// machine as a single node in that case.
+    fn get_numa_nodes() -> Vec<NumaNode> {
+        vec![]
+    }
+
+    /// Return the core that the current thread is running on.
+    /// Fallback: For unimplemented cases, return `None`.
+    fn get_current_core() -> Option<CoreId> {
+        None
+    }
 }
@@ -1,125 +1,202 @@
 use std::collections::HashSet;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Mutex;
+use std::time::Instant;
 use std::vec::Vec;
 
+use crossbeam::queue::SegQueue;
+
 use crate::plan::is_nursery_gc;
 use crate::plan::tracing::gc_work::DefaultObjectTracerContext;
 use crate::plan::tracing::Trace;
 use crate::scheduler::WorkBucketStage;
+use crate::util::constants::{LOG_BYTES_IN_MBYTE, LOG_BYTES_IN_PAGE, LOG_BYTES_IN_WORD};
+use crate::util::options::SoftReferencePolicy;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
 use crate::vm::ObjectTracer;
 use crate::vm::ObjectTracerContext;
 use crate::vm::ReferenceGlue;
 use crate::vm::VMBinding;
+use crate::vm::{ReferenceKind, ReferenceKindSpec, ReferenceStrength};
 
-/// Holds all reference processors for each weak reference Semantics.
-/// Currently this is based on Java's weak reference semantics (soft/weak/phantom).
-/// We should make changes to make this general rather than Java specific.
+/// Holds the reference processors for all the reference kinds, i.e. the built-in kinds (Java's
+/// soft, weak and phantom references) and the kinds registered by the binding with
+/// [`crate::MMTKBuilder::register_reference_kind`].
 pub struct ReferenceProcessors {
-    soft: ReferenceProcessor,
-    weak: ReferenceProcessor,
-    phantom: ReferenceProcessor,
+    /// The reference processors, indexed by [`ReferenceKind`].
+    processors: Vec<ReferenceProcessor>,
+    /// The clock for soft references, i.e. the time (in milliseconds since the reference
+    /// processors were created) at the end of the last GC.  See
+    /// [`crate::memory_manager::soft_reference_clock`].
+    soft_reference_clock: AtomicU64,
+    /// The number of free pages at the end of the last GC.  This is used by the LRU soft
+    /// reference policy.
+    free_pages_at_last_gc: AtomicUsize,
+    creation_time: Instant,
 }
 
 impl ReferenceProcessors {
-    pub fn new() -> Self {
+    pub fn new(kinds: &[ReferenceKindSpec]) -> Self {
         ReferenceProcessors {
-            soft: ReferenceProcessor::new(Semantics::SOFT),
-            weak: ReferenceProcessor::new(Semantics::WEAK),
-            phantom: ReferenceProcessor::new(Semantics::PHANTOM),
+            processors: kinds
+                .iter()
+                .enumerate()
+                .map(|(index, spec)| ReferenceProcessor::new(ReferenceKind(index), spec.clone()))
+                .collect(),
+            soft_reference_clock: AtomicU64::new(0),
+            free_pages_at_last_gc: AtomicUsize::new(0),
+            creation_time: Instant::now(),
         }
     }
 
-    pub fn get(&self, semantics: Semantics) -> &ReferenceProcessor {
-        match semantics {
-            Semantics::SOFT => &self.soft,
-            Semantics::WEAK => &self.weak,
-            Semantics::PHANTOM => &self.phantom,
-        }
+    /// The specifications of the built-in reference kinds.  Their indices match
+    /// [`ReferenceKind::SOFT`], [`ReferenceKind::WEAK`] and [`ReferenceKind::PHANTOM`].
+    pub(crate) fn builtin_kinds() -> Vec<ReferenceKindSpec> {
+        vec![
+            ReferenceKindSpec::new("soft", ReferenceStrength::Soft),
+            ReferenceKindSpec::new("weak", ReferenceStrength::Weak),
+            ReferenceKindSpec::new("phantom", ReferenceStrength::Phantom),
+        ]
     }
 
-    pub fn add_soft_candidate(&self, reff: ObjectReference) {
-        trace!("Add soft candidate: {}", reff);
-        self.soft.add_candidate(reff);
+    pub fn get(&self, kind: ReferenceKind) -> &ReferenceProcessor {
+        &self.processors[kind.0]
     }
 
-    pub fn add_weak_candidate(&self, reff: ObjectReference) {
-        trace!("Add weak candidate: {}", reff);
-        self.weak.add_candidate(reff);
+    /// The reference processors of the given strength, in the order of registration.
+    fn with_strength(
+        &self,
+        strength: ReferenceStrength,
+    ) -> impl Iterator<Item = &ReferenceProcessor> + '_ {
+        self.processors
+            .iter()
+            .filter(move |processor| processor.spec.strength == strength)
     }
 
-    pub fn add_phantom_candidate(&self, reff: ObjectReference) {
-        trace!("Add phantom candidate: {}", reff);
-        self.phantom.add_candidate(reff);
+    /// The reference processors of the given strength and rank, in the order of registration.
+    fn with_strength_and_rank(
+        &self,
+        strength: ReferenceStrength,
+        rank: u32,
+    ) -> impl Iterator<Item = &ReferenceProcessor> + '_ {
+        self.with_strength(strength)
+            .filter(move |processor| processor.spec.rank == rank)
     }
 
-    /// This will invoke enqueue for each reference processor, which will
-    /// call back to the VM to enqueue references whose referents are cleared
-    /// in this GC.
-    pub fn enqueue_refs<VM: VMBinding>(&self, tls: VMWorkerThread) {
-        self.soft.enqueue::<VM>(tls);
-        self.weak.enqueue::<VM>(tls);
-        self.phantom.enqueue::<VM>(tls);
+    /// The ranks of the reference kinds of the given strength, from the strongest to the weakest.
+    fn ranks(&self, strength: ReferenceStrength) -> Vec<u32> {
+        let mut ranks: Vec<u32> = self
+            .with_strength(strength)
+            .map(|processor| processor.spec.rank)
+            .collect();
+        ranks.sort_unstable();
+        ranks.dedup();
+        ranks
     }
 
-    /// A separate reference forwarding step. Normally when we scan refs, we deal with forwarding.
-    /// However, for some plans like mark compact, at the point we do ref scanning, we do not know
-    /// the forwarding addresses yet, thus we cannot do forwarding during scan refs. And for those
-    /// plans, this separate step is required.
-    pub fn forward_refs<VM: VMBinding, OT: ObjectTracer>(
-        &self,
-        trace: &mut OT,
-        mmtk: &'static MMTK<VM>,
-    ) {
-        debug_assert!(
-            mmtk.get_plan().constraints().needs_forward_after_liveness,
-            "A plan with needs_forward_after_liveness=false does not need a separate forward step"
-        );
-        self.soft
-            .forward::<VM, OT>(trace, is_nursery_gc(mmtk.get_plan()));
-        self.weak
-            .forward::<VM, OT>(trace, is_nursery_gc(mmtk.get_plan()));
-        self.phantom
-            .forward::<VM, OT>(trace, is_nursery_gc(mmtk.get_plan()));
+    pub fn add_candidate(&self, kind: ReferenceKind, reff: ObjectReference) {
+        trace!("Add {} candidate: {}", self.get(kind).spec.name, reff);
+        self.get(kind).add_candidate(reff);
     }
 
-    // Methods for scanning weak references. It needs to be called in a decreasing order of reference strengths, i.e. soft > weak > phantom
+    pub fn add_soft_candidate(&self, reff: ObjectReference) {
+        self.add_candidate(ReferenceKind::SOFT, reff);
+    }
 
-    pub fn retain_soft_refs<VM: VMBinding, OT: ObjectTracer>(
-        &self,
-        trace: &mut OT,
-        mmtk: &'static MMTK<VM>,
-    ) {
-        self.soft
-            .retain::<VM, OT>(trace, is_nursery_gc(mmtk.get_plan()));
+    pub fn add_weak_candidate(&self, reff: ObjectReference) {
+        self.add_candidate(ReferenceKind::WEAK, reff);
     }
 
-    /// Scan soft references.
-    pub fn scan_soft_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        // This will update the references (and the referents).
-        self.soft.scan::<VM>(is_nursery_gc(mmtk.get_plan()));
+    pub fn add_phantom_candidate(&self, reff: ObjectReference) {
+        self.add_candidate(ReferenceKind::PHANTOM, reff);
     }
 
-    /// Scan weak references.
-    pub fn scan_weak_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        self.weak.scan::<VM>(is_nursery_gc(mmtk.get_plan()));
+    /// Get the current value of the soft reference clock.
+    pub fn soft_reference_clock(&self) -> u64 {
+        self.soft_reference_clock.load(Ordering::Relaxed)
     }
 
-    /// Scan phantom references.
-    pub fn scan_phantom_refs<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        self.phantom.scan::<VM>(is_nursery_gc(mmtk.get_plan()));
+    /// Update the soft reference clock and the free heap size at the end of a GC.
+    pub(crate) fn on_gc_end(&self, free_pages: usize) {
+        let now = self.creation_time.elapsed().as_millis() as u64;
+        self.soft_reference_clock.store(now, Ordering::Relaxed);
+        self.free_pages_at_last_gc
+            .store(free_pages, Ordering::Relaxed);
+    }
+
+    /// Decide how the references of a soft reference kind are treated in the current GC.
+    fn soft_reference_retention<VM: VMBinding>(
+        &self,
+        processor: &ReferenceProcessor,
+        mmtk: &'static MMTK<VM>,
+    ) -> SoftReferenceRetention {
+        // Always clear soft references in emergency collections.
+        if mmtk.state.is_emergency_collection() {
+            return SoftReferenceRetention::ClearAll;
+        }
+        let policy = processor
+            .spec
+            .soft_reference_policy
+            .unwrap_or(*mmtk.options.soft_reference_policy);
+        match policy {
+            SoftReferencePolicy::Retain => SoftReferenceRetention::RetainAll,
+            SoftReferencePolicy::AlwaysClear => SoftReferenceRetention::ClearAll,
+            SoftReferencePolicy::ClearOnFullHeap => {
+                if is_nursery_gc(mmtk.get_plan()) {
+                    SoftReferenceRetention::RetainAll
+                } else {
+                    SoftReferenceRetention::ClearAll
+                }
+            }
+            SoftReferencePolicy::Lru => {
+                let free_pages = self.free_pages_at_last_gc.load(Ordering::Relaxed);
+                let free_mb = (free_pages << LOG_BYTES_IN_PAGE) >> LOG_BYTES_IN_MBYTE;
+                let max_idle_ms =
+                    free_mb as u64 * *mmtk.options.soft_reference_lru_ms_per_mb as u64;
+                SoftReferenceRetention::RetainRecentlyUsed {
+                    clock: self.soft_reference_clock(),
+                    max_idle_ms,
+                }
+            }
+        }
+    }
+
+    /// This will invoke enqueue for each reference processor, from the strongest kind to the
+    /// weakest kind, which will call back to the VM to enqueue references whose referents are
+    /// cleared in this GC.
+    pub fn enqueue_refs<VM: VMBinding>(&self, tls: VMWorkerThread) {
+        for strength in ReferenceStrength::ALL {
+            for rank in self.ranks(strength) {
+                for processor in self.with_strength_and_rank(strength, rank) {
+                    processor.enqueue::<VM>(tls);
+                }
+            }
+        }
     }
 }
 
 impl Default for ReferenceProcessors {
     fn default() -> Self {
-        Self::new()
+        Self::new(&Self::builtin_kinds())
     }
 }
 
+/// How soft references are treated in a GC, as decided by the [`SoftReferencePolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SoftReferenceRetention {
+    /// Retain the referents of all reachable soft references.
+    RetainAll,
+    /// Do not retain any referent.  Soft references are treated like weak references.
+    ClearAll,
+    /// Retain the referents of reachable soft references that have been accessed within
+    /// `max_idle_ms` milliseconds before `clock`.
+    RetainRecentlyUsed { clock: u64, max_idle_ms: u64 },
+}
+
 // XXX: We differ from the original implementation
 //      by ignoring "stress," i.e. where the array
 //      of references is grown by 1 each time. We
@@ -129,19 +206,41 @@ impl Default for ReferenceProcessors {
 //      luckily this is also the value used by Java MMTk.)
 const INITIAL_SIZE: usize = 256;
 
-/// We create a reference processor for each semantics. Generally we expect these
+/// The number of shards of the reference table of each reference kind.  Each shard is processed in its
+/// own work packet so that all GC workers can take part in reference processing.
+pub(crate) const NUM_REFERENCE_SHARDS: usize = 32;
+
+/// The shard a reference belongs to.
+fn shard_of(reff: ObjectReference) -> usize {
+    (reff.to_raw_address().as_usize() >> LOG_BYTES_IN_WORD) % NUM_REFERENCE_SHARDS
+}
+
+/// We create a reference processor for each reference kind. Generally we expect these
 /// to happen for each processor:
 /// 1. The VM adds reference candidates. They could either do it when a weak reference
 ///    is created, or when a weak reference is traced during GC.
 /// 2. We scan references after the GC determins liveness.
 /// 3. We forward references if the GC needs forwarding after liveness.
 /// 4. We inform the binding of references whose referents are cleared during this GC by enqueue'ing.
+///
+/// The reference table is partitioned into [`NUM_REFERENCE_SHARDS`] shards by the address of the
+/// reference objects, and steps 2 and 3 are done for each shard in a separate work packet.
+/// Candidates are added to a lock-free queue, and are moved into their shards at the beginning of
+/// each step.
 pub struct ReferenceProcessor {
-    /// Most of the reference processor is protected by a mutex.
-    sync: Mutex<ReferenceProcessorSync>,
+    /// Candidates added since they were last moved into the shards.  Mutators and GC workers can
+    /// add candidates without taking any lock.
+    new_candidates: SegQueue<ObjectReference>,
+
+    /// The shards of the reference table.  During a GC, each shard is only accessed by one work
+    /// packet at a time.
+    shards: Vec<Mutex<ReferenceProcessorSync>>,
+
+    /// The reference kind of the reference processor
+    kind: ReferenceKind,
 
-    /// The semantics for the reference processor
-    semantics: Semantics,
+    /// How the references of this kind are processed
+    spec: ReferenceKindSpec,
 
     /// Is it allowed to add candidate to this reference processor? The value is true for most of the time,
     /// but it is set to false once we finish forwarding references, at which point we do not expect to encounter
@@ -158,50 +257,73 @@ pub struct ReferenceProcessor {
     allow_new_candidate: AtomicBool,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum Semantics {
-    SOFT,
-    WEAK,
-    PHANTOM,
-}
-
+/// A shard of the reference table.
 struct ReferenceProcessorSync {
-    /// The table of reference objects for the current semantics. We add references to this table by
-    /// add_candidate(). After scanning this table, a reference in the table should either
+    /// The table of reference objects for the current reference kind that have survived at least one
+    /// GC. After scanning this table, a reference in the table should either
     /// stay in the table (if the referent is alive) or go to enqueued_reference (if the referent is dead and cleared).
     /// Note that this table should not have duplicate entries, otherwise we will scan the duplicates multiple times, and
     /// that may lead to incorrect results.
     references: HashSet<ObjectReference>,
 
+    /// The table of reference objects added by add_candidate() since the last GC.  A nursery GC only
+    /// scans this table, because the references and referents in `references` are mature, and
+    /// are neither moved nor reclaimed in a nursery GC.  After scanning, the surviving references
+    /// are moved to `references`.  If the binding sets the referent of a reference, it should add
+    /// the reference as a candidate again so that a young referent is processed in nursery GCs.
+    nursery_references: HashSet<ObjectReference>,
+
+    /// Surviving references that have been moved, and now belong to other shards.  They are moved
+    /// to the `references` table of their new shards at the beginning of the next step.  We cannot
+    /// insert them into other shards directly because other shards may be being processed.
+    migrants: Vec<ObjectReference>,
+
     /// References whose referents are cleared during this GC. We add references to this table during
     /// scanning, and we pop from this table during the enqueue work at the end of GC.
     enqueued_references: Vec<ObjectReference>,
+}
 
-    /// Index into the references table for the start of nursery objects
-    nursery_index: usize,
+impl ReferenceProcessorSync {
+    fn new() -> Self {
+        Self {
+            references: HashSet::with_capacity(INITIAL_SIZE / NUM_REFERENCE_SHARDS),
+            nursery_references: HashSet::new(),
+            migrants: vec![],
+            enqueued_references: vec![],
+        }
+    }
+
+    /// Keep a surviving reference in the mature table of this shard, or record it as a migrant if
+    /// it belongs to another shard after being moved.
+    fn keep(&mut self, shard: usize, reff: ObjectReference) {
+        if shard_of(reff) == shard {
+            self.references.insert(reff);
+        } else {
+            self.migrants.push(reff);
+        }
+    }
 }
 
 impl ReferenceProcessor {
-    pub fn new(semantics: Semantics) -> Self {
+    pub fn new(kind: ReferenceKind, spec: ReferenceKindSpec) -> Self {
         ReferenceProcessor {
-            sync: Mutex::new(ReferenceProcessorSync {
-                references: HashSet::with_capacity(INITIAL_SIZE),
-                enqueued_references: vec![],
-                nursery_index: 0,
-            }),
-            semantics,
+            new_candidates: SegQueue::new(),
+            shards: (0..NUM_REFERENCE_SHARDS)
+                .map(|_| Mutex::new(ReferenceProcessorSync::new()))
+                .collect(),
+            kind,
+            spec,
             allow_new_candidate: AtomicBool::new(true),
         }
     }
 
-    /// Add a candidate.
+    /// Add a candidate.  This does not take any lock.
     pub fn add_candidate(&self, reff: ObjectReference) {
         if !self.allow_new_candidate.load(Ordering::SeqCst) {
             return;
         }
 
-        let mut sync = self.sync.lock().unwrap();
-        sync.references.insert(reff);
+        self.new_candidates.push(reff);
     }
 
     fn disallow_new_candidate(&self) {
@@ -212,6 +334,47 @@ impl ReferenceProcessor {
         self.allow_new_candidate.store(true, Ordering::SeqCst);
     }
 
+    /// Move new candidates and migrants into the shards they belong to, and return the shards
+    /// that have references to process (only considering the nursery tables if `nursery` is true).
+    /// This must be called before fanning out the work packets of a step, when no shard is being
+    /// processed.
+    fn distribute(&self, nursery: bool) -> Vec<usize> {
+        let mut new_candidates = vec![vec![]; NUM_REFERENCE_SHARDS];
+        while let Some(reff) = self.new_candidates.pop() {
+            new_candidates[shard_of(reff)].push(reff);
+        }
+        let mut migrants = vec![vec![]; NUM_REFERENCE_SHARDS];
+        for shard in self.shards.iter() {
+            for reff in std::mem::take(&mut shard.lock().unwrap().migrants) {
+                migrants[shard_of(reff)].push(reff);
+            }
+        }
+
+        let mut shards_to_process = vec![];
+        for (index, (shard, (new_candidates, migrants))) in self
+            .shards
+            .iter()
+            .zip(new_candidates.into_iter().zip(migrants))
+            .enumerate()
+        {
+            let mut sync = shard.lock().unwrap();
+            for reff in migrants {
+                sync.nursery_references.remove(&reff);
+                sync.references.insert(reff);
+            }
+            for reff in new_candidates {
+                // A reference may be added again while it is already in the mature table.
+                if !sync.references.contains(&reff) {
+                    sync.nursery_references.insert(reff);
+                }
+            }
+            if !sync.nursery_references.is_empty() || (!nursery && !sync.references.is_empty()) {
+                shards_to_process.push(index);
+            }
+        }
+        shards_to_process
+    }
+
     // These functions call `ObjectReference::get_forwarded_object`, not `trace_object()`.
     // They are used by steps that do not expand the transitive closure.  Processing weak and
     // phantom references never expand the transitive closure.  Soft references, when not retained,
@@ -264,51 +427,63 @@ impl ReferenceProcessor {
 
     /// Inform the binding to enqueue the weak references whose referents were cleared in this GC.
     pub fn enqueue<VM: VMBinding>(&self, tls: VMWorkerThread) {
-        // We will acquire a lock below. If anyone tries to insert new weak refs which will acquire the same lock, a deadlock will occur.
-        // This does happen for OpenJDK with ConcurrentImmix where a write barrier is triggered during the enqueueing of weak references,
-        // and the write barrier scans the objects and attempts to add new weak references.
-        // Disallow new candidates to prevent the deadlock.
-        self.disallow_new_candidate();
-        let mut sync = self.sync.lock().unwrap();
-
-        // This is the end of a GC. We do some assertions here to make sure our reference tables are correct.
-        #[cfg(debug_assertions)]
-        {
-            // For references in the table, the reference needs to be valid, and if the referent is not cleared, it should be valid as well
-            sync.references.iter().for_each(|reff| {
-                debug_assert!(reff.is_in_any_space());
-                if let Some(referent) = VM::VMReferenceGlue::get_referent(*reff) {
-                    debug_assert!(
-                        referent.is_in_any_space(),
-                        "Referent {:?} (of reference {:?}) is not in any space",
-                        referent,
-                        reff
-                    );
-                }
-            });
-            // For references that will be enqueue'd, the reference needs to be valid, and the referent needs to be cleared.
-            sync.enqueued_references.iter().for_each(|reff| {
-                debug_assert!(reff.is_in_any_space());
-                let maybe_referent = VM::VMReferenceGlue::get_referent(*reff);
-                debug_assert!(maybe_referent.is_none());
-            });
+        self.distribute(false);
+
+        let mut enqueued_references = vec![];
+        for shard in self.shards.iter() {
+            let mut sync = shard.lock().unwrap();
+
+            // This is the end of a GC. We do some assertions here to make sure our reference tables are correct.
+            #[cfg(debug_assertions)]
+            {
+                // For references in the table, the reference needs to be valid, and if the referent is not cleared, it should be valid as well
+                sync.references
+                    .iter()
+                    .chain(sync.nursery_references.iter())
+                    .for_each(|reff| {
+                        debug_assert!(reff.is_in_any_space());
+                        if let Some(referent) = VM::VMReferenceGlue::get_referent(*reff) {
+                            debug_assert!(
+                                referent.is_in_any_space(),
+                                "Referent {:?} (of reference {:?}) is not in any space",
+                                referent,
+                                reff
+                            );
+                        }
+                    });
+                // For references that will be enqueue'd, the reference needs to be valid, and the referent needs to be cleared.
+                sync.enqueued_references.iter().for_each(|reff| {
+                    debug_assert!(reff.is_in_any_space());
+                    let maybe_referent = VM::VMReferenceGlue::get_referent(*reff);
+                    debug_assert!(maybe_referent.is_none());
+                });
+            }
+
+            enqueued_references.append(&mut sync.enqueued_references);
         }
 
-        if !sync.enqueued_references.is_empty() {
-            trace!("enqueue: {:?}", sync.enqueued_references);
-            VM::VMReferenceGlue::enqueue_references(&sync.enqueued_references, tls);
-            sync.enqueued_references.clear();
+        // No lock is held when calling into the binding.  This matters for OpenJDK with
+        // ConcurrentImmix where a write barrier is triggered during the enqueueing of weak
+        // references, and the write barrier scans the objects and adds new weak references.
+        if !enqueued_references.is_empty() {
+            trace!("enqueue: {:?}", enqueued_references);
+            match self.spec.enqueue {
+                Some(enqueue) => enqueue(&enqueued_references, tls),
+                None => VM::VMReferenceGlue::enqueue_references(&enqueued_references, tls),
+            }
         }
 
         self.allow_new_candidate();
     }
 
-    /// Forward the reference tables in the reference processor. This is only needed if a plan does not forward
+    /// Forward a shard of the reference table. This is only needed if a plan does not forward
     /// objects in their first transitive closure.
-    /// nursery is not used for this.
-    pub fn forward<VM: VMBinding, OT: ObjectTracer>(&self, trace: &mut OT, _nursery: bool) {
-        let mut sync = self.sync.lock().unwrap();
-        debug!("Starting ReferenceProcessor.forward({:?})", self.semantics);
+    fn forward<VM: VMBinding, OT: ObjectTracer>(&self, shard: usize, trace: &mut OT) {
+        let mut sync = self.shards[shard].lock().unwrap();
+        debug!(
+            "Starting ReferenceProcessor.forward({}, shard: {})",
+            self.spec.name, shard
+        );
 
         // Forward a single reference
         fn forward_reference<VM: VMBinding, OT: ObjectTracer>(
@@ -342,11 +517,14 @@ impl ReferenceProcessor {
             new_reference
         }
 
-        sync.references = sync
-            .references
-            .iter()
-            .map(|reff| forward_reference::<VM, OT>(trace, *reff))
-            .collect();
+        // Plans that forward after liveness are not generational, so the nursery table (if not
+        // already drained by scanning) can be merged into the mature table.
+        let references = std::mem::take(&mut sync.references);
+        let nursery_references = std::mem::take(&mut sync.nursery_references);
+        for reff in references.into_iter().chain(nursery_references) {
+            let new_reff = forward_reference::<VM, OT>(trace, reff);
+            sync.keep(shard, new_reff);
+        }
 
         sync.enqueued_references = sync
             .enqueued_references
@@ -354,49 +532,49 @@ impl ReferenceProcessor {
             .map(|reff| forward_reference::<VM, OT>(trace, *reff))
             .collect();
 
-        debug!("Ending ReferenceProcessor.forward({:?})", self.semantics);
-
-        // We finish forwarding. No longer accept new candidates.
-        self.disallow_new_candidate();
+        debug!(
+            "Ending ReferenceProcessor.forward({}, shard: {})",
+            self.spec.name, shard
+        );
     }
 
-    /// Scan the reference table, and update each reference/referent.
+    /// Scan a shard of the reference table, and update each reference/referent.
     /// It doesn't keep the reference or the referent alive.
-    // TODO: nursery is currently ignored. We used to use Vec for the reference table, and use an int
-    // to point to the reference that we last scanned. However, when we use HashSet for reference table,
-    // we can no longer do that.
-    fn scan<VM: VMBinding>(&self, _nursery: bool) {
-        let mut sync = self.sync.lock().unwrap();
-
-        debug!("Starting ReferenceProcessor.scan({:?})", self.semantics);
-
-        trace!(
-            "{:?} Reference table is {:?}",
-            self.semantics,
-            sync.references
+    /// A nursery GC only scans the references added since the last GC.
+    fn scan<VM: VMBinding>(&self, shard: usize, nursery: bool) {
+        let mut sync = self.shards[shard].lock().unwrap();
+
+        debug!(
+            "Starting ReferenceProcessor.scan({}, shard: {}, nursery: {})",
+            self.spec.name, shard, nursery
         );
 
-        //debug_assert!(sync.enqueued_references.is_empty());
+        let mut to_scan = std::mem::take(&mut sync.nursery_references);
+        if !nursery {
+            to_scan.extend(std::mem::take(&mut sync.references));
+        }
+
+        trace!("{} Reference table is {:?}", self.spec.name, to_scan);
+
         // Put enqueued reference in this vec
         let mut enqueued_references = vec![];
 
         // Determinine liveness for each reference and only keep the refs if `process_reference()` returns Some.
-        let new_set: HashSet<ObjectReference> = sync
-            .references
+        let new_set: Vec<ObjectReference> = to_scan
             .iter()
             .filter_map(|reff| self.process_reference::<VM>(*reff, &mut enqueued_references))
             .collect();
 
-        let num_old = sync.references.len();
+        let num_old = to_scan.len();
         let num_new = new_set.len();
         let num_enqueued = enqueued_references.len();
 
         debug!(
-            "{:?} reference table from {} to {} ({} enqueued)",
-            self.semantics, num_old, num_new, num_enqueued,
+            "{} reference table shard {} from {} to {} ({} enqueued)",
+            self.spec.name, shard, num_old, num_new, num_enqueued,
         );
 
-        let semantics_int = self.semantics as usize;
+        let semantics_int = self.kind.0;
 
         probe!(
             mmtk,
@@ -407,33 +585,59 @@ impl ReferenceProcessor {
             num_enqueued
         );
 
-        sync.references = new_set;
+        for reff in new_set {
+            sync.keep(shard, reff);
+        }
         sync.enqueued_references.extend(enqueued_references);
 
-        debug!("Ending ReferenceProcessor.scan({:?})", self.semantics);
+        debug!(
+            "Ending ReferenceProcessor.scan({}, shard: {})",
+            self.spec.name, shard
+        );
     }
 
-    /// Retain referent in the reference table. This method deals only with soft references.
+    /// Retain referent in a shard of the reference table. This method deals only with soft references.
     /// It retains the referent if the reference is definitely reachable. This method does
     /// not update reference or referent. So after this method, scan() should be used to update
     /// the references/referents.
-    fn retain<VM: VMBinding, OT: ObjectTracer>(&self, trace: &mut OT, _nursery: bool) {
-        debug_assert!(self.semantics == Semantics::SOFT);
+    /// A nursery GC only retains the referents of the references added since the last GC.
+    /// With [`SoftReferenceRetention::RetainRecentlyUsed`], the referents of references that have
+    /// not been accessed recently are not retained.
+    fn retain<VM: VMBinding, OT: ObjectTracer>(
+        &self,
+        shard: usize,
+        trace: &mut OT,
+        nursery: bool,
+        retention: SoftReferenceRetention,
+    ) {
+        debug_assert!(self.spec.strength == ReferenceStrength::Soft);
 
-        let sync = self.sync.lock().unwrap();
+        let sync = self.shards[shard].lock().unwrap();
 
-        debug!("Starting ReferenceProcessor.retain({:?})", self.semantics);
-        trace!(
-            "{:?} Reference table is {:?}",
-            self.semantics,
-            sync.references
+        debug!(
+            "Starting ReferenceProcessor.retain({}, shard: {}, nursery: {})",
+            self.spec.name, shard, nursery
         );
 
-        let num_refs = sync.references.len();
+        let mature_references = if nursery {
+            None
+        } else {
+            Some(sync.references.iter())
+        };
+        let to_retain = sync
+            .nursery_references
+            .iter()
+            .chain(mature_references.into_iter().flatten());
+
+        let num_refs = if nursery {
+            sync.nursery_references.len()
+        } else {
+            sync.nursery_references.len() + sync.references.len()
+        };
         let mut num_live = 0usize;
         let mut num_retained = 0usize;
 
-        for reference in sync.references.iter() {
+        for reference in to_retain {
             trace!("Processing reference: {:?}", reference);
 
             if !reference.is_live() {
@@ -442,6 +646,14 @@ impl ReferenceProcessor {
                 continue;
             }
             num_live += 1;
+            if let SoftReferenceRetention::RetainRecentlyUsed { clock, max_idle_ms } = retention {
+                let timestamp = VM::VMReferenceGlue::get_soft_reference_timestamp(*reference);
+                if clock.saturating_sub(timestamp) > max_idle_ms {
+                    // Not accessed recently.  Let the referent be cleared if it is not reachable otherwise.
+                    trace!(" ~> not retained (last accessed at {})", timestamp);
+                    continue;
+                }
+            }
             // Reference is definitely reachable.  Retain the referent.
             if let Some(referent) = VM::VMReferenceGlue::get_referent(*reference) {
                 Self::keep_referent_alive(trace, referent);
@@ -452,7 +664,10 @@ impl ReferenceProcessor {
 
         probe!(mmtk, reference_retained, num_refs, num_live, num_retained,);
 
-        debug!("Ending ReferenceProcessor.retain({:?})", self.semantics);
+        debug!(
+            "Ending ReferenceProcessor.retain({}, shard: {})",
+            self.spec.name, shard
+        );
     }
 
     /// Process a reference.
@@ -526,62 +741,239 @@ use crate::scheduler::GCWorker;
 use crate::MMTK;
 use std::marker::PhantomData;
 
-#[derive(Default)]
+impl ReferenceProcessor {
+    /// Create a work packet for each shard that needs to be scanned.
+    fn scan_packets<VM: VMBinding>(&self, nursery: bool) -> Vec<Box<dyn GCWork<VM>>> {
+        self.distribute(nursery)
+            .into_iter()
+            .map(|shard| {
+                Box::new(RefScanWork::<VM> {
+                    kind: self.kind,
+                    shard,
+                    nursery,
+                    phantom_data: PhantomData,
+                }) as Box<dyn GCWork<VM>>
+            })
+            .collect()
+    }
+}
+
+/// Scan the references of all the kinds of the given strength in parallel.  The work packets are
+/// added to the bucket `stage`, which is the bucket currently being processed.
+fn add_scan_work<VM: VMBinding>(
+    worker: &mut GCWorker<VM>,
+    mmtk: &'static MMTK<VM>,
+    strength: ReferenceStrength,
+    stage: WorkBucketStage,
+) {
+    add_scan_work_for(
+        worker,
+        mmtk,
+        mmtk.reference_processors.with_strength(strength),
+        stage,
+    );
+}
+
+/// Scan the references of the given reference processors in parallel.  The work packets are added
+/// to the bucket `stage`.
+fn add_scan_work_for<'a, VM: VMBinding>(
+    worker: &mut GCWorker<VM>,
+    mmtk: &'static MMTK<VM>,
+    processors: impl Iterator<Item = &'a ReferenceProcessor>,
+    stage: WorkBucketStage,
+) {
+    let nursery = is_nursery_gc(mmtk.get_plan());
+    let packets = processors
+        .flat_map(|processor| processor.scan_packets::<VM>(nursery))
+        .collect();
+    worker.scheduler().work_buckets[stage].bulk_add(packets);
+}
+
+/// Scan a shard of the reference table of a reference processor.
+pub(crate) struct RefScanWork<VM: VMBinding> {
+    kind: ReferenceKind,
+    shard: usize,
+    nursery: bool,
+    phantom_data: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> GCWork<VM> for RefScanWork<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        mmtk.reference_processors
+            .get(self.kind)
+            .scan::<VM>(self.shard, self.nursery);
+    }
+}
+
+/// Retain the referents of a shard of the reference table of a soft reference kind.
+pub(crate) struct RefRetainWork<T: Trace> {
+    kind: ReferenceKind,
+    shard: usize,
+    nursery: bool,
+    retention: SoftReferenceRetention,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for RefRetainWork<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let tracer_context = DefaultObjectTracerContext::<T>::new(WorkBucketStage::SoftRefClosure);
+        tracer_context.with_tracer(worker, |tracer| {
+            mmtk.reference_processors.get(self.kind).retain::<T::VM, _>(
+                self.shard,
+                tracer,
+                self.nursery,
+                self.retention,
+            );
+        });
+    }
+}
+
+/// Forward a shard of the reference table of a reference processor.
+pub(crate) struct RefForwardWork<T: Trace> {
+    kind: ReferenceKind,
+    shard: usize,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for RefForwardWork<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let tracer_context = DefaultObjectTracerContext::<T>::new(WorkBucketStage::RefForwarding);
+        tracer_context.with_tracer(worker, |tracer| {
+            mmtk.reference_processors
+                .get(self.kind)
+                .forward::<T::VM, _>(self.shard, tracer);
+        });
+    }
+}
+
+/// Scan the references of the given strengths again, after the transitive closure expanded by
+/// retaining soft references or resurrecting finalizable objects.  The scanning work packets are
+/// added to the bucket `stage`.
 pub(crate) struct RescanReferences<VM: VMBinding> {
-    pub soft: bool,
-    pub weak: bool,
+    pub strengths: &'static [ReferenceStrength],
+    pub stage: WorkBucketStage,
     pub phantom_data: PhantomData<VM>,
 }
 
 impl<VM: VMBinding> GCWork<VM> for RescanReferences<VM> {
-    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
-        if self.soft {
-            mmtk.reference_processors.scan_soft_refs(mmtk);
-        }
-        if self.weak {
-            mmtk.reference_processors.scan_weak_refs(mmtk);
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        for strength in self.strengths.iter().copied() {
+            add_scan_work(worker, mmtk, strength, self.stage);
         }
     }
 }
 
+/// Process the reference kinds of the [`ReferenceStrength::Soft`] strength, one rank after
+/// another.
 #[derive(Default)]
-pub(crate) struct SoftRefProcessing<T: Trace>(PhantomData<T>);
+pub(crate) struct SoftRefProcessing<T: Trace> {
+    /// The ranks that are not processed, yet, or `None` if no rank is processed.
+    ranks: Option<Vec<u32>>,
+    phantom_data: PhantomData<T>,
+}
+
 impl<T: Trace> GCWork<T::VM> for SoftRefProcessing<T> {
     fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
-        if !mmtk.state.is_emergency_collection() {
-            // Postpone the scanning to the end of the transitive closure from strongly reachable
-            // soft references.
-            let rescan = Box::new(RescanReferences {
-                soft: true,
-                weak: false,
-                phantom_data: PhantomData,
-            });
-            worker.scheduler().work_buckets[WorkBucketStage::SoftRefClosure].set_sentinel(rescan);
-
-            // Retain soft references.  This will expand the transitive closure.
-            let tracer_context =
-                DefaultObjectTracerContext::<T>::new(WorkBucketStage::SoftRefClosure);
-            tracer_context.with_tracer(worker, |tracer| {
-                mmtk.reference_processors.retain_soft_refs(tracer, mmtk);
-            });
+        let stage = WorkBucketStage::SoftRefClosure;
+        let nursery = is_nursery_gc(mmtk.get_plan());
+        let ranks = self
+            .ranks
+            .take()
+            .unwrap_or_else(|| mmtk.reference_processors.ranks(ReferenceStrength::Soft));
+        let Some((&rank, rest)) = ranks.split_first() else {
+            return;
+        };
+        let mut packets: Vec<Box<dyn GCWork<T::VM>>> = vec![];
+        for processor in mmtk
+            .reference_processors
+            .with_strength_and_rank(ReferenceStrength::Soft, rank)
+        {
+            let retention = mmtk
+                .reference_processors
+                .soft_reference_retention(processor, mmtk);
+            debug!(
+                "Soft reference retention for {}: {:?}",
+                processor.spec.name, retention
+            );
+            if retention != SoftReferenceRetention::ClearAll {
+                // Retain soft references.  This will expand the transitive closure.
+                packets.extend(processor.distribute(nursery).into_iter().map(|shard| {
+                    Box::new(RefRetainWork::<T> {
+                        kind: processor.kind,
+                        shard,
+                        nursery,
+                        retention,
+                        phantom_data: PhantomData,
+                    }) as Box<dyn GCWork<T::VM>>
+                }));
+            }
+        }
+        let mut scan = ScanSoftReferences::<T> {
+            rank,
+            rest: rest.to_vec(),
+            phantom_data: PhantomData,
+        };
+        if packets.is_empty() {
+            // Nothing is retained.  Scan soft references immediately.
+            scan.do_work(worker, mmtk);
         } else {
-            // Scan soft references immediately without retaining.
-            mmtk.reference_processors.scan_soft_refs(mmtk);
+            // Postpone the scanning of all soft reference kinds of this rank (including those
+            // that retain nothing) to the end of the transitive closure from strongly reachable
+            // soft references.  Otherwise, a kind may clear a referent that another kind retains.
+            worker.scheduler().work_buckets[stage].set_sentinel(Box::new(scan));
+            worker.scheduler().work_buckets[stage].bulk_add(packets);
         }
     }
 }
 
 impl<T: Trace> SoftRefProcessing<T> {
     pub fn new() -> Self {
-        Self(PhantomData)
+        Self {
+            ranks: None,
+            phantom_data: PhantomData,
+        }
     }
 }
 
+/// Scan the soft reference kinds of a rank after retaining their referents, and then continue with
+/// the next rank.  The next rank is not retained until the scanning is finished, because retaining
+/// may move the referents that are being scanned.
+struct ScanSoftReferences<T: Trace> {
+    rank: u32,
+    rest: Vec<u32>,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: Trace> GCWork<T::VM> for ScanSoftReferences<T> {
+    fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
+        let stage = WorkBucketStage::SoftRefClosure;
+        if !self.rest.is_empty() {
+            worker.scheduler().work_buckets[stage].set_sentinel(Box::new(SoftRefProcessing::<T> {
+                ranks: Some(std::mem::take(&mut self.rest)),
+                phantom_data: PhantomData,
+            }));
+        }
+        add_scan_work_for(
+            worker,
+            mmtk,
+            mmtk.reference_processors
+                .with_strength_and_rank(ReferenceStrength::Soft, self.rank),
+            stage,
+        );
+    }
+}
+
+/// Process the reference kinds of the [`ReferenceStrength::Weak`] strength.
 #[derive(Default)]
 pub(crate) struct WeakRefProcessing<VM: VMBinding>(PhantomData<VM>);
 impl<VM: VMBinding> GCWork<VM> for WeakRefProcessing<VM> {
-    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
-        mmtk.reference_processors.scan_weak_refs(mmtk);
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        add_scan_work(
+            worker,
+            mmtk,
+            ReferenceStrength::Weak,
+            WorkBucketStage::WeakRefClosure,
+        );
     }
 }
 impl<VM: VMBinding> WeakRefProcessing<VM> {
@@ -590,11 +982,38 @@ impl<VM: VMBinding> WeakRefProcessing<VM> {
     }
 }
 
+/// Process the reference kinds of the [`ReferenceStrength::Final`] strength.  This is only
+/// scheduled if finalization is disabled.  Otherwise, those kinds are processed after finalizable
+/// objects are resurrected (see [`crate::util::finalizable_processor::FinishFinalization`]).
+#[derive(Default)]
+pub(crate) struct FinalRefProcessing<VM: VMBinding>(PhantomData<VM>);
+impl<VM: VMBinding> GCWork<VM> for FinalRefProcessing<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        add_scan_work(
+            worker,
+            mmtk,
+            ReferenceStrength::Final,
+            WorkBucketStage::FinalRefClosure,
+        );
+    }
+}
+impl<VM: VMBinding> FinalRefProcessing<VM> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Process the reference kinds of the [`ReferenceStrength::Phantom`] strength.
 #[derive(Default)]
 pub(crate) struct PhantomRefProcessing<VM: VMBinding>(PhantomData<VM>);
 impl<VM: VMBinding> GCWork<VM> for PhantomRefProcessing<VM> {
-    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
-        mmtk.reference_processors.scan_phantom_refs(mmtk);
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        add_scan_work(
+            worker,
+            mmtk,
+            ReferenceStrength::Phantom,
+            WorkBucketStage::PhantomRefClosure,
+        );
     }
 }
 impl<VM: VMBinding> PhantomRefProcessing<VM> {
@@ -603,14 +1022,34 @@ impl<VM: VMBinding> PhantomRefProcessing<VM> {
     }
 }
 
+/// A separate reference forwarding step. Normally when we scan refs, we deal with forwarding.
+/// However, for some plans like mark compact, at the point we do ref scanning, we do not know
+/// the forwarding addresses yet, thus we cannot do forwarding during scan refs. And for those
+/// plans, this separate step is required.
 #[derive(Default)]
 pub(crate) struct RefForwarding<T: Trace>(PhantomData<T>);
 impl<T: Trace> GCWork<T::VM> for RefForwarding<T> {
     fn do_work(&mut self, worker: &mut GCWorker<T::VM>, mmtk: &'static MMTK<T::VM>) {
-        let tracer_context = DefaultObjectTracerContext::<T>::new(WorkBucketStage::RefForwarding);
-        tracer_context.with_tracer(worker, |tracer| {
-            mmtk.reference_processors.forward_refs(tracer, mmtk);
-        });
+        debug_assert!(
+            mmtk.get_plan().constraints().needs_forward_after_liveness,
+            "A plan with needs_forward_after_liveness=false does not need a separate forward step"
+        );
+        let mut packets: Vec<Box<dyn GCWork<T::VM>>> = vec![];
+        for processor in mmtk.reference_processors.processors.iter() {
+            let shards = processor.distribute(false);
+            // No longer accept new candidates.  This must happen before any shard is forwarded,
+            // because the objects traced when forwarding one shard may be scanned while other
+            // shards are still being forwarded.
+            processor.disallow_new_candidate();
+            packets.extend(shards.into_iter().map(|shard| {
+                Box::new(RefForwardWork::<T> {
+                    kind: processor.kind,
+                    shard,
+                    phantom_data: PhantomData,
+                }) as Box<dyn GCWork<T::VM>>
+            }));
+        }
+        worker.scheduler().work_buckets[WorkBucketStage::RefForwarding].bulk_add(packets);
     }
 }
 impl<T: Trace> RefForwarding<T> {
@@ -631,3 +1070,127 @@ impl<VM: VMBinding> RefEnqueue<VM> {
         Self(PhantomData)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::Address;
+
+    fn object(i: usize) -> ObjectReference {
+        ObjectReference::from_raw_address(unsafe { Address::from_usize(0x1000_0000 + (i << 3)) })
+            .unwrap()
+    }
+
+    fn processor() -> ReferenceProcessor {
+        ReferenceProcessor::new(
+            ReferenceKind(0),
+            ReferenceKindSpec::new("test", ReferenceStrength::Weak),
+        )
+    }
+
+    fn nursery_len(processor: &ReferenceProcessor, shard: usize) -> usize {
+        processor.shards[shard]
+            .lock()
+            .unwrap()
+            .nursery_references
+            .len()
+    }
+
+    fn mature_len(processor: &ReferenceProcessor, shard: usize) -> usize {
+        processor.shards[shard].lock().unwrap().references.len()
+    }
+
+    #[test]
+    fn ranks_of_strength() {
+        let kind = |strength, rank| ReferenceKindSpec {
+            rank,
+            ..ReferenceKindSpec::new("test", strength)
+        };
+        let processors = ReferenceProcessors::new(&[
+            kind(ReferenceStrength::Soft, 2),
+            kind(ReferenceStrength::Weak, 1),
+            kind(ReferenceStrength::Soft, 0),
+            kind(ReferenceStrength::Soft, 2),
+        ]);
+        assert_eq!(processors.ranks(ReferenceStrength::Soft), vec![0, 2]);
+        assert_eq!(processors.ranks(ReferenceStrength::Weak), vec![1]);
+        assert!(processors.ranks(ReferenceStrength::Phantom).is_empty());
+        let kinds: Vec<ReferenceKind> = processors
+            .with_strength_and_rank(ReferenceStrength::Soft, 2)
+            .map(|processor| processor.kind)
+            .collect();
+        assert_eq!(kinds, vec![ReferenceKind(0), ReferenceKind(3)]);
+    }
+
+    #[test]
+    fn consecutive_references_use_all_shards() {
+        let shards: HashSet<usize> = (0..NUM_REFERENCE_SHARDS)
+            .map(|i| shard_of(object(i)))
+            .collect();
+        assert_eq!(shards.len(), NUM_REFERENCE_SHARDS);
+    }
+
+    #[test]
+    fn distribute_candidates_to_nursery_tables() {
+        let processor = processor();
+        for i in 0..NUM_REFERENCE_SHARDS * 2 {
+            processor.add_candidate(object(i));
+        }
+        // A candidate added twice is only processed once.
+        processor.add_candidate(object(0));
+
+        let shards = processor.distribute(true);
+        assert_eq!(shards, (0..NUM_REFERENCE_SHARDS).collect::<Vec<_>>());
+        for shard in 0..NUM_REFERENCE_SHARDS {
+            assert_eq!(nursery_len(&processor, shard), 2);
+            assert_eq!(mature_len(&processor, shard), 0);
+        }
+        assert!(processor.new_candidates.is_empty());
+    }
+
+    #[test]
+    fn nursery_distribution_skips_mature_tables() {
+        let processor = processor();
+        let reff = object(0);
+        let shard = shard_of(reff);
+        processor.shards[shard].lock().unwrap().keep(shard, reff);
+
+        assert!(processor.distribute(true).is_empty());
+        assert_eq!(processor.distribute(false), vec![shard]);
+
+        // Adding a mature reference again does not make it young.
+        processor.add_candidate(reff);
+        assert!(processor.distribute(true).is_empty());
+        assert_eq!(nursery_len(&processor, shard), 0);
+        assert_eq!(mature_len(&processor, shard), 1);
+    }
+
+    #[test]
+    fn moved_references_migrate_to_their_shards() {
+        let processor = processor();
+        let old = object(0);
+        let new = object(1);
+        let old_shard = shard_of(old);
+        let new_shard = shard_of(new);
+        assert_ne!(old_shard, new_shard);
+
+        processor.add_candidate(old);
+        processor.distribute(true);
+        {
+            // Scanning the old shard finds that the reference has moved.
+            let mut sync = processor.shards[old_shard].lock().unwrap();
+            sync.nursery_references.remove(&old);
+            sync.keep(old_shard, new);
+            assert_eq!(sync.migrants, vec![new]);
+        }
+
+        assert_eq!(processor.distribute(false), vec![new_shard]);
+        assert_eq!(mature_len(&processor, old_shard), 0);
+        assert_eq!(mature_len(&processor, new_shard), 1);
+        assert!(processor.shards[old_shard]
+            .lock()
+            .unwrap()
+            .migrants
+            .is_empty());
+    }
+}
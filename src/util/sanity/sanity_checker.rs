@@ -159,6 +159,7 @@ impl<P: Plan> GCWork<P::VM> for SanityClosure<P> {
         for roots in &sanity_checker.root_nodes {
             queue.extend(roots);
         }
+        queue.extend(mmtk.handle_table.strong_objects());
 
         let tls = worker.tls;
 
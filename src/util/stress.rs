@@ -0,0 +1,225 @@
+//! Deterministic GC stress testing.
+//!
+//! With a stress seed, the points at which stress GCs are triggered, the order in which each GC
+//! worker executes work packets (with `randomize_gc_work_order`) and the defragmentation decisions
+//! of Immix (with `stress_random_defrag`) are drawn from pseudo-random sequences derived from the
+//! seed.  Together with `deterministic_gc_work`, which executes all work packets on one GC worker,
+//! a failing run of a deterministic program can be replayed by setting `stress_seed` to the seed
+//! printed when a GC worker panics.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::util::options::{Options, DEFAULT_STRESS_FACTOR};
+
+/// The streams of pseudo-random numbers derived from a stress seed.  Each stream is independent so
+/// that the decisions of one kind do not shift the decisions of another kind.
+const STREAM_STRESS_GC: u64 = 1;
+const STREAM_DEFRAG: u64 = 2;
+const STREAM_WORK_ORDER: u64 = 3;
+
+/// The SplitMix64 mixing function.  It maps each input to a well-distributed output.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A small, seeded pseudo-random number generator (SplitMix64).  The same seed always produces
+/// the same sequence on every platform.
+#[derive(Clone, Debug)]
+pub(crate) struct StressRng {
+    state: u64,
+}
+
+impl StressRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Create the generator of an independent stream derived from `seed`.
+    fn for_stream(seed: u64, stream: u64) -> Self {
+        Self::new(mix(seed ^ mix(stream)))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let z = mix(self.state);
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z
+    }
+
+    /// Return a number in `0..bound`.  `bound` must not be zero.
+    pub fn next_below(&mut self, bound: usize) -> usize {
+        debug_assert_ne!(bound, 0);
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
+
+/// The seeded stress testing state of an MMTk instance.
+pub(crate) struct GCStress {
+    /// The stress seed, or `None` if stress testing is not seeded.
+    seed: Option<u64>,
+    /// Whether `randomize_gc_work_order` is set.
+    randomize_work_order: bool,
+    /// Whether `stress_random_defrag` is set.
+    random_defrag: bool,
+    /// The generator for the intervals between stress GCs.
+    stress_gc_rng: Mutex<StressRng>,
+    /// The generator for defragmentation decisions.
+    defrag_rng: Mutex<StressRng>,
+    /// The number of allocated bytes that triggers the next stress GC if the seed is set.
+    next_stress_gc_bytes: AtomicUsize,
+}
+
+impl Default for GCStress {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            randomize_work_order: false,
+            random_defrag: false,
+            stress_gc_rng: Mutex::new(StressRng::new(0)),
+            defrag_rng: Mutex::new(StressRng::new(0)),
+            next_stress_gc_bytes: AtomicUsize::new(DEFAULT_STRESS_FACTOR),
+        }
+    }
+}
+
+impl GCStress {
+    pub fn new(options: &Options) -> Self {
+        let randomize_work_order = *options.randomize_gc_work_order;
+        let random_defrag = *options.stress_random_defrag;
+        let seed = if *options.stress_seed != 0 {
+            Some(*options.stress_seed)
+        } else if randomize_work_order || random_defrag {
+            // Pick a seed so that the run can be replayed.
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64);
+            Some(mix(nanos).max(1))
+        } else {
+            None
+        };
+        let Some(seed) = seed else {
+            return Self::default();
+        };
+        info!("GC stress seed: {}", seed);
+
+        let stress = Self {
+            seed: Some(seed),
+            randomize_work_order,
+            random_defrag,
+            stress_gc_rng: Mutex::new(StressRng::for_stream(seed, STREAM_STRESS_GC)),
+            defrag_rng: Mutex::new(StressRng::for_stream(seed, STREAM_DEFRAG)),
+            next_stress_gc_bytes: AtomicUsize::new(DEFAULT_STRESS_FACTOR),
+        };
+        stress.schedule_next_stress_gc(*options.stress_factor);
+        stress
+    }
+
+    /// The stress seed, or `None` if stress testing is not seeded.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The number of allocated bytes that triggers the next stress GC.
+    pub fn stress_gc_bytes(&self, stress_factor: usize) -> usize {
+        if self.seed.is_some() {
+            self.next_stress_gc_bytes.load(Ordering::Relaxed)
+        } else {
+            stress_factor
+        }
+    }
+
+    /// Draw the number of allocated bytes until the next stress GC, between 1 and twice
+    /// `stress_factor`.  `BasePlan::collection_required` calls this whenever it triggers a stress
+    /// GC and resets `allocation_bytes`, so each interval is the next number in the sequence.
+    pub fn schedule_next_stress_gc(&self, stress_factor: usize) {
+        if self.seed.is_none() || stress_factor == DEFAULT_STRESS_FACTOR {
+            return;
+        }
+        let bound = stress_factor.saturating_mul(2).max(1);
+        let bytes = 1 + self.stress_gc_rng.lock().unwrap().next_below(bound);
+        trace!("Next stress GC after {} bytes", bytes);
+        self.next_stress_gc_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Create the generator that orders the work packets of the GC worker `ordinal`, or `None` if
+    /// work packets should not be randomized.
+    pub fn work_order_rng(&self, ordinal: usize) -> Option<StressRng> {
+        let seed = self.seed?;
+        self.randomize_work_order
+            .then(|| StressRng::for_stream(seed, STREAM_WORK_ORDER + ordinal as u64))
+    }
+
+    /// Decide whether the current GC should defragment.  Return `None` if the decision should not
+    /// be randomized.
+    pub fn random_defrag(&self) -> Option<bool> {
+        self.random_defrag
+            .then(|| self.defrag_rng.lock().unwrap().next_bool())
+    }
+
+    /// Draw a salt for choosing defragmentation sources in the current GC with
+    /// [`is_random_defrag_source`].  Return `None` if the choice should not be randomized.
+    pub fn random_defrag_source_salt(&self) -> Option<u64> {
+        self.random_defrag
+            .then(|| self.defrag_rng.lock().unwrap().next_u64())
+    }
+}
+
+/// Decide whether the block at `block_start` is a defragmentation source.  The decision only
+/// depends on the salt and the address, so it does not depend on which GC worker makes it.
+pub(crate) fn is_random_defrag_source(salt: u64, block_start: usize) -> bool {
+    mix(salt ^ block_start as u64) & 1 == 1
+}
+
+/// Print the stress seed if the current thread panics while this is alive.
+pub(crate) struct ReportSeedOnPanic(pub u64);
+
+impl Drop for ReportSeedOnPanic {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            eprintln!(
+                "GC stress seed: {}.  Set the option stress_seed={} to replay.",
+                self.0, self.0
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = StressRng::for_stream(42, STREAM_STRESS_GC);
+        let mut b = StressRng::for_stream(42, STREAM_STRESS_GC);
+        let mut c = StressRng::for_stream(42, STREAM_DEFRAG);
+        let seq_a = (0..16).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let seq_b = (0..16).map(|_| b.next_u64()).collect::<Vec<_>>();
+        let seq_c = (0..16).map(|_| c.next_u64()).collect::<Vec<_>>();
+        assert_eq!(seq_a, seq_b);
+        assert_ne!(seq_a, seq_c);
+    }
+
+    #[test]
+    fn stress_gc_bytes_are_bounded() {
+        let mut options = Options::default();
+        assert!(options.set_from_string("stress_factor", "4096"));
+        assert!(options.set_from_string("stress_seed", "7"));
+        let stress = GCStress::new(&options);
+        assert_eq!(stress.seed(), Some(7));
+        for _ in 0..100 {
+            let bytes = stress.stress_gc_bytes(4096);
+            assert!((1..=8192).contains(&bytes));
+            stress.schedule_next_stress_gc(4096);
+        }
+        // Without a seed, stress GCs happen every `stress_factor` bytes.
+        assert_eq!(GCStress::default().stress_gc_bytes(4096), 4096);
+    }
+}
@@ -0,0 +1,383 @@
+//! A mock heap for tests that run real GCs with [`MockVM`].
+//!
+//! Objects in the mock heap are arrays of reference fields.  The fields are followed by hidden
+//! fields, which are not reported by `Scanning::scan_object`, and can be used to hold the
+//! referents of reference objects, or the keys and the values of ephemerons.  The layout of an
+//! object, from the object start, is:
+//!
+//! | Offset (words) | Content                                                     |
+//! |----------------|-------------------------------------------------------------|
+//! | 0              | Unused. The object reference points to the next word.       |
+//! | 1              | The header.  MMTk may store a forwarding pointer here.      |
+//! | 2              | The number of fields.                                       |
+//! | 3              | The number of hidden fields.                                |
+//! | 4..            | The fields, and then the hidden fields.                     |
+//!
+//! The referent of a reference object is its hidden field 0.  The key and the value of an
+//! ephemeron are its hidden fields 0 and 1.
+//!
+//! A test sets up the `MockVM` with [`mock_gc_setup`] (and may override some methods), and then
+//! creates a [`MockGC`], which spawns GC worker threads and binds a mutator.  Objects are kept
+//! alive by strong handles, see [`MockGC::root`].
+
+// Not all tests use all the functions in this module.
+#![allow(dead_code)]
+
+use std::any::Any;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use crate::memory_manager;
+use crate::util::constants::BYTES_IN_WORD;
+use crate::util::handle_table::GlobalHandle;
+use crate::util::options::GCTriggerSelector;
+use crate::util::test_util::mock_method::*;
+use crate::util::test_util::mock_vm::*;
+use crate::util::{Address, ObjectReference, OpaquePointer};
+use crate::util::{VMMutatorThread, VMThread, VMWorkerThread};
+use crate::vm::{GCThreadContext, ObjectModel};
+use crate::{AllocationSemantics, MMTKBuilder, Mutator, MMTK};
+
+const NUM_FIELDS_OFFSET: usize = BYTES_IN_WORD;
+const NUM_HIDDEN_FIELDS_OFFSET: usize = 2 * BYTES_IN_WORD;
+const FIELDS_OFFSET: usize = 3 * BYTES_IN_WORD;
+
+/// The default heap size for [`MockGC`].
+pub const DEFAULT_HEAP_SIZE: usize = 16 * 1024 * 1024;
+
+/// How long we wait for a GC before we consider the test failed.
+const GC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The size in bytes of an object with the given numbers of fields and hidden fields.
+pub fn object_size(fields: usize, hidden_fields: usize) -> usize {
+    DEFAULT_OBJECT_REF_OFFSET + FIELDS_OFFSET + (fields + hidden_fields) * BYTES_IN_WORD
+}
+
+/// The number of fields of an object, excluding hidden fields.
+pub fn num_fields(object: ObjectReference) -> usize {
+    unsafe { (object.to_raw_address() + NUM_FIELDS_OFFSET).load::<usize>() }
+}
+
+/// The number of hidden fields of an object.
+pub fn num_hidden_fields(object: ObjectReference) -> usize {
+    unsafe { (object.to_raw_address() + NUM_HIDDEN_FIELDS_OFFSET).load::<usize>() }
+}
+
+/// The current size in bytes of an object.
+pub fn size_of_object(object: ObjectReference) -> usize {
+    object_size(num_fields(object), num_hidden_fields(object))
+}
+
+/// The slot of the field `index` of an object.  Hidden fields follow the fields.
+pub fn field_slot(object: ObjectReference, index: usize) -> Address {
+    debug_assert!(index < num_fields(object) + num_hidden_fields(object));
+    object.to_raw_address() + FIELDS_OFFSET + index * BYTES_IN_WORD
+}
+
+/// The slot of the hidden field `index` of an object.
+pub fn hidden_field_slot(object: ObjectReference, index: usize) -> Address {
+    field_slot(object, num_fields(object) + index)
+}
+
+/// Load a reference from a slot.
+pub fn load(slot: Address) -> Option<ObjectReference> {
+    ObjectReference::from_raw_address(unsafe { slot.load::<Address>() })
+}
+
+/// Store a reference (or null if `None`) to a slot.
+pub fn store(slot: Address, object: Option<ObjectReference>) {
+    unsafe { slot.store(object.map_or(Address::ZERO, |o| o.to_raw_address())) }
+}
+
+/// Allocate an object with the given numbers of fields and hidden fields.  All the fields are null.
+pub fn alloc_object(
+    mutator: &mut Mutator<MockVM>,
+    fields: usize,
+    hidden_fields: usize,
+) -> ObjectReference {
+    let size = object_size(fields, hidden_fields);
+    let semantics = AllocationSemantics::Default;
+    let start = memory_manager::alloc(mutator, size, BYTES_IN_WORD, 0, semantics);
+    assert!(!start.is_zero());
+    let object = MockVM::object_start_to_ref(start);
+    unsafe {
+        (object.to_raw_address() + NUM_FIELDS_OFFSET).store(fields);
+        (object.to_raw_address() + NUM_HIDDEN_FIELDS_OFFSET).store(hidden_fields);
+    }
+    for i in 0..fields + hidden_fields {
+        store(field_slot(object, i), None);
+    }
+    memory_manager::post_alloc(mutator, object, size, semantics);
+    object
+}
+
+/// A [`MockAny`] that ignores its arguments, and returns the default value of `R`.  It is used for
+/// the methods that can be called with arguments of any type in real GCs, such as
+/// `Scanning::scan_vm_specific_roots`.
+pub struct IgnoreArgs<R>(PhantomData<R>);
+
+impl<R: Default + 'static> MockAny for IgnoreArgs<R> {
+    fn call_any(&mut self, _args: Box<dyn Any>) -> Box<dyn Any> {
+        Box::new(R::default())
+    }
+}
+
+impl<R> Default for IgnoreArgs<R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// The MMTk instance created by [`MockGC::new`].
+static MMTK_INSTANCE: AtomicPtr<MMTK<MockVM>> = AtomicPtr::new(std::ptr::null_mut());
+/// The number of mutators bound by [`MockGC`].  It is also used for their thread-local storage.
+static NUM_MUTATORS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct MockGCSync {
+    /// The mutators bound by [`MockGC`].
+    mutators: Vec<usize>,
+    /// The number of times mutators are resumed, i.e. the number of finished GC pauses.
+    pauses: usize,
+    /// The references enqueued by `ReferenceGlue::enqueue_references`.
+    enqueued_references: Vec<ObjectReference>,
+}
+
+#[derive(Default)]
+struct MockGCShared {
+    sync: Mutex<MockGCSync>,
+    mutators_resumed: Condvar,
+}
+
+lazy_static! {
+    static ref SHARED: MockGCShared = MockGCShared::default();
+}
+
+fn mmtk() -> &'static MMTK<MockVM> {
+    let mmtk = MMTK_INSTANCE.load(Ordering::SeqCst);
+    assert!(!mmtk.is_null(), "MockGC is not created");
+    unsafe { &*mmtk }
+}
+
+fn mutators() -> Vec<&'static mut Mutator<MockVM>> {
+    let sync = SHARED.sync.lock().unwrap();
+    sync.mutators
+        .iter()
+        .map(|&mutator| unsafe { &mut *(mutator as *mut Mutator<MockVM>) })
+        .collect()
+}
+
+/// Create a `MockVM` that supports real GCs in the mock heap.  A test may override the mock
+/// methods it needs to check, as long as the mock heap still works.
+pub fn mock_gc_setup() -> MockVM {
+    MockVM {
+        number_of_mutators: MockMethod::new_fixed(Box::new(|()| {
+            SHARED.sync.lock().unwrap().mutators.len()
+        })),
+        mutator: MockMethod::new_fixed(Box::new(|tls| {
+            mutators()
+                .into_iter()
+                .find(|mutator| mutator.mutator_tls == tls)
+                .expect("Not a mutator bound by MockGC")
+        })),
+        mutators: MockMethod::new_fixed(Box::new(|()| Box::new(mutators().into_iter()))),
+        stop_all_mutators: MockMethod::new_fixed(Box::new(|(_tls, mut visitor)| {
+            for mutator in mutators() {
+                visitor(mutator);
+            }
+        })),
+        resume_mutators: MockMethod::new_fixed(Box::new(|_tls| {
+            SHARED.sync.lock().unwrap().pauses += 1;
+            SHARED.mutators_resumed.notify_all();
+        })),
+        // Block until the requested GC has finished, like a real binding.
+        block_for_gc: MockMethod::new_fixed(Box::new(|_tls| {
+            let mmtk = mmtk();
+            let sync = SHARED.sync.lock().unwrap();
+            let (_sync, timeout) = SHARED
+                .mutators_resumed
+                .wait_timeout_while(sync, GC_TIMEOUT, |_| {
+                    mmtk.gc_trigger.is_gc_requested() || mmtk.gc_in_progress()
+                })
+                .unwrap();
+            assert!(!timeout.timed_out(), "Timed out waiting for GC");
+        })),
+        spawn_gc_thread: MockMethod::new_fixed(Box::new(|(_tls, context)| {
+            let GCThreadContext::Worker(worker) = context;
+            let mmtk = mmtk();
+            std::thread::spawn(move || {
+                // Use the address of the worker as the thread-local storage of the GC thread.
+                let tls = VMWorkerThread(VMThread(OpaquePointer::from_address(Address::from_ref(
+                    &*worker,
+                ))));
+                memory_manager::start_worker(mmtk, tls, worker);
+            });
+        })),
+        copy_object: MockMethod::new_fixed(Box::new(|(from, semantics, copy_context)| {
+            let bytes = size_of_object(from);
+            let to_start = copy_context.alloc_copy(from, bytes, BYTES_IN_WORD, 0, semantics);
+            let from_start = MockVM::ref_to_object_start(from);
+            unsafe {
+                std::ptr::copy_nonoverlapping::<u8>(
+                    from_start.to_ptr(),
+                    to_start.to_mut_ptr(),
+                    bytes,
+                );
+            }
+            let to = MockVM::object_start_to_ref(to_start);
+            copy_context.post_copy(to, bytes, semantics);
+            to
+        })),
+        get_object_size: MockMethod::new_fixed(Box::new(size_of_object)),
+        get_object_size_when_copied: MockMethod::new_fixed(Box::new(size_of_object)),
+        get_object_reference_when_copied_to: MockMethod::new_fixed(Box::new(|(_from, to)| {
+            MockVM::object_start_to_ref(to)
+        })),
+        dump_object: MockMethod::new_default(),
+        weakref_clear_referent: MockMethod::new_fixed(Box::new(|reference| {
+            store(hidden_field_slot(reference, 0), None)
+        })),
+        weakref_get_referent: MockMethod::new_fixed(Box::new(|reference| {
+            load(hidden_field_slot(reference, 0))
+        })),
+        weakref_set_referent: MockMethod::new_fixed(Box::new(|(reference, referent)| {
+            store(hidden_field_slot(reference, 0), Some(referent))
+        })),
+        weakref_enqueue_references: MockMethod::new_fixed(Box::new(|(references, _tls)| {
+            let mut sync = SHARED.sync.lock().unwrap();
+            sync.enqueued_references.extend_from_slice(references);
+        })),
+        ephemeron_get_key: MockMethod::new_fixed(Box::new(|e| load(hidden_field_slot(e, 0)))),
+        ephemeron_get_value: MockMethod::new_fixed(Box::new(|e| load(hidden_field_slot(e, 1)))),
+        ephemeron_set_key: MockMethod::new_fixed(Box::new(|(e, key)| {
+            store(hidden_field_slot(e, 0), Some(key))
+        })),
+        ephemeron_set_value: MockMethod::new_fixed(Box::new(|(e, value)| {
+            store(hidden_field_slot(e, 1), Some(value))
+        })),
+        ephemeron_clear: MockMethod::new_fixed(Box::new(|e| {
+            store(hidden_field_slot(e, 0), None);
+            store(hidden_field_slot(e, 1), None);
+        })),
+        scan_object: MockMethod::new_fixed(Box::new(|(_tls, object, slot_visitor)| {
+            for i in 0..num_fields(object) {
+                slot_visitor.visit_slot(field_slot(object, i));
+            }
+        })),
+        scan_roots_in_mutator_thread: Box::<IgnoreArgs<()>>::default(),
+        scan_vm_specific_roots: Box::<IgnoreArgs<()>>::default(),
+        notify_initial_thread_scan_complete: MockMethod::new_default(),
+        prepare_for_roots_re_scanning: MockMethod::new_default(),
+        process_weak_refs: Box::<IgnoreArgs<bool>>::default(),
+        forward_weak_refs: Box::<IgnoreArgs<()>>::default(),
+        ..MockVM::default()
+    }
+}
+
+/// An MMTk instance that runs real GCs in the mock heap, with a bound mutator.  The `MockVM` must
+/// have been set up with [`mock_gc_setup`].  Only one `MockGC` can be created in a process.
+pub struct MockGC {
+    mmtk: &'static MMTK<MockVM>,
+    pub mutator: Box<Mutator<MockVM>>,
+}
+
+impl MockGC {
+    /// Create an MMTk instance with a heap of [`DEFAULT_HEAP_SIZE`], and the options set by
+    /// `with_builder`.  Then initialize collection, and bind a mutator.
+    pub fn new<F>(with_builder: F) -> Self
+    where
+        F: FnOnce(&mut MMTKBuilder),
+    {
+        let mut builder = MMTKBuilder::new();
+        builder
+            .options
+            .gc_trigger
+            .set(GCTriggerSelector::FixedHeapSize(DEFAULT_HEAP_SIZE));
+        with_builder(&mut builder);
+        let mmtk: &'static MMTK<MockVM> = Box::leak(memory_manager::mmtk_init(&builder));
+        let old = MMTK_INSTANCE.swap(mmtk as *const _ as *mut _, Ordering::SeqCst);
+        assert!(old.is_null(), "Only one MockGC can be created in a process");
+        memory_manager::initialize_collection(mmtk, VMThread::UNINITIALIZED);
+        let mutator = Self::bind_mutator_for(mmtk);
+        Self { mmtk, mutator }
+    }
+
+    fn bind_mutator_for(mmtk: &'static MMTK<MockVM>) -> Box<Mutator<MockVM>> {
+        // Each mutator has a distinct thread-local storage, so that `ActivePlan::mutator` works.
+        let id = NUM_MUTATORS.fetch_add(1, Ordering::SeqCst) + 1;
+        let tls = VMMutatorThread(VMThread(OpaquePointer::from_address(unsafe {
+            Address::from_usize(id)
+        })));
+        let mut mutator = memory_manager::bind_mutator(mmtk, tls);
+        let mut sync = SHARED.sync.lock().unwrap();
+        sync.mutators
+            .push(&mut *mutator as *mut Mutator<MockVM> as usize);
+        mutator
+    }
+
+    pub fn mmtk(&self) -> &'static MMTK<MockVM> {
+        self.mmtk
+    }
+
+    /// Bind another mutator.  MMTk visits it in every GC, so it must not be dropped.
+    pub fn bind_mutator(&self) -> Box<Mutator<MockVM>> {
+        Self::bind_mutator_for(self.mmtk)
+    }
+
+    /// Allocate an object with the mutator.  See [`alloc_object`].
+    pub fn alloc(&mut self, fields: usize, hidden_fields: usize) -> ObjectReference {
+        alloc_object(&mut self.mutator, fields, hidden_fields)
+    }
+
+    /// Keep an object alive with a strong handle.
+    pub fn root(&self, object: ObjectReference) -> GlobalHandle {
+        memory_manager::create_strong_handle(self.mmtk, object)
+    }
+
+    /// Get the object that a handle refers to.
+    pub fn resolve(&mut self, handle: GlobalHandle) -> Option<ObjectReference> {
+        memory_manager::resolve_handle(self.mmtk, &mut self.mutator, handle)
+    }
+
+    /// Trigger a GC with the mutator, and wait until it finishes.  For generational plans, this is
+    /// usually a nursery GC.
+    pub fn collect(&mut self) {
+        let pauses = pauses();
+        assert!(memory_manager::handle_user_collection_request(
+            self.mmtk,
+            self.mutator.mutator_tls
+        ));
+        wait_for_pauses(pauses + 1);
+    }
+
+    /// Trigger a full-heap GC with the mutator, and wait until it finishes.
+    pub fn collect_full_heap(&mut self) {
+        let pauses = pauses();
+        assert!(self
+            .mmtk
+            .handle_user_collection_request(self.mutator.mutator_tls, true, true));
+        wait_for_pauses(pauses + 1);
+    }
+}
+
+/// Take the references enqueued by `ReferenceGlue::enqueue_references` so far.
+pub fn take_enqueued_references() -> Vec<ObjectReference> {
+    std::mem::take(&mut SHARED.sync.lock().unwrap().enqueued_references)
+}
+
+/// The number of GC pauses that have finished, i.e. the number of times mutators are resumed.
+pub fn pauses() -> usize {
+    SHARED.sync.lock().unwrap().pauses
+}
+
+/// Wait until `pauses` GC pauses have finished.
+pub fn wait_for_pauses(pauses: usize) {
+    let sync = SHARED.sync.lock().unwrap();
+    let (_sync, timeout) = SHARED
+        .mutators_resumed
+        .wait_timeout_while(sync, GC_TIMEOUT, |sync| sync.pauses < pauses)
+        .unwrap();
+    assert!(!timeout.timed_out(), "Timed out waiting for GC");
+}
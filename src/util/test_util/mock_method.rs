@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::sync::Arc;
 
 /// `MockAny` hides any type information. It is useful when we want to create
 /// a mock method for methods with generic type parameters.
@@ -37,22 +38,25 @@ pub enum MockImpl<I, R> {
 /// The function pointer for the mock closure.
 pub type MockClosureSignature<I, R> = Box<dyn Fn(I) -> R + Send + Sync>;
 
+/// The closure returned by [`MockMethod::begin_call`].
+pub type MockClosureRef<I, R> = Arc<dyn Fn(I) -> R + Send + Sync>;
+
 /// The function pointer for the closure, and some metadata.
 pub struct MockClosure<I, R> {
-    closure: MockClosureSignature<I, R>,
+    closure: MockClosureRef<I, R>,
     call_count: usize,
 }
 
 impl<I, R> MockClosure<I, R> {
     fn new(closure: MockClosureSignature<I, R>) -> Self {
         Self {
-            closure,
+            closure: Arc::from(closure),
             call_count: 0,
         }
     }
-    fn call(&mut self, args: I) -> R {
+    fn begin_call(&mut self) -> MockClosureRef<I, R> {
         self.call_count += 1;
-        (self.closure)(args)
+        self.closure.clone()
     }
 }
 
@@ -96,14 +100,21 @@ impl<I, R> MockMethod<I, R> {
 
     /// Call the mock method.
     pub fn call(&mut self, args: I) -> R {
+        (self.begin_call())(args)
+    }
+
+    /// Count a call to the mock method, and return the closure to call.  Unlike
+    /// [`MockMethod::call`], the caller can release the lock that protects the mock method before
+    /// calling the closure, so that the closure can call other mock methods.
+    pub fn begin_call(&mut self) -> MockClosureRef<I, R> {
         let cur_call = self.call_count();
 
         match &mut self.imp {
             MockImpl::Sequence(closures) => {
                 let len = closures.len();
-                closures[cur_call % len].call(args)
+                closures[cur_call % len].begin_call()
             }
-            MockImpl::Fixed(closure) => closure.call(args),
+            MockImpl::Fixed(closure) => closure.begin_call(),
         }
     }
 
@@ -6,6 +6,7 @@ use crate::plan::tracing::gc_work::DefaultObjectTracerContext;
 use crate::plan::tracing::UnsupportedTrace;
 use crate::plan::ObjectQueue;
 use crate::scheduler::*;
+use crate::util::accounting_context::AccountingContext;
 use crate::util::alloc::AllocationError;
 use crate::util::copy::*;
 use crate::util::heap::gc_trigger::GCTriggerPolicy;
@@ -13,6 +14,7 @@ use crate::util::opaque_pointer::*;
 use crate::util::{Address, ObjectReference};
 use crate::vm::object_model::specs::*;
 use crate::vm::GCThreadContext;
+use crate::vm::GCWorkerEvent;
 use crate::vm::ObjectTracer;
 use crate::vm::ObjectTracerContext;
 use crate::vm::RootsWorkFactory;
@@ -53,12 +55,14 @@ macro_rules! lifetime {
     };
 }
 
-/// Call `MockMethod`.
+/// Call `MockMethod`.  The closure is called without holding the lock of the `MockVM`, so it can
+/// call other mock methods, e.g. when a slot visitor traces objects during `scan_object`.
 macro_rules! mock {
     ($fn: ident($($arg:expr),*)) => {
         {
             let arg_tuple = ($($arg),*);
-            write_mockvm(|mock| mock.$fn.call(arg_tuple))
+            let closure = write_mockvm(|mock| mock.$fn.begin_call());
+            closure(arg_tuple)
         }
     };
 }
@@ -210,19 +214,24 @@ pub struct MockVM {
         MockMethod<(VMWorkerThread, Box<dyn FnMut(&'static mut Mutator<MockVM>)>), ()>,
     pub resume_mutators: MockMethod<VMWorkerThread, ()>,
     pub block_for_gc: MockMethod<VMMutatorThread, ()>,
+    pub block_for_gc_driver: MockMethod<(VMMutatorThread, &'static mut dyn FnMut()), ()>,
     pub spawn_gc_thread: MockMethod<(VMThread, GCThreadContext<MockVM>), ()>,
     pub out_of_memory: MockMethod<(VMThread, AllocationError), ()>,
+    pub on_accounting_hard_quota_exceeded:
+        MockMethod<(VMMutatorThread, AccountingContext, usize, usize, usize), ()>,
     pub schedule_finalization: MockMethod<VMWorkerThread, ()>,
     pub post_forwarding: MockMethod<VMWorkerThread, ()>,
     pub vm_live_bytes: MockMethod<(), usize>,
     pub is_collection_enabled: MockMethod<(), bool>,
     pub create_gc_trigger: MockMethod<(), Box<dyn GCTriggerPolicy<MockVM>>>,
+    pub on_gc_worker_event: MockMethod<(VMWorkerThread, usize, GCWorkerEvent), ()>,
+    pub report_gc_events_on_workers: MockMethod<(), bool>,
     // object model
     pub copy_object: MockMethod<
         (
             ObjectReference,
             CopySemantics,
-            &'static GCWorkerCopyContext<MockVM>,
+            &'static mut GCWorkerCopyContext<MockVM>,
         ),
         ObjectReference,
     >,
@@ -242,6 +251,12 @@ pub struct MockVM {
     pub weakref_set_referent: MockMethod<(ObjectReference, ObjectReference), ()>,
     pub weakref_get_referent: MockMethod<ObjectReference, Option<ObjectReference>>,
     pub weakref_enqueue_references: MockMethod<(&'static [ObjectReference], VMWorkerThread), ()>,
+    pub weakref_get_soft_reference_timestamp: MockMethod<ObjectReference, u64>,
+    pub ephemeron_get_key: MockMethod<ObjectReference, Option<ObjectReference>>,
+    pub ephemeron_get_value: MockMethod<ObjectReference, Option<ObjectReference>>,
+    pub ephemeron_set_key: MockMethod<(ObjectReference, ObjectReference), ()>,
+    pub ephemeron_set_value: MockMethod<(ObjectReference, ObjectReference), ()>,
+    pub ephemeron_clear: MockMethod<ObjectReference, ()>,
     // scanning
     pub support_slot_enqueuing: MockMethod<(VMWorkerThread, ObjectReference), bool>,
     pub scan_object: MockMethod<
@@ -283,15 +298,19 @@ impl Default for MockVM {
             stop_all_mutators: MockMethod::new_unimplemented(),
             resume_mutators: MockMethod::new_unimplemented(),
             block_for_gc: MockMethod::new_unimplemented(),
+            block_for_gc_driver: MockMethod::new_fixed(Box::new(|(_, wait)| wait())),
             spawn_gc_thread: MockMethod::new_default(),
             out_of_memory: MockMethod::new_fixed(Box::new(|(_, err)| {
                 panic!("Out of memory with {:?}!", err)
             })),
+            on_accounting_hard_quota_exceeded: MockMethod::new_default(),
             schedule_finalization: MockMethod::new_default(),
             post_forwarding: MockMethod::new_default(),
             vm_live_bytes: MockMethod::new_default(),
             is_collection_enabled: MockMethod::new_fixed(Box::new(|_| true)),
             create_gc_trigger: MockMethod::new_unimplemented(),
+            on_gc_worker_event: MockMethod::new_default(),
+            report_gc_events_on_workers: MockMethod::new_fixed(Box::new(|_| false)),
 
             copy_object: MockMethod::new_unimplemented(),
             copy_object_to: MockMethod::new_unimplemented(),
@@ -313,6 +332,12 @@ impl Default for MockVM {
             weakref_get_referent: MockMethod::new_unimplemented(),
             weakref_set_referent: MockMethod::new_unimplemented(),
             weakref_enqueue_references: MockMethod::new_unimplemented(),
+            weakref_get_soft_reference_timestamp: MockMethod::new_unimplemented(),
+            ephemeron_get_key: MockMethod::new_unimplemented(),
+            ephemeron_get_value: MockMethod::new_unimplemented(),
+            ephemeron_set_key: MockMethod::new_unimplemented(),
+            ephemeron_set_value: MockMethod::new_unimplemented(),
+            ephemeron_clear: MockMethod::new_unimplemented(),
 
             support_slot_enqueuing: MockMethod::new_fixed(Box::new(|_| true)),
             scan_object: MockMethod::new_unimplemented(),
@@ -439,6 +464,10 @@ impl crate::vm::Collection<MockVM> for MockVM {
         mock!(block_for_gc(tls))
     }
 
+    fn block_for_gc_driver(tls: VMMutatorThread, wait: &mut dyn FnMut()) {
+        mock!(block_for_gc_driver(tls, lifetime!(wait)))
+    }
+
     fn spawn_gc_thread(tls: VMThread, ctx: GCThreadContext<MockVM>) {
         mock!(spawn_gc_thread(tls, ctx))
     }
@@ -447,6 +476,18 @@ impl crate::vm::Collection<MockVM> for MockVM {
         mock!(out_of_memory(tls, err_kind))
     }
 
+    fn on_accounting_hard_quota_exceeded(
+        tls: VMMutatorThread,
+        context: AccountingContext,
+        usage: usize,
+        bytes: usize,
+        hard_limit: usize,
+    ) {
+        mock!(on_accounting_hard_quota_exceeded(
+            tls, context, usage, bytes, hard_limit
+        ))
+    }
+
     fn schedule_finalization(tls: VMWorkerThread) {
         mock!(schedule_finalization(tls))
     }
@@ -466,20 +507,32 @@ impl crate::vm::Collection<MockVM> for MockVM {
     fn create_gc_trigger() -> Box<dyn GCTriggerPolicy<MockVM>> {
         mock!(create_gc_trigger())
     }
+
+    fn on_gc_worker_event(tls: VMWorkerThread, ordinal: usize, event: GCWorkerEvent) {
+        mock!(on_gc_worker_event(tls, ordinal, event))
+    }
+
+    fn report_gc_events_on_workers() -> bool {
+        mock!(report_gc_events_on_workers())
+    }
 }
 
 impl crate::vm::ObjectModel<MockVM> for MockVM {
-    const GLOBAL_LOG_BIT_SPEC: VMGlobalLogBitSpec = VMGlobalLogBitSpec::in_header(0);
+    // Only the forwarding pointer is in the header, so that the metadata bits do not overlap with
+    // each other, and objects can be copied with their headers in real GCs.
+    const GLOBAL_LOG_BIT_SPEC: VMGlobalLogBitSpec = VMGlobalLogBitSpec::side_first();
     const LOCAL_FORWARDING_POINTER_SPEC: VMLocalForwardingPointerSpec =
         VMLocalForwardingPointerSpec::in_header(0);
     const LOCAL_FORWARDING_BITS_SPEC: VMLocalForwardingBitsSpec =
-        VMLocalForwardingBitsSpec::in_header(0);
-    const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec = VMLocalMarkBitSpec::in_header(0);
+        VMLocalForwardingBitsSpec::side_first();
+    const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec =
+        VMLocalMarkBitSpec::side_after(Self::LOCAL_FORWARDING_BITS_SPEC.as_spec());
     const LOCAL_LOS_MARK_NURSERY_SPEC: VMLocalLOSMarkNurserySpec =
-        VMLocalLOSMarkNurserySpec::in_header(0);
+        VMLocalLOSMarkNurserySpec::side_after(Self::LOCAL_MARK_BIT_SPEC.as_spec());
 
     #[cfg(feature = "object_pinning")]
-    const LOCAL_PINNING_BIT_SPEC: VMLocalPinningBitSpec = VMLocalPinningBitSpec::in_header(0);
+    const LOCAL_PINNING_BIT_SPEC: VMLocalPinningBitSpec =
+        VMLocalPinningBitSpec::side_after(Self::LOCAL_LOS_MARK_NURSERY_SPEC.as_spec());
 
     const OBJECT_REF_OFFSET_LOWER_BOUND: isize = DEFAULT_OBJECT_REF_OFFSET as isize;
 
@@ -549,6 +602,24 @@ impl crate::vm::ReferenceGlue<MockVM> for MockVM {
     fn enqueue_references(references: &[ObjectReference], tls: VMWorkerThread) {
         mock!(weakref_enqueue_references(lifetime!(references), tls))
     }
+    fn get_soft_reference_timestamp(reference: ObjectReference) -> u64 {
+        mock!(weakref_get_soft_reference_timestamp(reference))
+    }
+    fn get_ephemeron_key(ephemeron: ObjectReference) -> Option<ObjectReference> {
+        mock!(ephemeron_get_key(ephemeron))
+    }
+    fn get_ephemeron_value(ephemeron: ObjectReference) -> Option<ObjectReference> {
+        mock!(ephemeron_get_value(ephemeron))
+    }
+    fn set_ephemeron_key(ephemeron: ObjectReference, key: ObjectReference) {
+        mock!(ephemeron_set_key(ephemeron, key))
+    }
+    fn set_ephemeron_value(ephemeron: ObjectReference, value: ObjectReference) {
+        mock!(ephemeron_set_value(ephemeron, value))
+    }
+    fn clear_ephemeron(ephemeron: ObjectReference) {
+        mock!(ephemeron_clear(ephemeron))
+    }
 }
 
 impl crate::vm::Scanning<MockVM> for MockVM {
@@ -9,6 +9,8 @@ use std::time::Duration;
 #[cfg(feature = "mock_test")]
 pub mod fixtures;
 #[cfg(feature = "mock_test")]
+pub mod mock_gc;
+#[cfg(feature = "mock_test")]
 pub mod mock_method;
 #[cfg(feature = "mock_test")]
 pub mod mock_vm;
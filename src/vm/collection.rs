@@ -1,3 +1,4 @@
+use crate::util::accounting_context::AccountingContext;
 use crate::util::alloc::AllocationError;
 use crate::util::heap::gc_trigger::GCTriggerPolicy;
 use crate::util::opaque_pointer::*;
@@ -11,6 +12,47 @@ pub enum GCThreadContext<VM: VMBinding> {
     Worker(Box<GCWorker<VM>>),
 }
 
+/// An event in the life cycle of a GC worker.  It is reported to the binding with
+/// [`Collection::on_gc_worker_event`] on the thread of the worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GCWorkerEvent {
+    /// The worker thread started, and is about to execute work packets.  This is reported in
+    /// [`GCWorker::run`] after the thread affinity has been set.  It is not reported for the
+    /// worker driven by mutators if the option `mutator_driven_gc` is set.
+    Started,
+    /// The worker is about to execute work packets of a GC.  It is reported before the worker
+    /// executes any work packet of the GC.  Workers that stay inactive during the GC do not report
+    /// it.  It is only reported if [`Collection::report_gc_events_on_workers`] returns true.
+    GCStarted,
+    /// The worker has executed all of its work packets of a GC.  It is reported by each worker
+    /// that reported [`GCWorkerEvent::GCStarted`] for the GC, after all work packets of the GC
+    /// have been executed, and before mutators are resumed.  It is only reported if
+    /// [`Collection::report_gc_events_on_workers`] returns true.
+    GCFinished,
+    /// The worker found no work packets to execute, and is about to park.  It may not block
+    /// waiting if it is the last parked worker, and it finds more work to do.  It is not reported
+    /// for the worker driven by mutators.
+    Parked,
+    /// The worker resumed after it parked.
+    Unparked,
+    /// The worker thread is about to exit.  [`GCWorker::run`] will return after this event.
+    Exiting(GCWorkerExitReason),
+}
+
+/// The reason why a GC worker thread exits.  See [`GCWorkerEvent::Exiting`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GCWorkerExitReason {
+    /// The VM is about to call `fork()`.  See [`crate::mmtk::MMTK::prepare_to_fork`].  The worker
+    /// will be spawned again in [`crate::mmtk::MMTK::after_fork`].
+    Fork,
+    /// MMTk is shutting down.  See [`crate::mmtk::MMTK::shutdown`].
+    Shutdown,
+    /// The number of GC workers is being changed with
+    /// [`crate::memory_manager::set_num_of_workers`].  All worker threads exit, and the workers
+    /// that remain in the worker pool are spawned again.
+    Resize,
+}
+
 /// VM-specific methods for garbage collection.
 pub trait Collection<VM: VMBinding> {
     /// Stop all the mutator threads. MMTk calls this method when it requires all the mutator to yield for a pause.
@@ -39,15 +81,37 @@ pub trait Collection<VM: VMBinding> {
     /// is going to happen. Then MMTk starts a GC. For a stop-the-world GC, MMTk will then call `stop_all_mutators()`
     /// before the GC, and call `resume_mutators()` after the GC.
     ///
+    /// If the option `mutator_driven_gc` is set, MMTk does not call this method.  Instead, the current thread runs the GC
+    /// itself as a GC worker (see [`crate::memory_manager::run_gc_to_completion`]).  In that case, `stop_all_mutators()`
+    /// and `resume_mutators()` are called on the current thread with a `VMWorkerThread` that wraps the `tls` of the
+    /// mutator, and the binding should not wait for the current thread to stop.
+    ///
     /// Arguments:
     /// * `tls`: The current thread pointer that should be blocked. The VM can optionally check if the current thread matches `tls`.
     fn block_for_gc(tls: VMMutatorThread);
 
+    /// Block the current mutator while another mutator is running a GC.  This is only called if
+    /// the option `mutator_driven_gc` is set, when the mutator has to wait for a GC (see
+    /// [`crate::memory_manager::run_gc_to_completion`]) but another mutator is running it.  The
+    /// binding must call `wait`, which returns when the other mutator returns from the GC.  Like in
+    /// `block_for_gc()`, the binding should make the current thread count as stopped for
+    /// `stop_all_mutators()` while `wait` is blocking.  After this method returns, the current
+    /// thread may run a GC itself.
+    ///
+    /// Arguments:
+    /// * `tls`: The current thread pointer that should be blocked.
+    /// * `wait`: Blocks until the other mutator returns from the GC.
+    fn block_for_gc_driver(_tls: VMMutatorThread, wait: &mut dyn FnMut()) {
+        wait();
+    }
+
     /// Ask the VM to spawn a GC thread for MMTk. A GC thread may later call into the VM through these VM traits. Some VMs
     /// have assumptions that those calls needs to be within VM internal threads.
     /// As a result, MMTk does not spawn GC threads itself to avoid breaking this kind of assumptions.
     /// MMTk calls this method to spawn GC threads during [`crate::mmtk::MMTK::initialize_collection`]
-    /// and [`crate::mmtk::MMTK::after_fork`].
+    /// and [`crate::mmtk::MMTK::after_fork`], and when the number of workers is changed with
+    /// [`crate::memory_manager::set_num_of_workers`].  If the option `mutator_driven_gc` is set, MMTk spawns one worker thread
+    /// fewer than the `threads` option, because mutators run GC work as the first worker.
     ///
     /// Arguments:
     /// * `tls`: The thread pointer for the parent thread that we spawn new threads from. This is the same `tls` when the VM
@@ -116,6 +180,51 @@ pub trait Collection<VM: VMBinding> {
         panic!("Out of memory with {:?}!", err_kind);
     }
 
+    /// Inform the VM that an accounting context has gone beyond its soft quota.  This is only
+    /// called if the option `accounting_contexts` is enabled and a soft limit has been set for the
+    /// context with [`crate::memory_manager::set_accounting_quota`].  It is called in the
+    /// allocation slow path of the mutator whose allocation crossed the limit, after the
+    /// allocation has succeeded.  The binding may use it to throttle the context, or to request a
+    /// GC.  It will not be called again for the same context until a GC brings the usage of the
+    /// context below the soft limit.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the mutator whose allocation crossed the limit.
+    /// * `context`: The accounting context that exceeded its soft quota.
+    /// * `usage`: The current usage of the context in bytes.
+    /// * `soft_limit`: The soft limit of the context in bytes.
+    fn on_accounting_soft_quota_exceeded(
+        _tls: VMMutatorThread,
+        _context: AccountingContext,
+        _usage: usize,
+        _soft_limit: usize,
+    ) {
+    }
+
+    /// Inform the VM that an allocation has failed because it would take its accounting context
+    /// beyond its hard quota.  This is only called if the option `accounting_contexts` is enabled
+    /// and a hard limit has been set for the context with
+    /// [`crate::memory_manager::set_accounting_quota`].  It is called in the allocation slow path
+    /// of the allocating mutator, before the allocation returns a null address.  No GC is
+    /// triggered.  Unlike [`Collection::out_of_memory`], this does not abort by default, because
+    /// other contexts can still allocate.  The binding may use it to report an error to the
+    /// context, or to request a GC and retry.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the mutator whose allocation failed.
+    /// * `context`: The accounting context that would exceed its hard quota.
+    /// * `usage`: The current usage of the context in bytes.
+    /// * `bytes`: The bytes that the allocation would be charged.
+    /// * `hard_limit`: The hard limit of the context in bytes.
+    fn on_accounting_hard_quota_exceeded(
+        _tls: VMMutatorThread,
+        _context: AccountingContext,
+        _usage: usize,
+        _bytes: usize,
+        _hard_limit: usize,
+    ) {
+    }
+
     /// Inform the VM to schedule finalization threads.
     ///
     /// Arguments:
@@ -203,4 +312,30 @@ pub trait Collection<VM: VMBinding> {
     fn create_gc_trigger() -> Box<dyn GCTriggerPolicy<VM>> {
         unimplemented!()
     }
+
+    /// Inform the VM of an event in the life cycle of a GC worker.  It is always called on the
+    /// thread of the worker, so the binding can use it to name the thread, set its priority,
+    /// attach per-thread profilers, or set up and tear down thread-local VM states.  See
+    /// [`GCWorkerEvent`] for when each event is reported.
+    ///
+    /// This function is called while the worker is not holding any MMTk lock, but it delays the
+    /// worker, and GC-related events delay the GC.  It should return quickly.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the worker.  For the worker driven by mutators, this wraps
+    ///   the `tls` of the mutator thread.
+    /// * `ordinal`: The ordinal of the worker, from 0 to the number of workers minus one.
+    /// * `event`: The event.
+    fn on_gc_worker_event(_tls: VMWorkerThread, _ordinal: usize, _event: GCWorkerEvent) {}
+
+    /// Return true if every GC worker should report [`GCWorkerEvent::GCStarted`] and
+    /// [`GCWorkerEvent::GCFinished`] with [`Collection::on_gc_worker_event`].  Reporting them
+    /// wakes up all workers that take part in a GC at the start and the end of the GC, so they are
+    /// not reported by default.  Other events are always reported.
+    ///
+    /// This is called at the start and the end of each GC, sometimes while MMTk holds a lock of
+    /// the scheduler.  It should return the same value each time, without blocking.
+    fn report_gc_events_on_workers() -> bool {
+        false
+    }
 }
@@ -24,10 +24,15 @@ pub mod slot;
 pub use self::active_plan::ActivePlan;
 pub use self::collection::Collection;
 pub use self::collection::GCThreadContext;
+pub use self::collection::GCWorkerEvent;
+pub use self::collection::GCWorkerExitReason;
 pub use self::object_model::specs::*;
 pub use self::object_model::ObjectModel;
 pub use self::reference_glue::Finalizable;
 pub use self::reference_glue::ReferenceGlue;
+pub use self::reference_glue::ReferenceKind;
+pub use self::reference_glue::ReferenceKindSpec;
+pub use self::reference_glue::ReferenceStrength;
 pub use self::scanning::ObjectTracer;
 pub use self::scanning::ObjectTracerContext;
 pub(crate) use self::scanning::RootsKind;
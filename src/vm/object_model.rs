@@ -1,6 +1,7 @@
 use atomic::Ordering;
 
 use self::specs::*;
+use crate::util::accounting_context::AccountingContext;
 use crate::util::copy::*;
 use crate::util::metadata::header_metadata::HeaderMetadataSpec;
 use crate::util::metadata::MetadataValue;
@@ -476,6 +477,16 @@ pub trait ObjectModel<VM: VMBinding> {
     fn is_object_sane(_object: ObjectReference) -> bool {
         true
     }
+
+    /// Return the accounting context that an object is charged to. This is only called during GC
+    /// if both the options `accounting_contexts` and `count_live_bytes_in_gc` are enabled, in
+    /// order to count live bytes per accounting context. See [`crate::util::accounting_context`].
+    ///
+    /// Arguments:
+    /// * `object`: The object to be queried.
+    fn get_accounting_context(_object: ObjectReference) -> AccountingContext {
+        AccountingContext::DEFAULT
+    }
 }
 
 pub mod specs {
@@ -1,9 +1,10 @@
+use crate::util::options::SoftReferencePolicy;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
 use crate::vm::ObjectTracer;
 use crate::vm::VMBinding;
 
-/// VM-specific methods for reference processing, including weak references, and finalizers.
+/// VM-specific methods for reference processing, including weak references, ephemerons, and finalizers.
 /// We handle weak references and finalizers differently:
 /// * for weak references, we assume they are implemented as normal reference objects (also known as weak objects)
 ///   with a referent that is actually weakly reachable. This trait provides a few methods to access
@@ -48,6 +49,62 @@ pub trait ReferenceGlue<VM: VMBinding> {
     /// the references slice will be cleared after this call is returned. That means
     /// MMTk will no longer keep these references alive once this method is returned.
     fn enqueue_references(references: &[ObjectReference], tls: VMWorkerThread);
+
+    /// Get the time when a soft reference was last accessed, i.e. the value of
+    /// [`crate::memory_manager::soft_reference_clock`] when the reference was created or its
+    /// referent was last accessed.  This is only used by the `Lru` soft reference policy (see
+    /// [`crate::util::options::SoftReferencePolicy`]).
+    ///
+    /// The default implementation returns `u64::MAX`, i.e. every soft reference is considered
+    /// recently used, and the `Lru` policy retains the referents of all reachable soft references.
+    ///
+    /// Arguments:
+    /// * `reference`: The soft reference object.
+    fn get_soft_reference_timestamp(_reference: ObjectReference) -> u64 {
+        u64::MAX
+    }
+
+    // The following methods are used for ephemerons.  A binding that registers ephemerons with
+    // `memory_manager::add_ephemeron_candidate` must implement them.  With the default
+    // implementations, ephemerons have no keys, and MMTk drops them without tracing anything.
+
+    /// Get the key of an ephemeron.  The default implementation returns `None`.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The ephemeron object.  `None` if it currently does not have a key.  This may
+    ///   happen if the ephemeron has been cleared.
+    fn get_ephemeron_key(_ephemeron: ObjectReference) -> Option<ObjectReference> {
+        None
+    }
+
+    /// Get the value of an ephemeron.  The default implementation returns `None`.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The ephemeron object.  `None` if it currently does not have a value.
+    fn get_ephemeron_value(_ephemeron: ObjectReference) -> Option<ObjectReference> {
+        None
+    }
+
+    /// Set the key of an ephemeron.  The default implementation does nothing.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The ephemeron object.
+    /// * `key`: The new reference to the key.
+    fn set_ephemeron_key(_ephemeron: ObjectReference, _key: ObjectReference) {}
+
+    /// Set the value of an ephemeron.  The default implementation does nothing.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The ephemeron object.
+    /// * `value`: The new reference to the value.
+    fn set_ephemeron_value(_ephemeron: ObjectReference, _value: ObjectReference) {}
+
+    /// Clear both the key and the value of an ephemeron because its key is dead.  MMTk no longer
+    /// keeps track of the ephemeron after this call.  The default implementation does nothing.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The ephemeron object.
+    fn clear_ephemeron(_ephemeron: ObjectReference) {}
 }
 
 /// A finalizable object for MMTk. MMTk needs to know the actual object reference in the type,
@@ -80,3 +137,84 @@ impl Finalizable for ObjectReference {
         *self = trace.trace_object(*self);
     }
 }
+
+/// Identifies a kind of reference objects.  The built-in kinds are Java's soft, weak and phantom
+/// references.  A binding may register more kinds with
+/// [`crate::MMTKBuilder::register_reference_kind`], and add reference objects of a kind with
+/// [`crate::memory_manager::add_reference_candidate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReferenceKind(pub(crate) usize);
+
+impl ReferenceKind {
+    /// Java's soft references.
+    pub const SOFT: ReferenceKind = ReferenceKind(0);
+    /// Java's weak references.
+    pub const WEAK: ReferenceKind = ReferenceKind(1);
+    /// Java's phantom references.
+    pub const PHANTOM: ReferenceKind = ReferenceKind(2);
+}
+
+/// The strength of a reference kind.  It decides when the references of the kind are processed
+/// during a GC, from the strongest (`Soft`) to the weakest (`Phantom`).  A reference is cleared if
+/// its referent is not reachable by the time its kind is processed.  The kinds of the same strength
+/// are further ordered by [`ReferenceKindSpec::rank`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReferenceStrength {
+    /// Processed first.  The referents of reachable references may be retained, according to the
+    /// soft reference policy.  This is the strength of Java's soft references.
+    Soft,
+    /// Processed after soft references, before finalizable objects are resurrected.  This is the
+    /// strength of Java's weak references.
+    Weak,
+    /// Processed after finalizable objects are resurrected, i.e. a referent reachable from an
+    /// object to be finalized is not cleared until it is finalized.
+    Final,
+    /// Processed last, after finalizable objects are resurrected.  This is the strength of Java's
+    /// phantom references.
+    Phantom,
+}
+
+impl ReferenceStrength {
+    /// All strengths, from the strongest to the weakest.
+    pub const ALL: [ReferenceStrength; 4] = [
+        ReferenceStrength::Soft,
+        ReferenceStrength::Weak,
+        ReferenceStrength::Final,
+        ReferenceStrength::Phantom,
+    ];
+}
+
+/// Describes how the references of a reference kind are processed.
+#[derive(Clone, Debug)]
+pub struct ReferenceKindSpec {
+    /// The name of the kind, used for logging.
+    pub name: &'static str,
+    /// The strength of the kind.
+    pub strength: ReferenceStrength,
+    /// The order of the kind among the kinds of the same strength.  Kinds of a lower rank are
+    /// stronger.  For the `Soft` strength, the referents of a rank are retained, and the
+    /// transitive closure from them is finished, before the references of the next rank are
+    /// processed, so a referent retained only by a weaker rank is cleared for the stronger ranks.
+    /// For the other strengths, which never retain referents, the rank only decides the order in
+    /// which cleared references are enqueued.  Kinds of the same rank are processed together.
+    pub rank: u32,
+    /// The clearing policy for the `Soft` strength.  If `None`, the `soft_reference_policy` option
+    /// is used.  This is ignored for other strengths, which never retain referents.
+    pub soft_reference_policy: Option<SoftReferencePolicy>,
+    /// Called with the references whose referents are cleared in a GC.  If `None`,
+    /// [`ReferenceGlue::enqueue_references`] is called instead.
+    pub enqueue: Option<fn(&[ObjectReference], VMWorkerThread)>,
+}
+
+impl ReferenceKindSpec {
+    /// Create a specification of rank 0 with the default clearing policy and enqueue callback.
+    pub fn new(name: &'static str, strength: ReferenceStrength) -> Self {
+        Self {
+            name,
+            strength,
+            rank: 0,
+            soft_reference_policy: None,
+            enqueue: None,
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::plan::Mutator;
 use crate::scheduler::GCWorker;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
-use crate::vm::slot::Slot;
+use crate::vm::slot::{SimpleSlot, Slot};
 use crate::vm::VMBinding;
 
 /// Callback trait of scanning functions that report slots.
@@ -134,6 +134,22 @@ pub trait RootsWorkFactory<SL: Slot>: Clone + Send + 'static {
     /// Arguments:
     /// * `nodes`: A vector of references to objects pointed by edges from roots.
     fn create_process_tpinning_roots_work(&mut self, nodes: Vec<ObjectReference>);
+
+    /// Create work packets to handle the strong handles in MMTk's [`HandleTable`].  MMTk core
+    /// calls this method when scanning VM-specific roots.  VM bindings do not need to call it.
+    ///
+    /// The work packet may update the slots.  The default implementation loads the slots and
+    /// treats the objects as non-transitively pinning roots, which is correct but prevents those
+    /// objects from moving.
+    ///
+    /// Arguments:
+    /// * `slots`: A vector of slots of strong handles.
+    ///
+    /// [`HandleTable`]: crate::util::handle_table::HandleTable
+    fn create_process_handle_roots_work(&mut self, slots: Vec<SimpleSlot>) {
+        let nodes = slots.iter().flat_map(|slot| slot.load()).collect();
+        self.create_process_pinning_roots_work(nodes);
+    }
 }
 
 /// For USDT tracepoints for roots.
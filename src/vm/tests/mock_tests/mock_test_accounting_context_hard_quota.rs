@@ -0,0 +1,113 @@
+use super::mock_test_prelude::*;
+
+use crate::util::accounting_context::{AccountingContext, AccountingQuota};
+use crate::util::alloc::allocator::AllocationOptions;
+use crate::AllocationSemantics;
+
+/// Allocations of an accounting context that exceed its hard quota fail without triggering a GC
+/// or calling `out_of_memory`, while allocations of other contexts still succeed.
+#[test]
+pub fn accounting_context_hard_quota() {
+    with_mockvm(
+        || -> MockVM {
+            MockVM {
+                on_accounting_hard_quota_exceeded: MockMethod::new_fixed(Box::new(
+                    |(_tls, context, usage, bytes, hard_limit)| {
+                        assert_ne!(context, AccountingContext::DEFAULT);
+                        assert!(usage + bytes > hard_limit);
+                    },
+                )),
+                ..MockVM::default()
+            }
+        },
+        || {
+            const MB: usize = 1024 * 1024;
+            const TENANT: AccountingContext = AccountingContext(1);
+            const SMALL_TENANT: AccountingContext = AccountingContext(2);
+            let mut fixture = MutatorFixture::create_with_builder(|builder| {
+                builder.options.gc_trigger.set(
+                    crate::util::options::GCTriggerSelector::FixedHeapSize(20 * MB),
+                );
+                builder.options.accounting_contexts.set(true);
+            });
+            memory_manager::set_accounting_quota(
+                fixture.mmtk(),
+                TENANT,
+                AccountingQuota {
+                    soft_limit: None,
+                    hard_limit: Some(MB),
+                },
+            );
+
+            // Allocating more than the quota fails.
+            memory_manager::set_mutator_accounting_context(&fixture.mutator, TENANT);
+            let addr = memory_manager::alloc(
+                &mut fixture.mutator,
+                2 * MB,
+                8,
+                0,
+                AllocationSemantics::Default,
+            );
+            assert!(addr.is_zero());
+            read_mockvm(|mock| {
+                assert!(!mock.block_for_gc.is_called());
+                assert!(!mock.out_of_memory.is_called());
+                assert!(mock.on_accounting_hard_quota_exceeded.is_called());
+            });
+            let stats = memory_manager::get_accounting_stats(fixture.mmtk(), TENANT).unwrap();
+            assert_eq!(stats.total_allocated_bytes, 0);
+
+            // The same allocation charged to another context succeeds.
+            let addr = memory_manager::alloc_with_options(
+                &mut fixture.mutator,
+                2 * MB,
+                8,
+                0,
+                AllocationSemantics::Default,
+                AllocationOptions {
+                    accounting_context: AccountingContext::DEFAULT,
+                    ..Default::default()
+                },
+            );
+            assert!(!addr.is_zero());
+            let stats =
+                memory_manager::get_accounting_stats(fixture.mmtk(), AccountingContext::DEFAULT)
+                    .unwrap();
+            assert!(stats.total_allocated_bytes >= 2 * MB);
+
+            // The quota is checked against the bytes that are charged, i.e. whole thread-local
+            // buffers, so small allocations cannot take the context beyond its hard limit.
+            const SMALL_LIMIT: usize = 100 * 1024;
+            memory_manager::set_accounting_quota(
+                fixture.mmtk(),
+                SMALL_TENANT,
+                AccountingQuota {
+                    soft_limit: None,
+                    hard_limit: Some(SMALL_LIMIT),
+                },
+            );
+            let options = AllocationOptions {
+                accounting_context: SMALL_TENANT,
+                ..Default::default()
+            };
+            let mut allocated = 0;
+            while !memory_manager::alloc_with_options(
+                &mut fixture.mutator,
+                64,
+                8,
+                0,
+                AllocationSemantics::Default,
+                options,
+            )
+            .is_zero()
+            {
+                allocated += 64;
+                assert!(allocated <= SMALL_LIMIT);
+            }
+            let stats = memory_manager::get_accounting_stats(fixture.mmtk(), SMALL_TENANT).unwrap();
+            assert!(stats.total_allocated_bytes > 0);
+            assert!(stats.usage() <= SMALL_LIMIT);
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,115 @@
+// GITHUB-CI: MMTK_PLAN=ConcurrentImmix
+
+// With the option `concurrent_marking_assists`, mutators execute packets of the `Concurrent`
+// bucket.  The bucket must not look drained while a mutator holds its packets, or the final mark
+// pause would be triggered before marking finishes.
+
+use super::mock_test_prelude::*;
+use crate::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use crate::util::test_util::mock_gc::*;
+use crate::util::{ObjectReference, VMThread};
+use crate::MMTK;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread::ThreadId;
+
+const LIST_LENGTH: usize = 10000;
+
+lazy_static! {
+    /// The thread of the mutator, and whether the `Concurrent` bucket looked drained when a
+    /// `Probe` was executed on it.
+    static ref PROBES: Mutex<(Option<ThreadId>, Vec<bool>)> = Mutex::new((None, vec![]));
+    /// The thread-local storage of the mutator.
+    static ref MUTATOR_TLS: Mutex<Option<VMThread>> = Mutex::new(None);
+}
+
+/// The number of objects scanned by the mutator while it assists marking.
+static MUTATOR_SCANS: AtomicUsize = AtomicUsize::new(0);
+
+/// A packet that records whether the `Concurrent` bucket is drained while the packet is executed.
+struct Probe;
+
+impl GCWork<MockVM> for Probe {
+    fn do_work(&mut self, _worker: &mut GCWorker<MockVM>, mmtk: &'static MMTK<MockVM>) {
+        let drained = mmtk.scheduler.work_buckets[WorkBucketStage::Concurrent].is_drained();
+        let mut probes = PROBES.lock().unwrap();
+        if probes.0 == Some(std::thread::current().id()) {
+            probes.1.push(drained);
+        }
+    }
+}
+
+fn check_list(head: ObjectReference, length: usize) {
+    let mut node = Some(head);
+    for i in 0..length {
+        let object = node.unwrap();
+        assert_eq!(unsafe { hidden_field_slot(object, 0).load::<usize>() }, i);
+        node = load(field_slot(object, 0));
+    }
+    assert_eq!(node, None);
+}
+
+#[test]
+pub fn concurrent_marking_assists() {
+    with_mockvm(
+        || MockVM {
+            scan_object: MockMethod::new_fixed(Box::new(|(tls, object, slot_visitor)| {
+                if Some(tls.0) == *MUTATOR_TLS.lock().unwrap() {
+                    MUTATOR_SCANS.fetch_add(1, Ordering::Relaxed);
+                }
+                for i in 0..num_fields(object) {
+                    slot_visitor.visit_slot(field_slot(object, i));
+                }
+            })),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.concurrent_marking_assists.set(true);
+                builder.options.threads.set(1);
+                // Let the GC worker fall behind so that the mutator has to assist.
+                builder.options.concurrent_gc_duty_cycle.set(0.01);
+            });
+            let mmtk = gc.mmtk();
+            let tls = gc.mutator.mutator_tls;
+            *MUTATOR_TLS.lock().unwrap() = Some(tls.0);
+
+            // A probe executed by the mutator sees that the bucket is not drained, although the
+            // queue of the bucket is empty.  Probes executed by the GC worker are not recorded.
+            PROBES.lock().unwrap().0 = Some(std::thread::current().id());
+            let bucket = &mmtk.scheduler.work_buckets[WorkBucketStage::Concurrent];
+            bucket.set_enabled(true);
+            bucket.open();
+            while PROBES.lock().unwrap().1.is_empty() {
+                bucket.add(Probe);
+                mmtk.scheduler.assist_concurrent_work(tls, || true);
+            }
+            assert!(PROBES.lock().unwrap().1.iter().all(|&drained| !drained));
+            bucket.set_enabled(false);
+            bucket.close();
+
+            // Real concurrent marking cycles, with the mutator assisting.
+            let mut head = None;
+            for i in (0..LIST_LENGTH).rev() {
+                let node = gc.alloc(1, 1);
+                store(field_slot(node, 0), head);
+                unsafe { hidden_field_slot(node, 0).store(i) };
+                head = Some(node);
+            }
+            let list = gc.root(head.unwrap());
+            // Whether the mutator gets marking work before the GC worker depends on timing, so
+            // keep going for a few more cycles until it has assisted.
+            let pauses_before = pauses();
+            while pauses() < pauses_before + 4 || MUTATOR_SCANS.load(Ordering::Relaxed) == 0 {
+                assert!(
+                    pauses() < pauses_before + 100,
+                    "The mutator never assists marking"
+                );
+                gc.alloc(8, 0);
+            }
+            check_list(gc.resolve(list).unwrap(), LIST_LENGTH);
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,85 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix
+
+// An ephemeron whose key is only reachable from an object to be finalized must not be cleared,
+// and its value must be kept alive, because finalization resurrects the key.  An ephemeron whose
+// key is not resurrected is cleared.
+
+use super::mock_test_prelude::*;
+use crate::util::test_util::mock_gc::*;
+use crate::util::ObjectReference;
+
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref CLEARED: Mutex<Vec<ObjectReference>> = Mutex::new(vec![]);
+}
+
+fn ephemeron_setup() -> MockVM {
+    MockVM {
+        ephemeron_clear: MockMethod::new_fixed(Box::new(|e| {
+            store(hidden_field_slot(e, 0), None);
+            store(hidden_field_slot(e, 1), None);
+            CLEARED.lock().unwrap().push(e);
+        })),
+        ..mock_gc_setup()
+    }
+}
+
+#[test]
+pub fn ephemeron_key_resurrected_by_finalization() {
+    with_mockvm(
+        ephemeron_setup,
+        || {
+            let mut gc = MockGC::new(|_| {});
+            let mmtk = gc.mmtk();
+
+            // An ephemeron whose key is finalizable.  Its value has a child.
+            let ephemeron = gc.alloc(0, 2);
+            let key = gc.alloc(0, 0);
+            let value = gc.alloc(1, 0);
+            let child = gc.alloc(0, 0);
+            store(field_slot(value, 0), Some(child));
+            store(hidden_field_slot(ephemeron, 0), Some(key));
+            store(hidden_field_slot(ephemeron, 1), Some(value));
+            memory_manager::add_finalizer(mmtk, key);
+
+            // An ephemeron whose key simply dies.
+            let dead_ephemeron = gc.alloc(0, 2);
+            let dead_key = gc.alloc(0, 0);
+            let dead_value = gc.alloc(0, 0);
+            store(hidden_field_slot(dead_ephemeron, 0), Some(dead_key));
+            store(hidden_field_slot(dead_ephemeron, 1), Some(dead_value));
+
+            let ephemeron_handle = gc.root(ephemeron);
+            let dead_ephemeron_handle = gc.root(dead_ephemeron);
+            memory_manager::add_ephemeron_candidate(mmtk, ephemeron);
+            memory_manager::add_ephemeron_candidate(mmtk, dead_ephemeron);
+
+            gc.collect();
+
+            let ephemeron = gc.resolve(ephemeron_handle).unwrap();
+            let dead_ephemeron = gc.resolve(dead_ephemeron_handle).unwrap();
+            assert_eq!(*CLEARED.lock().unwrap(), vec![dead_ephemeron]);
+
+            // The key is resurrected, and the value and its child are kept alive.
+            let key = memory_manager::get_finalized_object(mmtk).unwrap();
+            assert_eq!(load(hidden_field_slot(ephemeron, 0)), Some(key));
+            let value = load(hidden_field_slot(ephemeron, 1)).unwrap();
+            assert!(memory_manager::is_live_object(value));
+            let child = load(field_slot(value, 0)).unwrap();
+            assert!(memory_manager::is_live_object(child));
+
+            // The key is live as long as the binding holds it.  The ephemeron is still registered.
+            let key_handle = gc.root(key);
+            gc.collect();
+            let ephemeron = gc.resolve(ephemeron_handle).unwrap();
+            assert_eq!(
+                load(hidden_field_slot(ephemeron, 0)),
+                gc.resolve(key_handle)
+            );
+            assert_eq!(CLEARED.lock().unwrap().len(), 1);
+            assert_eq!(mmtk.ephemeron_processor.num_candidates(), 1);
+        },
+        no_cleanup,
+    )
+}
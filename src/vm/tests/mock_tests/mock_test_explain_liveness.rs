@@ -0,0 +1,74 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep
+
+// `explain_liveness` finds the path from a root to an object in a GC, and several threads can
+// query at the same time.  The plan must not move objects, because a query made while a GC is in
+// progress is answered in the next GC.
+
+use super::mock_test_prelude::*;
+use crate::util::liveness::{PathStep, RootSource};
+use crate::util::test_util::mock_gc::*;
+
+const NUM_THREADS: usize = 4;
+const QUERIES_PER_THREAD: usize = 3;
+
+#[test]
+pub fn explain_liveness() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut gc = MockGC::new(|_| {});
+            let mmtk = gc.mmtk();
+
+            // a -> b -> c, where a is a root.  d is not reachable.
+            let a = gc.alloc(1, 0);
+            let b = gc.alloc(1, 0);
+            let c = gc.alloc(0, 0);
+            let d = gc.alloc(0, 0);
+            store(field_slot(a, 0), Some(b));
+            store(field_slot(b, 0), Some(c));
+            let _root = gc.root(a);
+
+            let tls = gc.mutator.mutator_tls;
+            let pauses_before = pauses();
+            let path = memory_manager::explain_liveness(mmtk, tls, c).unwrap();
+            assert!(pauses() > pauses_before);
+            assert!(matches!(path.root, RootSource::Handle(_)));
+            assert_eq!(
+                path.steps,
+                vec![
+                    PathStep {
+                        slot: None,
+                        object: a
+                    },
+                    PathStep {
+                        slot: Some(field_slot(a, 0)),
+                        object: b
+                    },
+                    PathStep {
+                        slot: Some(field_slot(b, 0)),
+                        object: c
+                    },
+                ]
+            );
+            assert_eq!(memory_manager::explain_liveness(mmtk, tls, d), None);
+
+            // Concurrent queries from several mutators.
+            let threads: Vec<_> = (0..NUM_THREADS)
+                .map(|_| {
+                    let mutator = Box::leak(gc.bind_mutator());
+                    let tls = mutator.mutator_tls;
+                    std::thread::spawn(move || {
+                        for _ in 0..QUERIES_PER_THREAD {
+                            let path = memory_manager::explain_liveness(mmtk, tls, c).unwrap();
+                            assert_eq!(path.steps.last().unwrap().object, c);
+                        }
+                    })
+                })
+                .collect();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+        },
+        no_cleanup,
+    )
+}
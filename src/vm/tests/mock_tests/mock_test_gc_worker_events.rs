@@ -0,0 +1,139 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,SemiSpace
+
+// GC workers report their life cycle events with `Collection::on_gc_worker_event` on their own
+// threads.  `GCStarted` and `GCFinished` are only reported if the binding asks for them with
+// `Collection::report_gc_events_on_workers`.
+
+use super::mock_test_prelude::*;
+use crate::util::test_util::mock_gc::*;
+use crate::vm::GCWorkerEvent;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const NUM_WORKERS: usize = 4;
+
+lazy_static! {
+    /// The ordinals of the workers and the events they reported.
+    static ref EVENTS: Mutex<Vec<(usize, GCWorkerEvent)>> = Mutex::new(vec![]);
+    /// The last event reported by each worker.
+    static ref LAST_EVENTS: Mutex<[Option<GCWorkerEvent>; NUM_WORKERS]> = Mutex::new([None; NUM_WORKERS]);
+}
+
+/// The result of `Collection::report_gc_events_on_workers`.
+static REPORT_GC_EVENTS: AtomicBool = AtomicBool::new(false);
+
+/// Take the events reported by each worker so far.
+fn take_events() -> Vec<Vec<GCWorkerEvent>> {
+    let events = std::mem::take(&mut *EVENTS.lock().unwrap());
+    (0..NUM_WORKERS)
+        .map(|ordinal| {
+            events
+                .iter()
+                .filter(|(o, _)| *o == ordinal)
+                .map(|(_, event)| *event)
+                .collect()
+        })
+        .collect()
+}
+
+/// Wait until all workers have parked after a GC.  Workers may still be parking after mutators
+/// are resumed.
+fn wait_for_workers_to_park() {
+    let start = Instant::now();
+    while LAST_EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|event| *event != Some(GCWorkerEvent::Parked))
+    {
+        assert!(
+            start.elapsed() < Duration::from_secs(60),
+            "Workers did not park"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Check that a worker parked and unparked in turns, and only reported GC events while it was
+/// not parked.  Return the GC events.
+fn check_parking(events: &[GCWorkerEvent], mut parked: bool) -> Vec<GCWorkerEvent> {
+    let mut gc_events = vec![];
+    for &event in events {
+        match event {
+            GCWorkerEvent::Parked => {
+                assert!(!parked, "{:?}", events);
+                parked = true;
+            }
+            GCWorkerEvent::Unparked => {
+                assert!(parked, "{:?}", events);
+                parked = false;
+            }
+            GCWorkerEvent::GCStarted | GCWorkerEvent::GCFinished => {
+                assert!(!parked, "{:?}", events);
+                gc_events.push(event);
+            }
+            _ => panic!("Unexpected event {:?} in {:?}", event, events),
+        }
+    }
+    gc_events
+}
+
+#[test]
+pub fn gc_worker_events() {
+    with_mockvm(
+        || MockVM {
+            on_gc_worker_event: MockMethod::new_fixed(Box::new(|(_tls, ordinal, event)| {
+                EVENTS.lock().unwrap().push((ordinal, event));
+                LAST_EVENTS.lock().unwrap()[ordinal] = Some(event);
+            })),
+            report_gc_events_on_workers: MockMethod::new_fixed(Box::new(|()| {
+                REPORT_GC_EVENTS.load(Ordering::SeqCst)
+            })),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.threads.set(NUM_WORKERS);
+            });
+            let object = gc.alloc(1, 0);
+            let _root = gc.root(object);
+
+            // Workers report that they started, and park until the first GC.
+            gc.collect();
+            wait_for_workers_to_park();
+            for events in take_events() {
+                assert_eq!(events.first(), Some(&GCWorkerEvent::Started));
+                assert!(events.contains(&GCWorkerEvent::Unparked));
+                assert_eq!(check_parking(&events[1..], false), vec![]);
+            }
+
+            // Each worker reports the start and the end of each GC once, in order.
+            REPORT_GC_EVENTS.store(true, Ordering::SeqCst);
+            gc.collect();
+            gc.collect();
+            wait_for_workers_to_park();
+            for events in take_events() {
+                assert_eq!(
+                    check_parking(&events, true),
+                    [
+                        GCWorkerEvent::GCStarted,
+                        GCWorkerEvent::GCFinished,
+                        GCWorkerEvent::GCStarted,
+                        GCWorkerEvent::GCFinished,
+                    ],
+                );
+            }
+
+            // The GC events are not reported by default.
+            REPORT_GC_EVENTS.store(false, Ordering::SeqCst);
+            gc.collect();
+            wait_for_workers_to_park();
+            for events in take_events() {
+                assert_eq!(check_parking(&events, true), vec![]);
+            }
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,50 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,SemiSpace
+
+// In the guardian finalization mode, unreachable finalizable objects are handed to the binding
+// without being kept alive, so their children are not resurrected.
+
+use super::mock_test_prelude::*;
+use crate::util::options::FinalizationMode;
+use crate::util::test_util::mock_gc::*;
+
+#[test]
+pub fn guardian_finalization() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder
+                    .options
+                    .finalization_mode
+                    .set(FinalizationMode::Guardian);
+            });
+            let mmtk = gc.mmtk();
+
+            // An unreachable finalizable object with a child that is not finalizable.
+            let guarded = gc.alloc(1, 0);
+            let child = gc.alloc(0, 0);
+            store(field_slot(guarded, 0), Some(child));
+            let weak_child = memory_manager::create_weak_handle(mmtk, child);
+            memory_manager::add_finalizer(mmtk, guarded);
+
+            // A reachable finalizable object.
+            let live = gc.alloc(0, 0);
+            let live_root = gc.root(live);
+            memory_manager::add_finalizer(mmtk, live);
+
+            gc.collect();
+            // The child is not resurrected.
+            assert_eq!(gc.resolve(weak_child), None);
+
+            // The object stays ready for finalization until the binding takes it, but it is not
+            // scanned again.  It is only returned as an identity.
+            gc.collect();
+            assert_eq!(memory_manager::get_finalized_object(mmtk), Some(guarded));
+            assert_eq!(memory_manager::get_finalized_object(mmtk), None);
+
+            let live = gc.resolve(live_root).unwrap();
+            assert_eq!(memory_manager::get_all_finalizers(mmtk), vec![live]);
+        },
+        no_cleanup,
+    )
+}
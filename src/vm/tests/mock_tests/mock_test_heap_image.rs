@@ -0,0 +1,126 @@
+// GITHUB-CI: MMTK_PLAN=Immix
+// GITHUB-CI: FEATURES=heap_image
+
+// This test only runs for 64bits.
+// It saves a small object graph in the immortal space as a heap image, and restores it at an
+// address different from the preferred start so that the references need to be relocated.
+
+use super::mock_test_prelude::*;
+use crate::util::heap::layout::vm_layout::BYTES_IN_CHUNK;
+use crate::util::heap_image::HeapImage;
+use crate::util::os::*;
+use crate::util::{Address, ObjectReference, VMMutatorThread, VMThread};
+use crate::vm::slot::Slot;
+use crate::AllocationSemantics;
+
+/// Each object has a header word, followed by a single reference field.
+const OBJECT_SIZE: usize = 3 * crate::util::constants::BYTES_IN_WORD;
+
+fn field(object: ObjectReference) -> Address {
+    object.to_raw_address() + crate::util::constants::BYTES_IN_WORD
+}
+
+#[test]
+pub fn heap_image() {
+    with_mockvm(
+        || -> MockVM {
+            MockVM {
+                get_object_size: MockMethod::new_fixed(Box::new(|_| OBJECT_SIZE)),
+                scan_object: MockMethod::new_fixed(Box::new(|(_, object, slot_visitor)| {
+                    slot_visitor.visit_slot(field(object));
+                })),
+                ..MockVM::default()
+            }
+        },
+        || {
+            let mut fixture = MMTKFixture::create_with_builder(
+                |builder| {
+                    builder.options.gc_trigger.set(
+                        crate::util::options::GCTriggerSelector::FixedHeapSize(1 << 20),
+                    );
+                },
+                true,
+            );
+            let tls = VMThread::UNINITIALIZED;
+            let mut mutator =
+                memory_manager::bind_mutator(fixture.get_mmtk(), VMMutatorThread(tls));
+            let mut new_obj = || {
+                let start = memory_manager::alloc(
+                    &mut mutator,
+                    OBJECT_SIZE,
+                    8,
+                    0,
+                    AllocationSemantics::Immortal,
+                );
+                let object = MockVM::object_start_to_ref(start);
+                memory_manager::post_alloc(
+                    &mut mutator,
+                    object,
+                    OBJECT_SIZE,
+                    AllocationSemantics::Immortal,
+                );
+                object
+            };
+            // a -> b -> c -> a
+            let (a, b, c) = (new_obj(), new_obj(), new_obj());
+            Slot::store(&field(a), b);
+            Slot::store(&field(b), c);
+            Slot::store(&field(c), a);
+
+            let preferred_start = unsafe { Address::from_usize(0x7862_0000_0000) };
+            let image = memory_manager::save_heap_image(
+                fixture.get_mmtk(),
+                tls,
+                &["immortal"],
+                &[a],
+                preferred_start,
+            )
+            .unwrap();
+            assert_eq!(image.num_objects(), 3);
+            // The original objects are not changed.
+            assert_eq!(Slot::load(&field(a)), Some(b));
+            assert_eq!(Slot::load(&field(c)), Some(a));
+
+            let mut buffer = vec![];
+            image.write_to(&mut buffer).unwrap();
+            let image = HeapImage::read_from(&mut buffer.as_slice()).unwrap();
+
+            // Restore the image somewhere else.
+            let start = OS::dzmmap_anywhere(
+                image.size(),
+                BYTES_IN_CHUNK,
+                MmapStrategy::default(),
+                crate::mmap_anno_test!(),
+            )
+            .unwrap();
+            assert_ne!(start, image.preferred_start());
+
+            // An image whose references point outside the image is rejected.  Moving the
+            // preferred start of the image (the word after the magic number and the version)
+            // makes all references in it point outside the image.
+            let mut corrupted = buffer.clone();
+            let wrong_start = preferred_start + image.size();
+            corrupted[12..20].copy_from_slice(&(wrong_start.as_usize() as u64).to_le_bytes());
+            let corrupted = HeapImage::read_from(&mut corrupted.as_slice()).unwrap();
+            let err =
+                memory_manager::restore_heap_image(fixture.get_mmtk_mut(), tls, &corrupted, start)
+                    .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+            let roots =
+                memory_manager::restore_heap_image(fixture.get_mmtk_mut(), tls, &image, start)
+                    .unwrap();
+            assert_eq!(roots.len(), 1);
+
+            let new_a = roots[0];
+            assert!(new_a.to_raw_address() >= start);
+            assert!(new_a.to_raw_address() < start + image.size());
+            assert!(memory_manager::is_in_mmtk_spaces(new_a));
+            let new_b = Slot::load(&field(new_a)).unwrap();
+            let new_c = Slot::load(&field(new_b)).unwrap();
+            assert_eq!(Slot::load(&field(new_c)), Some(new_a));
+            assert!(new_b.to_raw_address() >= start);
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,92 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,GenImmix
+// GITHUB-CI: FEATURES=heap_verifier
+
+// The heap verifier reports the slots that refer to reclaimed objects or to memory outside MMTk
+// spaces, and (for generational plans) old-to-young references that the write barrier missed.
+
+use super::mock_test_prelude::*;
+use crate::util::heap_verifier::{find_violations, VerificationPoint};
+use crate::util::test_util::mock_gc::*;
+use crate::util::{Address, ObjectReference, VMThread, VMWorkerThread};
+
+const TLS: VMWorkerThread = VMWorkerThread(VMThread::UNINITIALIZED);
+
+/// Find the violations, and return their sources, targets and reasons.
+fn violations(
+    gc: &MockGC,
+    point: VerificationPoint,
+) -> Vec<(ObjectReference, ObjectReference, String)> {
+    find_violations(gc.mmtk(), TLS, point)
+        .into_iter()
+        .map(|violation| (violation.source, violation.target, violation.reason))
+        .collect()
+}
+
+#[test]
+pub fn heap_verifier() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut gc = MockGC::new(|_| {});
+
+            let source = gc.alloc(2, 0);
+            store(field_slot(source, 0), Some(gc.alloc(0, 0)));
+            let dead = gc.alloc(0, 0);
+            let source_handle = gc.root(source);
+            gc.collect_full_heap();
+            let source = gc.resolve(source_handle).unwrap();
+            assert!(violations(&gc, VerificationPoint::BeforeGC).is_empty());
+            assert!(violations(&gc, VerificationPoint::AfterGC).is_empty());
+
+            // A reference to a reclaimed object.
+            store(field_slot(source, 1), Some(dead));
+            assert_eq!(
+                violations(&gc, VerificationPoint::BeforeGC),
+                vec![(
+                    source,
+                    dead,
+                    "the target is not a valid object (VO bit not set)".to_string()
+                )]
+            );
+
+            // A reference to memory that is not in any MMTk space.
+            let outside = Box::new([0usize; 4]);
+            let outside =
+                ObjectReference::from_raw_address(Address::from_ref(&outside[1])).unwrap();
+            store(field_slot(source, 1), Some(outside));
+            assert_eq!(
+                violations(&gc, VerificationPoint::AfterGC),
+                vec![(
+                    source,
+                    outside,
+                    "the target is not a valid object (VO bit not set)".to_string()
+                )]
+            );
+            store(field_slot(source, 1), None);
+
+            if gc.mmtk().get_plan().generational().is_some() {
+                // An old-to-young reference written without the write barrier.
+                let young = gc.alloc(0, 0);
+                store(field_slot(source, 1), Some(young));
+                assert_eq!(
+                    violations(&gc, VerificationPoint::BeforeGC),
+                    vec![(
+                        source,
+                        young,
+                        "an old-to-young reference is not remembered (the source is unlogged)"
+                            .to_string()
+                    )]
+                );
+                assert_eq!(
+                    violations(&gc, VerificationPoint::AfterGC),
+                    vec![(
+                        source,
+                        young,
+                        "the target is still in the nursery".to_string()
+                    )]
+                );
+            }
+        },
+        no_cleanup,
+    )
+}
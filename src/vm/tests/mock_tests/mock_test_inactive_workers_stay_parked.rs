@@ -0,0 +1,90 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace,Immix,GenCopy
+
+// With `adaptive_gc_workers`, workers that do not participate in a GC stay parked during the GC.
+// They are not woken up to prepare or release their copy contexts, or to report GC events.
+
+use super::mock_test_prelude::*;
+use crate::util::test_util::mock_gc::*;
+use crate::vm::GCWorkerEvent;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const NUM_WORKERS: usize = 4;
+
+lazy_static! {
+    /// The ordinals of the workers and the events they reported.
+    static ref EVENTS: Mutex<Vec<(usize, GCWorkerEvent)>> = Mutex::new(vec![]);
+    /// The last event reported by each worker.
+    static ref LAST_EVENTS: Mutex<[Option<GCWorkerEvent>; NUM_WORKERS]> = Mutex::new([None; NUM_WORKERS]);
+}
+
+/// Wait until all workers have parked after a GC.
+fn wait_for_workers_to_park() {
+    let start = Instant::now();
+    while LAST_EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|event| *event != Some(GCWorkerEvent::Parked))
+    {
+        assert!(
+            start.elapsed() < Duration::from_secs(60),
+            "Workers did not park"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+pub fn inactive_workers_stay_parked() {
+    with_mockvm(
+        || MockVM {
+            on_gc_worker_event: MockMethod::new_fixed(Box::new(|(_tls, ordinal, event)| {
+                EVENTS.lock().unwrap().push((ordinal, event));
+                LAST_EVENTS.lock().unwrap()[ordinal] = Some(event);
+            })),
+            report_gc_events_on_workers: MockMethod::new_fixed(Box::new(|()| true)),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.threads.set(NUM_WORKERS);
+                builder.options.adaptive_gc_workers.set(true);
+                // Every GC is small enough for one worker.
+                builder.options.gc_work_bytes_per_worker.set(usize::MAX);
+            });
+            let object = gc.alloc(1, 0);
+            let root = gc.root(object);
+
+            // All workers are active until the first GC chooses the active workers.
+            gc.collect();
+            wait_for_workers_to_park();
+            let active_workers = gc.mmtk().scheduler.num_active_workers();
+            assert_eq!(active_workers, 1);
+            EVENTS.lock().unwrap().clear();
+
+            for _ in 0..3 {
+                gc.collect();
+                assert!(gc.resolve(root).is_some());
+            }
+            wait_for_workers_to_park();
+
+            let events = std::mem::take(&mut *EVENTS.lock().unwrap());
+            for (ordinal, event) in events.iter() {
+                assert!(
+                    *ordinal < active_workers,
+                    "Inactive worker {ordinal} reported {event:?}: {events:?}"
+                );
+            }
+            let gc_events = events
+                .iter()
+                .filter(|(_, event)| {
+                    matches!(event, GCWorkerEvent::GCStarted | GCWorkerEvent::GCFinished)
+                })
+                .count();
+            assert_eq!(gc_events, 3 * 2);
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,151 @@
+// GITHUB-CI: MMTK_PLAN=ConcurrentImmix
+
+// With the option `incremental_marking`, GC workers do not mark between the initial mark and the
+// final mark pauses.  Mutators mark in slices at `gc_poll`, and each slice stops after the first
+// packet that reaches the limit of `incremental_marking_slice_bytes` or
+// `incremental_marking_slice_us`.
+
+use super::mock_test_prelude::*;
+use crate::util::handle_table::HANDLES_IN_BLOCK;
+use crate::util::test_util::mock_gc::*;
+use crate::util::{ObjectReference, VMThread};
+use crate::MMTK;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Each block of strong handles is scanned into one marking packet.
+const PACKETS: usize = 8;
+/// Each root is the head of a list of this many objects.
+const LIST_LENGTH: usize = 4;
+/// The scan work of one marking packet.
+const PACKET_BYTES: usize = HANDLES_IN_BLOCK * LIST_LENGTH * OBJECT_BYTES;
+const OBJECT_BYTES: usize = DEFAULT_OBJECT_REF_OFFSET + 5 * crate::util::constants::BYTES_IN_WORD;
+/// The work limit of a slice.  A slice executes packets until it reaches the limit.
+const SLICE_BYTES: usize = 2 * PACKET_BYTES;
+/// The time limit of a slice.
+const SLICE_US: usize = 20_000;
+/// How long the mutator takes to scan an object when scanning is slow.  A packet takes longer than
+/// the time limit of a slice.
+const SLOW_SCAN: Duration = Duration::from_micros(50);
+
+static MMTK_REF: OnceLock<&'static MMTK<MockVM>> = OnceLock::new();
+
+lazy_static! {
+    /// The thread-local storage of the mutator.
+    static ref MUTATOR_TLS: Mutex<Option<VMThread>> = Mutex::new(None);
+}
+
+/// The bytes of objects scanned by the mutator so far.
+static MUTATOR_SCANNED_BYTES: AtomicUsize = AtomicUsize::new(0);
+/// The number of objects scanned by GC workers while the mutator is responsible for marking.
+static WORKER_SCANS_DURING_MARKING: AtomicUsize = AtomicUsize::new(0);
+/// Whether the mutator scans objects slowly.
+static SLOW: AtomicBool = AtomicBool::new(false);
+
+fn check_list(head: ObjectReference, index: usize) {
+    let mut node = Some(head);
+    for _ in 0..LIST_LENGTH {
+        let object = node.unwrap();
+        assert_eq!(
+            unsafe { hidden_field_slot(object, 0).load::<usize>() },
+            index
+        );
+        node = load(field_slot(object, 0));
+    }
+    assert_eq!(node, None);
+}
+
+/// Allocate garbage until the initial mark pause, and then call `gc_poll` until the final mark
+/// pause.  Return the scan work of the mutator in the marking cycle, and the scan work of each
+/// slice done by `gc_poll`.  The allocation slow path may also do slices.
+fn mark_incrementally(gc: &mut MockGC) -> (usize, Vec<usize>) {
+    let mmtk = gc.mmtk();
+    let tls = gc.mutator.mutator_tls;
+    let pacer = &mmtk.state.pacer;
+    let start = MUTATOR_SCANNED_BYTES.load(Ordering::SeqCst);
+    while !pacer.is_marking_on_mutators() {
+        gc.alloc(8, 0);
+    }
+    let pauses_before = pauses();
+    let mut slices = vec![];
+    while pauses() == pauses_before {
+        assert!(slices.len() < 10000, "Marking does not finish");
+        let before = MUTATOR_SCANNED_BYTES.load(Ordering::SeqCst);
+        memory_manager::gc_poll(mmtk, tls);
+        slices.push(MUTATOR_SCANNED_BYTES.load(Ordering::SeqCst) - before);
+    }
+    assert!(!pacer.is_marking());
+    (MUTATOR_SCANNED_BYTES.load(Ordering::SeqCst) - start, slices)
+}
+
+#[test]
+pub fn incremental_marking() {
+    with_mockvm(
+        || MockVM {
+            scan_object: MockMethod::new_fixed(Box::new(|(tls, object, slot_visitor)| {
+                if Some(tls.0) == *MUTATOR_TLS.lock().unwrap() {
+                    MUTATOR_SCANNED_BYTES.fetch_add(size_of_object(object), Ordering::SeqCst);
+                    if SLOW.load(Ordering::SeqCst) {
+                        std::thread::sleep(SLOW_SCAN);
+                    }
+                } else if MMTK_REF.get().unwrap().state.pacer.is_marking_on_mutators() {
+                    WORKER_SCANS_DURING_MARKING.fetch_add(1, Ordering::SeqCst);
+                }
+                for i in 0..num_fields(object) {
+                    slot_visitor.visit_slot(field_slot(object, i));
+                }
+            })),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.incremental_marking.set(true);
+                builder
+                    .options
+                    .incremental_marking_slice_bytes
+                    .set(SLICE_BYTES);
+                builder.options.incremental_marking_slice_us.set(SLICE_US);
+            });
+            assert!(MMTK_REF.set(gc.mmtk()).is_ok());
+            *MUTATOR_TLS.lock().unwrap() = Some(gc.mutator.mutator_tls.0);
+            assert_eq!(object_size(1, 1), OBJECT_BYTES);
+
+            let roots = (0..PACKETS * HANDLES_IN_BLOCK)
+                .map(|index| {
+                    let mut head = None;
+                    for _ in 0..LIST_LENGTH {
+                        let node = gc.alloc(1, 1);
+                        store(field_slot(node, 0), head);
+                        unsafe { hidden_field_slot(node, 0).store(index) };
+                        head = Some(node);
+                    }
+                    gc.root(head.unwrap())
+                })
+                .collect::<Vec<_>>();
+
+            // Scanning is fast, so slices stop at the work limit.
+            let (total, slices) = mark_incrementally(&mut gc);
+            assert!(total >= PACKETS * PACKET_BYTES);
+            assert!(slices
+                .iter()
+                .all(|&bytes| bytes <= SLICE_BYTES + PACKET_BYTES));
+            assert!(slices.iter().filter(|&&bytes| bytes > 0).count() >= 2);
+
+            // Scanning is slow, so slices stop at the time limit after one packet.
+            SLOW.store(true, Ordering::SeqCst);
+            let (total, slices) = mark_incrementally(&mut gc);
+            SLOW.store(false, Ordering::SeqCst);
+            assert!(total >= PACKETS * PACKET_BYTES);
+            assert!(slices.iter().all(|&bytes| bytes <= PACKET_BYTES));
+            assert!(slices.iter().filter(|&&bytes| bytes > 0).count() >= 4);
+
+            assert_eq!(WORKER_SCANS_DURING_MARKING.load(Ordering::SeqCst), 0);
+            for (index, root) in roots.into_iter().enumerate() {
+                check_list(gc.resolve(root).unwrap(), index);
+            }
+        },
+        no_cleanup,
+    )
+}
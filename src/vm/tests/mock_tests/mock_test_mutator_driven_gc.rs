@@ -0,0 +1,117 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace,GenCopy
+
+// With the option `mutator_driven_gc`, mutators run GCs themselves.  The copy context of the
+// driven worker allocates on behalf of the mutator that drives the GC, and a mutator that waits
+// while another mutator drives a GC blocks in `Collection::block_for_gc_driver`.
+
+use super::mock_test_prelude::*;
+use crate::util::test_util::mock_gc::*;
+use crate::util::{ObjectReference, VMMutatorThread, VMThread};
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const LIST_LENGTH: usize = 100;
+
+lazy_static! {
+    /// The threads passed to `ActivePlan::is_mutator`, e.g. by the allocation slow path.
+    static ref IS_MUTATOR_CALLS: Mutex<Vec<VMThread>> = Mutex::new(vec![]);
+    /// The mutators blocked in `Collection::block_for_gc_driver`.
+    static ref BLOCKED: Mutex<Vec<VMMutatorThread>> = Mutex::new(vec![]);
+}
+
+/// Create a list of `length` objects.  The first hidden field of each object is its index.
+fn new_list(gc: &mut MockGC, length: usize) -> ObjectReference {
+    let mut head = None;
+    for i in (0..length).rev() {
+        let node = gc.alloc(1, 1);
+        store(field_slot(node, 0), head);
+        unsafe { hidden_field_slot(node, 0).store(i) };
+        head = Some(node);
+    }
+    head.unwrap()
+}
+
+fn check_list(head: ObjectReference, length: usize) {
+    let mut node = Some(head);
+    for i in 0..length {
+        let object = node.unwrap();
+        assert!(memory_manager::is_live_object(object));
+        assert_eq!(unsafe { hidden_field_slot(object, 0).load::<usize>() }, i);
+        node = load(field_slot(object, 0));
+    }
+    assert_eq!(node, None);
+}
+
+#[test]
+pub fn mutator_driven_gc() {
+    with_mockvm(
+        || MockVM {
+            is_mutator: MockMethod::new_fixed(Box::new(|tls| {
+                IS_MUTATOR_CALLS.lock().unwrap().push(tls);
+                true
+            })),
+            block_for_gc_driver: MockMethod::new_fixed(Box::new(|(tls, wait)| {
+                BLOCKED.lock().unwrap().push(tls);
+                wait();
+            })),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.mutator_driven_gc.set(true);
+                builder.options.threads.set(1);
+            });
+            let mmtk = gc.mmtk();
+
+            // The mutator of this thread drives the GC.
+            let head = new_list(&mut gc, LIST_LENGTH);
+            let list = gc.root(head);
+            gc.collect();
+            let moved = gc.resolve(list).unwrap();
+            assert_ne!(moved, head);
+            check_list(moved, LIST_LENGTH);
+
+            // Another mutator drives the GC.  The objects are copied with its thread.
+            let young = new_list(&mut gc, LIST_LENGTH);
+            let young = gc.root(young);
+            let other = Box::leak(gc.bind_mutator()).mutator_tls;
+            IS_MUTATOR_CALLS.lock().unwrap().clear();
+            let pauses_before = pauses();
+            std::thread::spawn(move || {
+                assert!(memory_manager::handle_user_collection_request(mmtk, other));
+            })
+            .join()
+            .unwrap();
+            assert_eq!(pauses(), pauses_before + 1);
+            check_list(gc.resolve(list).unwrap(), LIST_LENGTH);
+            check_list(gc.resolve(young).unwrap(), LIST_LENGTH);
+            let is_mutator_calls = std::mem::take(&mut *IS_MUTATOR_CALLS.lock().unwrap());
+            assert!(is_mutator_calls.contains(&other.0));
+            assert!(!is_mutator_calls.contains(&VMThread::UNINITIALIZED));
+
+            // A mutator requests a GC while the driver is held by another thread.  It blocks in
+            // `block_for_gc_driver`, and then drives the GC itself.
+            let waiting = Box::leak(gc.bind_mutator()).mutator_tls;
+            let driver = mmtk.scheduler.worker_group.lock_driver();
+            let pauses_before = pauses();
+            let thread = std::thread::spawn(move || {
+                assert!(memory_manager::handle_user_collection_request(
+                    mmtk, waiting
+                ));
+            });
+            let start = Instant::now();
+            while !BLOCKED.lock().unwrap().contains(&waiting) {
+                assert!(start.elapsed() < Duration::from_secs(60));
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert_eq!(pauses(), pauses_before);
+            drop(driver);
+            thread.join().unwrap();
+            assert_eq!(pauses(), pauses_before + 1);
+            check_list(gc.resolve(list).unwrap(), LIST_LENGTH);
+            assert_eq!(*BLOCKED.lock().unwrap(), vec![waiting]);
+        },
+        no_cleanup,
+    )
+}
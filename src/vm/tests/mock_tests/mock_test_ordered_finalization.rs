@@ -0,0 +1,106 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,SemiSpace
+
+// In the ordered finalization mode, an unreachable finalizable object is only ready for
+// finalization if no other unreachable finalizable object reaches it, unless both are in the same
+// cycle.  The objects reachable from finalizable objects are kept alive until they are finalized.
+
+use super::mock_test_prelude::*;
+use crate::util::options::FinalizationMode;
+use crate::util::test_util::mock_gc::*;
+use crate::util::ObjectReference;
+
+use std::collections::HashSet;
+
+// The name of an object is stored in its first hidden field, which is not scanned.
+fn new_object(gc: &mut MockGC, name: char, fields: usize) -> ObjectReference {
+    let object = gc.alloc(fields, 1);
+    unsafe { hidden_field_slot(object, 0).store(name as usize) };
+    object
+}
+
+fn name_of(object: ObjectReference) -> char {
+    char::from_u32(unsafe { hidden_field_slot(object, 0).load::<usize>() } as u32).unwrap()
+}
+
+fn take_finalized(gc: &MockGC) -> Vec<ObjectReference> {
+    std::iter::from_fn(|| memory_manager::get_finalized_object(gc.mmtk())).collect()
+}
+
+fn names(objects: &[ObjectReference]) -> HashSet<char> {
+    objects.iter().copied().map(name_of).collect()
+}
+
+#[test]
+pub fn ordered_finalization() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder
+                    .options
+                    .finalization_mode
+                    .set(FinalizationMode::Ordered);
+            });
+            let mmtk = gc.mmtk();
+
+            // Objects with one field each.  All of them but `x` are finalizable.
+            let objects: Vec<ObjectReference> = "abcdefghijkx"
+                .chars()
+                .map(|name| new_object(&mut gc, name, 1))
+                .collect();
+            let get = |name: char| *objects.iter().find(|o| name_of(**o) == name).unwrap();
+            let x = get('x');
+            let link =
+                |from: ObjectReference, to: ObjectReference| store(field_slot(from, 0), Some(to));
+            // A chain: a -> b
+            link(get('a'), get('b'));
+            // A cycle: c -> d -> c
+            link(get('c'), get('d'));
+            link(get('d'), get('c'));
+            // A self reference: e -> e
+            link(get('e'), get('e'));
+            // Through an object that is not finalizable: f -> x -> g
+            link(get('f'), x);
+            link(x, get('g'));
+            // A cycle reachable from another object: h -> i -> j -> i
+            link(get('h'), get('i'));
+            link(get('i'), get('j'));
+            link(get('j'), get('i'));
+            // An object on its own: k
+            for name in "abcdefghijk".chars() {
+                memory_manager::add_finalizer(mmtk, get(name));
+            }
+
+            gc.collect();
+            let finalized = take_finalized(&gc);
+            assert_eq!(names(&finalized), HashSet::from_iter("acdefhk".chars()));
+
+            // The finalized objects and their children are alive.
+            for object in finalized.iter().copied() {
+                assert!(memory_manager::is_live_object(object));
+            }
+            let f = finalized
+                .iter()
+                .copied()
+                .find(|o| name_of(*o) == 'f')
+                .unwrap();
+            let x = load(field_slot(f, 0)).unwrap();
+            assert_eq!(name_of(x), 'x');
+            let g = load(field_slot(x, 0)).unwrap();
+            assert!(memory_manager::is_live_object(g));
+            assert_eq!(name_of(g), 'g');
+
+            // The postponed objects are ready after the objects that reach them are finalized.
+            gc.collect();
+            assert_eq!(
+                names(&take_finalized(&gc)),
+                HashSet::from_iter("bgij".chars())
+            );
+
+            gc.collect();
+            assert!(take_finalized(&gc).is_empty());
+            assert!(memory_manager::get_all_finalizers(mmtk).is_empty());
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,106 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,SemiSpace
+
+// Reference kinds of the same strength are processed from the lowest rank to the highest rank.
+// A soft reference kind clears a referent that is only retained by a kind of a higher rank, and
+// cleared references are enqueued in the order of ranks.
+
+use super::mock_test_prelude::*;
+use crate::util::options::SoftReferencePolicy;
+use crate::util::test_util::mock_gc::*;
+use crate::util::{ObjectReference, VMWorkerThread};
+use crate::vm::{ReferenceKind, ReferenceKindSpec, ReferenceStrength};
+
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref ENQUEUED: Mutex<Vec<(&'static str, ObjectReference)>> = Mutex::new(vec![]);
+}
+
+macro_rules! recording_kind {
+    ($name: literal, $strength: expr, $rank: expr) => {
+        ReferenceKindSpec {
+            rank: $rank,
+            enqueue: Some(|references: &[ObjectReference], _tls: VMWorkerThread| {
+                let mut enqueued = ENQUEUED.lock().unwrap();
+                enqueued.extend(references.iter().map(|r| ($name, *r)));
+            }),
+            ..ReferenceKindSpec::new($name, $strength)
+        }
+    };
+}
+
+fn new_reference(
+    gc: &mut MockGC,
+    kind: ReferenceKind,
+    referent: ObjectReference,
+) -> ObjectReference {
+    let reference = gc.alloc(0, 1);
+    store(hidden_field_slot(reference, 0), Some(referent));
+    memory_manager::add_reference_candidate(gc.mmtk(), kind, reference);
+    reference
+}
+
+#[test]
+pub fn reference_kind_ranks() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut kinds = vec![];
+            let mut gc = MockGC::new(|builder| {
+                // Registered in the reverse order of ranks.
+                for spec in [
+                    ReferenceKindSpec {
+                        soft_reference_policy: Some(SoftReferencePolicy::Retain),
+                        ..recording_kind!("retaining soft", ReferenceStrength::Soft, 1)
+                    },
+                    ReferenceKindSpec {
+                        soft_reference_policy: Some(SoftReferencePolicy::AlwaysClear),
+                        ..recording_kind!("clearing soft", ReferenceStrength::Soft, 0)
+                    },
+                    recording_kind!("weak 1", ReferenceStrength::Weak, 1),
+                    recording_kind!("weak 0", ReferenceStrength::Weak, 0),
+                ] {
+                    kinds.push(builder.register_reference_kind(spec));
+                }
+            });
+            let [retaining_soft, clearing_soft, weak_1, weak_0] = kinds[..] else {
+                unreachable!()
+            };
+
+            // The clearing kind is stronger than the retaining kind, so it clears the referent
+            // although the retaining kind keeps it alive.
+            let referent = gc.alloc(0, 0);
+            let retaining = new_reference(&mut gc, retaining_soft, referent);
+            let clearing = new_reference(&mut gc, clearing_soft, referent);
+            let retaining = gc.root(retaining);
+            let clearing = gc.root(clearing);
+
+            // Both weak references are cleared.
+            let dead = gc.alloc(0, 0);
+            let weak_1 = new_reference(&mut gc, weak_1, dead);
+            let weak_0 = new_reference(&mut gc, weak_0, dead);
+            let weak_1 = gc.root(weak_1);
+            let weak_0 = gc.root(weak_0);
+
+            gc.collect();
+
+            let retaining = gc.resolve(retaining).unwrap();
+            let clearing = gc.resolve(clearing).unwrap();
+            let referent = load(hidden_field_slot(retaining, 0)).unwrap();
+            assert!(memory_manager::is_live_object(referent));
+            assert_eq!(load(hidden_field_slot(clearing, 0)), None);
+
+            let weak_1 = gc.resolve(weak_1).unwrap();
+            let weak_0 = gc.resolve(weak_0).unwrap();
+            assert_eq!(
+                *ENQUEUED.lock().unwrap(),
+                vec![
+                    ("clearing soft", clearing),
+                    ("weak 0", weak_0),
+                    ("weak 1", weak_1)
+                ]
+            );
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,123 @@
+use std::{
+    sync::{Condvar, Mutex, MutexGuard},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use super::mock_test_prelude::*;
+use crate::{
+    util::{options::GCTriggerSelector, Address, OpaquePointer, VMThread, VMWorkerThread},
+    vm::{GCWorkerEvent, GCWorkerExitReason},
+    MMTKBuilder, MMTK,
+};
+
+#[derive(Default)]
+struct ResizeTestShared {
+    sync: Mutex<ResizeTestSync>,
+    threads_exited: Condvar,
+}
+
+#[derive(Default)]
+struct ResizeTestSync {
+    join_handles: Vec<JoinHandle<()>>,
+    /// The ordinals of the workers spawned since the last time this was cleared.
+    spawned_ordinals: Vec<usize>,
+    exited_threads: usize,
+    exit_reasons: Vec<GCWorkerExitReason>,
+}
+
+lazy_static! {
+    static ref SHARED: ResizeTestShared = ResizeTestShared::default();
+}
+
+const INITIAL_WORKERS: usize = 2;
+const MAX_WORKERS: usize = 4;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn wait_for_exited_threads(expected: usize) -> MutexGuard<'static, ResizeTestSync> {
+    let sync = SHARED.sync.lock().unwrap();
+    let (sync, timeout_result) = SHARED
+        .threads_exited
+        .wait_timeout_while(sync, TIMEOUT, |sync| sync.exited_threads < expected)
+        .unwrap();
+    assert!(!timeout_result.timed_out());
+    sync
+}
+
+fn spawn_gc_thread(context: GCThreadContext<MockVM>, mmtk: &'static MMTK<MockVM>) {
+    let GCThreadContext::Worker(worker) = context;
+    let ordinal = worker.ordinal;
+    let join_handle = std::thread::spawn(move || {
+        let gc_thread_tls = VMWorkerThread(VMThread(OpaquePointer::from_address(Address::ZERO)));
+        memory_manager::start_worker(mmtk, gc_thread_tls, worker);
+
+        let mut sync = SHARED.sync.lock().unwrap();
+        sync.exited_threads += 1;
+        SHARED.threads_exited.notify_all();
+    });
+
+    let mut sync = SHARED.sync.lock().unwrap();
+    sync.join_handles.push(join_handle);
+    sync.spawned_ordinals.push(ordinal);
+}
+
+fn take_spawned_ordinals() -> Vec<usize> {
+    let mut ordinals = std::mem::take(&mut SHARED.sync.lock().unwrap().spawned_ordinals);
+    ordinals.sort();
+    ordinals
+}
+
+#[test]
+pub fn test_set_num_of_workers() {
+    let mut builder = MMTKBuilder::new();
+    let trigger = GCTriggerSelector::FixedHeapSize(1024 * 1024);
+    builder.options.gc_trigger.set(trigger);
+    builder.options.threads.set(INITIAL_WORKERS);
+    builder.options.max_threads.set(MAX_WORKERS);
+    let mmtk: &'static mut MMTK<MockVM> = Box::leak(Box::new(builder.build::<MockVM>()));
+
+    let mock_vm = MockVM {
+        spawn_gc_thread: MockMethod::new_fixed(Box::new(|(_vm_thread, context)| {
+            spawn_gc_thread(context, mmtk)
+        })),
+        on_gc_worker_event: MockMethod::new_fixed(Box::new(|(_tls, _ordinal, event)| {
+            if let GCWorkerEvent::Exiting(reason) = event {
+                SHARED.sync.lock().unwrap().exit_reasons.push(reason);
+            }
+        })),
+        ..Default::default()
+    };
+    write_mockvm(move |mock_vm_ref| *mock_vm_ref = mock_vm);
+
+    let test_thread_tls = VMThread(OpaquePointer::from_address(Address::ZERO));
+    mmtk.initialize_collection(test_thread_tls);
+    assert_eq!(take_spawned_ordinals(), vec![0, 1]);
+
+    // Grow.  All threads exit, and the workers are spawned again, including retired ones.
+    memory_manager::set_num_of_workers(mmtk, test_thread_tls, MAX_WORKERS);
+    assert_eq!(memory_manager::num_of_workers(mmtk), MAX_WORKERS);
+    assert_eq!(take_spawned_ordinals(), vec![0, 1, 2, 3]);
+    assert_eq!(wait_for_exited_threads(2).exited_threads, 2);
+
+    // Shrink.
+    memory_manager::set_num_of_workers(mmtk, test_thread_tls, 1);
+    assert_eq!(memory_manager::num_of_workers(mmtk), 1);
+    assert_eq!(take_spawned_ordinals(), vec![0]);
+    assert_eq!(wait_for_exited_threads(6).exited_threads, 6);
+
+    memory_manager::mmtk_shutdown(mmtk);
+
+    let join_handles = {
+        let mut sync = wait_for_exited_threads(7);
+        let resized = GCWorkerExitReason::Resize;
+        let shutdown = GCWorkerExitReason::Shutdown;
+        assert_eq!(
+            sync.exit_reasons,
+            [vec![resized; 6], vec![shutdown]].concat()
+        );
+        std::mem::take(&mut sync.join_handles)
+    };
+    for join_handle in join_handles {
+        join_handle.join().unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,SemiSpace
+
+// Two soft reference kinds with different policies are processed in parallel by several GC
+// workers.  A referent retained by one kind must not be cleared by the other kind, even if the
+// other kind never retains referents.
+
+use super::mock_test_prelude::*;
+use crate::util::options::SoftReferencePolicy;
+use crate::util::test_util::mock_gc::*;
+use crate::vm::{ReferenceKindSpec, ReferenceStrength};
+
+// Enough references to fill all the shards of the reference tables.
+const NUM_REFERENTS: usize = 500;
+
+#[test]
+pub fn soft_reference_kinds_retain_before_clearing() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut kinds = None;
+            let mut gc = MockGC::new(|builder| {
+                builder.options.threads.set(4);
+                let soft_kind = |name, policy| ReferenceKindSpec {
+                    soft_reference_policy: Some(policy),
+                    ..ReferenceKindSpec::new(name, ReferenceStrength::Soft)
+                };
+                kinds = Some((
+                    builder.register_reference_kind(soft_kind(
+                        "clear",
+                        SoftReferencePolicy::AlwaysClear,
+                    )),
+                    builder
+                        .register_reference_kind(soft_kind("retain", SoftReferencePolicy::Retain)),
+                ));
+            });
+            let (clear_kind, retain_kind) = kinds.unwrap();
+            let mmtk = gc.mmtk();
+
+            // Each referent is referred by a reference of each kind.
+            let mut references = vec![];
+            for _ in 0..NUM_REFERENTS {
+                let referent = gc.alloc(0, 0);
+                for kind in [clear_kind, retain_kind] {
+                    let reference = gc.alloc(0, 1);
+                    store(hidden_field_slot(reference, 0), Some(referent));
+                    memory_manager::add_reference_candidate(mmtk, kind, reference);
+                    references.push(gc.root(reference));
+                }
+            }
+            // A referent only referred by a reference of the clearing kind.
+            let cleared = gc.alloc(0, 1);
+            store(hidden_field_slot(cleared, 0), Some(gc.alloc(0, 0)));
+            memory_manager::add_reference_candidate(mmtk, clear_kind, cleared);
+            let cleared = gc.root(cleared);
+
+            gc.collect();
+
+            for pair in references.chunks(2) {
+                let clear_reference = gc.resolve(pair[0]).unwrap();
+                let retain_reference = gc.resolve(pair[1]).unwrap();
+                let referent = load(hidden_field_slot(retain_reference, 0)).unwrap();
+                assert!(memory_manager::is_live_object(referent));
+                assert_eq!(load(hidden_field_slot(clear_reference, 0)), Some(referent));
+            }
+            let cleared = gc.resolve(cleared).unwrap();
+            assert_eq!(load(hidden_field_slot(cleared, 0)), None);
+            assert_eq!(take_enqueued_references(), vec![cleared]);
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,88 @@
+// GITHUB-CI: MMTK_PLAN=MarkSweep,Immix,SemiSpace
+
+// With the `Lru` soft reference policy, the referent of a soft reference that was accessed
+// recently is retained, and the referent of a soft reference that has not been accessed for a long
+// time is cleared.  The binding records the access time of a soft reference in its second hidden
+// field.
+
+use super::mock_test_prelude::*;
+use crate::util::options::SoftReferencePolicy;
+use crate::util::test_util::mock_gc::*;
+use crate::util::{Address, ObjectReference};
+
+use std::time::Duration;
+
+fn timestamp_slot(reference: ObjectReference) -> Address {
+    hidden_field_slot(reference, 1)
+}
+
+#[test]
+pub fn soft_reference_lru() {
+    with_mockvm(
+        || MockVM {
+            weakref_get_soft_reference_timestamp: MockMethod::new_fixed(Box::new(
+                |reference| unsafe { timestamp_slot(reference).load::<u64>() },
+            )),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder
+                    .options
+                    .soft_reference_policy
+                    .set(SoftReferencePolicy::Lru);
+                // A soft reference may stay idle for at most 1ms per MB of free heap, i.e. no
+                // more than 16ms with the 16MB heap.
+                builder.options.soft_reference_lru_ms_per_mb.set(1);
+            });
+            let mmtk = gc.mmtk();
+
+            // Advance the soft reference clock well beyond the maximum idle time.
+            std::thread::sleep(Duration::from_millis(100));
+            gc.collect();
+            let clock = memory_manager::soft_reference_clock(mmtk);
+            assert!(clock >= 100);
+
+            let new_soft_reference = |gc: &mut MockGC, timestamp: u64| {
+                let reference = gc.alloc(0, 2);
+                let referent = gc.alloc(0, 0);
+                store(hidden_field_slot(reference, 0), Some(referent));
+                unsafe { timestamp_slot(reference).store(timestamp) };
+                memory_manager::add_soft_candidate(mmtk, reference);
+                gc.root(reference)
+            };
+            let recent_handle = new_soft_reference(&mut gc, clock);
+            let stale_handle = new_soft_reference(&mut gc, 0);
+
+            gc.collect();
+
+            let recent = gc.resolve(recent_handle).unwrap();
+            assert!(memory_manager::is_live_object(
+                load(hidden_field_slot(recent, 0)).unwrap()
+            ));
+            let stale = gc.resolve(stale_handle).unwrap();
+            assert_eq!(load(hidden_field_slot(stale, 0)), None);
+            assert_eq!(take_enqueued_references(), vec![stale]);
+
+            // The referent is retained as long as the reference is accessed between GCs.
+            for _ in 0..3 {
+                std::thread::sleep(Duration::from_millis(50));
+                let recent = gc.resolve(recent_handle).unwrap();
+                let clock = memory_manager::soft_reference_clock(mmtk);
+                unsafe { timestamp_slot(recent).store(clock) };
+                gc.collect();
+                let recent = gc.resolve(recent_handle).unwrap();
+                assert!(load(hidden_field_slot(recent, 0)).is_some());
+            }
+            assert!(take_enqueued_references().is_empty());
+
+            // Once the reference is no longer accessed, the referent is cleared.
+            std::thread::sleep(Duration::from_millis(100));
+            gc.collect();
+            let recent = gc.resolve(recent_handle).unwrap();
+            assert_eq!(load(hidden_field_slot(recent, 0)), None);
+            assert_eq!(take_enqueued_references(), vec![recent]);
+        },
+        no_cleanup,
+    )
+}
@@ -0,0 +1,55 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace,Immix
+
+// With a stress seed, the number of bytes allocated between two stress GCs is drawn from the
+// pseudo-random sequence of the seed for every stress GC, not only for the first one.
+
+use super::mock_test_prelude::*;
+use crate::util::stress::GCStress;
+use crate::util::test_util::mock_gc::*;
+
+const STRESS_FACTOR: usize = 16 * 1024;
+const STRESS_GCS: usize = 10;
+
+#[test]
+pub fn stress_seed() {
+    with_mockvm(
+        mock_gc_setup,
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.stress_factor.set(STRESS_FACTOR);
+                builder.options.stress_seed.set(42);
+            });
+            // Another instance with the same seed produces the same intervals.
+            let expected = GCStress::new(gc.mmtk().get_options());
+            let object_bytes = object_size(1, 0);
+
+            // The objects are garbage, so GC workers do not allocate.  The allocation that
+            // triggers a stress GC is not counted in either interval.
+            let mut intervals = vec![];
+            let mut allocated = 0;
+            while intervals.len() < STRESS_GCS {
+                let pauses_before = pauses();
+                gc.alloc(1, 0);
+                if pauses() == pauses_before {
+                    allocated += object_bytes;
+                } else {
+                    intervals.push(allocated);
+                    allocated = 0;
+                }
+            }
+
+            for interval in intervals.iter().copied() {
+                // The stress GC happens at the first allocation after the interval is exceeded.
+                let bytes = expected.stress_gc_bytes(STRESS_FACTOR);
+                assert!(
+                    interval > bytes && interval <= bytes + object_bytes,
+                    "{interval} bytes were allocated, but the interval is {bytes} bytes"
+                );
+                expected.schedule_next_stress_gc(STRESS_FACTOR);
+            }
+            // The intervals are not all the same.
+            assert!(intervals.iter().any(|&interval| interval != intervals[0]));
+        },
+        no_cleanup,
+    )
+}
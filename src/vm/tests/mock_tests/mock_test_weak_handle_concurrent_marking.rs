@@ -0,0 +1,109 @@
+// GITHUB-CI: MMTK_PLAN=ConcurrentImmix
+
+// A mutator that resolves a weak handle during concurrent marking may store the object into an
+// object that has already been scanned.  Resolving the handle must keep the object alive, or the
+// handle would be cleared at the final mark pause while the object is still reachable.
+//
+// The test uses incremental marking so that the mutator decides when objects are scanned.
+
+use super::mock_test_prelude::*;
+use crate::util::handle_table::HANDLES_IN_BLOCK;
+use crate::util::test_util::mock_gc::*;
+use crate::util::ObjectReference;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+lazy_static! {
+    /// The object that the mutator stores the resolved object into.
+    static ref HOLDER: Mutex<Option<ObjectReference>> = Mutex::new(None);
+}
+
+/// Whether the holder has been scanned.
+static HOLDER_SCANNED: AtomicBool = AtomicBool::new(false);
+
+#[test]
+pub fn weak_handle_concurrent_marking() {
+    with_mockvm(
+        || MockVM {
+            scan_object: MockMethod::new_fixed(Box::new(|(_tls, object, slot_visitor)| {
+                if Some(object) == *HOLDER.lock().unwrap() {
+                    HOLDER_SCANNED.store(true, Ordering::SeqCst);
+                }
+                for i in 0..num_fields(object) {
+                    slot_visitor.visit_slot(field_slot(object, i));
+                }
+            })),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|builder| {
+                builder.options.incremental_marking.set(true);
+                // Each slice executes one marking packet.
+                builder.options.incremental_marking_slice_bytes.set(1);
+            });
+            let mmtk = gc.mmtk();
+            let tls = gc.mutator.mutator_tls;
+
+            // Each block of strong handles is scanned in one marking packet.  The holder is in the
+            // middle block, so marking is not finished when the holder is scanned.
+            let mut roots = vec![];
+            for _ in 0..HANDLES_IN_BLOCK {
+                let object = gc.alloc(0, 0);
+                roots.push(gc.root(object));
+            }
+            let holder = gc.alloc(1, 0);
+            let holder_root = gc.root(holder);
+            *HOLDER.lock().unwrap() = Some(holder);
+            for _ in 0..2 * HANDLES_IN_BLOCK {
+                let object = gc.alloc(0, 0);
+                roots.push(gc.root(object));
+            }
+
+            // An object and its child, only reachable from a weak handle.
+            let object = gc.alloc(1, 1);
+            let child = gc.alloc(0, 1);
+            unsafe { hidden_field_slot(object, 0).store(1usize) };
+            unsafe { hidden_field_slot(child, 0).store(2usize) };
+            store(field_slot(object, 0), Some(child));
+            let weak = memory_manager::create_weak_handle(mmtk, object);
+
+            let pauses_before = pauses();
+            while !mmtk.state.pacer.is_marking_on_mutators() {
+                gc.alloc(8, 0);
+            }
+            while !HOLDER_SCANNED.load(Ordering::SeqCst) {
+                memory_manager::gc_poll(mmtk, tls);
+            }
+            assert_eq!(pauses(), pauses_before + 1);
+
+            // The holder is scanned, and the object is not marked.  Move the object from the
+            // weak handle to the holder.
+            let resolved = gc.resolve(weak).unwrap();
+            assert_eq!(resolved, object);
+            memory_manager::object_reference_write_pre(
+                &mut gc.mutator,
+                holder,
+                field_slot(holder, 0),
+                Some(resolved),
+            );
+            store(field_slot(holder, 0), Some(resolved));
+
+            // Finish marking, and reuse the memory of dead objects.
+            while pauses() == pauses_before + 1 {
+                memory_manager::gc_poll(mmtk, tls);
+            }
+            for _ in 0..10000 {
+                gc.alloc(1, 1);
+            }
+
+            assert_eq!(gc.resolve(weak), Some(object));
+            let holder = gc.resolve(holder_root).unwrap();
+            assert_eq!(load(field_slot(holder, 0)), Some(object));
+            assert_eq!(unsafe { hidden_field_slot(object, 0).load::<usize>() }, 1);
+            assert_eq!(load(field_slot(object, 0)), Some(child));
+            assert_eq!(unsafe { hidden_field_slot(child, 0).load::<usize>() }, 2);
+        },
+        no_cleanup,
+    )
+}
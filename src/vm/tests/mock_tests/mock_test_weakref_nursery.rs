@@ -0,0 +1,68 @@
+// GITHUB-CI: MMTK_PLAN=GenCopy,GenImmix,StickyImmix
+
+// A nursery GC only scans the references added since the previous GC, and a full-heap GC scans
+// all of them.  A scanned reference with a live referent has its referent updated with
+// `ReferenceGlue::set_referent`, which we record.
+
+use super::mock_test_prelude::*;
+use crate::util::test_util::mock_gc::*;
+use crate::util::ObjectReference;
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref SCANNED: Mutex<HashSet<ObjectReference>> = Mutex::new(HashSet::new());
+}
+
+fn take_scanned() -> HashSet<ObjectReference> {
+    std::mem::take(&mut *SCANNED.lock().unwrap())
+}
+
+#[test]
+pub fn nursery_gc_scans_new_references() {
+    with_mockvm(
+        || MockVM {
+            weakref_set_referent: MockMethod::new_fixed(Box::new(|(reference, referent)| {
+                store(hidden_field_slot(reference, 0), Some(referent));
+                SCANNED.lock().unwrap().insert(reference);
+            })),
+            ..mock_gc_setup()
+        },
+        || {
+            let mut gc = MockGC::new(|_| {});
+            let mmtk = gc.mmtk();
+
+            // Create a weak reference whose referent is kept alive by a handle.
+            let new_weak_reference = |gc: &mut MockGC| {
+                let reference = gc.alloc(0, 1);
+                let referent = gc.alloc(0, 0);
+                store(hidden_field_slot(reference, 0), Some(referent));
+                memory_manager::add_weak_candidate(mmtk, reference);
+                (gc.root(reference), gc.root(referent))
+            };
+
+            let (old, _) = new_weak_reference(&mut gc);
+            gc.collect();
+            assert_eq!(take_scanned(), HashSet::from([gc.resolve(old).unwrap()]));
+
+            // The reference added before the last GC is mature, and is not scanned again.
+            let (young, _) = new_weak_reference(&mut gc);
+            gc.collect();
+            assert_eq!(take_scanned(), HashSet::from([gc.resolve(young).unwrap()]));
+
+            // No reference is added since the last GC.
+            gc.collect();
+            assert!(take_scanned().is_empty());
+
+            // A full-heap GC scans all the references.
+            gc.collect_full_heap();
+            assert_eq!(
+                take_scanned(),
+                HashSet::from([gc.resolve(old).unwrap(), gc.resolve(young).unwrap()])
+            );
+            assert!(take_enqueued_references().is_empty());
+        },
+        no_cleanup,
+    )
+}
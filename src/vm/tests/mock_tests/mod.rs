@@ -23,6 +23,7 @@ pub(crate) mod mock_test_prelude {
     pub use crate::vm::*;
 }
 
+mod mock_test_accounting_context_hard_quota;
 mod mock_test_allocate_align_offset;
 mod mock_test_allocate_no_gc_oom_on_acquire_allow_oom_call;
 mod mock_test_allocate_no_gc_oom_on_acquire_no_oom_call;
@@ -36,14 +37,25 @@ mod mock_test_allocate_with_re_enable_collection;
 mod mock_test_allocate_without_initialize_collection;
 mod mock_test_allocator_info;
 mod mock_test_barrier_slow_path_assertion;
+mod mock_test_concurrent_marking_assists;
 #[cfg(feature = "vo_bit")]
 mod mock_test_conservatism;
 mod mock_test_debug_get_object_info;
+mod mock_test_ephemeron_finalization;
+mod mock_test_explain_liveness;
+mod mock_test_gc_worker_events;
+mod mock_test_guardian_finalization;
 #[cfg(target_os = "linux")]
 mod mock_test_handle_mmap_conflict;
 mod mock_test_handle_mmap_oom;
+#[cfg(all(target_pointer_width = "64", feature = "heap_image"))]
+mod mock_test_heap_image;
 #[cfg(feature = "vo_bit")]
 mod mock_test_heap_traversal;
+#[cfg(feature = "heap_verifier")]
+mod mock_test_heap_verifier;
+mod mock_test_inactive_workers_stay_parked;
+mod mock_test_incremental_marking;
 mod mock_test_init_fork;
 #[cfg(feature = "vo_bit")]
 mod mock_test_internal_ptr_before_object_ref;
@@ -63,15 +75,24 @@ mod mock_test_malloc_counted;
 mod mock_test_malloc_ms;
 #[cfg(all(target_pointer_width = "64", feature = "vm_space"))]
 mod mock_test_mmtk_julia_pr_143;
+mod mock_test_mutator_driven_gc;
 #[cfg(feature = "nogc_lock_free")]
 mod mock_test_nogc_lock_free;
+mod mock_test_ordered_finalization;
+mod mock_test_reference_kind_ranks;
+mod mock_test_set_num_of_workers;
 mod mock_test_shutdown;
 mod mock_test_slots;
+mod mock_test_soft_reference_kinds;
+mod mock_test_soft_reference_lru;
+mod mock_test_stress_seed;
 #[cfg(target_pointer_width = "64")]
 mod mock_test_vm_layout_compressed_pointer;
 mod mock_test_vm_layout_default;
 mod mock_test_vm_layout_heap_start;
 mod mock_test_vm_layout_log_address_space;
+mod mock_test_weak_handle_concurrent_marking;
+mod mock_test_weakref_nursery;
 
 mod mock_test_doc_avoid_resolving_allocator;
 mod mock_test_doc_mutator_storage;